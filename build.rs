@@ -14,4 +14,15 @@ fn main() {
         "cargo:rustc-env=WHISPER_CPP_VERSION={}",
         whisper_cpp_version
     );
+    // both are empty if whisper-rs-sys couldn't determine them
+    for (dep, env) in [
+        ("DEP_WHISPER_WHISPER_CPP_COMMIT", "WHISPER_CPP_COMMIT"),
+        ("DEP_WHISPER_GGML_COMMIT", "GGML_COMMIT"),
+    ] {
+        println!(
+            "cargo:rustc-env={}={}",
+            env,
+            env::var(dep).unwrap_or_default()
+        );
+    }
 }
@@ -4,7 +4,9 @@
 use hound;
 use std::fs::File;
 use std::io::Write;
-use whisper_rs::{FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters};
+use whisper_rs::{
+    FullParams, Language, SamplingStrategy, WhisperContext, WhisperContextParameters,
+};
 
 /// Loads a context and model, processes an audio file, and prints the resulting transcript to stdout.
 fn main() -> Result<(), &'static str> {
@@ -59,7 +61,7 @@ fn main() -> Result<(), &'static str> {
     // Enable translation.
     params.set_translate(true);
     // Set the language to translate to to English.
-    params.set_language(Some("en"));
+    params.set_language(Language::English);
     // Disable anything that prints to stdout.
     params.set_print_special(false);
     params.set_print_progress(false);
@@ -111,7 +113,7 @@ fn main() -> Result<(), &'static str> {
     let mut file = File::create("transcript.txt").expect("failed to create file");
 
     // Iterate through the segments of the transcript.
-    for segment in state.as_iter() {
+    for segment in state.segments() {
         // Get the transcribed text and timestamps for the current segment.
         let start_timestamp = segment.start_timestamp();
         let end_timestamp = segment.end_timestamp();
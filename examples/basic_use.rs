@@ -4,7 +4,9 @@ wget https://github.com/ggerganov/whisper.cpp/raw/master/samples/jfk.wav
 cargo run --example basic_use ggml-tiny.bin jfk.wav
 */
 
-use whisper_rs::{FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters};
+use whisper_rs::{
+    FullParams, Language, SamplingStrategy, WhisperContext, WhisperContextParameters,
+};
 
 fn main() {
     let model_path = std::env::args()
@@ -36,7 +38,7 @@ fn main() {
     });
 
     // and set the language to translate to as english
-    params.set_language(Some("en"));
+    params.set_language(Language::English);
 
     // we also explicitly disable anything that prints to stdout
     // despite all of this you will still get things printing to stdout,
@@ -63,7 +65,7 @@ fn main() {
         .expect("failed to run model");
 
     // fetch the results
-    for segment in state.as_iter() {
+    for segment in state.segments() {
         println!(
             "[{} - {}]: {}",
             // these timestamps are in centiseconds (10s of milliseconds)
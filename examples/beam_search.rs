@@ -0,0 +1,91 @@
+/*
+wget https://huggingface.co/ggerganov/whisper.cpp/resolve/main/ggml-tiny.en.bin
+wget https://github.com/ggerganov/whisper.cpp/raw/master/samples/jfk.wav
+cargo run --example beam_search ggml-tiny.en.bin jfk.wav
+*/
+
+//! Run the encoder once, then drive the decoder by hand with a small beam search of our own,
+//! instead of letting `WhisperState::full` do all of it.
+
+use whisper_rs::{WhisperContext, WhisperContextParameters, WhisperTokenId};
+
+const BEAM_SIZE: usize = 4;
+const MAX_TOKENS: usize = 64;
+
+/// Log-probabilities of the next token from raw logits.
+fn log_softmax(logits: &[f32]) -> Vec<f32> {
+    let max = logits.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    let sum: f32 = logits.iter().map(|l| (l - max).exp()).sum();
+    logits.iter().map(|l| l - max - sum.ln()).collect()
+}
+
+fn main() {
+    let model_path = std::env::args()
+        .nth(1)
+        .expect("Please specify path to model as argument 1");
+    let wav_path = std::env::args()
+        .nth(2)
+        .expect("Please specify path to wav file as argument 2");
+
+    // the file must already be 16 kHz mono
+    let samples: Vec<f32> = hound::WavReader::open(wav_path)
+        .unwrap()
+        .into_samples::<i16>()
+        .map(|x| x.unwrap() as f32 / 32768.0)
+        .collect();
+
+    let ctx = WhisperContext::new_with_params(&model_path, WhisperContextParameters::default())
+        .expect("failed to load model");
+    let mut state = ctx.create_state().expect("failed to create state");
+
+    // the encoder runs once, every hypothesis below attends to its output
+    state
+        .pcm_to_mel(&samples, 4)
+        .expect("failed to compute mel");
+    state.encode(0, 4).expect("failed to encode");
+
+    // the prompt of a plain english transcription without timestamps
+    let prompt = vec![ctx.token_sot(), ctx.token_not()];
+    let eot = ctx.token_eot();
+    // (tokens, total log-probability, finished)
+    let mut beams: Vec<(Vec<WhisperTokenId>, f32, bool)> = vec![(prompt.clone(), 0.0, false)];
+
+    for _ in 0..MAX_TOKENS {
+        let mut candidates = Vec::new();
+        for (tokens, score, finished) in &beams {
+            if *finished {
+                candidates.push((tokens.clone(), *score, true));
+                continue;
+            }
+            // the state caches a single sequence, so each beam is decoded in full; decoding
+            // only the new tokens with a matching n_past is faster when beams share a prefix
+            state.decode(tokens, 0, 4).expect("failed to decode");
+            let log_probs = log_softmax(state.get_logits().expect("no logits"));
+            let mut best: Vec<(usize, f32)> = log_probs.iter().copied().enumerate().collect();
+            best.sort_by(|a, b| b.1.total_cmp(&a.1));
+            for &(token, log_prob) in best.iter().take(BEAM_SIZE) {
+                let mut next = tokens.clone();
+                next.push(token as WhisperTokenId);
+                candidates.push((next, score + log_prob, token as WhisperTokenId == eot));
+            }
+        }
+        // rank by mean log-probability, so longer hypotheses aren't penalized
+        let mean = |(tokens, score, _): &(Vec<WhisperTokenId>, f32, bool)| {
+            score / (tokens.len() - prompt.len()).max(1) as f32
+        };
+        candidates.sort_by(|a, b| mean(b).total_cmp(&mean(a)));
+        candidates.truncate(BEAM_SIZE);
+        beams = candidates;
+        if beams.iter().all(|(_, _, finished)| *finished) {
+            break;
+        }
+    }
+
+    let (tokens, score, _) = &beams[0];
+    let text: String = tokens[prompt.len()..]
+        .iter()
+        .take_while(|&&token| token != eot)
+        .filter_map(|&token| ctx.token_to_str(token).ok())
+        .collect();
+    println!("{} (log-probability {:.2})", text.trim(), score);
+}
@@ -68,7 +68,7 @@ fn main() {
         .expect("failed to convert samples");
     let et = std::time::Instant::now();
 
-    for segment in state.as_iter() {
+    for segment in state.segments() {
         let start_timestamp = segment.start_timestamp();
         let end_timestamp = segment.end_timestamp();
         println!("[{} - {}]: {}", start_timestamp, end_timestamp, segment);
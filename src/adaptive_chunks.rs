@@ -0,0 +1,180 @@
+use crate::transcribe::{ms_to_samples, MIN_INPUT_MS};
+
+/// Samples per Silero VAD probability.
+pub(crate) const FRAME_SAMPLES: usize = 512;
+
+/// How a [`crate::StreamingTranscriber`] picks chunk boundaries from voice activity instead of
+/// cutting fixed windows, see [`crate::StreamingTranscriber::with_adaptive_chunking`].
+///
+/// The more of the upcoming audio is speech, the shorter the chunk: dense conversation is cut
+/// after [`Self::min_ms`] to keep latency steady, while audio that is mostly silence runs up to
+/// [`Self::max_ms`], since it is cheap to transcribe. Each cut is placed in the middle of a pause
+/// near that length, so no word is split between chunks and stitched back together wrongly.
+/// Without a pause, the chunk is cut at the quietest frame available.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AdaptiveChunking {
+    min_ms: u32,
+    max_ms: u32,
+    threshold: f32,
+    min_pause_ms: u32,
+}
+
+impl Default for AdaptiveChunking {
+    fn default() -> Self {
+        Self {
+            min_ms: 10_000,
+            max_ms: whisper_rs_sys::WHISPER_CHUNK_SIZE * 1000,
+            threshold: 0.5,
+            min_pause_ms: 300,
+        }
+    }
+}
+
+impl AdaptiveChunking {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the shortest chunk, used for continuous speech. Raised to just over one second, the
+    /// shortest audio whisper.cpp accepts.
+    ///
+    /// Defaults to 10 seconds.
+    pub fn min_ms(mut self, min_ms: u32) -> Self {
+        self.min_ms = min_ms.max(MIN_INPUT_MS);
+        self
+    }
+
+    /// Set the longest chunk, used for audio that is mostly silence.
+    ///
+    /// Defaults to 30 seconds, the window Whisper was trained on.
+    pub fn max_ms(mut self, max_ms: u32) -> Self {
+        self.max_ms = max_ms.max(1000);
+        self
+    }
+
+    /// Set the probability above which a frame is considered speech.
+    ///
+    /// Defaults to 0.5.
+    pub fn threshold(mut self, threshold: f32) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Set how long a pause must last for a chunk to be cut in it.
+    ///
+    /// Defaults to 300 ms.
+    pub fn min_pause_ms(mut self, min_pause_ms: u32) -> Self {
+        self.min_pause_ms = min_pause_ms;
+        self
+    }
+
+    /// Number of samples the next chunk may hold at most.
+    pub(crate) fn max_samples(&self) -> usize {
+        ms_to_samples(self.max_ms).max(self.min_frames() * FRAME_SAMPLES)
+    }
+
+    /// Number of frames the next chunk holds at least, rounded up so it is never shorter than
+    /// [`Self::min_ms`].
+    fn min_frames(&self) -> usize {
+        ms_to_samples(self.min_ms).div_ceil(FRAME_SAMPLES)
+    }
+
+    /// Where to end the next chunk, in samples, given the speech probability of each frame of
+    /// the upcoming audio.
+    pub(crate) fn cut(&self, probs: &[f32]) -> usize {
+        let max = (self.max_samples() / FRAME_SAMPLES).min(probs.len());
+        let min = self.min_frames().min(max);
+        if max == 0 {
+            return self.max_samples();
+        }
+        let probs = &probs[..max];
+        let speech = probs.iter().filter(|&&p| p >= self.threshold).count();
+        let target = max - (max - min) * speech / max;
+
+        // the middle of each long enough pause, in frames
+        let min_pause = (ms_to_samples(self.min_pause_ms) / FRAME_SAMPLES).max(1);
+        let mut pauses = Vec::new();
+        let mut start = None;
+        for (i, &p) in probs.iter().chain([&f32::INFINITY]).enumerate() {
+            match (p < self.threshold, start) {
+                (true, None) => start = Some(i),
+                (false, Some(s)) => {
+                    if i - s >= min_pause {
+                        pauses.push((s + i) / 2);
+                    }
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+        let in_range = |&&at: &&usize| at >= min && at > 0;
+        let frame = pauses
+            .iter()
+            .filter(in_range)
+            .rfind(|&&at| at <= target)
+            .or_else(|| pauses.iter().filter(in_range).find(|&&at| at > target))
+            .copied()
+            .unwrap_or_else(|| {
+                // the quietest frame from the target on, preferring later ones
+                (target.max(1)..max)
+                    .rev()
+                    .min_by(|&a, &b| probs[a].total_cmp(&probs[b]))
+                    .unwrap_or(max)
+            });
+        frame * FRAME_SAMPLES
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Probabilities for `seconds` of audio, with speech everywhere but the listed pauses.
+    fn speech_with_pauses(seconds: usize, pauses: &[(f32, f32)]) -> Vec<f32> {
+        let frames = seconds * 16000 / FRAME_SAMPLES;
+        (0..frames)
+            .map(|i| {
+                let t = (i * FRAME_SAMPLES) as f32 / 16000.0;
+                if pauses.iter().any(|&(a, b)| t >= a && t < b) {
+                    0.1
+                } else {
+                    0.9
+                }
+            })
+            .collect()
+    }
+
+    fn cut_seconds(chunking: &AdaptiveChunking, probs: &[f32]) -> f32 {
+        chunking.cut(probs) as f32 / 16000.0
+    }
+
+    #[test]
+    fn dense_speech_is_cut_early_in_a_pause() {
+        let chunking = AdaptiveChunking::new();
+        let probs = speech_with_pauses(30, &[(11.0, 11.5), (20.0, 20.5), (27.0, 27.5)]);
+        let cut = cut_seconds(&chunking, &probs);
+        assert!((11.0..11.5).contains(&cut), "cut at {cut}");
+    }
+
+    #[test]
+    fn sparse_speech_runs_long() {
+        let chunking = AdaptiveChunking::new();
+        let probs = speech_with_pauses(30, &[(2.0, 12.0), (14.0, 28.0)]);
+        let cut = cut_seconds(&chunking, &probs);
+        assert!((14.0..28.0).contains(&cut), "cut at {cut}");
+
+        // without any pause, the quietest frame is used
+        let mut probs = speech_with_pauses(30, &[]);
+        probs[800] = 0.6;
+        assert_eq!(chunking.cut(&probs), 800 * FRAME_SAMPLES);
+    }
+
+    #[test]
+    fn chunks_are_never_too_short_for_whisper_cpp() {
+        let chunking = AdaptiveChunking::new().min_ms(0).max_ms(0);
+        let probs = speech_with_pauses(30, &[]);
+        let cut = chunking.cut(&probs);
+        assert!(cut >= ms_to_samples(MIN_INPUT_MS), "cut at {cut}");
+        assert!(cut <= chunking.max_samples());
+    }
+}
@@ -0,0 +1,211 @@
+use crate::{EncoderBackend, WhisperContext};
+use std::ffi::{c_int, CStr};
+use std::path::Path;
+
+/// Kind of a ggml device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeviceKind {
+    Cpu,
+    Gpu,
+    /// An accelerator used alongside the CPU, such as a BLAS library.
+    Accelerator,
+}
+
+/// A ggml device, see [`BackendInfo`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceInfo {
+    /// Short name given by ggml, e.g. `CPU`, `CUDA0` or `Metal`.
+    pub name: String,
+    /// Description given by the driver, usually the product name.
+    pub description: String,
+    pub kind: DeviceKind,
+}
+
+/// Where a model runs, see [`WhisperContext::backend_info`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BackendInfo {
+    /// The device holding the model weights and running the model.
+    pub device: DeviceInfo,
+    /// Whether flash attention is enabled, see [`crate::WhisperContextParameters::flash_attn`].
+    pub flash_attn: bool,
+    /// Whether DTW token timestamps are computed. False when they weren't requested, and when
+    /// they were but didn't fit in memory, see [`crate::DtwParameters::with_memory_fallback`].
+    pub dtw_timestamps: bool,
+    /// The encoder new states are set up with. whisper.cpp can still fall back to the GGML
+    /// encoder when a state is created, see [`crate::WhisperState::encoder_backend`].
+    pub encoder: EncoderBackend,
+    /// Compile-time features of whisper.cpp and ggml, see [`crate::print_system_info`].
+    pub system_info: &'static str,
+}
+
+impl WhisperContext {
+    /// Report the device and features this model actually runs with, for display and logging.
+    ///
+    /// The device is chosen the way whisper.cpp chooses it when loading: with
+    /// [`crate::WhisperContextParameters::use_gpu`], the GPU numbered
+    /// [`crate::WhisperContextParameters::gpu_device`]; otherwise, or without any GPU, the CPU.
+    pub fn backend_info(&self) -> BackendInfo {
+        let ctx = self.inner();
+        let device = selected_device(ctx.use_gpu, ctx.gpu_device)
+            .map(|device| device.info)
+            .unwrap_or_else(|| DeviceInfo {
+                name: "CPU".to_string(),
+                description: String::new(),
+                kind: DeviceKind::Cpu,
+            });
+
+        BackendInfo {
+            device,
+            flash_attn: ctx.flash_attn,
+            dtw_timestamps: ctx.dtw,
+            encoder: encoder_for(
+                ctx.openvino_encoder.is_some(),
+                ctx.coreml_encoder_path.as_deref(),
+            ),
+            system_info: crate::print_system_info(),
+        }
+    }
+}
+
+/// The encoder new states are set up with, given whether an OpenVINO encoder is configured
+/// and where whisper.cpp looks for a CoreML one.
+fn encoder_for(openvino: bool, coreml_encoder_path: Option<&Path>) -> EncoderBackend {
+    if openvino {
+        EncoderBackend::OpenVino
+    } else if cfg!(feature = "coreml") && coreml_encoder_path.is_some_and(Path::exists) {
+        EncoderBackend::CoreMl
+    } else {
+        EncoderBackend::Ggml
+    }
+}
+
+pub(crate) struct Device {
+    pub(crate) handle: whisper_rs_sys::ggml_backend_dev_t,
+    pub(crate) info: DeviceInfo,
+}
+
+/// The device a model loaded with these parameters runs on.
+/// Mirrors `whisper_backend_init_gpu` in whisper.cpp.
+pub(crate) fn selected_device(use_gpu: bool, gpu_device: c_int) -> Option<Device> {
+    select(devices(), use_gpu, gpu_device)
+}
+
+fn select(devices: Vec<Device>, use_gpu: bool, gpu_device: c_int) -> Option<Device> {
+    let (mut gpus, others): (Vec<_>, Vec<_>) = devices
+        .into_iter()
+        .partition(|device| device.info.kind == DeviceKind::Gpu);
+    if use_gpu && !gpus.is_empty() {
+        let index = usize::try_from(gpu_device)
+            .ok()
+            .filter(|&i| i < gpus.len())
+            .unwrap_or(0);
+        return Some(gpus.swap_remove(index));
+    }
+    others
+        .into_iter()
+        .find(|device| device.info.kind == DeviceKind::Cpu)
+}
+
+/// Number of GPUs registered with ggml.
+pub(crate) fn gpu_count() -> usize {
+    devices()
+        .iter()
+        .filter(|device| device.info.kind == DeviceKind::Gpu)
+        .count()
+}
+
+/// Every device registered with ggml, in ggml's order.
+fn devices() -> Vec<Device> {
+    let to_string = |ptr: *const std::ffi::c_char| {
+        if ptr.is_null() {
+            String::new()
+        } else {
+            // SAFETY: ggml returns nul-terminated strings that live as long as the device
+            unsafe { CStr::from_ptr(ptr) }
+                .to_string_lossy()
+                .into_owned()
+        }
+    };
+
+    let count = unsafe { whisper_rs_sys::ggml_backend_dev_count() };
+    (0..count)
+        .map(|i| unsafe {
+            let handle = whisper_rs_sys::ggml_backend_dev_get(i);
+            let kind = match whisper_rs_sys::ggml_backend_dev_type(handle) {
+                whisper_rs_sys::ggml_backend_dev_type_GGML_BACKEND_DEVICE_TYPE_CPU => {
+                    DeviceKind::Cpu
+                }
+                whisper_rs_sys::ggml_backend_dev_type_GGML_BACKEND_DEVICE_TYPE_GPU => {
+                    DeviceKind::Gpu
+                }
+                _ => DeviceKind::Accelerator,
+            };
+            let info = DeviceInfo {
+                name: to_string(whisper_rs_sys::ggml_backend_dev_name(handle)),
+                description: to_string(whisper_rs_sys::ggml_backend_dev_description(handle)),
+                kind,
+            };
+            Device { handle, info }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn device(name: &str, kind: DeviceKind) -> Device {
+        Device {
+            handle: std::ptr::null_mut(),
+            info: DeviceInfo {
+                name: name.to_string(),
+                description: String::new(),
+                kind,
+            },
+        }
+    }
+
+    fn selected(use_gpu: bool, gpu_device: c_int) -> Option<String> {
+        let devices = vec![
+            device("BLAS", DeviceKind::Accelerator),
+            device("CPU", DeviceKind::Cpu),
+            device("CUDA0", DeviceKind::Gpu),
+            device("CUDA1", DeviceKind::Gpu),
+        ];
+        select(devices, use_gpu, gpu_device).map(|device| device.info.name)
+    }
+
+    #[test]
+    fn devices_are_selected_like_whisper_cpp() {
+        assert_eq!(selected(true, 1).as_deref(), Some("CUDA1"));
+        // an out of range device falls back to the first GPU
+        assert_eq!(selected(true, 2).as_deref(), Some("CUDA0"));
+        assert_eq!(selected(true, -1).as_deref(), Some("CUDA0"));
+        assert_eq!(selected(false, 1).as_deref(), Some("CPU"));
+
+        let cpu_only = vec![
+            device("BLAS", DeviceKind::Accelerator),
+            device("CPU", DeviceKind::Cpu),
+        ];
+        let selected = select(cpu_only, true, 0).map(|device| device.info.name);
+        assert_eq!(selected.as_deref(), Some("CPU"));
+        assert!(select(Vec::new(), true, 0).is_none());
+    }
+
+    #[test]
+    fn openvino_takes_precedence_over_coreml() {
+        let existing = Path::new(env!("CARGO_MANIFEST_DIR"));
+        assert_eq!(encoder_for(true, Some(existing)), EncoderBackend::OpenVino);
+        assert_eq!(encoder_for(false, None), EncoderBackend::Ggml);
+        assert_eq!(
+            encoder_for(false, Some(&existing.join("missing.mlmodelc"))),
+            EncoderBackend::Ggml
+        );
+        let coreml = if cfg!(feature = "coreml") {
+            EncoderBackend::CoreMl
+        } else {
+            EncoderBackend::Ggml
+        };
+        assert_eq!(encoder_for(false, Some(existing)), coreml);
+    }
+}
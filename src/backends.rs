@@ -0,0 +1,126 @@
+//! Explicit setup and teardown of the ggml backends, for hosts that manage library lifetimes.
+//!
+//! ggml registers its backends lazily the first time a model is loaded, and keeps them for the
+//! rest of the process. That is fine for most applications. Plugins that are loaded and unloaded
+//! repeatedly (audio plugin hosts, game engines) and processes that fork want the work done at a
+//! known point instead, and need to know when no context holds backend resources anymore.
+
+use crate::WhisperError;
+use std::ffi::CString;
+use std::path::Path;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+/// The live contexts and loaded backends, under one lock so that no context can start loading
+/// while [`shutdown_backends`] unloads the backends.
+static BACKENDS: Mutex<Backends> = Mutex::new(Backends {
+    live: 0,
+    loaded: Vec::new(),
+});
+
+struct Backends {
+    /// Number of contexts alive or loading, each holding buffers on its backend.
+    live: usize,
+    /// Backends loaded with [`load_backend`], as `ggml_backend_reg_t` addresses.
+    loaded: Vec<usize>,
+}
+
+fn backends() -> MutexGuard<'static, Backends> {
+    BACKENDS.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Register all backends and initialize the CPU backend now, rather than on the first model load.
+///
+/// With a build using dynamically loadable backends (`GGML_BACKEND_DL`), this also loads every
+/// backend library found next to the executable. Calling it more than once is harmless.
+///
+/// # C++ equivalent
+/// `void ggml_backend_load_all(void)` and `void ggml_cpu_init(void)`
+pub fn init_backends() {
+    unsafe {
+        whisper_rs_sys::ggml_backend_load_all();
+        whisper_rs_sys::ggml_cpu_init();
+    }
+}
+
+/// Load a backend from a dynamic library, such as `libggml-cuda.so`, to be unloaded again by
+/// [`shutdown_backends`].
+///
+/// # Returns
+/// `Err(WhisperError::InitError)` if the library couldn't be loaded or isn't a ggml backend.
+///
+/// # C++ equivalent
+/// `ggml_backend_reg_t ggml_backend_load(const char * path)`
+pub fn load_backend(path: impl AsRef<Path>) -> Result<(), WhisperError> {
+    let path = CString::new(path.as_ref().to_string_lossy().as_ref())?;
+    let reg = unsafe { whisper_rs_sys::ggml_backend_load(path.as_ptr()) };
+    if reg.is_null() {
+        return Err(WhisperError::InitError);
+    }
+    backends().loaded.push(reg as usize);
+    Ok(())
+}
+
+/// Unload the backends loaded with [`load_backend`], releasing their global resources.
+///
+/// Backends compiled into the library can't be unregistered by ggml and stay available; the
+/// resources they hold for models are released as each context is dropped.
+///
+/// # Returns
+/// `Err(WhisperError::BackendsInUse)` without unloading anything while any
+/// [`crate::WhisperContext`] or [`crate::WhisperVadContext`] is still alive or loading,
+/// see [`live_contexts`].
+///
+/// # C++ equivalent
+/// `void ggml_backend_unload(ggml_backend_reg_t reg)`
+pub fn shutdown_backends() -> Result<(), WhisperError> {
+    let mut backends = backends();
+    if backends.live > 0 {
+        return Err(WhisperError::BackendsInUse {
+            contexts: backends.live,
+        });
+    }
+    for reg in backends.loaded.drain(..).rev() {
+        unsafe { whisper_rs_sys::ggml_backend_unload(reg as whisper_rs_sys::ggml_backend_reg_t) };
+    }
+    Ok(())
+}
+
+/// Number of [`crate::WhisperContext`]s and [`crate::WhisperVadContext`]s currently alive or
+/// loading in this process, across all states and clones. Zero means no model holds backend
+/// memory, e.g. before forking.
+pub fn live_contexts() -> usize {
+    backends().live
+}
+
+/// Counts a context as alive until dropped. Created before the context is initialized, so that
+/// [`shutdown_backends`] can't unload the backends while it loads.
+#[derive(Debug)]
+pub(crate) struct LiveContext(());
+
+impl LiveContext {
+    pub(crate) fn new() -> Self {
+        backends().live += 1;
+        Self(())
+    }
+}
+
+impl Drop for LiveContext {
+    fn drop(&mut self) {
+        backends().live -= 1;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn live_contexts_block_shutdown() {
+        let live = LiveContext::new();
+        assert!(matches!(
+            shutdown_backends(),
+            Err(WhisperError::BackendsInUse { contexts }) if contexts >= 1
+        ));
+        drop(live);
+    }
+}
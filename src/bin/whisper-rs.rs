@@ -0,0 +1,354 @@
+//! Command line transcription, built only on the public API of the crate.
+//!
+//! ```text
+//! whisper-rs -m ggml-base.en.bin recording.wav
+//! whisper-rs -m ggml-base.bin -l auto -f srt -o talk.srt talk.wav
+//! arecord -f S16_LE -r 16000 -c 1 -t raw | whisper-rs -m ggml-base.en.bin --raw s16le --stream -
+//! ffmpeg -i talk.mp4 -f s16le -ac 2 -ar 48000 - | whisper-rs -m ggml-base.bin --raw s16le --rate 48000 --channels 2 --stream
+//! ```
+
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Write};
+use std::process::ExitCode;
+use whisper_rs::output::{write_srt, write_vtt, JsonlSink, SubtitleOptions};
+use whisper_rs::{
+    FullParams, Language, PcmFormat, PcmReader, PcmStreamError, SampleFormat, SamplingStrategy,
+    StreamingTranscriber, Transcribe, Transcript, TranscriptSegment, WhisperContext,
+    WhisperContextParameters,
+};
+
+const USAGE: &str = "\
+Usage: whisper-rs -m MODEL [OPTIONS] [FILE]...
+
+Transcribe 16-bit or 32-bit PCM or 32-bit float WAV files, or standard input if FILE is `-` or
+missing. Several FILEs are transcribed one after the other, with timestamps counted from the
+start of the first, as if they were joined.
+
+Options:
+  -m, --model PATH      whisper.cpp model file (required)
+  -l, --language LANG   spoken language, or `auto` to detect it [default: en]
+  -t, --threads N       threads to decode with
+  -b, --beam-size N     use beam search with N beams instead of greedy decoding
+  -f, --format FORMAT   txt, srt, vtt or jsonl [default: txt]
+  -o, --output PATH     write to PATH instead of standard output
+      --translate       translate to English
+      --raw FORMAT      input is headerless audio, s16le, s32le or f32le
+      --rate N          sample rate of --raw input in Hz [default: 16000]
+      --channels N      interleaved channels of --raw input [default: 1]
+      --stream          print segments as soon as they are transcribed
+      --chunk-ms N      audio transcribed at once with --stream [default: 30000]
+      --no-gpu          run on the CPU
+  -v, --verbose         show whisper.cpp's log output
+  -h, --help            print this help
+
+Recording from a microphone is out of scope; pipe raw audio from a recorder instead,
+e.g. `arecord -f S16_LE -r 16000 -c 1 -t raw | whisper-rs -m MODEL --raw s16le --stream`.
+";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    Txt,
+    Srt,
+    Vtt,
+    Jsonl,
+}
+
+#[derive(Debug)]
+struct Args {
+    model: String,
+    language: Language,
+    threads: Option<i32>,
+    beam_size: Option<i32>,
+    format: Format,
+    output: Option<String>,
+    translate: bool,
+    raw: Option<SampleFormat>,
+    rate: u32,
+    channels: u16,
+    stream: bool,
+    chunk_ms: u32,
+    use_gpu: bool,
+    verbose: bool,
+    inputs: Vec<String>,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Args, String> {
+    let mut model = None;
+    let mut parsed = Args {
+        model: String::new(),
+        language: Language::English,
+        threads: None,
+        beam_size: None,
+        format: Format::Txt,
+        output: None,
+        translate: false,
+        raw: None,
+        rate: 16000,
+        channels: 1,
+        stream: false,
+        chunk_ms: StreamingTranscriber::<WhisperContext>::DEFAULT_CHUNK_MS,
+        use_gpu: true,
+        verbose: false,
+        inputs: Vec::new(),
+    };
+
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| {
+            args.next()
+                .ok_or_else(|| format!("missing value for {}", name))
+        };
+        match arg.as_str() {
+            "-m" | "--model" => model = Some(value(&arg)?),
+            "-l" | "--language" => {
+                parsed.language = value(&arg)?.parse().map_err(|e| format!("{}", e))?
+            }
+            "-t" | "--threads" => parsed.threads = Some(number(&arg, value(&arg)?)?),
+            "-b" | "--beam-size" => parsed.beam_size = Some(number(&arg, value(&arg)?)?),
+            "-f" | "--format" => {
+                parsed.format = match value(&arg)?.as_str() {
+                    "txt" => Format::Txt,
+                    "srt" => Format::Srt,
+                    "vtt" => Format::Vtt,
+                    "jsonl" => Format::Jsonl,
+                    other => return Err(format!("unknown format: {}", other)),
+                }
+            }
+            "-o" | "--output" => parsed.output = Some(value(&arg)?),
+            "--translate" => parsed.translate = true,
+            "--raw" => {
+                parsed.raw = match value(&arg)?.as_str() {
+                    "s16le" => Some(SampleFormat::S16le),
+                    "s32le" => Some(SampleFormat::S32le),
+                    "f32le" => Some(SampleFormat::F32le),
+                    other => return Err(format!("unknown raw format: {}", other)),
+                }
+            }
+            "--rate" => parsed.rate = number(&arg, value(&arg)?)?,
+            "--channels" => parsed.channels = number(&arg, value(&arg)?)?,
+            "--stream" => parsed.stream = true,
+            "--chunk-ms" => parsed.chunk_ms = number(&arg, value(&arg)?)?,
+            "--no-gpu" => parsed.use_gpu = false,
+            "-v" | "--verbose" => parsed.verbose = true,
+            "-h" | "--help" => return Err(String::new()),
+            other if other.starts_with('-') && other != "-" => {
+                return Err(format!("unknown option: {}", other))
+            }
+            _ => parsed.inputs.push(arg),
+        }
+    }
+
+    parsed.model = model.ok_or("missing --model")?;
+    if parsed.rate == 0 || parsed.channels == 0 {
+        return Err("--rate and --channels must be at least 1".to_string());
+    }
+    if parsed.inputs.is_empty() {
+        parsed.inputs.push("-".to_string());
+    }
+    Ok(parsed)
+}
+
+fn number<T: std::str::FromStr>(name: &str, value: String) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid value for {}: {}", name, value))
+}
+
+/// Where transcribed segments go. Subtitles are numbered and so are written in one go at the end.
+enum Sink {
+    Text(Box<dyn Write>),
+    Jsonl(JsonlSink<Box<dyn Write>>),
+    Subtitles(Box<dyn Write>, Format, Vec<TranscriptSegment>),
+}
+
+impl Sink {
+    fn new(format: Format, out: Box<dyn Write>) -> Self {
+        match format {
+            Format::Txt => Self::Text(out),
+            Format::Jsonl => Self::Jsonl(JsonlSink::new(out)),
+            Format::Srt | Format::Vtt => Self::Subtitles(out, format, Vec::new()),
+        }
+    }
+
+    fn write(&mut self, segments: Vec<TranscriptSegment>) -> io::Result<()> {
+        match self {
+            Self::Text(out) => {
+                for segment in &segments {
+                    writeln!(
+                        out,
+                        "[{} --> {}] {}",
+                        timestamp(segment.start),
+                        timestamp(segment.end),
+                        segment.text.trim()
+                    )?;
+                }
+                out.flush()
+            }
+            Self::Jsonl(sink) => sink.write_segments(&segments),
+            Self::Subtitles(_, _, all) => {
+                all.extend(segments);
+                Ok(())
+            }
+        }
+    }
+
+    fn finish(self) -> io::Result<()> {
+        match self {
+            Self::Text(mut out) => out.flush(),
+            Self::Jsonl(sink) => sink.into_inner().flush(),
+            Self::Subtitles(mut out, format, segments) => {
+                let transcript = Transcript::new(segments);
+                let options = SubtitleOptions::default();
+                if format == Format::Srt {
+                    write_srt(&mut out, &transcript, &options)?;
+                } else {
+                    write_vtt(&mut out, &transcript, &options)?;
+                }
+                out.flush()
+            }
+        }
+    }
+}
+
+/// `hh:mm:ss.mmm` from centiseconds.
+fn timestamp(cs: i64) -> String {
+    let ms = cs.max(0) * 10;
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        ms / 3_600_000,
+        ms / 60_000 % 60,
+        ms / 1000 % 60,
+        ms % 1000
+    )
+}
+
+fn open_input(path: &str) -> io::Result<Box<dyn Read>> {
+    if path == "-" {
+        Ok(Box::new(io::stdin().lock()))
+    } else {
+        Ok(Box::new(File::open(path)?))
+    }
+}
+
+fn run(args: Args) -> Result<(), String> {
+    if !args.verbose {
+        whisper_rs::install_logging_hooks();
+    }
+
+    let mut ctx_params = WhisperContextParameters::default();
+    ctx_params.use_gpu(args.use_gpu);
+    let ctx = WhisperContext::new_with_params(&args.model, ctx_params)
+        .map_err(|e| format!("failed to load {}: {}", args.model, e))?;
+
+    let strategy = match args.beam_size {
+        Some(beam_size) => SamplingStrategy::BeamSearch {
+            beam_size,
+            patience: -1.0,
+        },
+        None => SamplingStrategy::Greedy { best_of: 1 },
+    };
+    let mut params = FullParams::new(strategy);
+    params.set_language(args.language);
+    params.set_translate(args.translate);
+    params.set_print_progress(false);
+    params.set_print_realtime(false);
+    params.set_print_timestamps(false);
+    if let Some(threads) = args.threads {
+        params.set_n_threads(threads);
+    }
+
+    let out: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(BufWriter::new(
+            File::create(path).map_err(|e| format!("failed to create {}: {}", path, e))?,
+        )),
+        None => Box::new(io::stdout().lock()),
+    };
+    let mut sink = Sink::new(args.format, out);
+    let write_error = |e: io::Error| format!("failed to write output: {}", e);
+
+    let mut state = ctx.create_state().map_err(|e| e.to_string())?;
+    // start of the current input in centiseconds, counting all inputs before it
+    let mut offset = 0;
+    for path in &args.inputs {
+        let input = open_input(path).map_err(|e| format!("failed to open {}: {}", path, e))?;
+        let read_error = |e: io::Error| format!("failed to read {}: {}", path, e);
+        let mut reader = match args.raw {
+            Some(sample_format) => PcmReader::raw(
+                input,
+                PcmFormat {
+                    sample_format,
+                    sample_rate: args.rate,
+                    channels: args.channels,
+                },
+            ),
+            None => PcmReader::wav(input).map_err(|e| match e.kind() {
+                io::ErrorKind::InvalidData | io::ErrorKind::UnexpectedEof => {
+                    format!("{}: {}; use --raw for headerless audio", path, e)
+                }
+                _ => read_error(e),
+            })?,
+        };
+
+        if args.stream {
+            // a stream per input, so nothing buffered or counted carries over to the next one
+            let mut stream =
+                StreamingTranscriber::new(state, params.clone()).with_chunk_ms(args.chunk_ms);
+            let mut written = Ok(());
+            reader
+                .transcribe_into(&mut stream, |segments| {
+                    if written.is_ok() {
+                        written = sink.write(shifted(segments, offset));
+                    }
+                })
+                .map_err(|e| match e {
+                    PcmStreamError::Io(e) => read_error(e),
+                    PcmStreamError::Transcribe(e) => e.to_string(),
+                })?;
+            written.map_err(write_error)?;
+            offset += stream.position();
+            state = stream.into_inner();
+            continue;
+        }
+
+        let audio = reader.read_to_end().map_err(read_error)?;
+        let segments = state
+            .transcribe(params.clone(), &audio)
+            .map_err(|e| e.to_string())?
+            .segments;
+        sink.write(shifted(segments, offset)).map_err(write_error)?;
+        offset += (audio.len() / SAMPLES_PER_CS) as i64;
+    }
+    sink.finish().map_err(write_error)
+}
+
+/// Samples in a centisecond, the unit of timestamps.
+const SAMPLES_PER_CS: usize = 160;
+
+/// Move `segments` of an input starting `offset` centiseconds into the output.
+fn shifted(mut segments: Vec<TranscriptSegment>, offset: i64) -> Vec<TranscriptSegment> {
+    for segment in &mut segments {
+        segment.start += offset;
+        segment.end += offset;
+    }
+    segments
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) if e.is_empty() => {
+            print!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Err(e) => {
+            eprintln!("whisper-rs: {}\n\n{}", e, USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+    match run(args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("whisper-rs: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use crate::{get_whisper_version, print_system_info, WHISPER_CPP_VERSION};
+use std::fmt;
+
+/// The acceleration features of whisper-rs, and whether each was enabled at compile time.
+const ACCELERATION: [(&str, bool); 8] = [
+    ("coreml", cfg!(feature = "coreml")),
+    ("cuda", cfg!(feature = "cuda")),
+    ("hipblas", cfg!(feature = "hipblas")),
+    ("intel-sycl", cfg!(feature = "intel-sycl")),
+    ("metal", cfg!(feature = "metal")),
+    ("openblas", cfg!(feature = "openblas")),
+    ("openmp", cfg!(feature = "openmp")),
+    ("vulkan", cfg!(feature = "vulkan")),
+];
+
+/// Which whisper.cpp and ggml this build of whisper-rs runs on, see [`build_info`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuildInfo {
+    /// The whisper.cpp version whisper-rs was compiled against, see [`WHISPER_CPP_VERSION`].
+    pub whisper_cpp_version: &'static str,
+    /// The version reported by the linked library, see [`get_whisper_version`]. Differs from
+    /// [`Self::whisper_cpp_version`] only if a different library was swapped in at runtime.
+    pub linked_version: &'static str,
+    /// The whisper.cpp commit built, if the sources were a git checkout.
+    pub whisper_cpp_commit: Option<&'static str>,
+    /// The ggml commit whisper.cpp bundles. With the `use-shared-ggml` feature this is the
+    /// commit whisper.cpp expects, not necessarily the one ggml-rs built.
+    pub ggml_commit: Option<&'static str>,
+    /// The acceleration features enabled, such as `cuda` or `metal`, by their Cargo feature name.
+    pub acceleration: Vec<&'static str>,
+    /// Whether ggml comes from ggml-rs rather than being built with whisper.cpp.
+    pub shared_ggml: bool,
+    /// Whether this is the CPU-only `minimal` build.
+    pub minimal: bool,
+    /// CPU features ggml was compiled with, see [`print_system_info`].
+    pub system_info: &'static str,
+}
+
+/// Report the whisper.cpp and ggml versions and acceleration features this binary was built with,
+/// for diagnostics and bug reports. See [`crate::WhisperContext::backend_info`] for the device
+/// a model actually runs on.
+pub fn build_info() -> BuildInfo {
+    BuildInfo {
+        whisper_cpp_version: WHISPER_CPP_VERSION,
+        linked_version: get_whisper_version(),
+        whisper_cpp_commit: non_empty(option_env!("WHISPER_CPP_COMMIT")),
+        ggml_commit: non_empty(option_env!("GGML_COMMIT")),
+        acceleration: enabled(&ACCELERATION),
+        shared_ggml: cfg!(feature = "use-shared-ggml"),
+        minimal: cfg!(feature = "minimal"),
+        system_info: print_system_info(),
+    }
+}
+
+fn non_empty(value: Option<&'static str>) -> Option<&'static str> {
+    value.map(str::trim).filter(|v| !v.is_empty())
+}
+
+fn enabled(features: &[(&'static str, bool)]) -> Vec<&'static str> {
+    features
+        .iter()
+        .filter(|(_, on)| *on)
+        .map(|(name, _)| *name)
+        .collect()
+}
+
+impl fmt::Display for BuildInfo {
+    /// A one-line summary, e.g. `whisper.cpp 1.7.5 (a1b2c3d), ggml 0f1e2d3, cpu`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "whisper.cpp {}", self.whisper_cpp_version)?;
+        if let Some(commit) = self.whisper_cpp_commit {
+            write!(f, " ({})", short(commit))?;
+        }
+        if self.linked_version != self.whisper_cpp_version {
+            write!(f, ", linked {}", self.linked_version)?;
+        }
+        if let Some(commit) = self.ggml_commit {
+            write!(f, ", ggml {}", short(commit))?;
+        }
+        if self.shared_ggml {
+            f.write_str(" (shared)")?;
+        }
+        if self.acceleration.is_empty() {
+            f.write_str(", cpu")
+        } else {
+            write!(f, ", {}", self.acceleration.join("+"))
+        }
+    }
+}
+
+/// The abbreviated form of a git commit hash.
+fn short(commit: &str) -> &str {
+    commit.get(..7).unwrap_or(commit)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn summary_abbreviates_commits() {
+        let mut info = BuildInfo {
+            whisper_cpp_version: "1.7.5",
+            linked_version: "1.7.5",
+            whisper_cpp_commit: Some("a1b2c3d4e5f6"),
+            ggml_commit: Some("0f1e2d3c4b5a"),
+            acceleration: Vec::new(),
+            shared_ggml: false,
+            minimal: false,
+            system_info: "",
+        };
+        assert_eq!(
+            info.to_string(),
+            "whisper.cpp 1.7.5 (a1b2c3d), ggml 0f1e2d3, cpu"
+        );
+        info.acceleration = enabled(&[("cuda", true), ("metal", false), ("openmp", true)]);
+        info.whisper_cpp_commit = None;
+        assert_eq!(
+            info.to_string(),
+            "whisper.cpp 1.7.5, ggml 0f1e2d3, cuda+openmp"
+        );
+        assert_eq!(non_empty(Some(" ")), None);
+    }
+}
@@ -0,0 +1,529 @@
+//! Content-addressed cache of finished transcripts.
+//!
+//! Entries are keyed by a hash of the audio, the decoding parameters and a caller-chosen model id,
+//! so identical requests can be answered from disk without running the model again.
+
+use crate::common_logging::generic_warn;
+use crate::models::sha1::{to_hex, Sha1};
+use crate::{FullParams, Transcribe, Transcript, TranscriptSegment, TranscriptToken};
+use std::ffi::CStr;
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Version line at the top of every cache file. Entries with a different version are treated as missing.
+const FILE_HEADER: &str = "whisper-rs transcript 1";
+const EXTENSION: &str = "transcript";
+
+/// Identifies one cache entry, see [`TranscriptCache::key`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CacheKey(String);
+
+impl CacheKey {
+    /// The key as a lowercase hex SHA-1 digest.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for CacheKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// A directory of cached transcripts, one file per entry.
+///
+/// The cache may be shared between processes: entries are written to a temporary file and renamed
+/// into place, so readers never see a partial entry.
+#[derive(Debug, Clone)]
+pub struct TranscriptCache {
+    dir: PathBuf,
+}
+
+impl TranscriptCache {
+    /// Open the cache in `dir`, creating it if necessary.
+    pub fn new(dir: impl Into<PathBuf>) -> io::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+
+    /// The directory entries are stored in.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Compute the key for transcribing `audio` with `params` on the model identified by `model_id`.
+    ///
+    /// `model_id` should change whenever the model does, e.g. the file name plus its checksum.
+    /// Every parameter that affects the output is part of the key, except callbacks (logits filters,
+    /// segment and abort callbacks), which can't be inspected. Thread counts are ignored.
+    ///
+    /// Unless [`FullParams::set_no_context`] is set, whisper.cpp also decodes following the text of
+    /// the previous run on the same state, which isn't part of the key. [`CachedTranscriber`]
+    /// doesn't use the cache for such runs.
+    pub fn key(model_id: &str, params: &FullParams, audio: &[f32]) -> CacheKey {
+        let mut hasher = Sha1::new();
+        hash_bytes(&mut hasher, model_id.as_bytes());
+        hash_params(&mut hasher, params);
+        // hash the raw bits, so -0.0 and 0.0 or different NaNs don't collide with each other
+        hasher.update(&(audio.len() as u64).to_le_bytes());
+        for chunk in audio.chunks(4096) {
+            let bytes: Vec<u8> = chunk
+                .iter()
+                .flat_map(|s| s.to_bits().to_le_bytes())
+                .collect();
+            hasher.update(&bytes);
+        }
+        CacheKey(to_hex(&hasher.finalize()))
+    }
+
+    /// Look up a cached transcript. Unreadable or outdated entries are reported as missing.
+    pub fn get(&self, key: &CacheKey) -> io::Result<Option<Transcript>> {
+        let contents = match fs::read_to_string(self.path_for(key)) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        Ok(decode(&contents))
+    }
+
+    /// Store `transcript` under `key`, replacing any previous entry.
+    pub fn insert(&self, key: &CacheKey, transcript: &Transcript) -> io::Result<()> {
+        let path = self.path_for(key);
+        let tmp = path.with_extension(format!("{}.{}.tmp", EXTENSION, std::process::id()));
+        let mut file = fs::File::create(&tmp)?;
+        file.write_all(encode(transcript).as_bytes())?;
+        file.sync_all()?;
+        if let Err(e) = fs::rename(&tmp, &path) {
+            let _ = fs::remove_file(&tmp);
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Remove one entry.
+    ///
+    /// # Returns
+    /// Whether the entry existed.
+    pub fn remove(&self, key: &CacheKey) -> io::Result<bool> {
+        match fs::remove_file(self.path_for(key)) {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Remove every entry.
+    pub fn clear(&self) -> io::Result<()> {
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == EXTENSION) {
+                fs::remove_file(path)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn path_for(&self, key: &CacheKey) -> PathBuf {
+        self.dir.join(format!("{}.{}", key.0, EXTENSION))
+    }
+}
+
+/// A [`Transcribe`] backend that answers repeated requests from a [`TranscriptCache`].
+///
+/// Cache errors never fail a transcription; they are logged and the backend is used instead.
+///
+/// Only runs with [`FullParams::set_no_context`] set are cached: otherwise the output depends on
+/// what the backend transcribed before, such as the context a reused [`crate::WhisperState`]
+/// carries over or restores from a snapshot, and those runs always go to the backend.
+pub struct CachedTranscriber<T> {
+    backend: T,
+    cache: TranscriptCache,
+    model_id: String,
+}
+
+impl<T: Transcribe> CachedTranscriber<T> {
+    /// Wrap `backend`. `model_id` is passed to [`TranscriptCache::key`].
+    pub fn new(backend: T, cache: TranscriptCache, model_id: impl Into<String>) -> Self {
+        Self {
+            backend,
+            cache,
+            model_id: model_id.into(),
+        }
+    }
+
+    /// The cache results are stored in.
+    pub fn cache(&self) -> &TranscriptCache {
+        &self.cache
+    }
+
+    /// Consume the wrapper, returning the wrapped backend.
+    pub fn into_inner(self) -> T {
+        self.backend
+    }
+}
+
+impl<T: Transcribe> Transcribe for CachedTranscriber<T> {
+    type Error = T::Error;
+
+    fn transcribe(
+        &mut self,
+        params: FullParams<'_, '_>,
+        audio: &[f32],
+    ) -> Result<Transcript, Self::Error> {
+        if !params.fp.no_context {
+            return self.backend.transcribe(params, audio);
+        }
+        let key = TranscriptCache::key(&self.model_id, &params, audio);
+        match self.cache.get(&key) {
+            Ok(Some(transcript)) => return Ok(transcript),
+            Ok(None) => {}
+            Err(e) => {
+                generic_warn!("failed to read transcript cache entry {}: {}", key, e);
+            }
+        }
+
+        let transcript = self.backend.transcribe(params, audio)?;
+        if let Err(e) = self.cache.insert(&key, &transcript) {
+            generic_warn!("failed to write transcript cache entry {}: {}", key, e);
+        }
+        Ok(transcript)
+    }
+}
+
+fn hash_bytes(hasher: &mut Sha1, bytes: &[u8]) {
+    hasher.update(&(bytes.len() as u64).to_le_bytes());
+    hasher.update(bytes);
+}
+
+fn hash_c_str(hasher: &mut Sha1, ptr: *const std::ffi::c_char) {
+    if ptr.is_null() {
+        hasher.update(&[0]);
+    } else {
+        hasher.update(&[1]);
+        // SAFETY: non-null strings in the params are always valid CStrings owned by or borrowed into them
+        hash_bytes(hasher, unsafe { CStr::from_ptr(ptr) }.to_bytes());
+    }
+}
+
+fn hash_params(hasher: &mut Sha1, params: &FullParams) {
+    let fp = &params.fp;
+    let ints = [
+        fp.strategy as i32,
+        fp.n_max_text_ctx,
+        fp.offset_ms,
+        fp.duration_ms,
+        fp.max_len,
+        fp.max_tokens,
+        fp.audio_ctx,
+        fp.greedy.best_of,
+        fp.beam_search.beam_size,
+        fp.vad_params.min_speech_duration_ms,
+        fp.vad_params.min_silence_duration_ms,
+        fp.vad_params.speech_pad_ms,
+    ];
+    let floats = [
+        fp.thold_pt,
+        fp.thold_ptsum,
+        fp.temperature,
+        fp.max_initial_ts,
+        fp.length_penalty,
+        fp.temperature_inc,
+        fp.entropy_thold,
+        fp.logprob_thold,
+        fp.no_speech_thold,
+        fp.beam_search.patience,
+        fp.grammar_penalty,
+        fp.vad_params.threshold,
+        fp.vad_params.max_speech_duration_s,
+        fp.vad_params.samples_overlap,
+    ];
+    let flags = [
+        fp.translate,
+        fp.no_context,
+        fp.no_timestamps,
+        fp.single_segment,
+        fp.token_timestamps,
+        fp.split_on_word,
+        fp.tdrz_enable,
+        fp.detect_language,
+        fp.suppress_blank,
+        fp.suppress_nst,
+        fp.vad,
+        params.pad_short_audio,
+    ];
+    for v in ints {
+        hasher.update(&v.to_le_bytes());
+    }
+    for v in floats {
+        hasher.update(&v.to_bits().to_le_bytes());
+    }
+    hasher.update(&flags.map(u8::from));
+
+    hash_c_str(hasher, fp.suppress_regex);
+    hash_c_str(hasher, fp.initial_prompt);
+    hash_c_str(hasher, fp.language);
+    hash_c_str(hasher, fp.vad_model_path);
+
+    let prompt_tokens = if fp.prompt_tokens.is_null() || fp.prompt_n_tokens <= 0 {
+        &[][..]
+    } else {
+        // SAFETY: set from a slice that outlives the params in `set_tokens`
+        unsafe { std::slice::from_raw_parts(fp.prompt_tokens, fp.prompt_n_tokens as usize) }
+    };
+    hasher.update(&(prompt_tokens.len() as u64).to_le_bytes());
+    for token in prompt_tokens {
+        hasher.update(&token.to_le_bytes());
+    }
+
+    match params.context_compression {
+        Some(compression) => {
+            hasher.update(&[1]);
+            hasher.update(&(compression.tail_tokens as u64).to_le_bytes());
+            hasher.update(&(compression.max_entities as u64).to_le_bytes());
+        }
+        None => hasher.update(&[0]),
+    }
+
+    let grammar = params.grammar.as_ref().map_or(&[][..], |g| &g.elements);
+    hasher.update(&(grammar.len() as u64).to_le_bytes());
+    hasher.update(&(fp.i_start_rule as u64).to_le_bytes());
+    for element in grammar {
+        hasher.update(&element.type_.to_le_bytes());
+        hasher.update(&element.value.to_le_bytes());
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\t' => out.push_str("\\t"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn unescape(text: &str) -> Option<String> {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        out.push(match chars.next()? {
+            '\\' => '\\',
+            't' => '\t',
+            'n' => '\n',
+            'r' => '\r',
+            _ => return None,
+        });
+    }
+    Some(out)
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// One line per segment: start, end, no-speech probability, speaker turn flag, speaker and text,
+/// tab separated. The speaker is prefixed with `+` if present, so an empty name can be told apart from none.
+/// Each segment line is followed by one line per token, starting with `~`: id, p, plog, t0, t1, t_dtw,
+/// special flag, raw bytes in hex and text.
+fn encode(transcript: &Transcript) -> String {
+    let mut out = format!("{}\n", FILE_HEADER);
+    for s in transcript {
+        let speaker = s
+            .speaker
+            .as_deref()
+            .map(|name| format!("+{}", escape(name)))
+            .unwrap_or_default();
+        out.push_str(&format!(
+            "{}\t{}\t{}\t{}\t{}\t{}\n",
+            s.start,
+            s.end,
+            s.no_speech_probability,
+            u8::from(s.speaker_turn_next),
+            speaker,
+            escape(&s.text)
+        ));
+        for t in &s.tokens {
+            out.push_str(&format!(
+                "~\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\n",
+                t.id,
+                t.p,
+                t.plog,
+                t.t0,
+                t.t1,
+                t.t_dtw,
+                u8::from(t.special),
+                to_hex(&t.bytes),
+                escape(&t.text)
+            ));
+        }
+    }
+    out
+}
+
+fn decode_segment(line: &str) -> Option<TranscriptSegment> {
+    let mut fields = line.splitn(6, '\t');
+    let mut segment = TranscriptSegment::new(
+        fields.next()?.parse().ok()?,
+        fields.next()?.parse().ok()?,
+        String::new(),
+    );
+    segment.no_speech_probability = fields.next()?.parse().ok()?;
+    segment.speaker_turn_next = fields.next()? == "1";
+    segment.speaker = match fields.next()? {
+        "" => None,
+        speaker => Some(unescape(speaker.strip_prefix('+')?)?),
+    };
+    segment.text = unescape(fields.next()?)?;
+    Some(segment)
+}
+
+fn decode_token(fields: &str) -> Option<TranscriptToken> {
+    let mut fields = fields.splitn(9, '\t');
+    Some(TranscriptToken {
+        id: fields.next()?.parse().ok()?,
+        p: fields.next()?.parse().ok()?,
+        plog: fields.next()?.parse().ok()?,
+        t0: fields.next()?.parse().ok()?,
+        t1: fields.next()?.parse().ok()?,
+        t_dtw: fields.next()?.parse().ok()?,
+        special: fields.next()? == "1",
+        bytes: from_hex(fields.next()?)?,
+        text: unescape(fields.next()?)?,
+    })
+}
+
+fn decode(contents: &str) -> Option<Transcript> {
+    let mut lines = contents.lines();
+    if lines.next()? != FILE_HEADER {
+        return None;
+    }
+    let mut segments: Vec<TranscriptSegment> = Vec::new();
+    for line in lines {
+        match line.strip_prefix("~\t") {
+            Some(token) => segments.last_mut()?.tokens.push(decode_token(token)?),
+            None => segments.push(decode_segment(line)?),
+        }
+    }
+    Some(Transcript::new(segments))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{ContextCompression, SamplingStrategy};
+
+    #[test]
+    fn entries_round_trip() {
+        let dir = std::env::temp_dir().join(format!("whisper-rs-cache-{}", std::process::id()));
+        let cache = TranscriptCache::new(&dir).unwrap();
+        let key = CacheKey("0123456789abcdef0123456789abcdef01234567".into());
+
+        let mut segment = TranscriptSegment::new(120, 480, " tabs\tand\\slashes\n");
+        segment.no_speech_probability = 0.25;
+        segment.speaker_turn_next = true;
+        segment.speaker = Some("agent".into());
+        segment.tokens = vec![TranscriptToken {
+            id: 50364,
+            text: "[_BEG_]".into(),
+            bytes: b"[_BEG_]".to_vec(),
+            p: 0.875,
+            plog: -0.13353139,
+            t0: 120,
+            t1: 120,
+            t_dtw: -1,
+            special: true,
+        }];
+        segment.tokens.push(TranscriptToken {
+            text: "\u{fffd}".into(),
+            bytes: vec![0xe6, 0x9c],
+            ..Default::default()
+        });
+        let transcript = Transcript::new(vec![segment, TranscriptSegment::new(480, 500, "")]);
+
+        assert_eq!(cache.get(&key).unwrap(), None);
+        cache.insert(&key, &transcript).unwrap();
+        assert_eq!(cache.get(&key).unwrap(), Some(transcript));
+
+        assert!(cache.remove(&key).unwrap());
+        assert!(!cache.remove(&key).unwrap());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn keys_cover_the_rust_side_parameters() {
+        let audio = [0.0f32; 16];
+        let params = FullParams::new(SamplingStrategy::default());
+        let key = TranscriptCache::key("model", &params, &audio);
+        assert_eq!(TranscriptCache::key("model", &params.clone(), &audio), key);
+
+        let mut unpadded = params.clone();
+        unpadded.set_pad_short_audio(false);
+        assert_ne!(TranscriptCache::key("model", &unpadded, &audio), key);
+
+        let mut compressed = params.clone();
+        compressed.set_context_compression(ContextCompression::new());
+        let compressed_key = TranscriptCache::key("model", &compressed, &audio);
+        assert_ne!(compressed_key, key);
+        compressed.set_context_compression(ContextCompression::new().tail_tokens(8));
+        assert_ne!(
+            TranscriptCache::key("model", &compressed, &audio),
+            compressed_key
+        );
+    }
+
+    #[cfg(feature = "test-stub")]
+    #[test]
+    fn runs_following_earlier_text_are_not_cached() {
+        use crate::stub::StubContext;
+
+        let dir = std::env::temp_dir().join(format!("whisper-rs-context-{}", std::process::id()));
+        let ctx = StubContext::with_text(0, 100, "hello");
+        let cache = TranscriptCache::new(&dir).unwrap();
+        let mut transcriber = CachedTranscriber::new(ctx.create_state().unwrap(), cache, "stub");
+        let audio = vec![0.0f32; 16000];
+        let mut params = FullParams::new(SamplingStrategy::default());
+
+        params.set_no_context(false);
+        transcriber.transcribe(params.clone(), &audio).unwrap();
+        transcriber.transcribe(params.clone(), &audio).unwrap();
+        assert_eq!(ctx.runs(), 2);
+
+        params.set_no_context(true);
+        transcriber.transcribe(params.clone(), &audio).unwrap();
+        transcriber.transcribe(params, &audio).unwrap();
+        assert_eq!(ctx.runs(), 3);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn outdated_entries_are_ignored() {
+        assert_eq!(decode("whisper-rs transcript 0\n0\t1\t0\t0\t\thi\n"), None);
+        assert_eq!(
+            decode(&format!("{}\n0\t1\tx\t0\t\thi\n", FILE_HEADER)),
+            None
+        );
+        assert_eq!(
+            decode(&format!("{}\n", FILE_HEADER)),
+            Some(Transcript::default())
+        );
+    }
+}
@@ -0,0 +1,291 @@
+use crate::{TranscriptSegment, TranscriptToken};
+use std::fmt;
+use std::str::FromStr;
+
+/// Probabilities are clamped to this distance from 0 and 1 before taking their logit.
+const EPSILON: f32 = 1e-6;
+
+/// Maps the probabilities Whisper reports for its tokens to calibrated confidences.
+///
+/// Raw token probabilities are overconfident: a token reported at 0.9 is right much less
+/// than 90% of the time, so thresholds on them are hard to choose. A calibration first rescales
+/// the log-odds of each probability by a temperature, then optionally maps the result through
+/// a table of observed accuracies. Both are fitted with [`Self::fit`] on tokens whose correctness
+/// is known, e.g. by aligning transcripts of your own recordings with their reference text.
+///
+/// Calibrations depend on the model and the audio they were fitted on. They can be saved and
+/// loaded as text with [`fmt::Display`] and [`FromStr`].
+///
+/// # Examples
+/// ```
+/// # use whisper_rs::Calibration;
+/// // (probability, whether the token was correct)
+/// let observed = [(0.95, true), (0.9, false), (0.85, true), (0.6, false), (0.3, false)];
+/// let calibration = Calibration::fit(&observed, 2);
+/// let saved = calibration.to_string();
+/// let loaded: Calibration = saved.parse().unwrap();
+/// assert_eq!(loaded.calibrate(0.9), calibration.calibrate(0.9));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Calibration {
+    temperature: f32,
+    /// Upper bounds of the bins of temperature-scaled probabilities, with the accuracy in each.
+    /// Sorted, and the accuracies never decrease.
+    table: Vec<(f32, f32)>,
+}
+
+impl Default for Calibration {
+    fn default() -> Self {
+        Self::temperature(1.0)
+    }
+}
+
+impl Calibration {
+    /// A calibration that only applies temperature scaling. Temperatures above 1 make
+    /// probabilities less extreme, which Whisper's usually need.
+    pub fn temperature(temperature: f32) -> Self {
+        Self {
+            temperature: temperature.max(EPSILON),
+            table: Vec::new(),
+        }
+    }
+
+    /// Fit a calibration to tokens whose correctness is known.
+    ///
+    /// The temperature is chosen to minimize the log loss of the scaled probabilities. With
+    /// `bins` above 0, the scaled probabilities are then split into that many bins of equal size,
+    /// and the accuracy observed in each becomes its calibrated value, so use bins only with
+    /// enough samples for each to be meaningful, at least a few hundred.
+    ///
+    /// # Arguments
+    /// * samples: The probability Whisper reported for each token, and whether it was correct.
+    /// * bins: Number of bins in the table. 0 for temperature scaling only.
+    pub fn fit(samples: &[(f32, bool)], bins: usize) -> Self {
+        let mut calibration = Self::temperature(fit_temperature(samples));
+        if bins == 0 || samples.is_empty() {
+            return calibration;
+        }
+
+        let mut scaled: Vec<(f32, bool)> = samples
+            .iter()
+            .map(|&(p, correct)| (calibration.scale(p), correct))
+            .collect();
+        scaled.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        // pool adjacent bins until their accuracy never decreases
+        let mut pooled: Vec<(f32, usize, usize)> = Vec::new();
+        for bin in scaled.chunks(scaled.len().div_ceil(bins)) {
+            let upper = bin.last().map_or(1.0, |s| s.0);
+            let correct = bin.iter().filter(|s| s.1).count();
+            pooled.push((upper, correct, bin.len()));
+            while let [.., (_, c0, n0), (upper, c1, n1)] = pooled[..] {
+                if (c0 * n1) <= (c1 * n0) {
+                    break;
+                }
+                pooled.truncate(pooled.len() - 2);
+                pooled.push((upper, c0 + c1, n0 + n1));
+            }
+        }
+        if let Some(last) = pooled.last_mut() {
+            last.0 = 1.0;
+        }
+        calibration.table = pooled
+            .into_iter()
+            .map(|(upper, correct, n)| (upper, correct as f32 / n as f32))
+            .collect();
+        calibration
+    }
+
+    /// The fitted temperature.
+    pub fn get_temperature(&self) -> f32 {
+        self.temperature
+    }
+
+    /// Calibrated confidence for a raw token probability.
+    pub fn calibrate(&self, p: f32) -> f32 {
+        let scaled = self.scale(p);
+        if self.table.is_empty() {
+            return scaled;
+        }
+        let i = self.table.partition_point(|&(upper, _)| upper < scaled);
+        self.table[i.min(self.table.len() - 1)].1
+    }
+
+    /// Calibrated confidence of a token.
+    pub fn token_confidence(&self, token: &TranscriptToken) -> f32 {
+        self.calibrate(token.p)
+    }
+
+    /// Mean calibrated confidence of the text tokens of a segment,
+    /// or `None` if it has none, e.g. because tokens weren't kept.
+    pub fn segment_confidence(&self, segment: &TranscriptSegment) -> Option<f32> {
+        let confidences: Vec<f32> = segment
+            .tokens
+            .iter()
+            .filter(|t| !t.special)
+            .map(|t| self.token_confidence(t))
+            .collect();
+        (!confidences.is_empty())
+            .then(|| confidences.iter().sum::<f32>() / confidences.len() as f32)
+    }
+
+    fn scale(&self, p: f32) -> f32 {
+        sigmoid(logit(p) / self.temperature)
+    }
+}
+
+/// How far confidences are from the observed accuracy on average, weighted by
+/// the number of samples in each of `bins` equal-width bins. 0 is perfectly calibrated.
+///
+/// Use it to compare calibrations on samples that weren't used to fit them.
+pub fn expected_calibration_error(
+    calibration: &Calibration,
+    samples: &[(f32, bool)],
+    bins: usize,
+) -> f32 {
+    let bins = bins.max(1);
+    let mut sums = vec![(0.0f32, 0usize, 0usize); bins];
+    for &(p, correct) in samples {
+        let confidence = calibration.calibrate(p);
+        let bin = ((confidence * bins as f32) as usize).min(bins - 1);
+        sums[bin].0 += confidence;
+        sums[bin].1 += correct as usize;
+        sums[bin].2 += 1;
+    }
+    let total = samples.len().max(1) as f32;
+    sums.iter()
+        .filter(|(_, _, n)| *n > 0)
+        .map(|&(confidence, correct, _)| (confidence - correct as f32).abs() / total)
+        .sum()
+}
+
+fn logit(p: f32) -> f32 {
+    let p = p.clamp(EPSILON, 1.0 - EPSILON);
+    (p / (1.0 - p)).ln()
+}
+
+fn sigmoid(x: f32) -> f32 {
+    1.0 / (1.0 + (-x).exp())
+}
+
+/// Temperature minimizing the log loss of `samples`, found by golden-section search on its log.
+fn fit_temperature(samples: &[(f32, bool)]) -> f32 {
+    let loss = |log_t: f32| {
+        let t = log_t.exp();
+        samples
+            .iter()
+            .map(|&(p, correct)| {
+                let q = sigmoid(logit(p) / t).clamp(EPSILON, 1.0 - EPSILON);
+                -if correct { q.ln() } else { (1.0 - q).ln() }
+            })
+            .sum::<f32>()
+    };
+
+    let ratio = (5f32.sqrt() - 1.0) / 2.0;
+    // temperatures from 0.05 to 20
+    let (mut lo, mut hi) = (-3.0f32, 3.0f32);
+    for _ in 0..60 {
+        let a = hi - ratio * (hi - lo);
+        let b = lo + ratio * (hi - lo);
+        if loss(a) < loss(b) {
+            hi = b;
+        } else {
+            lo = a;
+        }
+    }
+    ((lo + hi) / 2.0).exp()
+}
+
+/// The text form of a calibration: a `temperature` line, then one `bin` line per table entry
+/// with its upper bound and accuracy.
+impl fmt::Display for Calibration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "temperature {}", self.temperature)?;
+        for (upper, accuracy) in &self.table {
+            writeln!(f, "bin {} {}", upper, accuracy)?;
+        }
+        Ok(())
+    }
+}
+
+/// Error parsing a [`Calibration`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CalibrationParseError {
+    /// The line that couldn't be parsed, starting at 1.
+    pub line: usize,
+}
+
+impl fmt::Display for CalibrationParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid calibration on line {}", self.line)
+    }
+}
+
+impl std::error::Error for CalibrationParseError {}
+
+impl FromStr for Calibration {
+    type Err = CalibrationParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut temperature = None;
+        let mut table = Vec::new();
+        for (i, line) in s.lines().enumerate() {
+            let error = CalibrationParseError { line: i + 1 };
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let number = |s: &str| s.parse::<f32>().ok().filter(|x| x.is_finite());
+            match fields[..] {
+                [] => {}
+                ["temperature", t] => {
+                    temperature = Some(number(t).filter(|&t| t > 0.0).ok_or(error)?)
+                }
+                ["bin", upper, accuracy] => {
+                    let entry = number(upper).zip(number(accuracy)).ok_or(error.clone())?;
+                    let sorted = table
+                        .last()
+                        .is_none_or(|&(u, a)| u <= entry.0 && a <= entry.1);
+                    if !sorted {
+                        return Err(error);
+                    }
+                    table.push(entry);
+                }
+                _ => return Err(error),
+            }
+        }
+        let temperature = temperature.ok_or(CalibrationParseError { line: 1 })?;
+        Ok(Self { temperature, table })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn fitting_corrects_overconfidence() {
+        // tokens reported at 0.9 that are right 60% of the time
+        let samples: Vec<_> = (0..1000)
+            .map(|i| {
+                if i % 2 == 0 {
+                    (0.9, i % 10 < 6)
+                } else {
+                    (0.2, i % 10 == 1)
+                }
+            })
+            .collect();
+        let raw = Calibration::default();
+        let fitted = Calibration::fit(&samples, 4);
+        assert!(fitted.get_temperature() > 1.0);
+        assert!(
+            expected_calibration_error(&fitted, &samples, 10)
+                < expected_calibration_error(&raw, &samples, 10) / 2.0
+        );
+        assert!((fitted.calibrate(0.9) - 0.6).abs() < 0.05);
+
+        let parsed: Calibration = fitted.to_string().parse().unwrap();
+        assert_eq!(parsed, fitted);
+        assert_eq!(
+            "bin 1 1".parse::<Calibration>(),
+            Err(CalibrationParseError { line: 1 })
+        );
+    }
+}
@@ -0,0 +1,134 @@
+use crate::compat::{preset_matches, N_VOCAB_MULTILINGUAL};
+use crate::{DtwModelPreset, Language, WhisperContext, WhisperInnerContext};
+use std::ffi::c_int;
+
+/// What a loaded model can do, derived from its dimensions, see [`WhisperContext::capabilities`].
+///
+/// The crate consults these instead of assuming the dimensions of the original Whisper models,
+/// so new model families work, with these defaults, without the crate knowing about them:
+/// the mel spectrogram size comes from the model, and features a model can't support are
+/// reported as errors instead of silently producing bad output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ModelCapabilities {
+    /// Number of mel bands the model expects, 80 for most models and 128 for large-v3 and later.
+    /// [`crate::WhisperState::set_mel`] checks the spectrogram against this.
+    pub n_mels: c_int,
+    /// Whether the model can transcribe languages other than English.
+    pub multilingual: bool,
+    /// Whether the model can translate, see [`crate::FullParams::set_translate`].
+    /// English-only models can't.
+    pub translation: bool,
+    /// Whether the decoder has fewer layers than the encoder, as in large-v3-turbo and the
+    /// distilled models. These were only trained to transcribe, so their translations are often
+    /// unreliable; [`crate::WhisperState::full`] still runs them, but warns once per state.
+    pub pruned_decoder: bool,
+    /// The built-in DTW alignment heads for this model, if any, for
+    /// [`crate::DtwMode::ModelPreset`]. `None` for models without a preset, including fine-tunes
+    /// and distilled models, and for large-v1 and large-v2, which can't be told apart;
+    /// use [`crate::DtwMode::TopMost`] for those.
+    pub dtw_preset: Option<DtwModelPreset>,
+}
+
+impl ModelCapabilities {
+    pub(crate) fn of(ctx: &WhisperInnerContext) -> Self {
+        let n_audio_layer = ctx.model_n_audio_layer();
+        let n_text_layer = ctx.model_n_text_layer();
+        let n_vocab = ctx.model_n_vocab();
+        let multilingual = ctx.is_multilingual();
+        Self {
+            n_mels: ctx.model_n_mels(),
+            multilingual,
+            translation: multilingual,
+            pruned_decoder: n_text_layer < n_audio_layer,
+            dtw_preset: find_preset(n_audio_layer, n_text_layer, n_vocab),
+        }
+    }
+}
+
+/// The only preset matching these dimensions.
+fn find_preset(
+    n_audio_layer: c_int,
+    n_text_layer: c_int,
+    n_vocab: c_int,
+) -> Option<DtwModelPreset> {
+    use DtwModelPreset::*;
+    let presets = [
+        TinyEn,
+        Tiny,
+        BaseEn,
+        Base,
+        SmallEn,
+        Small,
+        MediumEn,
+        Medium,
+        LargeV1,
+        LargeV2,
+        LargeV3,
+        LargeV3Turbo,
+    ];
+    let mut matching = presets
+        .into_iter()
+        .filter(|preset| preset_matches(preset, n_audio_layer, n_text_layer, n_vocab));
+    match (matching.next(), matching.next()) {
+        (Some(preset), None) => Some(preset),
+        _ => None,
+    }
+}
+
+/// Number of language tokens in a vocabulary of `n_vocab` tokens.
+fn language_count(n_vocab: c_int, multilingual: bool) -> usize {
+    if !multilingual {
+        return 1;
+    }
+    // the multilingual vocabularies end with the language tokens, then the task and
+    // timestamp tokens; large-v3 added Cantonese
+    (n_vocab - N_VOCAB_MULTILINGUAL + 99).max(1) as usize
+}
+
+impl WhisperContext {
+    /// What the loaded model can do, see [`ModelCapabilities`].
+    pub fn capabilities(&self) -> ModelCapabilities {
+        ModelCapabilities::of(self.inner())
+    }
+
+    /// The languages the loaded model can transcribe, in order of their language id,
+    /// e.g. to fill a language selection.
+    ///
+    /// English-only models only yield [`Language::English`]. Multilingual models yield all
+    /// languages they have a token for, which excludes Cantonese before large-v3.
+    ///
+    /// # C++ equivalent
+    /// `int whisper_is_multilingual(struct whisper_context * ctx)`,
+    /// `int whisper_lang_max_id()` and `const char * whisper_lang_str(int id)`
+    pub fn supported_languages(&self) -> impl Iterator<Item = Language> {
+        let count = language_count(self.model_n_vocab(), self.is_multilingual());
+        (0..=crate::get_lang_max_id())
+            .take(count)
+            .filter_map(|id| crate::get_lang_str(id)?.parse().ok())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn presets_are_found_only_when_unambiguous() {
+        assert_eq!(find_preset(4, 4, 51865), Some(DtwModelPreset::Tiny));
+        assert_eq!(
+            find_preset(32, 4, 51866),
+            Some(DtwModelPreset::LargeV3Turbo)
+        );
+        // large-v1 and large-v2
+        assert_eq!(find_preset(32, 32, 51865), None);
+        // distil-large-v3
+        assert_eq!(find_preset(32, 2, 51866), None);
+    }
+
+    #[test]
+    fn languages_are_counted_from_the_vocabulary() {
+        assert_eq!(language_count(51864, false), 1);
+        assert_eq!(language_count(51865, true), 99);
+        assert_eq!(language_count(51866, true), 100);
+    }
+}
@@ -0,0 +1,252 @@
+use crate::{FullParams, Transcribe, Transcript};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/// Transcribes the two channels of a call recording separately and labels each segment with its channel.
+///
+/// Call-center systems usually record the agent and the customer on separate channels, which makes
+/// speaker attribution exact and much cheaper than diarization. Overlapping speech also comes out
+/// cleanly, since each channel only holds one voice.
+///
+/// # Examples
+/// ```no_run
+/// # use whisper_rs::{split_stereo_audio, DualChannel, FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters};
+/// # let mut ctx = WhisperContext::new_with_params("model.bin", WhisperContextParameters::default()).unwrap();
+/// # let recording = vec![0.0f32; 32000];
+/// let (agent, customer) = split_stereo_audio(&recording).unwrap();
+/// let params = FullParams::new(SamplingStrategy::default());
+/// let transcript = DualChannel::new("agent", "customer")
+///     .transcribe(&mut ctx, params, &agent, &customer)
+///     .unwrap();
+/// for segment in &transcript {
+///     println!("{}: {}", segment.speaker.as_deref().unwrap_or("?"), segment.text);
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DualChannel {
+    /// Speaker label for segments from the left channel.
+    pub left: String,
+    /// Speaker label for segments from the right channel.
+    pub right: String,
+}
+
+impl Default for DualChannel {
+    fn default() -> Self {
+        Self::new("left", "right")
+    }
+}
+
+impl DualChannel {
+    pub fn new(left: impl Into<String>, right: impl Into<String>) -> Self {
+        Self {
+            left: left.into(),
+            right: right.into(),
+        }
+    }
+
+    /// Transcribe both channels with `backend` and interleave the results by start time,
+    /// see [`merge_by_time`].
+    ///
+    /// Both channels are transcribed with a copy of `params`.
+    pub fn transcribe<T: Transcribe>(
+        &self,
+        backend: &mut T,
+        params: FullParams<'_, '_>,
+        left: &[f32],
+        right: &[f32],
+    ) -> Result<Transcript, T::Error> {
+        let left_transcript = backend.transcribe(params.clone(), left)?;
+        let right_transcript = backend.transcribe(params, right)?;
+        Ok(merge_by_time([
+            (self.left.as_str(), left_transcript),
+            (self.right.as_str(), right_transcript),
+        ]))
+    }
+}
+
+/// Transcribes one track per speaker concurrently, such as the per-participant recordings of a
+/// conferencing bridge, and merges them into a single labelled timeline.
+///
+/// Each worker thread gets a backend of its own from a factory, so with
+/// [`crate::WhisperContext::create_state`] all of them share the weights of one model.
+///
+/// # Examples
+/// ```no_run
+/// # use whisper_rs::{FullParams, MultiTrack, SamplingStrategy, WhisperContext, WhisperContextParameters};
+/// # let ctx = WhisperContext::new_with_params("model.bin", WhisperContextParameters::default()).unwrap();
+/// # let (alice, bob, carol) = (vec![0.0f32; 16000], vec![0.0f32; 16000], vec![0.0f32; 16000]);
+/// let params = FullParams::new(SamplingStrategy::default());
+/// let transcript = MultiTrack::new()
+///     .max_parallel(2)
+///     .transcribe(
+///         || ctx.create_state(),
+///         params,
+///         [("Alice", &alice[..]), ("Bob", &bob[..]), ("Carol", &carol[..])],
+///     )
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MultiTrack {
+    max_parallel: usize,
+}
+
+impl Default for MultiTrack {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MultiTrack {
+    pub fn new() -> Self {
+        Self { max_parallel: 4 }
+    }
+
+    /// Set how many tracks are transcribed at the same time, each on a thread and state of its own.
+    /// Every run also uses [`FullParams::set_n_threads`] threads, so keep the product near the
+    /// number of cores.
+    ///
+    /// Defaults to 4.
+    pub fn max_parallel(mut self, max_parallel: usize) -> Self {
+        self.max_parallel = max_parallel.max(1);
+        self
+    }
+
+    /// Transcribe every `(speaker, audio)` track and interleave the results by start time,
+    /// see [`merge_by_time`]. All tracks must start at the same moment.
+    ///
+    /// `new_backend` is called once per worker thread. Each track is transcribed with a copy of `params`.
+    ///
+    /// # Returns
+    /// The merged transcript, or the first error from creating a backend or transcribing.
+    pub fn transcribe<'t, T, F>(
+        &self,
+        new_backend: F,
+        params: FullParams<'_, '_>,
+        tracks: impl IntoIterator<Item = (&'t str, &'t [f32])>,
+    ) -> Result<Transcript, T::Error>
+    where
+        T: Transcribe,
+        T::Error: Send,
+        F: Fn() -> Result<T, T::Error> + Sync,
+    {
+        let tracks: Vec<_> = tracks.into_iter().collect();
+        let results: Vec<Mutex<Option<Transcript>>> =
+            tracks.iter().map(|_| Mutex::new(None)).collect();
+        let next = AtomicUsize::new(0);
+        let workers = self.max_parallel.min(tracks.len());
+
+        let worker = || -> Result<(), T::Error> {
+            let mut backend = new_backend()?;
+            loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let Some((_, audio)) = tracks.get(i) else {
+                    return Ok(());
+                };
+                let transcript = backend.transcribe(params.clone(), audio)?;
+                *results[i].lock().unwrap() = Some(transcript);
+            }
+        };
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..workers).map(|_| scope.spawn(worker)).collect();
+            handles
+                .into_iter()
+                .map(|handle| {
+                    handle
+                        .join()
+                        .unwrap_or_else(|e| std::panic::resume_unwind(e))
+                })
+                .collect::<Result<Vec<()>, _>>()
+        })?;
+
+        Ok(merge_by_time(tracks.iter().zip(results).map(
+            |((speaker, _), result)| {
+                let transcript = result.into_inner().unwrap().unwrap_or_default();
+                (*speaker, transcript)
+            },
+        )))
+    }
+}
+
+/// Merge transcripts of separate speakers into one, ordered by start time.
+///
+/// Each segment is labelled with the speaker of its transcript, and
+/// [`crate::TranscriptSegment::speaker_turn_next`] is set wherever the next segment
+/// belongs to someone else. Segments starting at the same time keep the order of `transcripts`.
+pub fn merge_by_time<'a>(
+    transcripts: impl IntoIterator<Item = (&'a str, Transcript)>,
+) -> Transcript {
+    let mut segments: Vec<_> = transcripts
+        .into_iter()
+        .flat_map(|(speaker, transcript)| {
+            transcript.segments.into_iter().map(move |mut segment| {
+                segment.speaker = Some(speaker.to_owned());
+                segment
+            })
+        })
+        .collect();
+    segments.sort_by_key(|segment| segment.start);
+
+    for i in 1..segments.len() {
+        segments[i - 1].speaker_turn_next = segments[i - 1].speaker != segments[i].speaker;
+    }
+    if let Some(last) = segments.last_mut() {
+        last.speaker_turn_next = false;
+    }
+    Transcript::new(segments)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::TranscriptSegment;
+
+    #[test]
+    fn channels_are_interleaved_and_labelled() {
+        let agent = Transcript::new(vec![
+            TranscriptSegment::new(0, 200, " Hello, how can I help?"),
+            TranscriptSegment::new(500, 700, " Sure."),
+            TranscriptSegment::new(700, 900, " One moment."),
+        ]);
+        let customer =
+            Transcript::new(vec![TranscriptSegment::new(250, 450, " My order is late.")]);
+
+        let merged = merge_by_time([("agent", agent), ("customer", customer)]);
+
+        let labelled: Vec<_> = merged
+            .iter()
+            .map(|s| (s.start, s.speaker.as_deref().unwrap(), s.speaker_turn_next))
+            .collect();
+        assert_eq!(
+            labelled,
+            vec![
+                (0, "agent", true),
+                (250, "customer", true),
+                (500, "agent", false),
+                (700, "agent", false)
+            ]
+        );
+    }
+
+    #[cfg(feature = "test-stub")]
+    #[test]
+    fn tracks_share_one_model() {
+        use crate::stub::StubContext;
+        use crate::SamplingStrategy;
+
+        let ctx = StubContext::with_text(0, 100, " hi");
+        let params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
+        let audio = [0.0; 16000];
+        let transcript = MultiTrack::new()
+            .max_parallel(2)
+            .transcribe(
+                || ctx.create_state(),
+                params,
+                [("a", &audio[..]), ("b", &audio[..]), ("c", &audio[..])],
+            )
+            .unwrap();
+
+        let speakers: Vec<_> = transcript.iter().map(|s| s.speaker.as_deref()).collect();
+        assert_eq!(speakers, [Some("a"), Some("b"), Some("c")]);
+        assert_eq!(ctx.runs(), 3);
+    }
+}
@@ -4,6 +4,9 @@ macro_rules! generic_error {
         log::error!($($expr)*);
         #[cfg(feature = "tracing_backend")]
         tracing::error!($($expr)*);
+        // keep arguments "used" when no logging backend is enabled
+        #[cfg(not(any(feature = "log_backend", feature = "tracing_backend")))]
+        let _ = format_args!($($expr)*);
     };
 }
 
@@ -13,6 +16,9 @@ macro_rules! generic_warn {
         log::warn!($($expr)*);
         #[cfg(feature = "tracing_backend")]
         tracing::warn!($($expr)*);
+        // keep arguments "used" when no logging backend is enabled
+        #[cfg(not(any(feature = "log_backend", feature = "tracing_backend")))]
+        let _ = format_args!($($expr)*);
     }
 }
 
@@ -22,6 +28,9 @@ macro_rules! generic_info {
         log::info!($($expr)*);
         #[cfg(feature = "tracing_backend")]
         tracing::info!($($expr)*);
+        // keep arguments "used" when no logging backend is enabled
+        #[cfg(not(any(feature = "log_backend", feature = "tracing_backend")))]
+        let _ = format_args!($($expr)*);
     }
 }
 
@@ -31,6 +40,9 @@ macro_rules! generic_debug {
         log::debug!($($expr)*);
         #[cfg(feature = "tracing_backend")]
         tracing::debug!($($expr)*);
+        // keep arguments "used" when no logging backend is enabled
+        #[cfg(not(any(feature = "log_backend", feature = "tracing_backend")))]
+        let _ = format_args!($($expr)*);
     }
 }
 
@@ -40,6 +52,9 @@ macro_rules! generic_trace {
         log::trace!($($expr)*);
         #[cfg(feature = "tracing_backend")]
         tracing::trace!($($expr)*);
+        // keep arguments "used" when no logging backend is enabled
+        #[cfg(not(any(feature = "log_backend", feature = "tracing_backend")))]
+        let _ = format_args!($($expr)*);
     }
 }
 
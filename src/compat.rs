@@ -0,0 +1,435 @@
+use crate::backend_info::{gpu_count, selected_device, DeviceKind};
+use crate::common_logging::generic_warn;
+use crate::{
+    DtwFallback, DtwMode, DtwModelPreset, WhisperContextParameters, WhisperError,
+    WhisperInnerContext,
+};
+use std::ffi::c_int;
+use std::fmt;
+
+/// Vocabulary size of the English-only models.
+const N_VOCAB_EN: c_int = 51864;
+/// Vocabulary size of the multilingual models before large-v3.
+pub(crate) const N_VOCAB_MULTILINGUAL: c_int = 51865;
+/// Vocabulary size of large-v3 and its derivatives, which added a language.
+const N_VOCAB_V3: c_int = 51866;
+/// Size of each attention head in every Whisper model.
+const HEAD_DIM: i64 = 64;
+
+/// A combination of context parameters that whisper.cpp would silently ignore or fail on,
+/// see [`WhisperError::UnsupportedConfiguration`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnsupportedConfiguration {
+    /// DTW token timestamps were requested along with flash attention,
+    /// which whisper.cpp turns them off for.
+    DtwWithFlashAttn,
+    /// Flash attention was requested, but the device the model runs on has no kernel for it.
+    FlashAttnUnsupported,
+    /// [`WhisperContextParameters::gpu_device`] is not one of the GPUs found.
+    /// whisper.cpp would use the first GPU instead.
+    GpuDeviceNotFound { gpu_device: c_int, gpus: usize },
+    /// The DTW alignment head preset is for a different model from the one loaded.
+    DtwPresetMismatch,
+    /// The DTW alignment heads refer to layers or heads the loaded model doesn't have.
+    DtwHeadsOutOfRange,
+    /// Translation was requested from a model that can't translate,
+    /// see [`crate::ModelCapabilities::translation`].
+    TranslationUnsupported,
+    /// The DTW buffer of [`crate::DtwParameters::dtw_mem_size`] bytes is larger than the memory
+    /// available, and [`crate::DtwParameters::with_memory_fallback`] was turned off.
+    DtwMemoryUnavailable { dtw_mem_size: usize, available: u64 },
+    /// A state is estimated to need `required` bytes, more than the `budget` given,
+    /// see [`crate::WhisperContext::create_state_within`].
+    MemoryBudgetExceeded { budget: u64, required: u64 },
+}
+
+impl fmt::Display for UnsupportedConfiguration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DtwWithFlashAttn => write!(
+                f,
+                "DTW token timestamps can't be combined with flash attention"
+            ),
+            Self::FlashAttnUnsupported => write!(
+                f,
+                "flash attention is not supported by the device the model runs on"
+            ),
+            Self::GpuDeviceNotFound { gpu_device, gpus } => {
+                write!(
+                    f,
+                    "GPU {} requested, but {} GPUs were found",
+                    gpu_device, gpus
+                )
+            }
+            Self::DtwPresetMismatch => write!(f, "the DTW preset is for a different model"),
+            Self::DtwHeadsOutOfRange => {
+                write!(f, "the DTW alignment heads don't exist in the loaded model")
+            }
+            Self::TranslationUnsupported => write!(f, "the loaded model can't translate"),
+            Self::DtwMemoryUnavailable {
+                dtw_mem_size,
+                available,
+            } => write!(
+                f,
+                "the DTW buffer of {} bytes doesn't fit in the {} bytes of memory available",
+                dtw_mem_size, available
+            ),
+            Self::MemoryBudgetExceeded { budget, required } => write!(
+                f,
+                "a state needs at least {} bytes, more than the budget of {} bytes",
+                required, budget
+            ),
+        }
+    }
+}
+
+/// Checks that can be made before loading a model.
+pub(crate) fn check_params(params: &WhisperContextParameters) -> Result<(), WhisperError> {
+    let unsupported = |reason| Err(WhisperError::UnsupportedConfiguration(reason));
+    let dtw = !matches!(params.dtw_parameters.mode, DtwMode::None);
+    if dtw && params.flash_attn {
+        return unsupported(UnsupportedConfiguration::DtwWithFlashAttn);
+    }
+
+    if params.use_gpu && params.gpu_device != 0 {
+        let gpus = gpu_count();
+        if gpus > 0 && !(0..gpus as c_int).contains(&params.gpu_device) {
+            return unsupported(UnsupportedConfiguration::GpuDeviceNotFound {
+                gpu_device: params.gpu_device,
+                gpus,
+            });
+        }
+    }
+
+    if params.flash_attn {
+        let device = selected_device(params.use_gpu, params.gpu_device);
+        if let Some(device) = device.filter(|d| d.info.kind != DeviceKind::Cpu) {
+            if !supports_flash_attn(device.handle) {
+                return unsupported(UnsupportedConfiguration::FlashAttnUnsupported);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Turn DTW off if its buffer is unlikely to be allocated, which whisper.cpp would abort on mid-run.
+///
+/// # Returns
+/// The fallback taken, if DTW timestamps were turned off.
+pub(crate) fn check_dtw_memory(
+    params: &mut WhisperContextParameters,
+) -> Result<Option<DtwFallback>, WhisperError> {
+    if matches!(params.dtw_parameters.mode, DtwMode::None) {
+        return Ok(None);
+    }
+    let fallback = dtw_memory_fallback(params, available_memory())?;
+    if let Some(fallback) = fallback {
+        generic_warn!(
+            "whisper-rs: only {} bytes of memory available for a DTW buffer of {} bytes, falling back to token timestamps",
+            fallback.available,
+            fallback.dtw_mem_size
+        );
+    }
+    Ok(fallback)
+}
+
+/// [`check_dtw_memory`] with `available` bytes of memory, if known.
+fn dtw_memory_fallback(
+    params: &mut WhisperContextParameters,
+    available: Option<u64>,
+) -> Result<Option<DtwFallback>, WhisperError> {
+    let dtw = &params.dtw_parameters;
+    let Some(available) = available else {
+        return Ok(None);
+    };
+    if dtw_fits(dtw.dtw_mem_size, available) {
+        return Ok(None);
+    }
+    if !dtw.memory_fallback() {
+        return Err(WhisperError::UnsupportedConfiguration(
+            UnsupportedConfiguration::DtwMemoryUnavailable {
+                dtw_mem_size: dtw.dtw_mem_size,
+                available,
+            },
+        ));
+    }
+    let fallback = DtwFallback {
+        dtw_mem_size: dtw.dtw_mem_size,
+        available,
+    };
+    params.dtw_parameters.mode = DtwMode::None;
+    Ok(Some(fallback))
+}
+
+/// Whether a DTW buffer fits, leaving half of the available memory for the model's own buffers,
+/// which are allocated once the model is loaded.
+fn dtw_fits(dtw_mem_size: usize, available: u64) -> bool {
+    dtw_mem_size as u64 <= available / 2
+}
+
+/// Memory the system can give to this process without swapping, if known.
+/// This is the smaller of what the system has available and what the process's cgroup allows.
+#[cfg(target_os = "linux")]
+fn available_memory() -> Option<u64> {
+    let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+    let line = meminfo.lines().find(|l| l.starts_with("MemAvailable:"))?;
+    let kib: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    let system = kib * 1024;
+    Some(cgroup_available_memory().map_or(system, |cgroup| cgroup.min(system)))
+}
+
+/// Memory the system can give to this process without swapping: its free, inactive and
+/// speculative pages.
+#[cfg(target_os = "macos")]
+fn available_memory() -> Option<u64> {
+    let out = std::process::Command::new("vm_stat").output().ok()?;
+    parse_vm_stat(&String::from_utf8_lossy(&out.stdout))
+}
+
+/// Physical memory the system has available.
+#[cfg(windows)]
+fn available_memory() -> Option<u64> {
+    #[repr(C)]
+    struct MemoryStatusEx {
+        length: u32,
+        memory_load: u32,
+        total_phys: u64,
+        avail_phys: u64,
+        total_page_file: u64,
+        avail_page_file: u64,
+        total_virtual: u64,
+        avail_virtual: u64,
+        avail_extended_virtual: u64,
+    }
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GlobalMemoryStatusEx(buffer: *mut MemoryStatusEx) -> i32;
+    }
+    let mut status = MemoryStatusEx {
+        length: std::mem::size_of::<MemoryStatusEx>() as u32,
+        memory_load: 0,
+        total_phys: 0,
+        avail_phys: 0,
+        total_page_file: 0,
+        avail_page_file: 0,
+        total_virtual: 0,
+        avail_virtual: 0,
+        avail_extended_virtual: 0,
+    };
+    // SAFETY: the struct has the layout of MEMORYSTATUSEX, with its length set as required
+    let ok = unsafe { GlobalMemoryStatusEx(&mut status) };
+    (ok != 0).then_some(status.avail_phys)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+fn available_memory() -> Option<u64> {
+    None
+}
+
+/// Parse the output of `vm_stat`, which starts with
+/// `Mach Virtual Memory Statistics: (page size of 16384 bytes)` and lists page counts such as
+/// `Pages free:    12345.`
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+fn parse_vm_stat(output: &str) -> Option<u64> {
+    let page_size: u64 = output
+        .split("page size of ")
+        .nth(1)?
+        .split_whitespace()
+        .next()?
+        .parse()
+        .ok()?;
+    let pages = |name: &str| -> Option<u64> {
+        let line = output.lines().find(|l| l.starts_with(name))?;
+        line[name.len()..].trim().trim_end_matches('.').parse().ok()
+    };
+    let free = pages("Pages free:")? + pages("Pages inactive:")? + pages("Pages speculative:")?;
+    Some(free * page_size)
+}
+
+/// Memory left under the limit of the process's cgroup, if it is in a cgroup v2 with a limit.
+#[cfg(target_os = "linux")]
+fn cgroup_available_memory() -> Option<u64> {
+    let cgroups = std::fs::read_to_string("/proc/self/cgroup").ok()?;
+    let path = cgroups.lines().find_map(|l| l.strip_prefix("0::"))?;
+    let dir = std::path::Path::new("/sys/fs/cgroup").join(path.trim_start_matches('/'));
+    let max = std::fs::read_to_string(dir.join("memory.max")).ok()?;
+    let current = std::fs::read_to_string(dir.join("memory.current")).ok()?;
+    cgroup_headroom(&max, &current)
+}
+
+/// Memory left between the contents of a cgroup's `memory.max` and `memory.current`,
+/// or `None` if it has no limit.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn cgroup_headroom(max: &str, current: &str) -> Option<u64> {
+    let max: u64 = max.trim().parse().ok()?;
+    let current: u64 = current.trim().parse().ok()?;
+    Some(max.saturating_sub(current))
+}
+
+/// Checks against the loaded model.
+pub(crate) fn check_model(
+    params: &WhisperContextParameters,
+    ctx: &WhisperInnerContext,
+) -> Result<(), WhisperError> {
+    let n_text_layer = ctx.model_n_text_layer();
+    let n_text_head = ctx.model_n_text_head();
+    let reason = match &params.dtw_parameters.mode {
+        DtwMode::None => return Ok(()),
+        DtwMode::TopMost { n_top } => (*n_top < 1 || *n_top > n_text_layer)
+            .then_some(UnsupportedConfiguration::DtwHeadsOutOfRange),
+        DtwMode::Custom { aheads } => aheads
+            .iter()
+            .any(|h| {
+                !(0..n_text_layer).contains(&h.n_text_layer)
+                    || !(0..n_text_head).contains(&h.n_head)
+            })
+            .then_some(UnsupportedConfiguration::DtwHeadsOutOfRange),
+        DtwMode::ModelPreset { model_preset } => (!preset_matches(
+            model_preset,
+            ctx.model_n_audio_layer(),
+            n_text_layer,
+            ctx.model_n_vocab(),
+        ))
+        .then_some(UnsupportedConfiguration::DtwPresetMismatch),
+    };
+    reason.map_or(Ok(()), |r| Err(WhisperError::UnsupportedConfiguration(r)))
+}
+
+/// Whether `preset` describes a model with these dimensions.
+pub(crate) fn preset_matches(
+    preset: &DtwModelPreset,
+    n_audio_layer: c_int,
+    n_text_layer: c_int,
+    n_vocab: c_int,
+) -> bool {
+    use DtwModelPreset::*;
+    let (audio, text, vocab) = match preset {
+        TinyEn => (4, 4, N_VOCAB_EN),
+        Tiny => (4, 4, N_VOCAB_MULTILINGUAL),
+        BaseEn => (6, 6, N_VOCAB_EN),
+        Base => (6, 6, N_VOCAB_MULTILINGUAL),
+        SmallEn => (12, 12, N_VOCAB_EN),
+        Small => (12, 12, N_VOCAB_MULTILINGUAL),
+        MediumEn => (24, 24, N_VOCAB_EN),
+        Medium => (24, 24, N_VOCAB_MULTILINGUAL),
+        LargeV1 | LargeV2 => (32, 32, N_VOCAB_MULTILINGUAL),
+        LargeV3 => (32, 32, N_VOCAB_V3),
+        LargeV3Turbo => (32, 4, N_VOCAB_V3),
+    };
+    (n_audio_layer, n_text_layer, n_vocab) == (audio, text, vocab)
+}
+
+/// Ask `device` whether it can run the flash attention operation whisper.cpp builds.
+fn supports_flash_attn(device: whisper_rs_sys::ggml_backend_dev_t) -> bool {
+    unsafe {
+        let ctx = whisper_rs_sys::ggml_init(whisper_rs_sys::ggml_init_params {
+            mem_size: whisper_rs_sys::ggml_tensor_overhead() * 4,
+            mem_buffer: std::ptr::null_mut(),
+            no_alloc: true,
+        });
+        if ctx.is_null() {
+            // can't tell; let whisper.cpp decide
+            return true;
+        }
+        let tensor = |ty, n| whisper_rs_sys::ggml_new_tensor_4d(ctx, ty, HEAD_DIM, n, 1, 1);
+        let q = tensor(whisper_rs_sys::ggml_type_GGML_TYPE_F32, 1);
+        let k = tensor(whisper_rs_sys::ggml_type_GGML_TYPE_F16, 256);
+        let v = tensor(whisper_rs_sys::ggml_type_GGML_TYPE_F16, 256);
+        let op = whisper_rs_sys::ggml_flash_attn_ext(
+            ctx,
+            q,
+            k,
+            v,
+            std::ptr::null_mut(),
+            1.0 / (HEAD_DIM as f32).sqrt(),
+            0.0,
+            0.0,
+        );
+        let supported = whisper_rs_sys::ggml_backend_dev_supports_op(device, op);
+        whisper_rs_sys::ggml_free(ctx);
+        supported
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::DtwParameters;
+
+    #[test]
+    fn dtw_presets_are_matched_to_models() {
+        assert!(preset_matches(&DtwModelPreset::BaseEn, 6, 6, 51864));
+        assert!(!preset_matches(&DtwModelPreset::Base, 6, 6, 51864));
+        assert!(preset_matches(&DtwModelPreset::LargeV3Turbo, 32, 4, 51866));
+        assert!(!preset_matches(&DtwModelPreset::Tiny, 32, 4, 51866));
+        // distil-large-v3
+        assert!(!preset_matches(&DtwModelPreset::LargeV3, 32, 2, 51866));
+    }
+
+    #[test]
+    fn dtw_buffer_leaves_room_for_the_model() {
+        let mib = 1024 * 1024;
+        assert!(dtw_fits(128 * mib, 1024 * mib as u64));
+        assert!(!dtw_fits(128 * mib, 200 * mib as u64));
+    }
+
+    #[test]
+    fn cgroup_limits_bound_available_memory() {
+        assert_eq!(cgroup_headroom("max\n", "1048576\n"), None);
+        assert_eq!(cgroup_headroom("4194304\n", "1048576\n"), Some(3145728));
+        assert_eq!(cgroup_headroom("1048576\n", "4194304\n"), Some(0));
+    }
+
+    #[test]
+    fn dtw_falls_back_when_its_buffer_does_not_fit() {
+        let mib = 1024 * 1024;
+        let mut params = WhisperContextParameters::default();
+        params
+            .dtw_parameters(DtwParameters::preset(DtwModelPreset::BaseEn).with_mem_size(128 * mib));
+        assert!(matches!(dtw_memory_fallback(&mut params, None), Ok(None)));
+        assert!(matches!(
+            dtw_memory_fallback(&mut params, Some(1024 * mib as u64)),
+            Ok(None)
+        ));
+        assert!(matches!(
+            params.dtw_parameters.mode,
+            DtwMode::ModelPreset { .. }
+        ));
+
+        let mut strict = WhisperContextParameters::default();
+        strict.dtw_parameters(
+            DtwParameters::preset(DtwModelPreset::BaseEn)
+                .with_mem_size(128 * mib)
+                .with_memory_fallback(false),
+        );
+        assert!(matches!(
+            dtw_memory_fallback(&mut strict, Some(200 * mib as u64)),
+            Err(WhisperError::UnsupportedConfiguration(
+                UnsupportedConfiguration::DtwMemoryUnavailable { .. }
+            ))
+        ));
+
+        let fallback = dtw_memory_fallback(&mut params, Some(200 * mib as u64));
+        assert_eq!(
+            fallback.ok().flatten(),
+            Some(DtwFallback {
+                dtw_mem_size: 128 * mib,
+                available: 200 * mib as u64,
+            })
+        );
+        assert!(matches!(params.dtw_parameters.mode, DtwMode::None));
+    }
+
+    #[test]
+    fn parses_vm_stat() {
+        let output = "Mach Virtual Memory Statistics: (page size of 16384 bytes)
+Pages free:                                3000.
+Pages active:                            200000.
+Pages inactive:                          100000.
+Pages speculative:                         1000.
+Pages wired down:                         90000.
+";
+        assert_eq!(parse_vm_stat(output), Some(104000 * 16384));
+        assert_eq!(parse_vm_stat("Pages free: 3000."), None);
+    }
+}
@@ -0,0 +1,215 @@
+use crate::{FullParams, WhisperTokenId};
+
+/// How [`crate::WhisperState::full`] shortens the text carried over from earlier runs before
+/// prompting the decoder with it, see [`FullParams::set_context_compression`].
+///
+/// whisper.cpp prompts each window with the raw tail of everything decoded before it. Over hours
+/// of audio, one misheard phrase in that tail is easily repeated in every following window, and
+/// the decoder can get stuck in a loop. A compressed context keeps only the last
+/// [`Self::tail_tokens`] as they are, plus the names, acronyms and numbers mentioned earlier, so
+/// their spelling stays consistent without carrying the phrasing along.
+///
+/// # Examples
+/// ```no_run
+/// # use whisper_rs::{ContextCompression, FullParams, SamplingStrategy};
+/// let mut params = FullParams::new(SamplingStrategy::default());
+/// params.set_context_compression(ContextCompression::new().tail_tokens(32));
+/// // then transcribe the audio one chunk at a time with the same state
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContextCompression {
+    pub(crate) tail_tokens: usize,
+    pub(crate) max_entities: usize,
+}
+
+impl Default for ContextCompression {
+    fn default() -> Self {
+        Self {
+            tail_tokens: 64,
+            max_entities: 16,
+        }
+    }
+}
+
+impl ContextCompression {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set how many of the last tokens are carried over unchanged. 0 keeps only the entities.
+    ///
+    /// Defaults to 64.
+    pub fn tail_tokens(mut self, tail_tokens: usize) -> Self {
+        self.tail_tokens = tail_tokens;
+        self
+    }
+
+    /// Set how many of the most recently mentioned entities are kept from before the tail.
+    ///
+    /// Defaults to 16.
+    pub fn max_entities(mut self, max_entities: usize) -> Self {
+        self.max_entities = max_entities;
+        self
+    }
+
+    /// Build the prompt for the next run from the `context` carried over and the `entities`
+    /// remembered from context dropped before it, most recent last.
+    ///
+    /// # Returns
+    /// The most recent entities not repeated in the tail, then the tail.
+    pub(crate) fn prompt(
+        &self,
+        entities: &[Word],
+        context: &[WhisperTokenId],
+        text_of: impl Fn(WhisperTokenId) -> String,
+    ) -> Vec<WhisperTokenId> {
+        let words = words(context, text_of);
+        let mut tail_start = words.len();
+        let mut tail_len = 0;
+        while tail_start > 0 && tail_len + words[tail_start - 1].tokens.len() <= self.tail_tokens {
+            tail_start -= 1;
+            tail_len += words[tail_start].tokens.len();
+        }
+        let (before, tail) = words.split_at(tail_start);
+        // entities already in the tail aren't repeated before it
+        let mut seen: Vec<String> = tail.iter().filter_map(Word::entity).collect();
+        let mut kept: Vec<&Word> = Vec::new();
+        let candidates = entities.iter().chain(entities_in(before));
+        for word in candidates.collect::<Vec<_>>().into_iter().rev() {
+            if kept.len() == self.max_entities {
+                break;
+            }
+            let Some(entity) = word.entity() else {
+                continue;
+            };
+            if !seen.contains(&entity) {
+                seen.push(entity);
+                kept.push(word);
+            }
+        }
+        kept.into_iter()
+            .rev()
+            .chain(tail)
+            .flat_map(|word| word.tokens.iter().copied())
+            .collect()
+    }
+
+    /// Append the entities of `dropped`, context about to be discarded, to `entities`, keeping
+    /// only the [`Self::max_entities`] most recent distinct ones.
+    pub(crate) fn remember(
+        &self,
+        entities: &mut Vec<Word>,
+        dropped: &[WhisperTokenId],
+        text_of: impl Fn(WhisperTokenId) -> String,
+    ) {
+        let words = words(dropped, text_of);
+        for word in entities_in(&words) {
+            let entity = word.entity();
+            entities.retain(|known| known.entity() != entity);
+            entities.push(word.clone());
+        }
+        let excess = entities.len().saturating_sub(self.max_entities);
+        entities.drain(..excess);
+    }
+}
+
+/// A word of decoded text and the tokens spelling it, including punctuation attached to it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Word {
+    text: String,
+    tokens: Vec<WhisperTokenId>,
+    /// Whether the word starts a sentence, where a capital letter doesn't mark a name.
+    sentence_start: bool,
+}
+
+impl Word {
+    /// The word without punctuation, if it looks like a name, acronym or number.
+    fn entity(&self) -> Option<String> {
+        let core = self.text.trim_matches(|c: char| !c.is_alphanumeric());
+        let first = core.chars().next()?;
+        let acronym = core.chars().filter(|c| c.is_alphabetic()).count() >= 2
+            && core.chars().all(|c| !c.is_lowercase());
+        let name = first.is_uppercase() && !self.sentence_start && core.chars().count() >= 2;
+        let number = core.chars().any(|c| c.is_ascii_digit());
+        (acronym || name || number).then(|| core.to_string())
+    }
+}
+
+/// Group `tokens` into words, starting a new one at each token that begins with whitespace.
+fn words(tokens: &[WhisperTokenId], text_of: impl Fn(WhisperTokenId) -> String) -> Vec<Word> {
+    let mut words: Vec<Word> = Vec::new();
+    for &token in tokens {
+        let text = text_of(token);
+        match words.last_mut() {
+            Some(word) if !text.starts_with(char::is_whitespace) => {
+                word.text.push_str(&text);
+                word.tokens.push(token);
+            }
+            last => {
+                let sentence_start =
+                    last.is_none_or(|word| word.text.trim_end().ends_with(['.', '?', '!']));
+                words.push(Word {
+                    text,
+                    tokens: vec![token],
+                    sentence_start,
+                });
+            }
+        }
+    }
+    words
+}
+
+fn entities_in(words: &[Word]) -> impl Iterator<Item = &Word> {
+    words.iter().filter(|word| word.entity().is_some())
+}
+
+impl FullParams<'_, '_> {
+    /// Prompt each run of [`crate::WhisperState::full`] with a compressed form of the text
+    /// decoded by the earlier runs of the same state, see [`ContextCompression`], instead of
+    /// the raw tail whisper.cpp carries over.
+    ///
+    /// This works between runs, so transcribe very long audio in chunks of a few minutes or
+    /// less with one state; within a run whisper.cpp still carries its own context between
+    /// 30 second windows. It has no effect on runs with [`Self::set_no_context`] or a prompt
+    /// of their own.
+    ///
+    /// Defaults to no compression.
+    pub fn set_context_compression(&mut self, compression: ContextCompression) {
+        self.context_compression = Some(compression);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const VOCAB: [&str; 12] = [
+        " So", " Alice", " met", " Bob", " at", " AWS", ".", " Then", " she", " left", " again",
+        " today",
+    ];
+
+    fn text_of(id: WhisperTokenId) -> String {
+        VOCAB[id as usize].to_string()
+    }
+
+    #[test]
+    fn keeps_entities_and_the_tail() {
+        // " So Alice met Bob at AWS. Then she left again today"
+        let context = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11];
+        let words = words(&context, text_of);
+        let entities: Vec<_> = words.iter().filter_map(Word::entity).collect();
+        assert_eq!(entities, ["Alice", "Bob", "AWS"]);
+
+        let compression = ContextCompression::new().tail_tokens(4);
+        let prompt = compression.prompt(&[], &context, text_of);
+        assert_eq!(prompt, [1, 3, 5, 6, 8, 9, 10, 11]);
+
+        // entities remembered from dropped context count as mentioned earlier
+        let mut remembered = Vec::new();
+        let compression = compression.max_entities(2);
+        compression.remember(&mut remembered, &context[..6], text_of);
+        assert_eq!(remembered.len(), 2);
+        let prompt = compression.prompt(&remembered, &context[7..], text_of);
+        assert_eq!(prompt, [3, 5, 8, 9, 10, 11]);
+    }
+}
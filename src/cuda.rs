@@ -0,0 +1,99 @@
+//! Configuration of ggml's CUDA backend.
+//!
+//! Every [`crate::WhisperState`] gets its own ggml CUDA backend, and with it its own CUDA streams,
+//! so states used from different threads already queue their work independently; ggml offers no
+//! way to choose the stream a state uses. What can be configured is how host memory is allocated,
+//! which ggml reads from the environment, see [`CudaOptions`].
+
+/// Host memory options of the CUDA backend.
+///
+/// # Examples
+/// ```no_run
+/// # use whisper_rs::cuda::CudaOptions;
+/// // at the very start of main, before any other thread is spawned
+/// CudaOptions::new().pinned_host_memory(true).install();
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CudaOptions {
+    pinned_host_memory: bool,
+    unified_memory: bool,
+    cuda_graphs: bool,
+}
+
+impl Default for CudaOptions {
+    fn default() -> Self {
+        Self {
+            pinned_host_memory: true,
+            unified_memory: false,
+            cuda_graphs: true,
+        }
+    }
+}
+
+impl CudaOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allocate host buffers as page-locked memory, which the GPU can copy from without staging,
+    /// so transfers don't stall when several states share one GPU.
+    /// Disable this on systems where locking a lot of memory is not allowed.
+    ///
+    /// Defaults to true.
+    pub fn pinned_host_memory(mut self, pinned_host_memory: bool) -> Self {
+        self.pinned_host_memory = pinned_host_memory;
+        self
+    }
+
+    /// Allocate GPU buffers as unified memory, which the driver pages out to host memory when
+    /// the GPU runs out, instead of failing. Much slower once that happens.
+    ///
+    /// Defaults to false.
+    pub fn unified_memory(mut self, unified_memory: bool) -> Self {
+        self.unified_memory = unified_memory;
+        self
+    }
+
+    /// Record the decoder's kernels as CUDA graphs and replay them, which saves launch overhead.
+    ///
+    /// Defaults to true.
+    pub fn cuda_graphs(mut self, cuda_graphs: bool) -> Self {
+        self.cuda_graphs = cuda_graphs;
+        self
+    }
+
+    /// Apply these options by setting the environment variables ggml reads.
+    ///
+    /// ggml reads graph support when a state is created and the memory options on every
+    /// allocation, so call this before creating contexts. Modifying the environment is not
+    /// thread-safe on most platforms, so call it before spawning threads.
+    pub fn install(&self) {
+        set_flag("GGML_CUDA_NO_PINNED", !self.pinned_host_memory);
+        set_flag("GGML_CUDA_ENABLE_UNIFIED_MEMORY", self.unified_memory);
+        set_flag("GGML_CUDA_DISABLE_GRAPHS", !self.cuda_graphs);
+    }
+}
+
+/// ggml only checks whether these variables are set, not their value.
+fn set_flag(var: &str, enabled: bool) {
+    if enabled {
+        std::env::set_var(var, "1");
+    } else {
+        std::env::remove_var(var);
+    }
+}
+
+#[cfg(test)]
+mod cuda_tests {
+    use super::*;
+
+    #[test]
+    fn options_are_installed() {
+        CudaOptions::new().pinned_host_memory(false).install();
+        assert!(std::env::var_os("GGML_CUDA_NO_PINNED").is_some());
+        assert!(std::env::var_os("GGML_CUDA_ENABLE_UNIFIED_MEMORY").is_none());
+
+        CudaOptions::new().install();
+        assert!(std::env::var_os("GGML_CUDA_NO_PINNED").is_none());
+    }
+}
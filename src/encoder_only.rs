@@ -0,0 +1,295 @@
+//! Loading ggml Whisper models without their decoder, see
+//! [`crate::WhisperContextParameters::encoder_only`].
+//!
+//! whisper.cpp allocates every tensor the header calls for and fails to load a model missing
+//! any of them, and it can't create states for a model without decoder layers. So the model is
+//! rewritten while whisper.cpp reads it to keep a single decoder layer: the header says there is
+//! one, and the tensors of the others are skipped. The token and positional embeddings of the
+//! decoder stay, everything else in the file is passed through unchanged.
+
+use std::io::{self, Read};
+
+/// `ggml` in ASCII, read as a little-endian integer.
+const GGML_MAGIC: u32 = 0x6767_6d6c;
+/// The magic and the 11 hyperparameters: n_vocab, n_audio_ctx, n_audio_state, n_audio_head,
+/// n_audio_layer, n_text_ctx, n_text_state, n_text_head, n_text_layer, n_mels and ftype.
+const HEADER_LEN: usize = 4 + 11 * 4;
+/// Offset of n_text_layer in the header.
+const N_TEXT_LAYER: usize = 4 + 8 * 4;
+
+/// A reader yielding the model read from `reader` with all decoder layers but the first dropped,
+/// without holding more than one tensor header in memory.
+///
+/// Reads fail with [`io::ErrorKind::InvalidData`] if `reader` doesn't hold a ggml Whisper model,
+/// or holds tensors of a type this doesn't know the size of.
+pub(crate) struct StripDecoder<R> {
+    reader: R,
+    /// Rewritten bytes not read yet, from `pending_pos` on.
+    pending: Vec<u8>,
+    pending_pos: usize,
+    /// Bytes still to be copied unchanged from `reader` before the next section.
+    passthrough: u64,
+    section: Section,
+}
+
+/// The part of the model a [`StripDecoder`] reads next.
+#[derive(Clone, Copy)]
+enum Section {
+    Header,
+    VocabSize,
+    Tokens { left: u64 },
+    Tensors,
+    End,
+}
+
+impl<R: Read> StripDecoder<R> {
+    pub(crate) fn new(reader: R) -> Self {
+        Self {
+            reader,
+            pending: Vec::new(),
+            pending_pos: 0,
+            passthrough: 0,
+            section: Section::Header,
+        }
+    }
+
+    /// Queue the next rewritten section, or move on to the end of the model.
+    fn next_section(&mut self) -> io::Result<()> {
+        self.pending.clear();
+        self.pending_pos = 0;
+        match self.section {
+            Section::Header => {
+                let mut header = [0u8; HEADER_LEN];
+                self.reader.read_exact(&mut header)?;
+                if u32::from_le_bytes(header[..4].try_into().unwrap()) != GGML_MAGIC {
+                    return Err(invalid("not a ggml Whisper model"));
+                }
+                if read_i32(&header[N_TEXT_LAYER..]) < 1 {
+                    return Err(invalid("the model has no decoder layers"));
+                }
+                header[N_TEXT_LAYER..N_TEXT_LAYER + 4].copy_from_slice(&1i32.to_le_bytes());
+                self.pending.extend_from_slice(&header);
+
+                // mel filters
+                let n_mel = self.queue_count()?;
+                let n_fft = self.queue_count()?;
+                self.passthrough = n_mel
+                    .checked_mul(n_fft)
+                    .and_then(|n| n.checked_mul(4))
+                    .ok_or_else(|| invalid("mel filters too large"))?;
+                self.section = Section::VocabSize;
+            }
+            Section::VocabSize => {
+                let left = self.queue_count()?;
+                self.section = Section::Tokens { left };
+            }
+            Section::Tokens { left: 0 } => self.section = Section::Tensors,
+            Section::Tokens { left } => {
+                self.passthrough = self.queue_count()?;
+                self.section = Section::Tokens { left: left - 1 };
+            }
+            Section::Tensors => self.next_tensor()?,
+            Section::End => {}
+        }
+        Ok(())
+    }
+
+    /// Queue the header of the next kept tensor and pass its data through, skipping dropped ones.
+    fn next_tensor(&mut self) -> io::Result<()> {
+        loop {
+            let mut head = [0u8; 12];
+            if !read_exact_or_eof(&mut self.reader, &mut head)? {
+                self.section = Section::End;
+                return Ok(());
+            }
+            let n_dims = read_i32(&head[0..]);
+            let name_len = read_i32(&head[4..]);
+            let ttype = read_i32(&head[8..]);
+            if !(1..=4).contains(&n_dims) || !(1..=4096).contains(&name_len) {
+                return Err(invalid("malformed tensor header"));
+            }
+            let mut dims = vec![0u8; n_dims as usize * 4];
+            self.reader.read_exact(&mut dims)?;
+            let mut name = vec![0u8; name_len as usize];
+            self.reader.read_exact(&mut name)?;
+            let ne: Vec<i32> = dims.chunks(4).map(read_i32).collect();
+            let size = tensor_bytes(ttype, &ne).ok_or_else(|| invalid("unknown tensor type"))?;
+
+            if !is_dropped(&name) {
+                self.pending.extend_from_slice(&head);
+                self.pending.extend_from_slice(&dims);
+                self.pending.extend_from_slice(&name);
+                self.passthrough = size;
+                return Ok(());
+            }
+            let skipped = io::copy(&mut self.reader.by_ref().take(size), &mut io::sink())?;
+            if skipped != size {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+        }
+    }
+
+    /// Read a non-negative 32-bit count, queue it and return it.
+    fn queue_count(&mut self) -> io::Result<u64> {
+        let mut bytes = [0u8; 4];
+        self.reader.read_exact(&mut bytes)?;
+        self.pending.extend_from_slice(&bytes);
+        u64::try_from(read_i32(&bytes)).map_err(|_| invalid("negative count"))
+    }
+}
+
+impl<R: Read> Read for StripDecoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        loop {
+            if self.pending_pos < self.pending.len() {
+                let queued = &self.pending[self.pending_pos..];
+                let n = queued.len().min(buf.len());
+                buf[..n].copy_from_slice(&queued[..n]);
+                self.pending_pos += n;
+                return Ok(n);
+            }
+            if self.passthrough > 0 {
+                let len = buf
+                    .len()
+                    .min(usize::try_from(self.passthrough).unwrap_or(usize::MAX));
+                let n = self.reader.read(&mut buf[..len])?;
+                if n == 0 {
+                    return Err(io::ErrorKind::UnexpectedEof.into());
+                }
+                self.passthrough -= n as u64;
+                return Ok(n);
+            }
+            if let Section::End = self.section {
+                return Ok(0);
+            }
+            self.next_section()?;
+        }
+    }
+}
+
+/// Whether `name` is a tensor of a decoder layer other than the first.
+fn is_dropped(name: &[u8]) -> bool {
+    let Some(rest) = name.strip_prefix(b"decoder.blocks.") else {
+        return false;
+    };
+    let digits = rest.iter().take_while(|c| c.is_ascii_digit()).count();
+    digits > 0 && rest[digits..].starts_with(b".") && rest[..digits] != *b"0"
+}
+
+/// Size of the data of a tensor of ggml type `ttype` and shape `ne`.
+fn tensor_bytes(ttype: i32, ne: &[i32]) -> Option<u64> {
+    // (bytes per block, elements per block)
+    let (block_bytes, block_len) = match ttype {
+        0 => (4, 1),      // f32
+        1 => (2, 1),      // f16
+        2 => (18, 32),    // q4_0
+        3 => (20, 32),    // q4_1
+        6 => (22, 32),    // q5_0
+        7 => (24, 32),    // q5_1
+        8 => (34, 32),    // q8_0
+        10 => (84, 256),  // q2_k
+        11 => (110, 256), // q3_k
+        12 => (144, 256), // q4_k
+        13 => (176, 256), // q5_k
+        14 => (210, 256), // q6_k
+        30 => (2, 1),     // bf16
+        _ => return None,
+    };
+    let mut elements = 1u64;
+    for &n in ne {
+        elements = elements.checked_mul(u64::try_from(n).ok()?)?;
+    }
+    if !elements.is_multiple_of(block_len) {
+        return None;
+    }
+    Some(elements / block_len * block_bytes)
+}
+
+fn read_i32(bytes: &[u8]) -> i32 {
+    i32::from_le_bytes(bytes[..4].try_into().unwrap())
+}
+
+/// Fill `buf`, or return false if `reader` is already at its end.
+fn read_exact_or_eof(reader: &mut impl Read, buf: &mut [u8]) -> io::Result<bool> {
+    let read = reader.read(buf)?;
+    if read == 0 {
+        return Ok(false);
+    }
+    reader.read_exact(&mut buf[read..])?;
+    Ok(true)
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn tensor(model: &mut Vec<u8>, name: &str, fill: u8) {
+        for n in [2, name.len() as i32, 0, 3, 2] {
+            model.extend_from_slice(&n.to_le_bytes());
+        }
+        model.extend_from_slice(name.as_bytes());
+        model.extend(std::iter::repeat_n(fill, 3 * 2 * 4));
+    }
+
+    /// Read all of `reader` a few bytes at a time, as whisper.cpp does.
+    fn read_small(mut reader: impl Read) -> io::Result<Vec<u8>> {
+        let mut out = Vec::new();
+        let mut buf = [0u8; 5];
+        loop {
+            match reader.read(&mut buf)? {
+                0 => return Ok(out),
+                n => out.extend_from_slice(&buf[..n]),
+            }
+        }
+    }
+
+    #[test]
+    fn decoder_layers_after_the_first_are_dropped() {
+        let mut model = GGML_MAGIC.to_le_bytes().to_vec();
+        for n in [3i32, 1500, 384, 6, 4, 448, 384, 6, 4, 80, 0] {
+            model.extend_from_slice(&n.to_le_bytes());
+        }
+        // one mel band of two bins, three tokens
+        for n in [1i32, 2, 0, 0, 3] {
+            model.extend_from_slice(&n.to_le_bytes());
+        }
+        for token in ["a", "bc", "d"] {
+            model.extend_from_slice(&(token.len() as u32).to_le_bytes());
+            model.extend_from_slice(token.as_bytes());
+        }
+        let tensors_start = model.len();
+        tensor(&mut model, "encoder.blocks.3.mlp.0.weight", 1);
+        tensor(&mut model, "decoder.blocks.0.attn.query.weight", 2);
+        tensor(&mut model, "decoder.blocks.1.attn.query.weight", 3);
+        tensor(&mut model, "decoder.blocks.10.mlp_ln.bias", 4);
+        tensor(&mut model, "decoder.ln.weight", 5);
+
+        let stripped = read_small(StripDecoder::new(&model[..])).unwrap();
+        assert_eq!(read_i32(&stripped[N_TEXT_LAYER..]), 1);
+        assert_eq!(stripped[..N_TEXT_LAYER], model[..N_TEXT_LAYER]);
+        assert_eq!(
+            stripped[N_TEXT_LAYER + 4..tensors_start],
+            model[N_TEXT_LAYER + 4..tensors_start]
+        );
+        let mut expected = stripped[..tensors_start].to_vec();
+        tensor(&mut expected, "encoder.blocks.3.mlp.0.weight", 1);
+        tensor(&mut expected, "decoder.blocks.0.attn.query.weight", 2);
+        tensor(&mut expected, "decoder.ln.weight", 5);
+        assert_eq!(stripped, expected);
+
+        // stripping is idempotent, and truncated models are rejected
+        assert_eq!(
+            read_small(StripDecoder::new(&stripped[..])).unwrap(),
+            stripped
+        );
+        assert!(read_small(StripDecoder::new(&model[..model.len() - 1])).is_err());
+        assert!(read_small(StripDecoder::new(&b"gguf"[..])).is_err());
+    }
+}
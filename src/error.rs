@@ -1,3 +1,5 @@
+use crate::compat::UnsupportedConfiguration;
+use crate::WatchdogReason;
 use std::ffi::{c_int, NulError};
 use std::str::Utf8Error;
 
@@ -25,6 +27,8 @@ pub enum WhisperError {
     FailedToDecode,
     /// Invalid number of mel bands.
     InvalidMelBands,
+    /// A spectrogram was empty or did not hold a whole number of frames of `n_mel` bands.
+    InvalidMelShape { len: usize, n_mel: c_int },
     /// Invalid thread count
     InvalidThreadCount,
     /// Invalid UTF-8 detected in a string from Whisper.
@@ -48,6 +52,65 @@ pub enum WhisperError {
     InputOutputLengthMismatch { input_len: usize, output_len: usize },
     /// Input slice was not an even number of samples.
     HalfSampleMissing(usize),
+    /// The requested audio context is larger than the one the model was trained with.
+    InvalidAudioCtx {
+        audio_ctx: c_int,
+        n_audio_ctx: c_int,
+    },
+    /// The requested offset lies past the end of the provided audio, or offset or duration is negative.
+    TimeRangeOutOfBounds {
+        offset_ms: c_int,
+        duration_ms: c_int,
+        audio_ms: i64,
+    },
+    /// The beam size is larger than the number of decoders whisper.cpp supports.
+    InvalidBeamSize { beam_size: c_int, max: c_int },
+    /// `best_of` is larger than the number of decoders whisper.cpp supports.
+    InvalidBestOf { best_of: c_int, max: c_int },
+    /// The maximum number of past text tokens was negative.
+    InvalidMaxTextCtx(c_int),
+    /// The pattern of [`crate::FullParams::set_suppress_regex`] has a syntax error at this byte
+    /// offset, which would throw a C++ exception inside whisper.cpp.
+    InvalidSuppressRegex { position: usize },
+    /// More samples were provided than whisper.cpp can address.
+    TooManySamples(usize),
+    /// Interleaved audio did not hold a whole number of frames, or had no channels.
+    IncompleteFrame { samples: usize, channels: usize },
+    /// The context was loaded without its decoder, see
+    /// [`crate::WhisperContextParameters::encoder_only`], so it can't decode or time tokens with DTW.
+    DecoderNotLoaded,
+    /// The context parameters ask for something the model or the backend can't do.
+    UnsupportedConfiguration(UnsupportedConfiguration),
+    /// The audio to transcribe, after any offset, is shorter than whisper.cpp accepts and
+    /// padding was turned off, see [`crate::FullParams::set_pad_short_audio`].
+    AudioTooShort { samples: usize, min_samples: usize },
+    /// The backends can't be shut down while contexts are still using them,
+    /// see [`crate::shutdown_backends`].
+    BackendsInUse { contexts: usize },
+    /// The run was aborted by its [`crate::Watchdog`], see [`crate::FullParams::set_watchdog`].
+    DeadlineExceeded { reason: WatchdogReason },
+    /// The run was stopped by its abort callback, see
+    /// [`crate::FullParams::set_abort_callback_safe`].
+    Aborted,
+}
+
+impl WhisperError {
+    /// Whether retrying the same call might succeed, see [`crate::RetryPolicy`].
+    ///
+    /// These are failures to compute the model, which a GPU reset, an ECC error or running
+    /// briefly out of device memory cause. Everything else, such as invalid parameters or audio,
+    /// fails the same way every time. Most CUDA errors abort the process instead, which can't
+    /// be retried from within it.
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            Self::UnableToCalculateSpectrogram
+                | Self::UnableToCalculateEvaluation
+                | Self::FailedToEncode
+                | Self::FailedToDecode
+                | Self::FailedToCreateState
+        )
+    }
 }
 
 impl From<Utf8Error> for WhisperError {
@@ -82,6 +145,11 @@ impl std::fmt::Display for WhisperError {
             FailedToEncode => write!(f, "Failed to run the encoder."),
             FailedToDecode => write!(f, "Failed to run the decoder."),
             InvalidMelBands => write!(f, "Invalid number of mel bands."),
+            InvalidMelShape { len, n_mel } => write!(
+                f,
+                "A spectrogram of {} values can't hold frames of {} mel bands.",
+                len, n_mel
+            ),
             InvalidThreadCount => write!(f, "Invalid thread count."),
             InvalidUtf8 {
                 valid_up_to,
@@ -134,6 +202,73 @@ impl std::fmt::Display for WhisperError {
                     size + 1
                 )
             }
+            InvalidAudioCtx {
+                audio_ctx,
+                n_audio_ctx,
+            } => write!(
+                f,
+                "Invalid audio context: {} is larger than the model's audio context of {}.",
+                audio_ctx, n_audio_ctx
+            ),
+            TimeRangeOutOfBounds {
+                offset_ms,
+                duration_ms,
+                audio_ms,
+            } => write!(
+                f,
+                "Offset {} ms with duration {} ms does not lie within the provided {} ms of audio.",
+                offset_ms, duration_ms, audio_ms
+            ),
+            InvalidBeamSize { beam_size, max } => write!(
+                f,
+                "Invalid beam size: {} (must be between 1 and {}).",
+                beam_size, max
+            ),
+            InvalidBestOf { best_of, max } => {
+                write!(f, "Invalid best_of: {} (must be at most {}).", best_of, max)
+            }
+            InvalidMaxTextCtx(n_max_text_ctx) => write!(
+                f,
+                "Invalid n_max_text_ctx: {} (must not be negative).",
+                n_max_text_ctx
+            ),
+            TooManySamples(len) => write!(
+                f,
+                "Too many samples: {} (at most {} are supported).",
+                len,
+                c_int::MAX
+            ),
+            IncompleteFrame { samples, channels } => write!(
+                f,
+                "Interleaved audio with {} channels can't have {} samples.",
+                channels, samples
+            ),
+            DecoderNotLoaded => write!(f, "The context was loaded without its decoder."),
+            UnsupportedConfiguration(reason) => {
+                write!(f, "Unsupported configuration: {}.", reason)
+            }
+            AudioTooShort {
+                samples,
+                min_samples,
+            } => write!(
+                f,
+                "Audio too short: {} samples, whisper.cpp needs at least {} (about one second).",
+                samples, min_samples
+            ),
+            BackendsInUse { contexts } => write!(
+                f,
+                "Backends are still in use by {} whisper context(s).",
+                contexts
+            ),
+            DeadlineExceeded { reason } => {
+                write!(f, "Run aborted by the watchdog: {}.", reason)
+            }
+            InvalidSuppressRegex { position } => write!(
+                f,
+                "Invalid suppress regex: syntax error at byte {}.",
+                position
+            ),
+            Aborted => write!(f, "Run aborted by the abort callback."),
         }
     }
 }
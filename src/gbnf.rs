@@ -0,0 +1,465 @@
+use crate::{FullParams, WhisperGrammarElement, WhisperGrammarElementType};
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use WhisperGrammarElementType::*;
+
+/// A grammar in GBNF, the notation of llama.cpp and whisper.cpp, compiled to the grammar
+/// elements whisper.cpp constrains decoding with, see [`FullParams::set_gbnf_grammar`].
+///
+/// Rules are written `name ::= alternatives`, one per line unless continued inside
+/// parentheses, and decoding starts from the rule named `root`. Alternatives are separated by
+/// `|` and consist of string literals such as `"yes"`, character classes such as `[a-z]` or
+/// `[^ ]`, references to other rules, groups in parentheses and the repetition operators `*`,
+/// `+` and `?`. `#` starts a comment.
+///
+/// # Examples
+/// ```
+/// # use whisper_rs::Grammar;
+/// let grammar: Grammar = r#"
+///     root   ::= " " command "."
+///     command ::= "turn " ("on" | "off") " the " device
+///     device ::= "lights" | "fan" | "radio"
+/// "#
+/// .parse()
+/// .unwrap();
+/// assert_eq!(grammar.rule_id("root"), Some(0));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Grammar {
+    rules: Vec<Vec<WhisperGrammarElement>>,
+    names: Vec<String>,
+}
+
+impl Grammar {
+    /// Compile a GBNF grammar.
+    pub fn parse(source: &str) -> Result<Self, GrammarParseError> {
+        Parser::new(source).parse()
+    }
+
+    /// The id of the rule named `name`, e.g. for [`FullParams::set_start_rule`]. Rules
+    /// generated for groups and repetitions are named after the rule they appear in, followed
+    /// by `_` and their id.
+    pub fn rule_id(&self, name: &str) -> Option<usize> {
+        self.names.iter().position(|n| n == name)
+    }
+
+    /// The elements of each rule, indexed by rule id. Each ends with
+    /// [`WhisperGrammarElementType::End`].
+    pub fn rules(&self) -> &[Vec<WhisperGrammarElement>] {
+        &self.rules
+    }
+
+    /// All the rules one after another, as [`FullParams::set_grammar`] takes them.
+    pub fn elements(&self) -> Vec<WhisperGrammarElement> {
+        self.rules.concat()
+    }
+}
+
+impl FromStr for Grammar {
+    type Err = GrammarParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+/// Error from [`Grammar::parse`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GrammarParseError {
+    /// The line of the error, starting at 1.
+    pub line: usize,
+    /// The column of the error in characters, starting at 1.
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for GrammarParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid grammar at line {}, column {}: {}",
+            self.line, self.column, self.message
+        )
+    }
+}
+
+impl std::error::Error for GrammarParseError {}
+
+/// A recursive descent parser following whisper.cpp's `grammar-parser.cpp`, so grammars get the
+/// same rule ids as with the whisper.cpp examples.
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    /// Rule names, indexed by id.
+    names: Vec<String>,
+    rules: Vec<Option<Vec<WhisperGrammarElement>>>,
+    /// Where each rule was first referenced, to report undefined ones.
+    references: HashMap<u32, usize>,
+}
+
+impl Parser {
+    fn new(source: &str) -> Self {
+        Self {
+            chars: source.chars().collect(),
+            pos: 0,
+            names: Vec::new(),
+            rules: Vec::new(),
+            references: HashMap::new(),
+        }
+    }
+
+    fn parse(mut self) -> Result<Grammar, GrammarParseError> {
+        self.skip_space(true);
+        while self.peek().is_some() {
+            self.rule()?;
+        }
+        if self.rules.is_empty() {
+            return Err(self.error_at(0, "the grammar has no rules".to_string()));
+        }
+        let mut rules = Vec::with_capacity(self.names.len());
+        for id in 0..self.names.len() {
+            match self.rules.get_mut(id).and_then(Option::take) {
+                Some(rule) => rules.push(rule),
+                None => {
+                    let at = self.references.get(&(id as u32)).copied().unwrap_or(0);
+                    let message = format!("undefined rule `{}`", self.names[id]);
+                    return Err(self.error_at(at, message));
+                }
+            }
+        }
+        Ok(Grammar {
+            rules,
+            names: self.names,
+        })
+    }
+
+    fn rule(&mut self) -> Result<(), GrammarParseError> {
+        let name = self
+            .name()
+            .ok_or_else(|| self.error("expecting a rule name"))?;
+        self.skip_space(false);
+        let id = self.symbol_id(&name);
+        if !self.eat("::=") {
+            return Err(self.error("expecting `::=`"));
+        }
+        self.skip_space(true);
+        self.alternates(&name, id, false)?;
+        self.eat("\r");
+        if !self.eat("\n") && self.peek().is_some() {
+            return Err(self.error("expecting a newline or the end of the grammar"));
+        }
+        self.skip_space(true);
+        Ok(())
+    }
+
+    fn alternates(&mut self, name: &str, id: u32, nested: bool) -> Result<(), GrammarParseError> {
+        let mut elements = self.sequence(name, nested)?;
+        while self.eat("|") {
+            elements.push(element(Alternate, 0));
+            self.skip_space(true);
+            elements.extend(self.sequence(name, nested)?);
+        }
+        elements.push(element(End, 0));
+        self.add_rule(id, elements);
+        Ok(())
+    }
+
+    fn sequence(
+        &mut self,
+        name: &str,
+        nested: bool,
+    ) -> Result<Vec<WhisperGrammarElement>, GrammarParseError> {
+        let mut out = Vec::new();
+        // start of the last item, which a repetition operator applies to
+        let mut last = 0;
+        while let Some(c) = self.peek() {
+            match c {
+                '"' => {
+                    self.pos += 1;
+                    last = out.len();
+                    while !self.eat("\"") {
+                        let c = self.char()?;
+                        out.push(element(Character, c));
+                    }
+                    self.skip_space(nested);
+                }
+                '[' => {
+                    self.pos += 1;
+                    let start = if self.eat("^") {
+                        NotCharacter
+                    } else {
+                        Character
+                    };
+                    last = out.len();
+                    while !self.eat("]") {
+                        let c = self.char()?;
+                        let kind = if out.len() > last {
+                            CharacterAlternate
+                        } else {
+                            start
+                        };
+                        out.push(element(kind, c));
+                        if self.peek() == Some('-') && self.peek_at(1) != Some(']') {
+                            self.pos += 1;
+                            let upper = self.char()?;
+                            out.push(element(CharacterRangeUpper, upper));
+                        }
+                    }
+                    self.skip_space(nested);
+                }
+                '(' => {
+                    self.pos += 1;
+                    self.skip_space(true);
+                    let id = self.generate_id(name);
+                    self.alternates(name, id, true)?;
+                    last = out.len();
+                    out.push(element(RuleReference, id));
+                    if !self.eat(")") {
+                        return Err(self.error("expecting `)`"));
+                    }
+                    self.skip_space(nested);
+                }
+                '*' | '+' | '?' => {
+                    if last == out.len() {
+                        return Err(self.error("expecting an item before the repetition"));
+                    }
+                    // S* becomes S' ::= S S' |, S+ becomes S' ::= S S' | S, S? becomes S' ::= S |
+                    let id = self.generate_id(name);
+                    let item = out.split_off(last);
+                    let mut rule = item.clone();
+                    if c != '?' {
+                        rule.push(element(RuleReference, id));
+                    }
+                    rule.push(element(Alternate, 0));
+                    if c == '+' {
+                        rule.extend(item);
+                    }
+                    rule.push(element(End, 0));
+                    self.add_rule(id, rule);
+                    out.push(element(RuleReference, id));
+                    self.pos += 1;
+                    self.skip_space(nested);
+                }
+                c if is_word_char(c) => {
+                    let at = self.pos;
+                    let reference = self.name().unwrap_or_default();
+                    let id = self.symbol_id(&reference);
+                    self.references.entry(id).or_insert(at);
+                    self.skip_space(nested);
+                    last = out.len();
+                    out.push(element(RuleReference, id));
+                }
+                _ => break,
+            }
+        }
+        Ok(out)
+    }
+
+    /// A possibly escaped character of a literal or character class.
+    fn char(&mut self) -> Result<u32, GrammarParseError> {
+        let c = self
+            .peek()
+            .ok_or_else(|| self.error("unexpected end of the grammar"))?;
+        self.pos += 1;
+        if c != '\\' {
+            return Ok(c as u32);
+        }
+        let escaped = self
+            .peek()
+            .ok_or_else(|| self.error("unexpected end of the grammar"))?;
+        self.pos += 1;
+        let digits = match escaped {
+            'x' => 2,
+            'u' => 4,
+            'U' => 8,
+            't' => return Ok('\t' as u32),
+            'r' => return Ok('\r' as u32),
+            'n' => return Ok('\n' as u32),
+            '\\' | '"' | '[' | ']' => return Ok(escaped as u32),
+            _ => return Err(self.error("unknown escape")),
+        };
+        let mut value = 0;
+        for _ in 0..digits {
+            let digit = self
+                .peek()
+                .and_then(|c| c.to_digit(16))
+                .ok_or_else(|| self.error(&format!("expecting {digits} hex digits")))?;
+            value = value * 16 + digit;
+            self.pos += 1;
+        }
+        Ok(value)
+    }
+
+    fn name(&mut self) -> Option<String> {
+        let start = self.pos;
+        while self.peek().is_some_and(is_word_char) {
+            self.pos += 1;
+        }
+        (self.pos > start).then(|| self.chars[start..self.pos].iter().collect())
+    }
+
+    /// Skip spaces and comments, and newlines if `newlines` is set.
+    fn skip_space(&mut self, newlines: bool) {
+        while let Some(c) = self.peek() {
+            match c {
+                ' ' | '\t' => self.pos += 1,
+                '\r' | '\n' if newlines => self.pos += 1,
+                '#' => {
+                    while self.peek().is_some_and(|c| c != '\r' && c != '\n') {
+                        self.pos += 1;
+                    }
+                }
+                _ => break,
+            }
+        }
+    }
+
+    fn symbol_id(&mut self, name: &str) -> u32 {
+        match self.names.iter().position(|n| n == name) {
+            Some(id) => id as u32,
+            None => {
+                self.names.push(name.to_string());
+                (self.names.len() - 1) as u32
+            }
+        }
+    }
+
+    fn generate_id(&mut self, base: &str) -> u32 {
+        let id = self.names.len();
+        self.names.push(format!("{base}_{id}"));
+        id as u32
+    }
+
+    fn add_rule(&mut self, id: u32, elements: Vec<WhisperGrammarElement>) {
+        let id = id as usize;
+        if self.rules.len() <= id {
+            self.rules.resize(id + 1, None);
+        }
+        self.rules[id] = Some(elements);
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.pos + offset).copied()
+    }
+
+    /// Consume `expected` if it comes next.
+    fn eat(&mut self, expected: &str) -> bool {
+        let len = expected.chars().count();
+        let matches = self
+            .chars
+            .get(self.pos..self.pos + len)
+            .is_some_and(|next| next.iter().copied().eq(expected.chars()));
+        if matches {
+            self.pos += len;
+        }
+        matches
+    }
+
+    fn error(&self, message: &str) -> GrammarParseError {
+        self.error_at(self.pos, message.to_string())
+    }
+
+    fn error_at(&self, at: usize, message: String) -> GrammarParseError {
+        let before = &self.chars[..at.min(self.chars.len())];
+        let line = before.iter().filter(|&&c| c == '\n').count() + 1;
+        let column = before.iter().rev().take_while(|&&c| c != '\n').count() + 1;
+        GrammarParseError {
+            line,
+            column,
+            message,
+        }
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '-' || c == '_'
+}
+
+fn element(element_type: WhisperGrammarElementType, value: u32) -> WhisperGrammarElement {
+    WhisperGrammarElement::new(element_type, value)
+}
+
+impl FullParams<'_, '_> {
+    /// Constrain decoding to `grammar`, starting from its `root` rule, or the first rule if it
+    /// has none. This replaces any grammar set with [`Self::set_grammar`].
+    ///
+    /// Tokens the grammar doesn't allow are penalized by [`Self::set_grammar_penalty`] rather
+    /// than ruled out, so the model can still leave the grammar for speech it can't fit.
+    ///
+    /// Defaults to no grammar.
+    pub fn set_gbnf_grammar(&mut self, grammar: &Grammar) {
+        self.set_grammar(Some(&grammar.elements()));
+        self.set_start_rule(grammar.rule_id("root").unwrap_or(0));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn compiles_like_whisper_cpp() {
+        let grammar = Grammar::parse(
+            "root ::= \"a\" [b-d^] item* # comment\nitem ::= ( \"x\" |\n [^\\x41] )\n",
+        )
+        .unwrap();
+        // root, item, the repetition and the group, in order of appearance
+        assert_eq!(grammar.rules().len(), 4);
+        assert_eq!(grammar.rule_id("item"), Some(1));
+        assert_eq!(grammar.rule_id("item_3"), Some(3));
+        assert_eq!(
+            grammar.rules()[0],
+            [
+                element(Character, 'a' as u32),
+                element(Character, 'b' as u32),
+                element(CharacterRangeUpper, 'd' as u32),
+                element(CharacterAlternate, '^' as u32),
+                element(RuleReference, 2),
+                element(End, 0),
+            ]
+        );
+        // item* ::= item item* |
+        assert_eq!(
+            grammar.rules()[2],
+            [
+                element(RuleReference, 1),
+                element(RuleReference, 2),
+                element(Alternate, 0),
+                element(End, 0),
+            ]
+        );
+        assert_eq!(
+            grammar.rules()[3],
+            [
+                element(Character, 'x' as u32),
+                element(Alternate, 0),
+                element(NotCharacter, 0x41),
+                element(End, 0),
+            ]
+        );
+        assert_eq!(grammar.elements().len(), 6 + 2 + 4 + 4);
+
+        // whisper.cpp gets a pointer to the start of each rule
+        let rules = crate::whisper_grammar::GrammarRules::new(&grammar.elements());
+        assert_eq!(rules.rules.len(), 4);
+        assert_eq!(rules.rules[2], rules.elements[8..].as_ptr());
+    }
+
+    #[test]
+    fn errors_point_at_the_problem() {
+        let error = Grammar::parse("root ::= \"a\" missing\n").unwrap_err();
+        assert_eq!((error.line, error.column), (1, 14));
+        assert_eq!(error.message, "undefined rule `missing`");
+
+        let error = Grammar::parse("root ::= \"a\"\n  other \"b\"\n").unwrap_err();
+        assert_eq!((error.line, error.column), (2, 9));
+        assert!(Grammar::parse("root ::= *").is_err());
+        assert!(Grammar::parse("# nothing\n").is_err());
+    }
+}
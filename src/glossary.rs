@@ -0,0 +1,165 @@
+use crate::{FullParams, WhisperContext, WhisperError, WhisperTokenId};
+
+/// Terminology to enforce when translating, see [`FullParams::set_translate`].
+///
+/// Each entry maps a term in the spoken language to the English term the translation must use,
+/// such as a product name or an agreed translation of a technical term.
+/// [`Self::apply`] installs a logits filter that nudges the decoder towards starting each English
+/// term, and strongly pushes it to finish a term once it has begun, so terms are never spelt
+/// or inflected differently from the glossary.
+///
+/// Biasing alone only helps the model choose the term; combine it with the entries in the prompt
+/// for the best results, e.g. `PromptBuilder::new().glossary(glossary.prompt_terms())`.
+///
+/// # Examples
+/// ```no_run
+/// # use whisper_rs::{FullParams, SamplingStrategy, TranslationGlossary, WhisperContext, WhisperContextParameters};
+/// # let ctx = WhisperContext::new_with_params("model.bin", WhisperContextParameters::default()).unwrap();
+/// let mut params = FullParams::new(SamplingStrategy::default());
+/// params.set_translate(true);
+/// TranslationGlossary::new()
+///     .term("Kundenkonto", "customer account")
+///     .term("Datenschutzerklärung", "Privacy Notice")
+///     .apply(&ctx, &mut params)
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct TranslationGlossary {
+    entries: Vec<(String, String)>,
+    start_bias: f32,
+    continue_bias: f32,
+}
+
+impl Default for TranslationGlossary {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TranslationGlossary {
+    pub fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+            start_bias: 1.5,
+            continue_bias: 10.0,
+        }
+    }
+
+    /// Require `target` wherever `source` is spoken.
+    pub fn term(mut self, source: impl Into<String>, target: impl Into<String>) -> Self {
+        self.entries.push((source.into(), target.into()));
+        self
+    }
+
+    /// Set the logit boost for the first token of each English term, at every position.
+    /// Keep this small, or terms start appearing where they weren't said.
+    ///
+    /// Defaults to 1.5.
+    pub fn start_bias(mut self, bias: f32) -> Self {
+        self.start_bias = bias;
+        self
+    }
+
+    /// Set the logit boost for the next token of an English term that has already begun.
+    ///
+    /// Defaults to 10.0.
+    pub fn continue_bias(mut self, bias: f32) -> Self {
+        self.continue_bias = bias;
+        self
+    }
+
+    /// The entries as `source = target` strings, for use in a prompt.
+    pub fn prompt_terms(&self) -> impl Iterator<Item = String> + '_ {
+        self.entries
+            .iter()
+            .map(|(source, target)| format!("{} = {}", source, target))
+    }
+
+    /// Install the glossary as a logits filter on `params`, see [`FullParams::add_logits_filter`].
+    ///
+    /// # Returns
+    /// `Err(WhisperError::NullByteInString)` if any term contains a nul byte.
+    pub fn apply(&self, ctx: &WhisperContext, params: &mut FullParams) -> Result<(), WhisperError> {
+        let mut sequences = Vec::new();
+        for (_, target) in &self.entries {
+            // mid-sentence terms are tokenized with their leading space, which changes the tokens
+            for text in [format!(" {}", target.trim()), target.trim().to_owned()] {
+                let tokens = ctx.tokenize_all(&text)?;
+                if !tokens.is_empty() && !sequences.contains(&tokens) {
+                    sequences.push(tokens);
+                }
+            }
+        }
+        if sequences.is_empty() {
+            return Ok(());
+        }
+
+        let bias = TermBias {
+            sequences,
+            eot: ctx.token_eot(),
+            start_bias: self.start_bias,
+            continue_bias: self.continue_bias,
+        };
+        let mut text = Vec::new();
+        params.add_logits_filter(move |tokens, logits| {
+            text.clear();
+            text.extend(tokens.iter().map(|t| t.id).filter(|&id| id < bias.eot));
+            bias.apply(&text, logits);
+        });
+        Ok(())
+    }
+}
+
+struct TermBias {
+    sequences: Vec<Vec<WhisperTokenId>>,
+    eot: WhisperTokenId,
+    start_bias: f32,
+    continue_bias: f32,
+}
+
+impl TermBias {
+    /// Bias `logits` given the text tokens decoded so far.
+    fn apply(&self, text: &[WhisperTokenId], logits: &mut [f32]) {
+        let mut boost = |token: WhisperTokenId, bias: f32| {
+            if let Some(logit) = usize::try_from(token).ok().and_then(|i| logits.get_mut(i)) {
+                *logit += bias;
+            }
+        };
+
+        for sequence in &self.sequences {
+            // longest partial match of the term at the end of the text
+            let matched = (1..sequence.len())
+                .rev()
+                .find(|&k| text.ends_with(&sequence[..k]));
+            match matched {
+                Some(k) => boost(sequence[k], self.continue_bias),
+                None => boost(sequence[0], self.start_bias),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn begun_terms_are_pushed_to_completion() {
+        let bias = TermBias {
+            sequences: vec![vec![3, 4, 5]],
+            eot: 10,
+            start_bias: 1.0,
+            continue_bias: 10.0,
+        };
+
+        let mut logits = [0.0; 10];
+        bias.apply(&[1, 2], &mut logits);
+        assert_eq!(logits[3], 1.0);
+        assert_eq!(logits.iter().sum::<f32>(), 1.0);
+
+        let mut logits = [0.0; 10];
+        bias.apply(&[1, 3, 4], &mut logits);
+        assert_eq!(logits[5], 10.0);
+        assert_eq!(logits.iter().sum::<f32>(), 10.0);
+    }
+}
@@ -0,0 +1,172 @@
+use crate::{FullParams, Language, SamplingStrategy, WhisperContext, WhisperError};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// Length of the health check input, in seconds.
+const PROBE_SECONDS: usize = 2;
+/// More text than this from two seconds of silence means the decoder is producing garbage.
+const MAX_PROBE_CHARS: usize = 100;
+
+/// Counters shared by a context and all of its states.
+#[derive(Debug, Default)]
+pub(crate) struct UsageCounters {
+    runs: AtomicU64,
+    errors: AtomicU64,
+    consecutive_errors: AtomicU64,
+}
+
+impl UsageCounters {
+    pub(crate) fn record<T>(&self, result: &Result<T, WhisperError>) {
+        self.runs.fetch_add(1, Ordering::Relaxed);
+        if result.is_ok() {
+            self.consecutive_errors.store(0, Ordering::Relaxed);
+        } else {
+            self.errors.fetch_add(1, Ordering::Relaxed);
+            self.consecutive_errors.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub(crate) fn snapshot(&self) -> ContextStats {
+        ContextStats {
+            runs: self.runs.load(Ordering::Relaxed),
+            errors: self.errors.load(Ordering::Relaxed),
+            consecutive_errors: self.consecutive_errors.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// How much a context has been used, see [`WhisperContext::stats`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ContextStats {
+    /// Calls to [`crate::WhisperState::full`] that reached whisper.cpp, over all states of the context.
+    /// Calls rejected by parameter validation are not counted.
+    pub runs: u64,
+    /// How many of those runs failed.
+    pub errors: u64,
+    /// Runs that failed since the last successful one.
+    /// A context that keeps failing is likely stuck in a bad GPU state and should be replaced.
+    pub consecutive_errors: u64,
+}
+
+/// Why a [`WhisperContext::health_check`] failed.
+#[derive(Debug, Clone)]
+pub enum HealthProblem {
+    /// Creating a state or running the model failed.
+    Failed(WhisperError),
+    /// The run took longer than allowed.
+    TooSlow(Duration),
+    /// The output doesn't fit the input, e.g. timestamps past its end or non-finite probabilities.
+    InvalidOutput(String),
+}
+
+impl std::fmt::Display for HealthProblem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Failed(e) => write!(f, "health check run failed: {}", e),
+            Self::TooSlow(elapsed) => write!(f, "health check took {:?}", elapsed),
+            Self::InvalidOutput(reason) => write!(f, "health check output is invalid: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for HealthProblem {}
+
+impl WhisperContext {
+    /// Usage counters of this model, shared by all of its clones and states.
+    pub fn stats(&self) -> ContextStats {
+        self.inner().usage.snapshot()
+    }
+
+    /// Transcribe two seconds of silence on a fresh state, and check that it completes within
+    /// `max_duration` with output that fits the input.
+    ///
+    /// Meant for pools of long-lived contexts, to detect one that has entered a bad GPU state so it
+    /// can be evicted. The run counts towards [`Self::stats`] like any other.
+    /// Allow for the first run on a GPU being slower, as kernels are compiled and buffers allocated.
+    ///
+    /// # Returns
+    /// `Ok(elapsed)` if the context is healthy, `Err(HealthProblem)` otherwise.
+    pub fn health_check(&self, max_duration: Duration) -> Result<Duration, HealthProblem> {
+        let started = Instant::now();
+        let mut state = self.create_state().map_err(|e| {
+            self.inner().usage.record::<()>(&Err(e));
+            HealthProblem::Failed(e)
+        })?;
+
+        let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
+        params.set_language(Language::English);
+        params.set_no_context(true);
+        params.set_single_segment(true);
+        params.set_n_threads(1);
+        params.set_print_progress(false);
+        params.set_print_realtime(false);
+        params.set_print_timestamps(false);
+        let audio = vec![0.0; PROBE_SECONDS * whisper_rs_sys::WHISPER_SAMPLE_RATE as usize];
+        state.full(params, &audio).map_err(HealthProblem::Failed)?;
+        let transcript = state.transcript().map_err(HealthProblem::Failed)?;
+
+        let elapsed = started.elapsed();
+        if elapsed > max_duration {
+            return Err(HealthProblem::TooSlow(elapsed));
+        }
+        check_output(&transcript, PROBE_SECONDS as i64 * 100)
+            .map_err(HealthProblem::InvalidOutput)?;
+        Ok(elapsed)
+    }
+}
+
+fn check_output(transcript: &crate::Transcript, audio_end: i64) -> Result<(), String> {
+    let chars: usize = transcript.iter().map(|s| s.text.chars().count()).sum();
+    if chars > MAX_PROBE_CHARS {
+        return Err(format!("{} characters of text from silence", chars));
+    }
+    for segment in transcript.iter() {
+        // whisper.cpp rounds the end of the last segment up to the next timestamp token
+        if segment.start < 0 || segment.end < segment.start || segment.end > audio_end + 100 {
+            return Err(format!(
+                "segment from {} to {} in {} cs of audio",
+                segment.start, segment.end, audio_end
+            ));
+        }
+        if let Some(token) = segment.tokens.iter().find(|t| !t.p.is_finite()) {
+            return Err(format!("token {} has probability {}", token.id, token.p));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Transcript, TranscriptSegment};
+
+    #[test]
+    fn counters_track_consecutive_errors() {
+        let usage = UsageCounters::default();
+        usage.record(&Ok(()));
+        usage.record::<()>(&Err(WhisperError::FailedToEncode));
+        usage.record::<()>(&Err(WhisperError::FailedToEncode));
+        assert_eq!(
+            usage.snapshot(),
+            ContextStats {
+                runs: 3,
+                errors: 2,
+                consecutive_errors: 2
+            }
+        );
+        usage.record(&Ok(()));
+        assert_eq!(usage.snapshot().consecutive_errors, 0);
+    }
+
+    #[test]
+    fn garbage_output_is_rejected() {
+        let ok = Transcript::new(vec![TranscriptSegment::new(0, 200, "")]);
+        assert!(check_output(&ok, 200).is_ok());
+
+        let past_end = Transcript::new(vec![TranscriptSegment::new(0, 3000, " you")]);
+        assert!(check_output(&past_end, 200).is_err());
+
+        let garbage = Transcript::new(vec![TranscriptSegment::new(0, 200, "!".repeat(500))]);
+        assert!(check_output(&garbage, 200).is_err());
+    }
+}
@@ -0,0 +1,326 @@
+use std::ffi::{c_char, c_int};
+use std::fmt;
+use std::str::FromStr;
+
+macro_rules! languages {
+    ($($variant:ident => $code:literal, $name:literal;)*) => {
+        /// A language Whisper can transcribe, or [`Language::Auto`] to detect it.
+        ///
+        /// Mirrors the language table of whisper.cpp, in the same order, so the position of
+        /// a language in [`Language::ALL`] is its whisper.cpp language id.
+        /// Parses from either the code or the English name of a language, ignoring case,
+        /// and displays as the code.
+        ///
+        /// # Examples
+        /// ```
+        /// # use whisper_rs::Language;
+        /// let language: Language = "German".parse().unwrap();
+        /// assert_eq!(language, Language::German);
+        /// assert_eq!(language.to_string(), "de");
+        /// assert!("xx".parse::<Language>().is_err());
+        /// ```
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum Language {
+            /// Detect the language from the audio.
+            Auto,
+            $($variant,)*
+        }
+
+        impl Language {
+            /// Every language, in the order of their whisper.cpp ids. Doesn't include [`Self::Auto`].
+            pub const ALL: &'static [Language] = &[$(Self::$variant,)*];
+
+            /// The code whisper.cpp uses for the language, e.g. `"de"`, or `"auto"`.
+            pub fn code(self) -> &'static str {
+                let code = self.c_code();
+                &code[..code.len() - 1]
+            }
+
+            /// The English name whisper.cpp uses for the language, e.g. `"german"`, or `"auto"`.
+            pub fn name(self) -> &'static str {
+                match self {
+                    Self::Auto => "auto",
+                    $(Self::$variant => $name,)*
+                }
+            }
+
+            /// The nul-terminated code.
+            fn c_code(self) -> &'static str {
+                match self {
+                    Self::Auto => "auto\0",
+                    $(Self::$variant => concat!($code, "\0"),)*
+                }
+            }
+        }
+    };
+}
+
+languages! {
+    English => "en", "english";
+    Chinese => "zh", "chinese";
+    German => "de", "german";
+    Spanish => "es", "spanish";
+    Russian => "ru", "russian";
+    Korean => "ko", "korean";
+    French => "fr", "french";
+    Japanese => "ja", "japanese";
+    Portuguese => "pt", "portuguese";
+    Turkish => "tr", "turkish";
+    Polish => "pl", "polish";
+    Catalan => "ca", "catalan";
+    Dutch => "nl", "dutch";
+    Arabic => "ar", "arabic";
+    Swedish => "sv", "swedish";
+    Italian => "it", "italian";
+    Indonesian => "id", "indonesian";
+    Hindi => "hi", "hindi";
+    Finnish => "fi", "finnish";
+    Vietnamese => "vi", "vietnamese";
+    Hebrew => "he", "hebrew";
+    Ukrainian => "uk", "ukrainian";
+    Greek => "el", "greek";
+    Malay => "ms", "malay";
+    Czech => "cs", "czech";
+    Romanian => "ro", "romanian";
+    Danish => "da", "danish";
+    Hungarian => "hu", "hungarian";
+    Tamil => "ta", "tamil";
+    Norwegian => "no", "norwegian";
+    Thai => "th", "thai";
+    Urdu => "ur", "urdu";
+    Croatian => "hr", "croatian";
+    Bulgarian => "bg", "bulgarian";
+    Lithuanian => "lt", "lithuanian";
+    Latin => "la", "latin";
+    Maori => "mi", "maori";
+    Malayalam => "ml", "malayalam";
+    Welsh => "cy", "welsh";
+    Slovak => "sk", "slovak";
+    Telugu => "te", "telugu";
+    Persian => "fa", "persian";
+    Latvian => "lv", "latvian";
+    Bengali => "bn", "bengali";
+    Serbian => "sr", "serbian";
+    Azerbaijani => "az", "azerbaijani";
+    Slovenian => "sl", "slovenian";
+    Kannada => "kn", "kannada";
+    Estonian => "et", "estonian";
+    Macedonian => "mk", "macedonian";
+    Breton => "br", "breton";
+    Basque => "eu", "basque";
+    Icelandic => "is", "icelandic";
+    Armenian => "hy", "armenian";
+    Nepali => "ne", "nepali";
+    Mongolian => "mn", "mongolian";
+    Bosnian => "bs", "bosnian";
+    Kazakh => "kk", "kazakh";
+    Albanian => "sq", "albanian";
+    Swahili => "sw", "swahili";
+    Galician => "gl", "galician";
+    Marathi => "mr", "marathi";
+    Punjabi => "pa", "punjabi";
+    Sinhala => "si", "sinhala";
+    Khmer => "km", "khmer";
+    Shona => "sn", "shona";
+    Yoruba => "yo", "yoruba";
+    Somali => "so", "somali";
+    Afrikaans => "af", "afrikaans";
+    Occitan => "oc", "occitan";
+    Georgian => "ka", "georgian";
+    Belarusian => "be", "belarusian";
+    Tajik => "tg", "tajik";
+    Sindhi => "sd", "sindhi";
+    Gujarati => "gu", "gujarati";
+    Amharic => "am", "amharic";
+    Yiddish => "yi", "yiddish";
+    Lao => "lo", "lao";
+    Uzbek => "uz", "uzbek";
+    Faroese => "fo", "faroese";
+    HaitianCreole => "ht", "haitian creole";
+    Pashto => "ps", "pashto";
+    Turkmen => "tk", "turkmen";
+    Nynorsk => "nn", "nynorsk";
+    Maltese => "mt", "maltese";
+    Sanskrit => "sa", "sanskrit";
+    Luxembourgish => "lb", "luxembourgish";
+    Myanmar => "my", "myanmar";
+    Tibetan => "bo", "tibetan";
+    Tagalog => "tl", "tagalog";
+    Malagasy => "mg", "malagasy";
+    Assamese => "as", "assamese";
+    Tatar => "tt", "tatar";
+    Hawaiian => "haw", "hawaiian";
+    Lingala => "ln", "lingala";
+    Hausa => "ha", "hausa";
+    Bashkir => "ba", "bashkir";
+    Javanese => "jw", "javanese";
+    Sundanese => "su", "sundanese";
+    Cantonese => "yue", "cantonese";
+}
+
+impl Language {
+    /// The whisper.cpp language id, or `None` for [`Self::Auto`].
+    pub fn id(self) -> Option<c_int> {
+        Self::ALL
+            .iter()
+            .position(|&language| language == self)
+            .map(|id| id as c_int)
+    }
+
+    /// The language with whisper.cpp language id `id`, the inverse of [`Self::id`].
+    pub fn from_id(id: c_int) -> Option<Language> {
+        Self::ALL.get(usize::try_from(id).ok()?).copied()
+    }
+
+    /// The English name capitalized for display, e.g. `"Haitian Creole"`, or `"Auto"`.
+    pub fn display_name(self) -> String {
+        self.name()
+            .split(' ')
+            .map(|word| {
+                let mut chars = word.chars();
+                chars.next().map_or_else(String::new, |first| {
+                    first.to_uppercase().chain(chars).collect()
+                })
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Pair per-language probabilities, indexed by language id, with their languages and sort
+    /// them most likely first.
+    pub(crate) fn ranked(probabilities: &[f32]) -> Vec<(Language, f32)> {
+        let mut ranked: Vec<(Language, f32)> = Self::ALL
+            .iter()
+            .copied()
+            .zip(probabilities.iter().copied())
+            .collect();
+        ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+        ranked
+    }
+
+    /// Keep the `allowed` languages of a ranking, scaled to sum to one.
+    pub(crate) fn restrict(
+        ranked: Vec<(Language, f32)>,
+        allowed: &[Language],
+    ) -> Vec<(Language, f32)> {
+        if allowed.is_empty() {
+            return ranked;
+        }
+        let mut kept: Vec<(Language, f32)> = ranked
+            .into_iter()
+            .filter(|(language, _)| allowed.contains(language))
+            .collect();
+        let total: f32 = kept.iter().map(|&(_, p)| p).sum();
+        if total > 0.0 {
+            kept.iter_mut().for_each(|(_, p)| *p /= total);
+        }
+        kept
+    }
+
+    /// Pointer to the nul-terminated code, valid for the whole program.
+    pub(crate) fn as_ptr(self) -> *const c_char {
+        self.c_code().as_ptr().cast()
+    }
+}
+
+impl Default for Language {
+    /// English, the language whisper.cpp decodes in by default.
+    fn default() -> Self {
+        Self::English
+    }
+}
+
+impl fmt::Display for Language {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+/// Error parsing a [`Language`] from a string that is neither a language code nor a language name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseLanguageError(pub String);
+
+impl fmt::Display for ParseLanguageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown language {:?}", self.0)
+    }
+}
+
+impl std::error::Error for ParseLanguageError {}
+
+impl FromStr for Language {
+    type Err = ParseLanguageError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        std::iter::once(Self::Auto)
+            .chain(Self::ALL.iter().copied())
+            .find(|l| l.code().eq_ignore_ascii_case(s) || l.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| ParseLanguageError(s.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn languages_round_trip() {
+        assert_eq!(Language::ALL.len(), 100);
+        for &language in Language::ALL {
+            assert_eq!(language.code().parse(), Ok(language));
+            assert_eq!(language.name().parse(), Ok(language));
+        }
+        assert_eq!(Language::German.id(), Some(2));
+        assert_eq!(Language::Cantonese.id(), Some(99));
+        assert_eq!(Language::Auto.id(), None);
+        assert_eq!("Haitian Creole".parse(), Ok(Language::HaitianCreole));
+        assert_eq!("auto".parse(), Ok(Language::Auto));
+        assert_eq!(
+            "klingon".parse::<Language>(),
+            Err(ParseLanguageError("klingon".to_string()))
+        );
+    }
+
+    #[test]
+    fn table_matches_whisper_cpp() {
+        let max_id = crate::get_lang_max_id();
+        assert_eq!(Language::ALL.len(), max_id as usize + 1);
+        for id in 0..=max_id {
+            let language = Language::from_id(id).unwrap();
+            assert_eq!(crate::get_lang_str(id), Some(language.code()));
+            assert_eq!(crate::get_lang_str_full(id), Some(language.name()));
+            assert_eq!(crate::get_lang_id(language.code()), Some(id));
+            assert_eq!(crate::get_lang_id(language.name()), Some(id));
+        }
+    }
+
+    #[test]
+    fn ids_and_display_names() {
+        for &language in Language::ALL {
+            assert_eq!(Language::from_id(language.id().unwrap()), Some(language));
+        }
+        assert_eq!(Language::from_id(100), None);
+        assert_eq!(Language::from_id(-1), None);
+        assert_eq!(Language::German.display_name(), "German");
+        assert_eq!(Language::HaitianCreole.display_name(), "Haitian Creole");
+        assert_eq!(Language::Auto.display_name(), "Auto");
+    }
+
+    #[test]
+    fn probabilities_are_ranked_by_language() {
+        let mut probabilities = vec![0.0; Language::ALL.len()];
+        probabilities[2] = 0.7;
+        probabilities[0] = 0.2;
+        let ranked = Language::ranked(&probabilities);
+        assert_eq!(ranked.len(), 100);
+        assert_eq!(ranked[0], (Language::German, 0.7));
+        assert_eq!(ranked[1], (Language::English, 0.2));
+
+        let restricted = Language::restrict(ranked, &[Language::English, Language::French]);
+        assert_eq!(
+            restricted,
+            [(Language::English, 1.0), (Language::French, 0.0)]
+        );
+    }
+}
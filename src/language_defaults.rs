@@ -0,0 +1,158 @@
+use crate::{FullParams, Language, WhisperError, WhisperState};
+use std::ffi::CStr;
+
+/// Languages written without spaces between words, where most characters take two or three
+/// byte-level tokens.
+const DENSE_SCRIPTS: &[&str] = &["zh", "ja", "ko", "yue", "th", "lo", "my", "km", "bo"];
+
+pub(crate) const NO_SPEECH_THOLD: u8 = 1 << 0;
+pub(crate) const ENTROPY_THOLD: u8 = 1 << 1;
+pub(crate) const SUPPRESS_BLANK: u8 = 1 << 2;
+pub(crate) const SUPPRESS_NST: u8 = 1 << 3;
+pub(crate) const SPLIT_ON_WORD: u8 = 1 << 4;
+
+/// Decoding parameters tuned for a language, see [`LanguageDefaults::for_language`].
+///
+/// [`FullParams::set_language`] applies these automatically, except for parameters that were set
+/// explicitly, so the order of the setter calls doesn't matter.
+/// Use [`FullParams::set_language_defaults`] to turn this off.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LanguageDefaults {
+    /// See [`FullParams::set_no_speech_thold`].
+    pub no_speech_thold: f32,
+    /// See [`FullParams::set_entropy_thold`].
+    pub entropy_thold: f32,
+    /// See [`FullParams::set_suppress_blank`].
+    pub suppress_blank: bool,
+    /// See [`FullParams::set_suppress_nst`].
+    pub suppress_nst: bool,
+    /// See [`FullParams::set_split_on_word`].
+    pub split_on_word: bool,
+}
+
+impl LanguageDefaults {
+    /// The whisper.cpp defaults, used for languages without an entry of their own.
+    pub const GENERIC: Self = Self {
+        no_speech_thold: 0.6,
+        entropy_thold: 2.4,
+        suppress_blank: true,
+        suppress_nst: false,
+        split_on_word: false,
+    };
+
+    /// Chinese, Japanese, Korean and the other languages in scripts without word spacing.
+    ///
+    /// Characters that are split into several byte tokens repeat the same lead-byte tokens, which
+    /// lowers the entropy of perfectly ordinary text. The lower threshold keeps it from being
+    /// mistaken for a repetition loop and decoded again at a higher temperature.
+    /// Non-speech token suppression stays off, since its list includes the 「」『』 quotes.
+    pub const DENSE_SCRIPT: Self = Self {
+        entropy_thold: 2.1,
+        ..Self::GENERIC
+    };
+
+    /// Look up the defaults for a language code such as `"de"` or `"ja"`.
+    /// Unknown codes, and `"auto"`, get [`Self::GENERIC`].
+    pub fn for_language(language: &str) -> Self {
+        if DENSE_SCRIPTS.contains(&language) {
+            Self::DENSE_SCRIPT
+        } else {
+            Self::GENERIC
+        }
+    }
+}
+
+impl FullParams<'_, '_> {
+    /// Apply the defaults for `language`, except to the parameters that were set explicitly.
+    ///
+    /// [`Self::set_language`] already does this; call it directly to use the defaults of a language
+    /// other than the one decoded, or after [`Self::set_language_defaults`] turned them off.
+    pub fn apply_language_defaults(&mut self, language: &str) {
+        let defaults = LanguageDefaults::for_language(language);
+        let unset = |field| self.explicit & field == 0;
+        if unset(NO_SPEECH_THOLD) {
+            self.fp.no_speech_thold = defaults.no_speech_thold;
+        }
+        if unset(ENTROPY_THOLD) {
+            self.fp.entropy_thold = defaults.entropy_thold;
+        }
+        if unset(SUPPRESS_BLANK) {
+            self.fp.suppress_blank = defaults.suppress_blank;
+        }
+        if unset(SUPPRESS_NST) {
+            self.fp.suppress_nst = defaults.suppress_nst;
+        }
+        if unset(SPLIT_ON_WORD) {
+            self.fp.split_on_word = defaults.split_on_word;
+        }
+    }
+
+    /// Apply [`LanguageDefaults`] whenever the language is set.
+    /// Turning this off restores the whisper.cpp defaults of all parameters not set explicitly.
+    ///
+    /// Defaults to true.
+    pub fn set_language_defaults(&mut self, enabled: bool) {
+        self.language_defaults = enabled;
+        let language = if enabled { self.language() } else { None };
+        self.apply_language_defaults(language.as_deref().unwrap_or("auto"));
+    }
+
+    fn language(&self) -> Option<String> {
+        if self.fp.language.is_null() {
+            return None;
+        }
+        // SAFETY: the language is either whisper.cpp's default, a string literal, or the static,
+        // nul-terminated code set_language stores from Language::as_ptr
+        let language = unsafe { CStr::from_ptr(self.fp.language) };
+        Some(language.to_string_lossy().into_owned())
+    }
+}
+
+impl WhisperState {
+    /// Detect the language of `samples` and set it on `params`, applying its [`LanguageDefaults`].
+    ///
+    /// whisper.cpp detects the language inside [`Self::full`], too late to adjust any parameters.
+    /// This runs the detection up front instead, on the first 30 seconds of `samples`;
+    /// [`Self::full`] then decodes in the detected language without detecting it again.
+    ///
+    /// # Returns
+    /// `Ok(language)` on success, where `language` is the detected language,
+    /// `Err(WhisperError)` on failure.
+    pub fn detect_language_defaults(
+        &mut self,
+        params: &mut FullParams<'_, '_>,
+        samples: &[f32],
+    ) -> Result<Language, WhisperError> {
+        let threads = params.fp.n_threads.max(1) as usize;
+        self.pcm_to_mel(samples, threads)?;
+        let (id, _) = self.lang_detect(0, threads)?;
+        let language = usize::try_from(id)
+            .ok()
+            .and_then(|id| Language::ALL.get(id).copied())
+            .ok_or(WhisperError::GenericError(id))?;
+        params.set_detect_language(false);
+        params.set_language(language);
+        Ok(language)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn dense_scripts_get_their_own_defaults() {
+        assert_eq!(
+            LanguageDefaults::for_language("ja"),
+            LanguageDefaults::DENSE_SCRIPT
+        );
+        assert_eq!(
+            LanguageDefaults::for_language("de"),
+            LanguageDefaults::GENERIC
+        );
+        assert_eq!(
+            LanguageDefaults::for_language("auto"),
+            LanguageDefaults::GENERIC
+        );
+    }
+}
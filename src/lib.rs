@@ -5,15 +5,85 @@
 //!
 //! You can get a [`WhisperState`] by first creating a [`WhisperContext`] using [`WhisperContext::new_with_params`],
 //! and then calling [`WhisperContext::create_state`].
+//!
+//! Applications that want to stay independent of the backend can be written against the [`Transcribe`]
+//! and [`StreamingTranscribe`] traits instead.
 
+#[cfg(feature = "cuda")]
+pub mod cuda;
 #[cfg(feature = "vulkan")]
 pub mod vulkan;
 
+#[cfg(feature = "streaming")]
+mod adaptive_chunks;
+#[cfg(feature = "audio-utils")]
+mod audio_pipeline;
+mod backend_info;
+mod backends;
+mod build_info;
+pub mod cache;
+mod calibration;
+mod capabilities;
+mod channels;
 mod common_logging;
+mod compat;
+mod context_compression;
+mod encoder_only;
 mod error;
+mod gbnf;
 mod ggml_logging_hook;
+mod glossary;
+mod health;
+mod language;
+mod language_defaults;
+mod logit_bias;
+mod memory_budget;
+mod model_fetch;
+mod model_info;
+#[cfg(feature = "server")]
+mod model_manager;
+pub mod models;
+mod observer;
+#[cfg(feature = "opus")]
+pub mod opus;
+#[cfg(feature = "output-formats")]
+pub mod output;
+#[cfg(feature = "audio-utils")]
+mod pcm;
+#[cfg(feature = "server")]
+mod pipeline;
+mod postprocess;
+mod power;
+mod presets;
+mod prompt;
+pub mod recording;
+#[cfg(feature = "server")]
+mod retry;
+#[cfg(feature = "rodio")]
+pub mod rodio;
+mod schedule;
+mod stable_text;
 mod standalone;
+#[cfg(feature = "streaming")]
+mod streaming;
+#[cfg(feature = "test-stub")]
+pub mod stub;
+#[cfg(feature = "unsafe-sys")]
+pub mod sys;
+#[cfg(feature = "audio-utils")]
+mod telephony;
+#[cfg(feature = "testing")]
+pub mod testing;
+mod threads;
+mod token_stream;
+mod transcribe;
+mod transcript;
 mod utilities;
+#[cfg(feature = "streaming")]
+mod vad_stream;
+mod vocabulary;
+mod watchdog;
+mod wav;
 mod whisper_ctx;
 mod whisper_ctx_wrapper;
 mod whisper_grammar;
@@ -22,21 +92,86 @@ mod whisper_params;
 mod whisper_state;
 mod whisper_vad;
 
+#[cfg(feature = "streaming")]
+pub use adaptive_chunks::AdaptiveChunking;
+#[cfg(feature = "audio-utils")]
+pub use audio_pipeline::{AudioPipeline, AudioPipelineStream};
+pub use backend_info::{BackendInfo, DeviceInfo, DeviceKind};
+pub use backends::{init_backends, live_contexts, load_backend, shutdown_backends};
+pub use build_info::{build_info, BuildInfo};
+pub use calibration::{expected_calibration_error, Calibration, CalibrationParseError};
+pub use capabilities::ModelCapabilities;
+pub use channels::{merge_by_time, DualChannel, MultiTrack};
 pub use common_logging::GGMLLogLevel;
+pub use compat::UnsupportedConfiguration;
+pub use context_compression::ContextCompression;
 pub use error::WhisperError;
+pub use gbnf::{Grammar, GrammarParseError};
+pub use glossary::TranslationGlossary;
+pub use health::{ContextStats, HealthProblem};
+pub use language::{Language, ParseLanguageError};
+pub use language_defaults::LanguageDefaults;
+pub use logit_bias::LogitBias;
+pub use memory_budget::StateMemoryEstimate;
+pub use model_fetch::{ModelFetcher, ModelLoadError, SendModelFetcher};
+pub use model_info::ModelInfo;
+#[cfg(feature = "server")]
+pub use model_manager::{ManagedModelStats, ModelManager, ModelManagerStats};
+pub use observer::{DtwFallback, Fallback, Observer, RunEnd, RunStart};
+#[cfg(feature = "audio-utils")]
+pub use pcm::{PcmFormat, PcmReader, PcmStreamError, SampleFormat};
+#[cfg(feature = "server")]
+pub use pipeline::{PipelineError, PrefetchPipeline};
+pub use postprocess::{
+    CaptionConditioner, InverseTextNormalizer, NoSpeechFilter, Processed, ProcessingPipeline,
+    ProfanityFilter, SilenceAction, SilenceSuppressor, TranscriptProcessor,
+};
+#[cfg(feature = "streaming")]
+pub use power::PowerAwareTranscriber;
+pub use power::{PowerMonitor, PowerProfile, PowerState, SystemPowerMonitor};
+pub use presets::{DistilPreset, TelephonyPreset, VocabularyPreset};
+pub use prompt::PromptBuilder;
+#[cfg(feature = "server")]
+pub use retry::RetryPolicy;
+pub use schedule::{DecodeAttempt, ScheduledTranscript, TemperatureSchedule};
+pub use stable_text::{CaptionStabilizer, StableUpdate};
 pub use standalone::*;
+#[cfg(feature = "streaming")]
+pub use streaming::StreamingTranscriber;
+#[cfg(feature = "audio-utils")]
+pub use telephony::G711;
+pub use threads::{CpuTopology, ThreadCounts};
+pub use token_stream::{StreamedToken, TokenEvent};
+pub use transcribe::{StreamingTranscribe, Transcribe};
+#[cfg(feature = "output-formats")]
+pub use transcript::TranscriptStore;
+pub use transcript::{
+    Confidence, DiffWord, DriftCorrector, DriftReport, EditList, TextAttribution, Transcript,
+    TranscriptDiff, TranscriptEditError, TranscriptSegment, TranscriptToken, WordChange,
+};
 pub use utilities::*;
+#[cfg(feature = "streaming")]
+pub use vad_stream::{VadGatedTranscriber, VadStreamError};
+pub use vocabulary::Vocabulary;
+pub use watchdog::{Watchdog, WatchdogReason};
+#[cfg(feature = "audio-utils")]
+pub use wav::write_wav;
 pub use whisper_ctx::DtwMode;
 pub use whisper_ctx::DtwModelPreset;
 pub use whisper_ctx::DtwParameters;
+pub use whisper_ctx::EncoderBackend;
+pub use whisper_ctx::OpenVinoEncoderParameters;
 pub use whisper_ctx::WhisperContextParameters;
 use whisper_ctx::WhisperInnerContext;
 pub use whisper_ctx_wrapper::WhisperContext;
 pub use whisper_grammar::{WhisperGrammarElement, WhisperGrammarElementType};
-pub use whisper_params::{FullParams, SamplingStrategy, SegmentCallbackData};
+pub use whisper_params::{FullParams, SamplingStrategy, SegmentBatch, SegmentCallbackData};
 #[cfg(feature = "raw-api")]
 pub use whisper_rs_sys;
-pub use whisper_state::{WhisperSegment, WhisperState, WhisperStateSegmentIterator, WhisperToken};
+pub use whisper_state::{
+    DecodeWindow, SegmentTokens, StateSnapshot, TokenData, WhisperSegment, WhisperState,
+    WhisperStateSegmentIterator, WhisperTimings, WhisperToken,
+};
 pub use whisper_vad::*;
 
 pub type WhisperSysContext = whisper_rs_sys::whisper_context;
@@ -0,0 +1,89 @@
+use crate::{FullParams, WhisperTokenId};
+use std::collections::HashMap;
+
+/// Fixed boosts and bans for individual tokens, applied at every decoding step.
+///
+/// Positive biases make a token more likely, negative ones less likely, and a bias of
+/// [`f32::NEG_INFINITY`] (see [`Self::ban`]) removes the token from consideration entirely.
+/// Biases are added to the raw logits, so a bias of about 2 to 5 already has a strong effect.
+///
+/// Token ids come from [`crate::WhisperContext::tokenize_all`]. A word usually tokenizes
+/// differently with a leading space, as it appears mid-sentence, so bias both forms. Banning a
+/// common token also bans it in every other word that contains it; for whole terms see
+/// [`crate::TranslationGlossary`].
+///
+/// # Examples
+/// ```no_run
+/// # use whisper_rs::{FullParams, LogitBias, SamplingStrategy, WhisperContext, WhisperContextParameters};
+/// # let ctx = WhisperContext::new_with_params("model.bin", WhisperContextParameters::default()).unwrap();
+/// let mut params = FullParams::new(SamplingStrategy::default());
+/// let acme = ctx.tokenize_all(" Acme").unwrap();
+/// LogitBias::new().bias(acme[0], 3.0).apply(&mut params);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LogitBias {
+    biases: HashMap<WhisperTokenId, f32>,
+}
+
+impl From<HashMap<WhisperTokenId, f32>> for LogitBias {
+    fn from(biases: HashMap<WhisperTokenId, f32>) -> Self {
+        Self { biases }
+    }
+}
+
+impl LogitBias {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add `bias` to the logit of `token`, replacing any bias set for it before.
+    pub fn bias(mut self, token: WhisperTokenId, bias: f32) -> Self {
+        self.biases.insert(token, bias);
+        self
+    }
+
+    /// Never produce `token`.
+    pub fn ban(self, token: WhisperTokenId) -> Self {
+        self.bias(token, f32::NEG_INFINITY)
+    }
+
+    /// The bias for every token, by id.
+    pub fn biases(&self) -> &HashMap<WhisperTokenId, f32> {
+        &self.biases
+    }
+
+    /// Install the biases as a logits filter on `params`, see [`FullParams::add_logits_filter`].
+    ///
+    /// Ids outside the model's vocabulary are ignored.
+    pub fn apply(self, params: &mut FullParams) {
+        if self.biases.is_empty() {
+            return;
+        }
+        params.add_logits_filter(move |_, logits| self.apply_to(logits));
+    }
+
+    fn apply_to(&self, logits: &mut [f32]) {
+        for (&token, &bias) in &self.biases {
+            if let Some(logit) = usize::try_from(token).ok().and_then(|i| logits.get_mut(i)) {
+                *logit += bias;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn biases_and_bans_known_tokens() {
+        let bias = LogitBias::new()
+            .bias(1, 2.0)
+            .ban(3)
+            .bias(-1, 5.0)
+            .bias(99, 5.0);
+        let mut logits = [1.0; 5];
+        bias.apply_to(&mut logits);
+        assert_eq!(logits, [1.0, 3.0, 1.0, f32::NEG_INFINITY, 1.0]);
+    }
+}
@@ -0,0 +1,211 @@
+use crate::common_logging::generic_warn;
+use crate::{
+    FullParams, ModelInfo, SamplingStrategy, UnsupportedConfiguration, WhisperContext,
+    WhisperError, WhisperState,
+};
+use std::ffi::c_int;
+
+const F16: u64 = 2;
+const F32: u64 = 4;
+
+/// Memory a [`WhisperState`] allocates on the model's device, estimated from the model's
+/// dimensions, see [`WhisperContext::estimate_state_memory`].
+///
+/// The KV caches are computed exactly as whisper.cpp sizes them. The compute buffers depend on
+/// how ggml schedules the graphs and are estimated from the largest activations, which comes
+/// within about a third of what whisper.cpp reports when a state is created.
+///
+/// The estimate is advisory: it doesn't include what the backend itself allocates, such as
+/// CUDA's context and cuBLAS workspaces, nor memory fragmentation, so leave some headroom when
+/// choosing a budget. The buffers are sized for the model's full audio context when a state is
+/// created and are reused by every run, so a smaller [`FullParams::set_audio_ctx`] doesn't
+/// lower the estimate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StateMemoryEstimate {
+    /// The decoder's self-attention cache. whisper.cpp sizes it for one decoder, and for
+    /// `n_decoders + 2` when a run uses more than one, to make up for fragmentation.
+    pub kv_self: u64,
+    /// The cross-attention cache over the encoder output.
+    pub kv_cross: u64,
+    /// Scratch buffers for running the encoder and decoder.
+    pub compute: u64,
+}
+
+impl StateMemoryEstimate {
+    pub fn total(&self) -> u64 {
+        self.kv_self + self.kv_cross + self.compute
+    }
+
+    fn of(info: &ModelInfo, n_decoders: usize, flash_attn: bool) -> Self {
+        let dim = |n: c_int| n.max(0) as u64;
+        // whisper.cpp pads cache lengths to 256
+        let pad = |n: c_int| dim(n).div_ceil(256) * 256;
+        let (audio_ctx, audio_state) = (dim(info.n_audio_ctx), dim(info.n_audio_state));
+        let (text_layer, text_state) = (dim(info.n_text_layer), dim(info.n_text_state));
+
+        let kv_self = 2 * text_layer * text_state * pad(info.n_text_ctx) * F16;
+        let kv_cross = 2 * text_layer * text_state * pad(info.n_audio_ctx) * F16
+            + 2 * audio_state * pad(info.n_audio_ctx) * F16;
+
+        let conv = 4 * audio_ctx * audio_state * F32;
+        let mut encoder = 8 * audio_ctx * audio_state * F32;
+        if !flash_attn {
+            encoder += dim(info.n_audio_head) * audio_ctx * audio_ctx * F32;
+        }
+        // logits and probabilities for a prompt of up to half the text context
+        let prompt = dim(info.n_text_ctx) / 2;
+        let mut decoder = 2 * dim(info.n_vocab) * prompt * F32;
+        if !flash_attn {
+            // cross-attention scores of the prompt over the whole audio context
+            decoder += dim(info.n_text_head) * prompt * audio_ctx * F32;
+        }
+        let factor = match n_decoders {
+            0 | 1 => 1,
+            n => n as u64 + 2,
+        };
+
+        Self {
+            kv_self: kv_self * factor,
+            kv_cross,
+            compute: conv + encoder + decoder,
+        }
+    }
+}
+
+/// The most parallel decoders, up to `max`, whose state fits in `budget` bytes.
+fn decoders_within(info: &ModelInfo, flash_attn: bool, max: usize, budget: u64) -> Option<usize> {
+    (1..=max.max(1))
+        .rev()
+        .find(|&n| StateMemoryEstimate::of(info, n, flash_attn).total() <= budget)
+}
+
+fn budget_exceeded(budget: u64, required: u64) -> WhisperError {
+    WhisperError::UnsupportedConfiguration(UnsupportedConfiguration::MemoryBudgetExceeded {
+        budget,
+        required,
+    })
+}
+
+impl WhisperContext {
+    /// Estimate the memory a state of this model allocates on the model's device when decoding
+    /// with `n_decoders` parallel decoders, see [`StateMemoryEstimate`]. The model weights are
+    /// not included; they are shared by all states.
+    ///
+    /// Greedy decoding uses `best_of` decoders and beam search `beam_size`, see
+    /// [`FullParams::fit_memory_budget`].
+    pub fn estimate_state_memory(&self, n_decoders: usize) -> StateMemoryEstimate {
+        StateMemoryEstimate::of(&self.model_info(), n_decoders, self.inner().flash_attn)
+    }
+
+    /// Create a state, but only if it is estimated to fit in `budget` bytes with a single
+    /// decoder, so one oversized model can't exhaust the memory of a GPU shared between requests.
+    ///
+    /// The buffers of a state are sized for a full 30 second window when it is created, so
+    /// [`FullParams::set_audio_ctx`] doesn't reduce them. Enabling
+    /// [`crate::WhisperContextParameters::flash_attn`] does.
+    ///
+    /// # Returns
+    /// `Err(WhisperError::UnsupportedConfiguration(UnsupportedConfiguration::MemoryBudgetExceeded))`
+    /// with the estimated minimum if the state doesn't fit.
+    pub fn create_state_within(&self, budget: u64) -> Result<WhisperState, WhisperError> {
+        let required = self.estimate_state_memory(1).total();
+        if required > budget {
+            return Err(budget_exceeded(budget, required));
+        }
+        self.create_state()
+    }
+}
+
+impl FullParams<'_, '_> {
+    /// Lower the number of parallel decoders (`best_of` or `beam_size`) until a state of `ctx`
+    /// is estimated to fit in `budget` bytes, see [`WhisperContext::estimate_state_memory`].
+    ///
+    /// whisper.cpp grows the self-attention cache of a state when a run needs more decoders than
+    /// it has, so limit every run sharing a budget this way.
+    ///
+    /// # Returns
+    /// `Err(WhisperError::UnsupportedConfiguration(UnsupportedConfiguration::MemoryBudgetExceeded))`
+    /// with the estimated minimum if even a single decoder doesn't fit.
+    pub fn fit_memory_budget(
+        &mut self,
+        ctx: &WhisperContext,
+        budget: u64,
+    ) -> Result<(), WhisperError> {
+        let strategy = self.sampling_strategy();
+        let n_decoders = match strategy {
+            SamplingStrategy::Greedy { best_of } => best_of,
+            SamplingStrategy::BeamSearch { beam_size, .. } => beam_size,
+        }
+        .max(1) as usize;
+
+        let info = ctx.model_info();
+        let flash_attn = ctx.inner().flash_attn;
+        let Some(fitting) = decoders_within(&info, flash_attn, n_decoders, budget) else {
+            let required = StateMemoryEstimate::of(&info, 1, flash_attn).total();
+            return Err(budget_exceeded(budget, required));
+        };
+        if fitting < n_decoders {
+            generic_warn!(
+                "whisper-rs: reducing from {} to {} decoders to fit the memory budget of {} bytes",
+                n_decoders,
+                fitting,
+                budget
+            );
+            self.set_sampling_strategy(match strategy {
+                SamplingStrategy::Greedy { .. } => SamplingStrategy::Greedy {
+                    best_of: fitting as c_int,
+                },
+                SamplingStrategy::BeamSearch { patience, .. } => SamplingStrategy::BeamSearch {
+                    beam_size: fitting as c_int,
+                    patience,
+                },
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn base() -> ModelInfo {
+        ModelInfo {
+            size: "base".to_string(),
+            n_vocab: 51865,
+            n_audio_ctx: 1500,
+            n_audio_state: 512,
+            n_audio_head: 8,
+            n_audio_layer: 6,
+            n_text_ctx: 448,
+            n_text_state: 512,
+            n_text_head: 8,
+            n_text_layer: 6,
+            n_mels: 80,
+            ftype: 1,
+            multilingual: true,
+        }
+    }
+
+    #[test]
+    fn caches_are_sized_like_whisper_cpp() {
+        let estimate = StateMemoryEstimate::of(&base(), 1, false);
+        // whisper.cpp reports 6.29 MB for the self cache, 18.87 + 3.15 MB for cross and pad
+        assert_eq!(estimate.kv_self, 6_291_456);
+        assert_eq!(estimate.kv_cross, 22_020_096);
+        let beams = StateMemoryEstimate::of(&base(), 5, false);
+        assert_eq!(beams.kv_self, 7 * estimate.kv_self);
+        let with_flash_attn = StateMemoryEstimate::of(&base(), 1, true);
+        assert!(with_flash_attn.compute < estimate.compute);
+    }
+
+    #[test]
+    fn decoders_are_reduced_to_fit() {
+        let info = base();
+        let one = StateMemoryEstimate::of(&info, 1, false).total();
+        let two = StateMemoryEstimate::of(&info, 2, false).total();
+        assert_eq!(decoders_within(&info, false, 5, two), Some(2));
+        assert_eq!(decoders_within(&info, false, 5, u64::MAX), Some(5));
+        assert_eq!(decoders_within(&info, false, 5, one - 1), None);
+    }
+}
@@ -0,0 +1,361 @@
+use crate::encoder_only::StripDecoder;
+use crate::{DtwMode, WhisperContext, WhisperContextParameters, WhisperError, WhisperInnerContext};
+use std::any::Any;
+use std::ffi::c_void;
+use std::fmt;
+use std::future::Future;
+use std::io::{self, BufRead, BufReader, Read};
+use std::panic::{self, AssertUnwindSafe};
+
+/// A source of model bytes for platforms without normal file access, such as WebAssembly in a
+/// browser (`fetch`) or Android (`AssetManager`), see [`WhisperContext::from_fetcher`].
+///
+/// Any async runtime works: the crate only awaits the returned futures. Fetchers whose futures
+/// are not [`Send`], such as those awaiting JavaScript promises, implement this trait; to load
+/// from code that is generic over the fetcher and needs a [`Send`] future, e.g. to spawn it on a
+/// multithreaded runtime, implement [`SendModelFetcher`] instead.
+pub trait ModelFetcher {
+    /// Size of the whole model in bytes, if known before fetching, to report progress against.
+    fn size_hint(&self) -> Option<u64> {
+        None
+    }
+
+    /// Fetch the next chunk of the model. `Ok(None)` ends the model.
+    fn next_chunk(&mut self) -> impl Future<Output = io::Result<Option<Vec<u8>>>>;
+}
+
+/// A [`ModelFetcher`] whose futures are [`Send`], so [`WhisperContext::from_fetcher`] is too,
+/// as long as its `progress` callback is.
+///
+/// Every `SendModelFetcher` is a [`ModelFetcher`].
+pub trait SendModelFetcher: Send {
+    /// See [`ModelFetcher::size_hint`].
+    fn size_hint(&self) -> Option<u64> {
+        None
+    }
+
+    /// See [`ModelFetcher::next_chunk`].
+    fn next_chunk(&mut self) -> impl Future<Output = io::Result<Option<Vec<u8>>>> + Send;
+}
+
+impl<T: SendModelFetcher> ModelFetcher for T {
+    fn size_hint(&self) -> Option<u64> {
+        SendModelFetcher::size_hint(self)
+    }
+
+    fn next_chunk(&mut self) -> impl Future<Output = io::Result<Option<Vec<u8>>>> {
+        SendModelFetcher::next_chunk(self)
+    }
+}
+
+/// Why [`WhisperContext::from_fetcher`] or [`WhisperContext::from_reader`] failed.
+#[derive(Debug)]
+pub enum ModelLoadError {
+    /// The fetcher or reader failed.
+    Fetch(io::Error),
+    /// The fetched bytes couldn't be loaded as a model.
+    Load(WhisperError),
+}
+
+impl fmt::Display for ModelLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Fetch(e) => write!(f, "failed to read the model: {}", e),
+            Self::Load(e) => write!(f, "failed to load the model: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ModelLoadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Fetch(e) => Some(e),
+            Self::Load(e) => Some(e),
+        }
+    }
+}
+
+impl WhisperContext {
+    /// Create a new WhisperContext from a model fetched in chunks by `fetcher`.
+    ///
+    /// `progress` is called after every chunk with the number of bytes fetched so far and the
+    /// total size, if known. The chunks are collected in memory and loaded with
+    /// [`Self::new_from_buffer_with_params`], which needs no file system access either.
+    /// For a model embedded in the binary with [`include_bytes!`], use that directly.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// # use whisper_rs::{ModelFetcher, WhisperContext, WhisperContextParameters};
+    /// /// A model split into assets of a few MB each, as some app stores require.
+    /// struct Assets(std::vec::IntoIter<&'static str>);
+    ///
+    /// impl ModelFetcher for Assets {
+    ///     async fn next_chunk(&mut self) -> std::io::Result<Option<Vec<u8>>> {
+    ///         self.0.next().map(std::fs::read).transpose()
+    ///     }
+    /// }
+    ///
+    /// # async fn load() -> Result<WhisperContext, whisper_rs::ModelLoadError> {
+    /// let assets = Assets(vec!["model.bin.0", "model.bin.1"].into_iter());
+    /// WhisperContext::from_fetcher(assets, WhisperContextParameters::default(), |done, total| {
+    ///     println!("{} of {:?} bytes", done, total);
+    /// })
+    /// .await
+    /// # }
+    /// ```
+    pub async fn from_fetcher(
+        mut fetcher: impl ModelFetcher,
+        parameters: WhisperContextParameters<'_>,
+        mut progress: impl FnMut(u64, Option<u64>),
+    ) -> Result<Self, ModelLoadError> {
+        let total = fetcher.size_hint();
+        let mut buffer = Vec::with_capacity(total.unwrap_or(0) as usize);
+        while let Some(chunk) = fetcher.next_chunk().await.map_err(ModelLoadError::Fetch)? {
+            buffer.extend_from_slice(&chunk);
+            progress(buffer.len() as u64, total);
+        }
+        Self::new_from_buffer_with_params(&buffer, parameters).map_err(ModelLoadError::Load)
+    }
+
+    /// Create a new WhisperContext from a model read from `reader`, such as an entry of a tar or
+    /// zip archive or a download, without collecting it in memory or on disk first.
+    ///
+    /// whisper.cpp reads the model from start to end once, so the reader doesn't need to seek.
+    /// With [`WhisperContextParameters::encoder_only`], the decoder layers are dropped on the way.
+    /// If `reader` fails, loading stops and its first error is returned; if it panics, the panic
+    /// is caught before it reaches whisper.cpp and resumed once loading has stopped.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// # use whisper_rs::{WhisperContext, WhisperContextParameters};
+    /// # use std::io::{Read, Seek, SeekFrom};
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let (offset, size) = (512, 147_951_465);
+    /// // an uncompressed model stored at `offset` in an archive
+    /// let mut archive = std::fs::File::open("models.tar")?;
+    /// archive.seek(SeekFrom::Start(offset))?;
+    /// let ctx = WhisperContext::from_reader(archive.take(size), WhisperContextParameters::default())?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # C++ equivalent
+    /// `struct whisper_context * whisper_init_with_params_no_state(struct whisper_model_loader * loader, struct whisper_context_params params);`
+    pub fn from_reader<R: Read>(
+        reader: R,
+        parameters: WhisperContextParameters<'_>,
+    ) -> Result<Self, ModelLoadError> {
+        load(reader, parameters).map(Self::wrap)
+    }
+}
+
+/// Load the model read from `reader`, leaving out its decoder layers for
+/// [`WhisperContextParameters::encoder_only`].
+pub(crate) fn load<R: Read>(
+    reader: R,
+    parameters: WhisperContextParameters<'_>,
+) -> Result<WhisperInnerContext, ModelLoadError> {
+    if !parameters.encoder_only {
+        return load_from(BufReader::new(reader), parameters);
+    }
+    if !matches!(parameters.dtw_parameters.mode, DtwMode::None) {
+        // DTW takes its alignment heads from the decoder layers
+        return Err(ModelLoadError::Load(WhisperError::DecoderNotLoaded));
+    }
+    load_from(BufReader::new(StripDecoder::new(reader)), parameters)
+}
+
+fn load_from<R: BufRead>(
+    reader: R,
+    parameters: WhisperContextParameters<'_>,
+) -> Result<WhisperInnerContext, ModelLoadError> {
+    let mut source = LoaderSource {
+        reader,
+        failure: None,
+    };
+    let mut loader = whisper_rs_sys::whisper_model_loader {
+        context: &mut source as *mut LoaderSource<_> as *mut c_void,
+        read: Some(loader_read::<R>),
+        eof: Some(loader_eof::<R>),
+        close: Some(loader_close),
+    };
+    // SAFETY: `source` outlives loading, and the callbacks are instantiated for its type
+    let ctx = unsafe { WhisperInnerContext::new_from_loader_with_params(&mut loader, parameters) };
+    match source.failure {
+        Some(Failure::Panic(payload)) => {
+            drop(ctx);
+            panic::resume_unwind(payload)
+        }
+        Some(Failure::Io(e)) => Err(ModelLoadError::Fetch(e)),
+        None => ctx.map_err(ModelLoadError::Load),
+    }
+}
+
+/// What went wrong while whisper.cpp was reading a model through a [`LoaderSource`].
+enum Failure {
+    Io(io::Error),
+    Panic(Box<dyn Any + Send>),
+}
+
+/// The context of a `whisper_model_loader` reading from Rust. After a failure, it reads as if
+/// at the end of the model, so whisper.cpp stops loading.
+struct LoaderSource<R> {
+    reader: R,
+    failure: Option<Failure>,
+}
+
+impl<R: BufRead> LoaderSource<R> {
+    /// Run `f` on the reader unless it already failed, recording any error or panic.
+    fn with_reader<T>(&mut self, f: impl FnOnce(&mut R) -> io::Result<T>) -> Option<T> {
+        if self.failure.is_some() {
+            return None;
+        }
+        match panic::catch_unwind(AssertUnwindSafe(|| f(&mut self.reader))) {
+            Ok(Ok(value)) => Some(value),
+            Ok(Err(e)) => {
+                self.failure = Some(Failure::Io(e));
+                None
+            }
+            Err(payload) => {
+                self.failure = Some(Failure::Panic(payload));
+                None
+            }
+        }
+    }
+}
+
+unsafe extern "C" fn loader_read<R: BufRead>(
+    ctx: *mut c_void,
+    output: *mut c_void,
+    read_size: usize,
+) -> usize {
+    let source = unsafe { &mut *(ctx as *mut LoaderSource<R>) };
+    let output = unsafe { std::slice::from_raw_parts_mut(output as *mut u8, read_size) };
+    // whisper.cpp doesn't check how much was read, so never leave the rest uninitialized
+    let read = source.with_reader(|reader| {
+        let mut filled = 0;
+        while filled < output.len() {
+            match reader.read(&mut output[filled..]) {
+                Ok(0) => break,
+                Ok(n) => filled += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(filled)
+    });
+    let read = read.unwrap_or(0);
+    output[read..].fill(0);
+    read
+}
+
+unsafe extern "C" fn loader_eof<R: BufRead>(ctx: *mut c_void) -> bool {
+    let source = unsafe { &mut *(ctx as *mut LoaderSource<R>) };
+    source
+        .with_reader(|reader| Ok(reader.fill_buf()?.is_empty()))
+        .unwrap_or(true)
+}
+
+/// The reader is dropped by [`load`] once loading has returned.
+unsafe extern "C" fn loader_close(_ctx: *mut c_void) {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::pin::pin;
+    use std::task::{Context, Poll, Waker};
+
+    /// A model held in memory, fetched a few bytes at a time.
+    struct InMemory(Vec<u8>);
+
+    impl SendModelFetcher for InMemory {
+        fn size_hint(&self) -> Option<u64> {
+            Some(self.0.len() as u64)
+        }
+
+        async fn next_chunk(&mut self) -> io::Result<Option<Vec<u8>>> {
+            if self.0.is_empty() {
+                return Ok(None);
+            }
+            let rest = self.0.split_off(self.0.len().min(4));
+            Ok(Some(std::mem::replace(&mut self.0, rest)))
+        }
+    }
+
+    fn assert_send<T: Send>(value: T) -> T {
+        value
+    }
+
+    /// Generic code can rely on the future being `Send`.
+    fn load_in_background<F: SendModelFetcher>(
+        fetcher: F,
+        progress: impl FnMut(u64, Option<u64>) + Send,
+    ) -> impl Future<Output = Result<WhisperContext, ModelLoadError>> + Send {
+        assert_send(WhisperContext::from_fetcher(
+            fetcher,
+            WhisperContextParameters::default(),
+            progress,
+        ))
+    }
+
+    #[test]
+    fn fetched_chunks_are_collected() {
+        let mut reports = Vec::new();
+        let future = load_in_background(InMemory(b"not a model".to_vec()), |done, total| {
+            reports.push((done, total))
+        });
+        let result = match pin!(future).poll(&mut Context::from_waker(Waker::noop())) {
+            Poll::Ready(result) => result,
+            Poll::Pending => panic!("in-memory fetches never wait"),
+        };
+        assert!(matches!(result, Err(ModelLoadError::Load(_))));
+        assert_eq!(reports, [(4, Some(11)), (8, Some(11)), (11, Some(11))]);
+    }
+
+    #[test]
+    fn reader_failures_end_the_model() {
+        struct Failing(usize);
+        impl Read for Failing {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                if self.0 == 0 {
+                    return Err(io::Error::other("connection reset"));
+                }
+                let n = buf.len().min(self.0);
+                buf[..n].fill(7);
+                self.0 -= n;
+                Ok(n)
+            }
+        }
+
+        let mut source = LoaderSource {
+            reader: BufReader::with_capacity(2, Failing(3)),
+            failure: None,
+        };
+        let ctx = &mut source as *mut LoaderSource<_> as *mut c_void;
+        let mut out = [1u8; 4];
+        unsafe {
+            assert!(!loader_eof::<BufReader<Failing>>(ctx));
+            assert_eq!(
+                loader_read::<BufReader<Failing>>(ctx, out.as_mut_ptr() as _, 2),
+                2
+            );
+            assert_eq!(out, [7, 7, 1, 1]);
+            // the error cuts the model short, and reads after it return nothing
+            assert_eq!(
+                loader_read::<BufReader<Failing>>(ctx, out.as_mut_ptr() as _, 4),
+                0
+            );
+            assert_eq!(out, [0; 4]);
+            assert!(loader_eof::<BufReader<Failing>>(ctx));
+        }
+        assert!(matches!(source.failure, Some(Failure::Io(_))));
+
+        let mut source = LoaderSource {
+            reader: BufReader::new(io::empty()),
+            failure: None,
+        };
+        let ctx = &mut source as *mut LoaderSource<_> as *mut c_void;
+        assert!(unsafe { loader_eof::<BufReader<io::Empty>>(ctx) });
+        assert!(source.failure.is_none());
+    }
+}
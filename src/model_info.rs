@@ -0,0 +1,129 @@
+use crate::WhisperContext;
+use std::ffi::c_int;
+use std::fmt;
+
+/// The hyperparameters of a loaded model, see [`WhisperContext::model_info`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModelInfo {
+    /// Size class as named by whisper.cpp, e.g. `base` or `large`, from the number of encoder layers.
+    pub size: String,
+    pub n_vocab: c_int,
+    /// Number of audio frames in the encoder context, 1500 for 30 seconds.
+    pub n_audio_ctx: c_int,
+    pub n_audio_state: c_int,
+    pub n_audio_head: c_int,
+    pub n_audio_layer: c_int,
+    /// Number of tokens in the decoder context.
+    pub n_text_ctx: c_int,
+    pub n_text_state: c_int,
+    pub n_text_head: c_int,
+    pub n_text_layer: c_int,
+    pub n_mels: c_int,
+    /// The ggml file type, see [`Self::ftype_name`].
+    pub ftype: c_int,
+    pub multilingual: bool,
+}
+
+impl ModelInfo {
+    /// Name of the weight type, such as `f16` or `q5_0`, or `None` for an unknown file type.
+    ///
+    /// Quantized models keep some tensors in higher precision; this is the type of the bulk of
+    /// the weights.
+    pub fn ftype_name(&self) -> Option<&'static str> {
+        Some(match self.ftype {
+            0 => "f32",
+            1 => "f16",
+            2 => "q4_0",
+            3 => "q4_1",
+            7 => "q8_0",
+            8 => "q5_0",
+            9 => "q5_1",
+            10 => "q2_k",
+            11 => "q3_k",
+            12 => "q4_k",
+            13 => "q5_k",
+            14 => "q6_k",
+            _ => return None,
+        })
+    }
+}
+
+impl fmt::Display for ModelInfo {
+    /// A one-line summary, e.g. `base (multilingual, f16): 6+6 layers, 512 dims, 80 mels`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} ({}, ",
+            self.size,
+            if self.multilingual {
+                "multilingual"
+            } else {
+                "English-only"
+            }
+        )?;
+        match self.ftype_name() {
+            Some(name) => f.write_str(name)?,
+            None => write!(f, "ftype {}", self.ftype)?,
+        }
+        write!(
+            f,
+            "): {}+{} layers, {} dims, {} mels",
+            self.n_audio_layer, self.n_text_layer, self.n_audio_state, self.n_mels
+        )
+    }
+}
+
+impl WhisperContext {
+    /// The hyperparameters of the loaded model, for display and logging.
+    pub fn model_info(&self) -> ModelInfo {
+        ModelInfo {
+            size: self
+                .model_type_readable_str_lossy()
+                .map(|size| size.into_owned())
+                .unwrap_or_default(),
+            n_vocab: self.model_n_vocab(),
+            n_audio_ctx: self.model_n_audio_ctx(),
+            n_audio_state: self.model_n_audio_state(),
+            n_audio_head: self.model_n_audio_head(),
+            n_audio_layer: self.model_n_audio_layer(),
+            n_text_ctx: self.model_n_text_ctx(),
+            n_text_state: self.model_n_text_state(),
+            n_text_head: self.model_n_text_head(),
+            n_text_layer: self.model_n_text_layer(),
+            n_mels: self.model_n_mels(),
+            ftype: self.model_ftype(),
+            multilingual: self.is_multilingual(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn summary_names_the_weight_type() {
+        let mut info = ModelInfo {
+            size: "base".to_string(),
+            n_vocab: 51865,
+            n_audio_ctx: 1500,
+            n_audio_state: 512,
+            n_audio_head: 8,
+            n_audio_layer: 6,
+            n_text_ctx: 448,
+            n_text_state: 512,
+            n_text_head: 8,
+            n_text_layer: 6,
+            n_mels: 80,
+            ftype: 1,
+            multilingual: true,
+        };
+        assert_eq!(
+            info.to_string(),
+            "base (multilingual, f16): 6+6 layers, 512 dims, 80 mels"
+        );
+        info.ftype = 99;
+        assert_eq!(info.ftype_name(), None);
+        assert!(info.to_string().contains("ftype 99"));
+    }
+}
@@ -0,0 +1,211 @@
+use crate::observer::Observers;
+use crate::{
+    Observer, WhisperContext, WhisperContextParameters, WhisperError, WhisperInnerContext,
+    WhisperState,
+};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, RwLock, Weak};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+struct ActiveModel {
+    ctx: WhisperContext,
+    generation: u64,
+    size_bytes: Option<u64>,
+}
+
+struct RetiredModel {
+    ctx: Weak<WhisperInnerContext>,
+    generation: u64,
+    size_bytes: Option<u64>,
+}
+
+/// Memory accounting for one model held by a [`ModelManager`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ManagedModelStats {
+    /// Incremented every time a new model is swapped in, starting at 0.
+    pub generation: u64,
+    /// Size of the model file in bytes, if it was loaded from a file.
+    pub size_bytes: Option<u64>,
+    /// Number of live [`WhisperContext`] clones and [`WhisperState`]s referencing this model,
+    /// not counting the manager itself.
+    pub users: usize,
+}
+
+/// Snapshot of a [`ModelManager`], see [`ModelManager::stats`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModelManagerStats {
+    /// The model new requests are served by.
+    pub active: ManagedModelStats,
+    /// Replaced models still kept alive by in-flight states.
+    pub draining: Vec<ManagedModelStats>,
+}
+
+impl ModelManagerStats {
+    /// Total size of all models currently in memory, counting only those with a known size.
+    pub fn total_bytes(&self) -> u64 {
+        std::iter::once(&self.active)
+            .chain(&self.draining)
+            .filter_map(|m| m.size_bytes)
+            .sum()
+    }
+}
+
+/// Serves a model that can be replaced while it is in use.
+///
+/// New states are always created from the active model. When a model is swapped out, states
+/// already created from it keep working, and its memory is freed once the last of them is dropped.
+/// This allows upgrading models in a running service without downtime.
+///
+/// Share it between threads with an [`Arc`].
+pub struct ModelManager {
+    active: RwLock<ActiveModel>,
+    retired: Mutex<Vec<RetiredModel>>,
+    observers: Observers,
+}
+
+impl ModelManager {
+    /// Start serving `ctx`.
+    pub fn new(ctx: WhisperContext) -> Self {
+        let size_bytes = model_size(&ctx);
+        Self {
+            active: RwLock::new(ActiveModel {
+                ctx,
+                generation: 0,
+                size_bytes,
+            }),
+            retired: Mutex::new(Vec::new()),
+            observers: Observers::default(),
+        }
+    }
+
+    /// Register `observer` on the active model and on every model swapped in later,
+    /// see [`WhisperContext::add_observer`].
+    pub fn add_observer(&self, observer: Arc<dyn Observer>) {
+        // hold the lock so a concurrent swap can't miss the observer
+        let active = self.active.read().expect("model lock poisoned");
+        self.observers.add(observer.clone());
+        active.ctx.add_observer(observer);
+    }
+
+    /// The active model.
+    pub fn current(&self) -> WhisperContext {
+        self.active.read().expect("model lock poisoned").ctx.clone()
+    }
+
+    /// Generation of the active model, see [`ManagedModelStats::generation`].
+    pub fn generation(&self) -> u64 {
+        self.active.read().expect("model lock poisoned").generation
+    }
+
+    /// Create a state from the active model.
+    pub fn create_state(&self) -> Result<WhisperState, WhisperError> {
+        self.current().create_state()
+    }
+
+    /// Atomically make `ctx` the active model. The previous model drains in the background.
+    ///
+    /// # Returns
+    /// The generation of the new model.
+    pub fn swap(&self, ctx: WhisperContext) -> u64 {
+        let size_bytes = model_size(&ctx);
+        let mut active = self.active.write().expect("model lock poisoned");
+        for observer in self.observers.get() {
+            ctx.add_observer(observer);
+        }
+        let generation = active.generation + 1;
+        let old = std::mem::replace(
+            &mut *active,
+            ActiveModel {
+                ctx,
+                generation,
+                size_bytes,
+            },
+        );
+        drop(active);
+
+        let mut retired = self.retired.lock().expect("retired model lock poisoned");
+        retired.push(RetiredModel {
+            ctx: old.ctx.downgrade(),
+            generation: old.generation,
+            size_bytes: old.size_bytes,
+        });
+        // dropping `old` here releases the manager's reference, so the model is freed
+        // as soon as no states use it any more
+        drop(old);
+        retired.retain(|m| m.ctx.strong_count() > 0);
+        generation
+    }
+
+    /// Load the model at `path` on the calling thread, then swap it in. See [`Self::swap`].
+    pub fn load(
+        &self,
+        path: &str,
+        parameters: WhisperContextParameters,
+    ) -> Result<u64, WhisperError> {
+        let ctx = WhisperContext::new_with_params(path, parameters)?;
+        Ok(self.swap(ctx))
+    }
+
+    /// Load the model at `path` on a new thread, then swap it in. The active model keeps serving
+    /// requests while loading. If loading fails, the active model is left untouched.
+    pub fn load_in_background(
+        self: &Arc<Self>,
+        path: impl Into<PathBuf>,
+        parameters: WhisperContextParameters<'static>,
+    ) -> JoinHandle<Result<u64, WhisperError>> {
+        let manager = Arc::clone(self);
+        let path = path.into();
+        std::thread::spawn(move || manager.load(&path.to_string_lossy(), parameters))
+    }
+
+    /// Current memory accounting for the active and draining models.
+    pub fn stats(&self) -> ModelManagerStats {
+        let active = self.active.read().expect("model lock poisoned");
+        let active_stats = ManagedModelStats {
+            generation: active.generation,
+            size_bytes: active.size_bytes,
+            users: active.ctx.downgrade().strong_count() - 1,
+        };
+        drop(active);
+
+        let mut retired = self.retired.lock().expect("retired model lock poisoned");
+        retired.retain(|m| m.ctx.strong_count() > 0);
+        let draining = retired
+            .iter()
+            .map(|m| ManagedModelStats {
+                generation: m.generation,
+                size_bytes: m.size_bytes,
+                users: m.ctx.strong_count(),
+            })
+            .collect();
+
+        ModelManagerStats {
+            active: active_stats,
+            draining,
+        }
+    }
+
+    /// Wait until all replaced models have been freed, or `timeout` elapses.
+    ///
+    /// # Returns
+    /// `true` if no replaced models remain in memory.
+    pub fn wait_for_drain(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if self.stats().draining.is_empty() {
+                return true;
+            }
+            if Instant::now() >= deadline {
+                return false;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+}
+
+fn model_size(ctx: &WhisperContext) -> Option<u64> {
+    ctx.model_path()
+        .and_then(|p| std::fs::metadata(p).ok())
+        .map(|m| m.len())
+}
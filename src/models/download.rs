@@ -0,0 +1,196 @@
+use super::sha1::sha1_file;
+use super::{KnownModel, ModelCache, DEFAULT_MODEL_BASE_URL, PARTIAL_EXTENSION};
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// How [`ModelCache::download_with`] fetches a model.
+#[derive(Debug, Clone)]
+pub struct DownloadOptions {
+    /// Base URLs serving the official file names, tried in order until one succeeds.
+    pub mirrors: Vec<String>,
+    /// Continue an interrupted download left in the cache instead of starting over,
+    /// if the server supports range requests.
+    pub resume: bool,
+    /// How many more times to try if all mirrors failed, or the downloaded file didn't match
+    /// its checksum. A file with the wrong checksum is deleted and downloaded again in full.
+    pub retries: u32,
+    /// Limit the download to this many bytes per second.
+    pub max_bytes_per_sec: Option<u64>,
+}
+
+impl Default for DownloadOptions {
+    fn default() -> Self {
+        Self {
+            mirrors: vec![DEFAULT_MODEL_BASE_URL.to_string()],
+            resume: true,
+            retries: 2,
+            max_bytes_per_sec: None,
+        }
+    }
+}
+
+impl ModelCache {
+    /// Download `model` into the cache from [`DEFAULT_MODEL_BASE_URL`],
+    /// unless a copy matching its checksum is already present.
+    ///
+    /// `progress` is called with the number of bytes downloaded so far and the total size, if known.
+    ///
+    /// # Returns
+    /// The path of the verified model file.
+    pub fn download(
+        &self,
+        model: &KnownModel,
+        progress: impl FnMut(u64, Option<u64>),
+    ) -> io::Result<PathBuf> {
+        self.download_with(model, &DownloadOptions::default(), progress)
+    }
+
+    /// Like [`Self::download`], but from a mirror serving the same file names under `base_url`.
+    pub fn download_from(
+        &self,
+        model: &KnownModel,
+        base_url: &str,
+        progress: impl FnMut(u64, Option<u64>),
+    ) -> io::Result<PathBuf> {
+        let options = DownloadOptions {
+            mirrors: vec![base_url.to_string()],
+            ..DownloadOptions::default()
+        };
+        self.download_with(model, &options, progress)
+    }
+
+    /// Like [`Self::download`], with resuming, mirrors, retries and bandwidth limiting
+    /// configured by `options`.
+    ///
+    /// Progress counts bytes resumed from an earlier attempt as downloaded.
+    pub fn download_with(
+        &self,
+        model: &KnownModel,
+        options: &DownloadOptions,
+        mut progress: impl FnMut(u64, Option<u64>),
+    ) -> io::Result<PathBuf> {
+        let path = self.path_for(model);
+        if path.is_file() && self.verify(model)? {
+            return Ok(path);
+        }
+        std::fs::create_dir_all(self.dir())?;
+        // download next to the final file, so the rename below cannot cross filesystems
+        let partial = path.with_extension(format!("bin.{}", PARTIAL_EXTENSION));
+        if !options.resume {
+            remove_if_exists(&partial)?;
+        }
+
+        let mut last_error =
+            io::Error::new(io::ErrorKind::InvalidInput, "no mirrors to download from");
+        for _ in 0..=options.retries {
+            let mut fetched = false;
+            for base_url in &options.mirrors {
+                match fetch(&model.url(base_url), &partial, options, &mut progress) {
+                    Ok(()) => {
+                        fetched = true;
+                        break;
+                    }
+                    Err(e) => last_error = e,
+                }
+            }
+            if !fetched {
+                continue;
+            }
+
+            let digest = sha1_file(&partial)?;
+            if digest == model.sha1 {
+                std::fs::rename(&partial, &path)?;
+                return Ok(path);
+            }
+            std::fs::remove_file(&partial)?;
+            last_error = io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "checksum mismatch for {}: expected {}, got {}",
+                    model.file_name(),
+                    model.sha1,
+                    digest
+                ),
+            );
+        }
+        Err(last_error)
+    }
+}
+
+/// Download `url` into `partial`, continuing after the bytes already in it.
+fn fetch(
+    url: &str,
+    partial: &Path,
+    options: &DownloadOptions,
+    progress: &mut impl FnMut(u64, Option<u64>),
+) -> io::Result<()> {
+    let offset = std::fs::metadata(partial).map_or(0, |m| m.len());
+    let mut request = ureq::get(url);
+    if offset > 0 {
+        request = request.set("Range", &format!("bytes={}-", offset));
+    }
+    let response = match request.call() {
+        Ok(response) => response,
+        // the partial file is already complete, or not from this file: start over
+        Err(ureq::Error::Status(416, _)) => {
+            std::fs::remove_file(partial)?;
+            ureq::get(url)
+                .call()
+                .map_err(|e| io::Error::other(e.to_string()))?
+        }
+        Err(e) => return Err(io::Error::other(e.to_string())),
+    };
+
+    let resumed = response.status() == 206;
+    let length = response
+        .header("Content-Length")
+        .and_then(|v| v.parse::<u64>().ok());
+    let mut downloaded = if resumed { offset } else { 0 };
+    let total = length.map(|len| len + downloaded);
+    let file = if resumed {
+        OpenOptions::new().append(true).open(partial)?
+    } else {
+        File::create(partial)?
+    };
+    let mut file = BufWriter::new(file);
+    let mut reader = response.into_reader();
+
+    let started = Instant::now();
+    let mut this_session = 0u64;
+    let mut buf = vec![0u8; 1 << 16];
+    loop {
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        file.write_all(&buf[..n])?;
+        downloaded += n as u64;
+        this_session += n as u64;
+        progress(downloaded, total);
+
+        if let Some(limit) = options.max_bytes_per_sec.filter(|&limit| limit > 0) {
+            let due = Duration::from_secs_f64(this_session as f64 / limit as f64);
+            if let Some(wait) = due.checked_sub(started.elapsed()) {
+                std::thread::sleep(wait);
+            }
+        }
+    }
+    file.flush()?;
+
+    if total.is_some_and(|total| downloaded < total) {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            format!("connection closed after {} bytes", downloaded),
+        ));
+    }
+    Ok(())
+}
+
+fn remove_if_exists(path: &Path) -> io::Result<()> {
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,436 @@
+//! Known Whisper model variants, and management of a local cache of model files.
+//!
+//! The registry lists the official GGML conversions published alongside whisper.cpp,
+//! with their approximate sizes and SHA-1 checksums. [`ModelCache`] inspects a directory
+//! of downloaded models, verifies them against the registry, and prunes old files.
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+#[cfg(feature = "downloader")]
+mod download;
+#[cfg(feature = "downloader")]
+pub use download::DownloadOptions;
+pub(crate) mod sha1;
+
+/// Base URL the official GGML models are downloaded from.
+pub const DEFAULT_MODEL_BASE_URL: &str =
+    "https://huggingface.co/ggerganov/whisper.cpp/resolve/main";
+
+/// Extension used for files that are still being downloaded.
+pub(crate) const PARTIAL_EXTENSION: &str = "part";
+
+/// An official model variant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KnownModel {
+    /// Short name, as used by whisper.cpp's `download-ggml-model` script, e.g. `base.en`.
+    pub name: &'static str,
+    /// Approximate size of the model file in MiB.
+    pub size_mib: u32,
+    /// Approximate peak memory use while transcribing with this model, in MiB.
+    pub memory_mib: u32,
+    /// Lowercase hex SHA-1 of the model file.
+    pub sha1: &'static str,
+    /// Whether the model can transcribe languages other than English.
+    pub multilingual: bool,
+}
+
+/// Every official model variant, smallest first.
+///
+/// Sizes and memory use are the figures from the whisper.cpp README, and are only meant as estimates.
+pub const KNOWN_MODELS: &[KnownModel] = &[
+    KnownModel {
+        name: "tiny",
+        size_mib: 75,
+        memory_mib: 273,
+        sha1: "bd577a113a864445d4c299885e0cb97d4ba92b5f",
+        multilingual: true,
+    },
+    KnownModel {
+        name: "tiny.en",
+        size_mib: 75,
+        memory_mib: 273,
+        sha1: "c78c86eb1a8faa21b369bcd33207cc90d64ae9df",
+        multilingual: false,
+    },
+    KnownModel {
+        name: "base",
+        size_mib: 142,
+        memory_mib: 388,
+        sha1: "465707469ff3a37a2b9b8d8f89f2f99de7299dac",
+        multilingual: true,
+    },
+    KnownModel {
+        name: "base.en",
+        size_mib: 142,
+        memory_mib: 388,
+        sha1: "137c40403d78fd54d454da0f9bd998f78703390c",
+        multilingual: false,
+    },
+    KnownModel {
+        name: "small",
+        size_mib: 466,
+        memory_mib: 852,
+        sha1: "55356645c2b361a969dfd0ef2c5a50d530afd8d5",
+        multilingual: true,
+    },
+    KnownModel {
+        name: "small.en",
+        size_mib: 466,
+        memory_mib: 852,
+        sha1: "db8a495a91d927739e50b3fc1cc4c6b8f6c2d022",
+        multilingual: false,
+    },
+    KnownModel {
+        name: "medium",
+        size_mib: 1500,
+        memory_mib: 2100,
+        sha1: "fd9727b6e1217c2f614f9b698455c4ffd82463b4",
+        multilingual: true,
+    },
+    KnownModel {
+        name: "medium.en",
+        size_mib: 1500,
+        memory_mib: 2100,
+        sha1: "8c30f0e44ce9560643ebd10bbe50cd20eafd3723",
+        multilingual: false,
+    },
+    KnownModel {
+        name: "large-v1",
+        size_mib: 2900,
+        memory_mib: 3900,
+        sha1: "b1caaf735c4cc1429223d5a74f0f4d0b9b59a299",
+        multilingual: true,
+    },
+    KnownModel {
+        name: "large-v2",
+        size_mib: 2900,
+        memory_mib: 3900,
+        sha1: "0f4c8e34f21cf1a914c59d8b3ce882345ad349d6",
+        multilingual: true,
+    },
+    KnownModel {
+        name: "large-v3",
+        size_mib: 2900,
+        memory_mib: 3900,
+        sha1: "ad82bf6a9043ceed055076d0fd39f5f186ff8062",
+        multilingual: true,
+    },
+    KnownModel {
+        name: "large-v3-turbo",
+        size_mib: 1500,
+        memory_mib: 2500,
+        sha1: "4af2b29d7ec73d781377bfd1758ca957a807e941",
+        multilingual: true,
+    },
+];
+
+impl KnownModel {
+    /// Look up a model by its short name (e.g. `base.en`) or file name (e.g. `ggml-base.en.bin`).
+    pub fn find(name: &str) -> Option<&'static KnownModel> {
+        let name = name
+            .strip_prefix("ggml-")
+            .and_then(|n| n.strip_suffix(".bin"))
+            .unwrap_or(name);
+        KNOWN_MODELS.iter().find(|m| m.name == name)
+    }
+
+    /// File name of this model, e.g. `ggml-base.en.bin`.
+    pub fn file_name(&self) -> String {
+        format!("ggml-{}.bin", self.name)
+    }
+
+    /// URL this model can be downloaded from, under `base_url`.
+    pub fn url(&self, base_url: &str) -> String {
+        format!("{}/{}", base_url.trim_end_matches('/'), self.file_name())
+    }
+
+    /// Size of the model file in bytes (approximate).
+    pub fn size_bytes(&self) -> u64 {
+        self.size_mib as u64 * 1024 * 1024
+    }
+
+    /// Estimate how much memory is needed to transcribe with this model on `backend`.
+    pub fn estimate_memory(&self, backend: MemoryBackend) -> MemoryEstimate {
+        let total = self.memory_mib as u64 * 1024 * 1024;
+        match backend {
+            MemoryBackend::Cpu => MemoryEstimate {
+                host_bytes: total,
+                device_bytes: 0,
+            },
+            // weights and compute buffers live on the device, the host keeps the mel
+            // spectrogram, the decoded text and some scratch space
+            MemoryBackend::Gpu => MemoryEstimate {
+                host_bytes: total.saturating_sub(self.size_bytes()) / 4,
+                device_bytes: total,
+            },
+        }
+    }
+}
+
+/// Where the model weights and compute buffers live, for [`KnownModel::estimate_memory`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemoryBackend {
+    /// Everything is kept in host RAM.
+    Cpu,
+    /// Weights and compute buffers are offloaded to a GPU (CUDA, Metal, Vulkan, ...).
+    Gpu,
+}
+
+/// Estimated memory requirements of a model, see [`KnownModel::estimate_memory`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryEstimate {
+    /// Bytes of host RAM.
+    pub host_bytes: u64,
+    /// Bytes of GPU memory.
+    pub device_bytes: u64,
+}
+
+/// A model file found in a [`ModelCache`].
+#[derive(Debug, Clone)]
+pub struct CachedModel {
+    pub path: PathBuf,
+    /// Size of the file in bytes.
+    pub size_bytes: u64,
+    /// When the file was last modified.
+    pub modified: SystemTime,
+    /// The registry entry matching the file name, if any.
+    pub known: Option<&'static KnownModel>,
+}
+
+/// The user's cache directory: `$XDG_CACHE_HOME` or `~/.cache` on Linux, `~/Library/Caches` on macOS,
+/// `%LOCALAPPDATA%` on Windows.
+pub(crate) fn user_cache_dir() -> Option<PathBuf> {
+    if cfg!(target_os = "windows") {
+        std::env::var_os("LOCALAPPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        std::env::var_os("HOME").map(|h| PathBuf::from(h).join("Library/Caches"))
+    } else {
+        std::env::var_os("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".cache")))
+    }
+}
+
+/// A directory holding downloaded `ggml-*.bin` model files.
+#[derive(Debug, Clone)]
+pub struct ModelCache {
+    dir: PathBuf,
+}
+
+impl ModelCache {
+    /// Use `dir` as the cache directory. It is created on demand.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// The platform cache directory for whisper-rs models.
+    ///
+    /// This is `$WHISPER_RS_MODEL_DIR` if set, otherwise `whisper-rs/models` inside the
+    /// user's cache directory (`$XDG_CACHE_HOME` or `~/.cache` on Linux, `~/Library/Caches` on macOS,
+    /// `%LOCALAPPDATA%` on Windows).
+    pub fn default_dir() -> Option<PathBuf> {
+        if let Some(dir) = std::env::var_os("WHISPER_RS_MODEL_DIR") {
+            return Some(PathBuf::from(dir));
+        }
+
+        user_cache_dir().map(|b| b.join("whisper-rs").join("models"))
+    }
+
+    /// Open the cache at [`Self::default_dir`].
+    pub fn open_default() -> io::Result<Self> {
+        Self::default_dir()
+            .map(Self::new)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no cache directory found"))
+    }
+
+    /// The directory backing this cache.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Where `model` is (or would be) stored in this cache.
+    pub fn path_for(&self, model: &KnownModel) -> PathBuf {
+        self.dir.join(model.file_name())
+    }
+
+    /// Whether a file for `model` is present. Does not verify its contents, see [`Self::verify`].
+    pub fn contains(&self, model: &KnownModel) -> bool {
+        self.path_for(model).is_file()
+    }
+
+    /// List all model files in the cache, ordered by file name.
+    ///
+    /// Partially downloaded files are not included.
+    pub fn list(&self) -> io::Result<Vec<CachedModel>> {
+        let entries = match std::fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+
+        let mut models = Vec::new();
+        for entry in entries {
+            let entry = entry?;
+            let name = entry.file_name();
+            let Some(name) = name.to_str() else {
+                continue;
+            };
+            if !(name.starts_with("ggml-") && name.ends_with(".bin")) {
+                continue;
+            }
+            let metadata = entry.metadata()?;
+            if !metadata.is_file() {
+                continue;
+            }
+            models.push(CachedModel {
+                path: entry.path(),
+                size_bytes: metadata.len(),
+                modified: metadata.modified()?,
+                known: KnownModel::find(name),
+            });
+        }
+        models.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(models)
+    }
+
+    /// Verify the cached file for `model` against its published checksum.
+    ///
+    /// # Returns
+    /// * `Ok(true)` if the file exists and matches
+    /// * `Ok(false)` if the file exists but is corrupt or incomplete
+    /// * `Err` if the file could not be read (including if it is missing)
+    pub fn verify(&self, model: &KnownModel) -> io::Result<bool> {
+        Ok(sha1::sha1_file(&self.path_for(model))? == model.sha1)
+    }
+
+    /// Remove the cached file for `model`, if present.
+    pub fn remove(&self, model: &KnownModel) -> io::Result<()> {
+        match std::fs::remove_file(self.path_for(model)) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+            r => r,
+        }
+    }
+
+    /// Delete model files according to `options`.
+    /// Leftover partial downloads are always removed.
+    ///
+    /// # Returns
+    /// The paths that were removed.
+    pub fn prune(&self, options: &PruneOptions) -> io::Result<Vec<PathBuf>> {
+        let mut removed = self.remove_partial_downloads()?;
+
+        let now = SystemTime::now();
+        let mut models: Vec<_> = self
+            .list()?
+            .into_iter()
+            .filter(|m| {
+                !m.known
+                    .is_some_and(|k| options.keep.iter().any(|keep| keep == k.name))
+            })
+            .collect();
+        // oldest first
+        models.sort_by_key(|m| m.modified);
+
+        let mut total: u64 = models.iter().map(|m| m.size_bytes).sum();
+        for model in models {
+            let too_old = options
+                .older_than
+                .is_some_and(|age| now.duration_since(model.modified).unwrap_or_default() > age);
+            let over_budget = options.max_total_bytes.is_some_and(|max| total > max);
+            if too_old || over_budget {
+                std::fs::remove_file(&model.path)?;
+                total -= model.size_bytes;
+                removed.push(model.path);
+            }
+        }
+        Ok(removed)
+    }
+
+    fn remove_partial_downloads(&self) -> io::Result<Vec<PathBuf>> {
+        let entries = match std::fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        let mut removed = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().is_some_and(|e| e == PARTIAL_EXTENSION) {
+                std::fs::remove_file(&path)?;
+                removed.push(path);
+            }
+        }
+        Ok(removed)
+    }
+}
+
+/// What [`ModelCache::prune`] should delete.
+#[derive(Debug, Clone, Default)]
+pub struct PruneOptions {
+    /// Delete models not modified within this duration.
+    pub older_than: Option<Duration>,
+    /// Delete the oldest models until the cache is at most this many bytes.
+    pub max_total_bytes: Option<u64>,
+    /// Short names of models that are never deleted.
+    pub keep: Vec<String>,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn temp_cache(name: &str) -> ModelCache {
+        let dir = std::env::temp_dir().join(format!("whisper-rs-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        ModelCache::new(dir)
+    }
+
+    #[test]
+    fn find_known_models() {
+        assert_eq!(KnownModel::find("base.en").unwrap().name, "base.en");
+        assert_eq!(
+            KnownModel::find("ggml-large-v3.bin").unwrap().name,
+            "large-v3"
+        );
+        assert!(KnownModel::find("huge").is_none());
+        assert_eq!(
+            KnownModel::find("tiny")
+                .unwrap()
+                .url(DEFAULT_MODEL_BASE_URL),
+            "https://huggingface.co/ggerganov/whisper.cpp/resolve/main/ggml-tiny.bin"
+        );
+        assert!(KNOWN_MODELS.iter().all(|m| m.sha1.len() == 40));
+    }
+
+    #[test]
+    fn list_verify_and_prune() {
+        let cache = temp_cache("models");
+        let tiny = KnownModel::find("tiny").unwrap();
+        let base = KnownModel::find("base").unwrap();
+        std::fs::write(cache.path_for(tiny), b"not really a model").unwrap();
+        std::fs::write(cache.path_for(base), b"not really a model either").unwrap();
+        std::fs::write(cache.dir().join("ggml-small.bin.part"), b"partial").unwrap();
+        std::fs::write(cache.dir().join("notes.txt"), b"ignored").unwrap();
+
+        let listed = cache.list().unwrap();
+        assert_eq!(listed.len(), 2);
+        assert_eq!(listed[0].known, Some(base));
+        assert!(!cache.verify(tiny).unwrap());
+
+        let removed = cache
+            .prune(&PruneOptions {
+                max_total_bytes: Some(0),
+                keep: vec!["tiny".to_string()],
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(removed.len(), 2);
+        assert!(cache.contains(tiny));
+        assert!(!cache.contains(base));
+
+        std::fs::remove_dir_all(cache.dir()).unwrap();
+    }
+}
@@ -0,0 +1,290 @@
+use crate::{WhisperContext, WhisperError, WhisperSegment};
+use std::ffi::{c_int, c_void};
+use std::fmt;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+/// Receives events from transcription runs, to feed whatever logging or metrics stack an
+/// application uses. Implement it once and register it on a context with
+/// [`crate::WhisperContext::add_observer`], or on a [`crate::ModelManager`], which keeps it
+/// registered across model swaps.
+///
+/// Every method has an empty default. Observers are called on the thread running the model,
+/// so they should return quickly. Runs that fail, including those rejected before reaching
+/// whisper.cpp, still report their start and end.
+pub trait Observer: Send + Sync {
+    /// A call to [`crate::WhisperState::full`] is starting.
+    fn on_run_start(&self, _run: &RunStart) {}
+    /// A segment was produced. Called for every segment once decoding has finished,
+    /// before [`Self::on_run_end`].
+    fn on_segment(&self, _segment: &WhisperSegment<'_>) {}
+    /// A decoding attempt was rejected, and the next one is starting: either whisper.cpp is
+    /// retrying a window at a higher temperature, see [`crate::FullParams::set_temperature_inc`],
+    /// or an attempt of an observed [`crate::TemperatureSchedule`] failed.
+    fn on_fallback(&self, _fallback: &Fallback) {}
+    /// The run computes token timestamps without DTW, because its buffer didn't fit in the memory
+    /// available when the model was loaded, see [`crate::DtwParameters::with_memory_fallback`].
+    /// Called after [`Self::on_run_start`] of every run of such a context.
+    fn on_dtw_fallback(&self, _fallback: &DtwFallback) {}
+    /// A call to [`crate::WhisperState::full`] finished.
+    fn on_run_end(&self, _run: &RunEnd) {}
+}
+
+/// See [`Observer::on_run_start`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RunStart {
+    /// Number of samples to transcribe.
+    pub samples: usize,
+    /// Threads decoding with, see [`crate::FullParams::set_n_threads`].
+    pub n_threads: c_int,
+}
+
+/// See [`Observer::on_fallback`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fallback {
+    /// Index of the attempt that is starting. Counted per window for whisper.cpp's fallback.
+    pub attempt: usize,
+    pub temperature: f32,
+}
+
+/// See [`Observer::on_dtw_fallback`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DtwFallback {
+    /// Bytes of the DTW buffer, see [`crate::DtwParameters::dtw_mem_size`].
+    pub dtw_mem_size: usize,
+    /// Bytes of memory available when the model was loaded.
+    pub available: u64,
+}
+
+/// See [`Observer::on_run_end`].
+#[derive(Debug, Clone, Copy)]
+pub struct RunEnd {
+    /// Wall-clock time the run took.
+    pub duration: Duration,
+    /// Length of the audio, for computing the real-time factor.
+    pub audio_duration: Duration,
+    /// Number of segments produced.
+    pub segments: c_int,
+    /// The error the run failed with, if any.
+    pub error: Option<WhisperError>,
+}
+
+impl RunEnd {
+    /// Processing time divided by audio duration; below 1 is faster than real time.
+    pub fn real_time_factor(&self) -> f64 {
+        self.duration.as_secs_f64() / self.audio_duration.as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+impl WhisperContext {
+    /// Notify `observer` of the runs of every state of this context, including existing ones.
+    /// Adding an observer that is already registered has no effect.
+    pub fn add_observer(&self, observer: Arc<dyn Observer>) {
+        self.inner().observers.add(observer);
+    }
+}
+
+/// The observers registered on a context.
+#[derive(Default)]
+pub(crate) struct Observers(RwLock<Vec<Arc<dyn Observer>>>);
+
+impl Observers {
+    pub(crate) fn add(&self, observer: Arc<dyn Observer>) {
+        let mut observers = self.0.write().unwrap_or_else(|e| e.into_inner());
+        if !observers.iter().any(|o| Arc::ptr_eq(o, &observer)) {
+            observers.push(observer);
+        }
+    }
+
+    /// A snapshot of the registered observers, so none are called with the lock held.
+    pub(crate) fn get(&self) -> Vec<Arc<dyn Observer>> {
+        self.0.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
+
+impl fmt::Debug for Observers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Observers({})", self.get().len())
+    }
+}
+
+/// Reports whisper.cpp's temperature fallback to [`Observer::on_fallback`], chaining to the
+/// encoder-begin and logits filter callbacks already set on the params.
+///
+/// whisper.cpp has no callback for the fallback itself, but after encoding a window it filters
+/// the logits once with no tokens at the start of every attempt, so attempts are counted from
+/// those calls and reset when the next window is encoded.
+pub(crate) struct FallbackRecorder<'a> {
+    observers: &'a [Arc<dyn Observer>],
+    temperature: f32,
+    temperature_inc: f32,
+    /// Attempts started on the current window.
+    attempts: usize,
+    encoder_begin: whisper_rs_sys::whisper_encoder_begin_callback,
+    encoder_begin_user_data: *mut c_void,
+    logits_filter: whisper_rs_sys::whisper_logits_filter_callback,
+    logits_filter_user_data: *mut c_void,
+}
+
+impl<'a> FallbackRecorder<'a> {
+    pub(crate) fn new(
+        observers: &'a [Arc<dyn Observer>],
+        fp: &whisper_rs_sys::whisper_full_params,
+    ) -> Self {
+        Self {
+            observers,
+            temperature: fp.temperature,
+            temperature_inc: fp.temperature_inc,
+            attempts: 0,
+            encoder_begin: fp.encoder_begin_callback,
+            encoder_begin_user_data: fp.encoder_begin_callback_user_data,
+            logits_filter: fp.logits_filter_callback,
+            logits_filter_user_data: fp.logits_filter_callback_user_data,
+        }
+    }
+
+    /// Route the callbacks of `fp` through this recorder, which must not move or be dropped
+    /// until whisper.cpp is done with `fp`.
+    pub(crate) fn install(&mut self, fp: &mut whisper_rs_sys::whisper_full_params) {
+        let user_data = self as *mut Self as *mut c_void;
+        fp.encoder_begin_callback = Some(record_encoder_begin);
+        fp.encoder_begin_callback_user_data = user_data;
+        fp.logits_filter_callback = Some(record_logits_filter);
+        fp.logits_filter_callback_user_data = user_data;
+    }
+}
+
+unsafe extern "C" fn record_encoder_begin(
+    ctx: *mut whisper_rs_sys::whisper_context,
+    state: *mut whisper_rs_sys::whisper_state,
+    user_data: *mut c_void,
+) -> bool {
+    // SAFETY: user_data is the FallbackRecorder installed for this run
+    let recorder = &mut *(user_data as *mut FallbackRecorder);
+    recorder.attempts = 0;
+    match recorder.encoder_begin {
+        Some(callback) => callback(ctx, state, recorder.encoder_begin_user_data),
+        None => true,
+    }
+}
+
+unsafe extern "C" fn record_logits_filter(
+    ctx: *mut whisper_rs_sys::whisper_context,
+    state: *mut whisper_rs_sys::whisper_state,
+    tokens: *const whisper_rs_sys::whisper_token_data,
+    n_tokens: c_int,
+    logits: *mut f32,
+    user_data: *mut c_void,
+) {
+    // SAFETY: user_data is the FallbackRecorder installed for this run
+    let recorder = &mut *(user_data as *mut FallbackRecorder);
+    if n_tokens == 0 {
+        if recorder.attempts > 0 {
+            let fallback = Fallback {
+                attempt: recorder.attempts,
+                temperature: recorder.temperature
+                    + recorder.attempts as f32 * recorder.temperature_inc,
+            };
+            recorder
+                .observers
+                .iter()
+                .for_each(|o| o.on_fallback(&fallback));
+        }
+        recorder.attempts += 1;
+    }
+    if let Some(callback) = recorder.logits_filter {
+        callback(
+            ctx,
+            state,
+            tokens,
+            n_tokens,
+            logits,
+            recorder.logits_filter_user_data,
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::ptr::null_mut;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct Recording {
+        events: Mutex<Vec<String>>,
+    }
+
+    impl Observer for Recording {
+        fn on_run_start(&self, run: &RunStart) {
+            self.events
+                .lock()
+                .unwrap()
+                .push(format!("start {}", run.samples));
+        }
+        fn on_fallback(&self, fallback: &Fallback) {
+            self.events.lock().unwrap().push(format!(
+                "fallback {} {:.1}",
+                fallback.attempt, fallback.temperature
+            ));
+        }
+        fn on_run_end(&self, run: &RunEnd) {
+            let error = run.error.map(|e| e.to_string());
+            self.events
+                .lock()
+                .unwrap()
+                .push(format!("end {} {:?}", run.segments, error));
+        }
+    }
+
+    #[test]
+    fn observers_are_registered_once() {
+        let observers = Observers::default();
+        let observer: Arc<dyn Observer> = Arc::new(Recording::default());
+        observers.add(observer.clone());
+        observers.add(observer);
+        observers.add(Arc::new(Recording::default()));
+        assert_eq!(observers.get().len(), 2);
+    }
+
+    #[test]
+    fn fallbacks_are_counted_per_window() {
+        let recording = Arc::new(Recording::default());
+        let observers: Vec<Arc<dyn Observer>> = vec![recording.clone()];
+        let params = crate::FullParams::new(crate::SamplingStrategy::Greedy { best_of: 1 });
+        let mut fp = params.fp;
+        fp.temperature = 0.0;
+        fp.temperature_inc = 0.2;
+        let mut recorder = FallbackRecorder::new(&observers, &fp);
+        recorder.install(&mut fp);
+
+        let encode = || unsafe {
+            assert!(fp.encoder_begin_callback.unwrap()(
+                null_mut(),
+                null_mut(),
+                fp.encoder_begin_callback_user_data
+            ));
+        };
+        let filter = |n_tokens| unsafe {
+            fp.logits_filter_callback.unwrap()(
+                null_mut(),
+                null_mut(),
+                std::ptr::null(),
+                n_tokens,
+                null_mut(),
+                fp.logits_filter_callback_user_data,
+            )
+        };
+        // the first window falls back twice, the second decodes on the first attempt
+        encode();
+        for n_tokens in [0, 1, 2, 0, 1, 0, 1] {
+            filter(n_tokens);
+        }
+        encode();
+        filter(0);
+        filter(1);
+
+        let events = recording.events.lock().unwrap();
+        assert_eq!(*events, ["fallback 1 0.2", "fallback 2 0.4"]);
+    }
+}
@@ -0,0 +1,262 @@
+//! Opus input, for sources such as WebRTC or Discord that deliver Opus rather than PCM.
+//!
+//! [`OpusDecoder`] turns individual packets into the 16 kHz mono audio Whisper expects,
+//! and [`OggOpusReader`] reads `.opus` / `.ogg` files. Use
+//! [`crate::StreamingTranscriber::push_opus_packet`] to feed packets straight into a stream.
+
+mod ogg;
+
+use crate::{StreamingTranscribe, StreamingTranscriber, Transcribe, TranscriptSegment};
+use ogg::OggPacketReader;
+use std::fmt;
+use std::io::{self, Read};
+
+/// Longest frame an Opus packet can hold: 120 ms, at 16 kHz.
+const MAX_FRAME_SAMPLES: usize = 1920;
+
+/// Error decoding Opus input.
+#[derive(Debug)]
+pub enum OpusError {
+    /// libopus rejected a packet or the decoder configuration.
+    Decoder(::opus::Error),
+    /// Reading the input failed.
+    Io(io::Error),
+    /// The input is not a valid Ogg Opus stream.
+    InvalidStream(&'static str),
+    /// The stream uses more than two channels, which needs a multistream decoder.
+    UnsupportedChannelCount(u8),
+}
+
+impl fmt::Display for OpusError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use OpusError::*;
+        match self {
+            Decoder(e) => write!(f, "Opus decoder error: {}", e),
+            Io(e) => write!(f, "Failed to read Opus input: {}", e),
+            InvalidStream(reason) => write!(f, "Invalid Ogg Opus stream: {}", reason),
+            UnsupportedChannelCount(n) => write!(
+                f,
+                "Opus streams with {} channels are not supported, only mono and stereo.",
+                n
+            ),
+        }
+    }
+}
+
+impl std::error::Error for OpusError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            OpusError::Decoder(e) => Some(e),
+            OpusError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<::opus::Error> for OpusError {
+    fn from(e: ::opus::Error) -> Self {
+        Self::Decoder(e)
+    }
+}
+
+impl From<io::Error> for OpusError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+/// Decodes Opus packets to 16 kHz mono PCM. Stereo streams are downmixed.
+pub struct OpusDecoder {
+    decoder: ::opus::Decoder,
+    channels: usize,
+    buffer: Vec<f32>,
+}
+
+impl OpusDecoder {
+    /// Create a decoder for a stream with `channels` channels (1 or 2).
+    pub fn new(channels: u8) -> Result<Self, OpusError> {
+        let layout = match channels {
+            1 => ::opus::Channels::Mono,
+            2 => ::opus::Channels::Stereo,
+            n => return Err(OpusError::UnsupportedChannelCount(n)),
+        };
+        Ok(Self {
+            decoder: ::opus::Decoder::new(whisper_rs_sys::WHISPER_SAMPLE_RATE, layout)?,
+            channels: channels as usize,
+            buffer: vec![0.0; MAX_FRAME_SAMPLES * channels as usize],
+        })
+    }
+
+    /// Decode one packet, appending the audio to `out`.
+    ///
+    /// # Returns
+    /// The number of samples appended.
+    pub fn decode_into(&mut self, packet: &[u8], out: &mut Vec<f32>) -> Result<usize, OpusError> {
+        let n = self.decoder.decode_float(packet, &mut self.buffer, false)?;
+        let frames = &self.buffer[..n * self.channels];
+        if self.channels == 1 {
+            out.extend_from_slice(frames);
+        } else {
+            out.extend(frames.chunks_exact(2).map(|lr| (lr[0] + lr[1]) / 2.0));
+        }
+        Ok(n)
+    }
+
+    /// Decode one packet.
+    pub fn decode(&mut self, packet: &[u8]) -> Result<Vec<f32>, OpusError> {
+        let mut out = Vec::with_capacity(MAX_FRAME_SAMPLES);
+        self.decode_into(packet, &mut out)?;
+        Ok(out)
+    }
+}
+
+/// Reads an Ogg Opus stream (`.opus` files, `audio/ogg; codecs=opus`).
+pub struct OggOpusReader<R> {
+    packets: OggPacketReader<R>,
+    channels: u8,
+    pre_skip: usize,
+}
+
+impl<R: Read> OggOpusReader<R> {
+    /// Read the stream headers.
+    pub fn new(reader: R) -> Result<Self, OpusError> {
+        let mut packets = OggPacketReader::new(reader);
+        let head = packets
+            .next_packet()?
+            .ok_or(OpusError::InvalidStream("empty stream"))?;
+        if head.len() < 19 || &head[..8] != b"OpusHead" {
+            return Err(OpusError::InvalidStream("missing OpusHead header"));
+        }
+        if head[8] >> 4 != 0 {
+            return Err(OpusError::InvalidStream("unsupported OpusHead version"));
+        }
+        let channels = head[9];
+        if head[18] != 0 && channels > 2 {
+            return Err(OpusError::UnsupportedChannelCount(channels));
+        }
+        // pre-skip is counted at 48 kHz
+        let pre_skip = u16::from_le_bytes([head[10], head[11]]) as usize
+            * whisper_rs_sys::WHISPER_SAMPLE_RATE as usize
+            / 48000;
+
+        let tags = packets
+            .next_packet()?
+            .ok_or(OpusError::InvalidStream("missing OpusTags header"))?;
+        if !tags.starts_with(b"OpusTags") {
+            return Err(OpusError::InvalidStream("missing OpusTags header"));
+        }
+
+        Ok(Self {
+            packets,
+            channels,
+            pre_skip,
+        })
+    }
+
+    /// Number of channels in the stream.
+    pub fn channels(&self) -> u8 {
+        self.channels
+    }
+
+    /// The next audio packet, or `None` at the end of the stream.
+    pub fn next_packet(&mut self) -> Result<Option<Vec<u8>>, OpusError> {
+        self.packets.next_packet()
+    }
+
+    /// Decode the rest of the stream to 16 kHz mono PCM.
+    pub fn decode_all(mut self) -> Result<Vec<f32>, OpusError> {
+        let mut decoder = OpusDecoder::new(self.channels)?;
+        let mut out = Vec::new();
+        while let Some(packet) = self.next_packet()? {
+            decoder.decode_into(&packet, &mut out)?;
+        }
+        // the first samples are encoder warm-up, not audio
+        out.drain(..self.pre_skip.min(out.len()));
+        Ok(out)
+    }
+}
+
+/// Decode a whole Ogg Opus stream to 16 kHz mono PCM.
+pub fn decode_ogg_opus<R: Read>(reader: R) -> Result<Vec<f32>, OpusError> {
+    OggOpusReader::new(reader)?.decode_all()
+}
+
+/// Error from [`StreamingTranscriber::push_opus_packet`].
+#[derive(Debug)]
+pub enum OpusStreamError<E> {
+    Opus(OpusError),
+    Transcribe(E),
+}
+
+impl<E: fmt::Display> fmt::Display for OpusStreamError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Opus(e) => e.fmt(f),
+            Self::Transcribe(e) => e.fmt(f),
+        }
+    }
+}
+
+impl<E: std::error::Error + 'static> std::error::Error for OpusStreamError<E> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Opus(e) => Some(e),
+            Self::Transcribe(e) => Some(e),
+        }
+    }
+}
+
+impl<T: Transcribe> StreamingTranscriber<'_, '_, T> {
+    /// Decode an Opus packet with `decoder` and feed the audio into the stream,
+    /// see [`StreamingTranscribe::push_audio`].
+    pub fn push_opus_packet(
+        &mut self,
+        decoder: &mut OpusDecoder,
+        packet: &[u8],
+    ) -> Result<Vec<TranscriptSegment>, OpusStreamError<T::Error>> {
+        let audio = decoder.decode(packet).map_err(OpusStreamError::Opus)?;
+        self.push_audio(&audio).map_err(OpusStreamError::Transcribe)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ogg::test::page;
+    use super::*;
+
+    fn opus_head(channels: u8, pre_skip: u16) -> Vec<u8> {
+        let mut head = b"OpusHead".to_vec();
+        head.extend_from_slice(&[1, channels]);
+        head.extend_from_slice(&pre_skip.to_le_bytes());
+        head.extend_from_slice(&48000u32.to_le_bytes());
+        head.extend_from_slice(&[0, 0, 0]);
+        head
+    }
+
+    #[test]
+    fn headers_are_parsed() {
+        let head = opus_head(2, 312);
+        let mut stream = page(9, 0x02, &[head.len() as u8], &head);
+        stream.extend(page(9, 0x00, &[8], b"OpusTags"));
+        stream.extend(page(9, 0x04, &[2], &[0xfc, 0xff]));
+
+        let mut reader = OggOpusReader::new(&stream[..]).unwrap();
+        assert_eq!(reader.channels(), 2);
+        assert_eq!(reader.pre_skip, 104);
+        assert_eq!(reader.next_packet().unwrap().unwrap(), [0xfc, 0xff]);
+        assert!(reader.next_packet().unwrap().is_none());
+    }
+
+    #[test]
+    fn non_opus_streams_are_rejected() {
+        let stream = page(9, 0x02, &[6], b"\x01vorbi");
+        assert!(matches!(
+            OggOpusReader::new(&stream[..]),
+            Err(OpusError::InvalidStream(_))
+        ));
+        assert!(matches!(
+            OpusDecoder::new(6),
+            Err(OpusError::UnsupportedChannelCount(6))
+        ));
+    }
+}
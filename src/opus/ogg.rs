@@ -0,0 +1,170 @@
+//! Just enough of the Ogg container format (RFC 3533) to pull packets out of a single logical stream.
+
+use super::OpusError;
+use std::collections::VecDeque;
+use std::io::{self, Read};
+
+const CAPTURE_PATTERN: &[u8; 4] = b"OggS";
+const HEADER_LEN: usize = 27;
+
+/// Reads the packets of the first logical stream in an Ogg file, ignoring any others.
+pub(crate) struct OggPacketReader<R> {
+    reader: R,
+    serial: Option<u32>,
+    packets: VecDeque<Vec<u8>>,
+    partial: Vec<u8>,
+    eof: bool,
+}
+
+impl<R: Read> OggPacketReader<R> {
+    pub(crate) fn new(reader: R) -> Self {
+        Self {
+            reader,
+            serial: None,
+            packets: VecDeque::new(),
+            partial: Vec::new(),
+            eof: false,
+        }
+    }
+
+    pub(crate) fn next_packet(&mut self) -> Result<Option<Vec<u8>>, OpusError> {
+        while self.packets.is_empty() && !self.eof {
+            self.read_page()?;
+        }
+        Ok(self.packets.pop_front())
+    }
+
+    fn read_page(&mut self) -> Result<(), OpusError> {
+        let mut header = [0u8; HEADER_LEN];
+        if !read_exact_or_eof(&mut self.reader, &mut header)? {
+            self.eof = true;
+            return Ok(());
+        }
+        if &header[..4] != CAPTURE_PATTERN || header[4] != 0 {
+            return Err(OpusError::InvalidStream("not an Ogg page"));
+        }
+        let header_type = header[5];
+        let serial = u32::from_le_bytes(header[14..18].try_into().expect("4 bytes"));
+        let crc = u32::from_le_bytes(header[22..26].try_into().expect("4 bytes"));
+
+        let mut lacing = vec![0u8; header[26] as usize];
+        self.reader.read_exact(&mut lacing)?;
+        let mut body = vec![0u8; lacing.iter().map(|&l| l as usize).sum()];
+        self.reader.read_exact(&mut body)?;
+
+        // the checksum covers the whole page with the checksum field zeroed
+        header[22..26].fill(0);
+        let mut page_crc = crc32(0, &header);
+        page_crc = crc32(page_crc, &lacing);
+        page_crc = crc32(page_crc, &body);
+        if page_crc != crc {
+            return Err(OpusError::InvalidStream("Ogg page checksum mismatch"));
+        }
+
+        match self.serial {
+            None => self.serial = Some(serial),
+            Some(s) if s != serial => return Ok(()),
+            Some(_) => {}
+        }
+        // a continued packet whose start we never saw (e.g. after a lost page) can't be used
+        if header_type & 0x01 == 0 {
+            self.partial.clear();
+        }
+
+        let mut offset = 0;
+        for &len in &lacing {
+            let len = len as usize;
+            self.partial.extend_from_slice(&body[offset..offset + len]);
+            offset += len;
+            if len < 255 {
+                self.packets.push_back(std::mem::take(&mut self.partial));
+            }
+        }
+        if header_type & 0x04 != 0 {
+            // end of stream
+            self.eof = true;
+        }
+        Ok(())
+    }
+}
+
+fn read_exact_or_eof<R: Read>(reader: &mut R, buf: &mut [u8]) -> io::Result<bool> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) if filled == 0 => return Ok(false),
+            Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(true)
+}
+
+/// The CRC-32 variant used by Ogg: polynomial 0x04c11db7, no reflection, no final xor.
+fn crc32(mut crc: u32, data: &[u8]) -> u32 {
+    for &byte in data {
+        crc ^= (byte as u32) << 24;
+        for _ in 0..8 {
+            crc = if crc & 0x8000_0000 != 0 {
+                (crc << 1) ^ 0x04c1_1db7
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+
+    /// Build a single Ogg page from its lacing values and body.
+    pub(crate) fn page(serial: u32, header_type: u8, lacing: &[u8], body: &[u8]) -> Vec<u8> {
+        let mut page = Vec::new();
+        page.extend_from_slice(CAPTURE_PATTERN);
+        page.extend_from_slice(&[0, header_type]);
+        page.extend_from_slice(&0u64.to_le_bytes());
+        page.extend_from_slice(&serial.to_le_bytes());
+        page.extend_from_slice(&0u32.to_le_bytes());
+        page.extend_from_slice(&0u32.to_le_bytes());
+        page.push(lacing.len() as u8);
+        page.extend_from_slice(lacing);
+        page.extend_from_slice(body);
+        let crc = crc32(0, &page);
+        page[22..26].copy_from_slice(&crc.to_le_bytes());
+        page
+    }
+
+    #[test]
+    fn packets_are_reassembled_across_pages() {
+        let long = vec![7u8; 300];
+        let mut stream = page(1, 0x02, &[3], b"one");
+        stream.extend(page(2, 0x02, &[5], b"other"));
+        stream.extend(page(1, 0x00, &[255], &long[..255]));
+        stream.extend(page(
+            1,
+            0x01 | 0x04,
+            &[45, 3],
+            &[&long[255..], b"end"].concat(),
+        ));
+
+        let mut reader = OggPacketReader::new(&stream[..]);
+        assert_eq!(reader.next_packet().unwrap().unwrap(), b"one");
+        assert_eq!(reader.next_packet().unwrap().unwrap(), long);
+        assert_eq!(reader.next_packet().unwrap().unwrap(), b"end");
+        assert!(reader.next_packet().unwrap().is_none());
+    }
+
+    #[test]
+    fn corrupt_pages_are_rejected() {
+        let mut stream = page(1, 0x02, &[3], b"one");
+        stream[30] ^= 0xff;
+        assert!(matches!(
+            OggPacketReader::new(&stream[..]).next_packet(),
+            Err(OpusError::InvalidStream(_))
+        ));
+    }
+}
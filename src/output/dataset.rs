@@ -0,0 +1,188 @@
+use super::json;
+use crate::transcribe::SAMPLES_PER_CS;
+use crate::wav::write_wav;
+use crate::Transcript;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+/// How [`DatasetExporter`] describes the clips it writes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DatasetLayout {
+    /// A `metadata.csv` with `file_name,text` rows next to the clips, the audio folder layout
+    /// Hugging Face `datasets` loads directly.
+    MetadataCsv,
+    /// A `manifest.jsonl` with an `audio_filepath`, `duration` in seconds and `text` per clip,
+    /// the manifest format of NeMo and similar toolkits.
+    JsonlManifest,
+}
+
+impl DatasetLayout {
+    fn file_name(self) -> &'static str {
+        match self {
+            Self::MetadataCsv => "metadata.csv",
+            Self::JsonlManifest => "manifest.jsonl",
+        }
+    }
+}
+
+/// Turns transcripts into training data: the audio of each segment is written to a WAV clip,
+/// and its text to the metadata of a [`DatasetLayout`], see [`Self::export`].
+///
+/// Segments without text, or too short or long to train on, are skipped.
+///
+/// # Examples
+/// ```no_run
+/// # use whisper_rs::output::{DatasetExporter, DatasetLayout};
+/// # let (transcript, audio) = (whisper_rs::Transcript::new(Vec::new()), vec![0.0f32; 16000]);
+/// let exporter = DatasetExporter::new(DatasetLayout::MetadataCsv);
+/// let clips = exporter.export("dataset", "episode-01", &transcript, &audio).unwrap();
+/// println!("wrote {} clips", clips);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DatasetExporter {
+    layout: DatasetLayout,
+    min_ms: u32,
+    max_ms: u32,
+}
+
+impl DatasetExporter {
+    pub fn new(layout: DatasetLayout) -> Self {
+        Self {
+            layout,
+            min_ms: 1000,
+            max_ms: whisper_rs_sys::WHISPER_CHUNK_SIZE * 1000,
+        }
+    }
+
+    /// Skip clips shorter than this.
+    ///
+    /// Defaults to 1 second.
+    pub fn min_ms(mut self, min_ms: u32) -> Self {
+        self.min_ms = min_ms;
+        self
+    }
+
+    /// Skip clips longer than this.
+    ///
+    /// Defaults to 30 seconds, the longest input Whisper is trained on.
+    pub fn max_ms(mut self, max_ms: u32) -> Self {
+        self.max_ms = max_ms;
+        self
+    }
+
+    /// Write a clip per segment of `transcript` into `dir`, named `name` followed by the
+    /// segment index, e.g. `episode-01-0007.wav`, and add them to the metadata there.
+    ///
+    /// `dir` is created if needed. The metadata is appended to, so transcripts of many files
+    /// can be exported into the same dataset under different names.
+    ///
+    /// # Arguments
+    /// * dir: The dataset directory.
+    /// * name: Prefix of the clip names, unique within the dataset.
+    /// * transcript: The transcript of `audio`.
+    /// * audio: The audio `transcript` was produced from, 16 kHz mono.
+    ///
+    /// # Returns
+    /// The number of clips written.
+    pub fn export(
+        &self,
+        dir: impl AsRef<Path>,
+        name: &str,
+        transcript: &Transcript,
+        audio: &[f32],
+    ) -> io::Result<usize> {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)?;
+        let path = dir.join(self.layout.file_name());
+        let mut metadata = OpenOptions::new().create(true).append(true).open(&path)?;
+        if metadata.metadata()?.len() == 0 && self.layout == DatasetLayout::MetadataCsv {
+            metadata.write_all(b"file_name,text\n")?;
+        }
+
+        let (min, max) = (
+            self.min_ms as usize * SAMPLES_PER_CS / 10,
+            self.max_ms as usize * SAMPLES_PER_CS / 10,
+        );
+        let mut lines = String::new();
+        let mut clips = 0;
+        for (index, segment) in transcript.segments.iter().enumerate() {
+            let text = segment.text.trim();
+            let clip = transcript.segment_audio(index, audio);
+            if text.is_empty() || clip.len() < min || clip.len() > max {
+                continue;
+            }
+            let file_name = format!("{}-{:04}.wav", name, index);
+            write_wav(BufWriter::new(File::create(dir.join(&file_name))?), clip)?;
+            clips += 1;
+
+            match self.layout {
+                DatasetLayout::MetadataCsv => {
+                    lines.push_str(&csv_field(&file_name));
+                    lines.push(',');
+                    lines.push_str(&csv_field(text));
+                }
+                DatasetLayout::JsonlManifest => {
+                    lines.push_str("{\"audio_filepath\":");
+                    json::write_str(&mut lines, &file_name);
+                    let seconds = clip.len() as f64 / whisper_rs_sys::WHISPER_SAMPLE_RATE as f64;
+                    lines.push_str(&format!(",\"duration\":{:.3},\"text\":", seconds));
+                    json::write_str(&mut lines, text);
+                    lines.push('}');
+                }
+            }
+            lines.push('\n');
+        }
+        // the metadata only lists clips that were written completely
+        metadata.write_all(lines.as_bytes())?;
+        metadata.flush()?;
+        Ok(clips)
+    }
+}
+
+/// Quote a CSV field if it contains a separator, quote or line break.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::TranscriptSegment;
+
+    #[test]
+    fn clips_and_metadata_are_written() {
+        let dir = std::env::temp_dir().join(format!("whisper-rs-dataset-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let audio = vec![0.1f32; 16000 * 10];
+        let transcript = Transcript::new(vec![
+            TranscriptSegment::new(0, 200, " Hello, \"world\""),
+            TranscriptSegment::new(200, 250, " too short"),
+            TranscriptSegment::new(250, 500, "   "),
+            TranscriptSegment::new(500, 800, " Bye"),
+        ]);
+
+        let csv = DatasetExporter::new(DatasetLayout::MetadataCsv);
+        assert_eq!(csv.export(&dir, "a", &transcript, &audio).unwrap(), 2);
+        assert_eq!(csv.export(&dir, "b", &transcript, &audio).unwrap(), 2);
+        assert_eq!(
+            fs::read_to_string(dir.join("metadata.csv")).unwrap(),
+            "file_name,text\na-0000.wav,\"Hello, \"\"world\"\"\"\na-0003.wav,Bye\n\
+             b-0000.wav,\"Hello, \"\"world\"\"\"\nb-0003.wav,Bye\n"
+        );
+        let clip = fs::metadata(dir.join("a-0003.wav")).unwrap();
+        assert_eq!(clip.len(), 44 + 3 * 16000 * 2);
+
+        let jsonl = DatasetExporter::new(DatasetLayout::JsonlManifest).min_ms(2500);
+        assert_eq!(jsonl.export(&dir, "c", &transcript, &audio).unwrap(), 1);
+        assert_eq!(
+            fs::read_to_string(dir.join("manifest.jsonl")).unwrap(),
+            "{\"audio_filepath\":\"c-0003.wav\",\"duration\":3.000,\"text\":\"Bye\"}\n"
+        );
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
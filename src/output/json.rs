@@ -0,0 +1,94 @@
+use crate::{TranscriptSegment, TranscriptToken};
+use std::fmt::Write;
+
+/// Append `segment` to `out` as a single-line JSON object.
+///
+/// Times are in centiseconds, as everywhere else. Tokens are only included if `tokens` is set,
+/// as they make up most of the size.
+pub(crate) fn write_segment(out: &mut String, segment: &TranscriptSegment, tokens: bool) {
+    let _ = write!(
+        out,
+        "{{\"start\":{},\"end\":{},\"text\":",
+        segment.start, segment.end
+    );
+    write_str(out, &segment.text);
+    out.push_str(",\"no_speech_probability\":");
+    write_f32(out, segment.no_speech_probability);
+    let _ = write!(out, ",\"speaker_turn_next\":{}", segment.speaker_turn_next);
+    out.push_str(",\"speaker\":");
+    match &segment.speaker {
+        Some(speaker) => write_str(out, speaker),
+        None => out.push_str("null"),
+    }
+    if tokens {
+        out.push_str(",\"tokens\":[");
+        for (i, token) in segment.tokens.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            write_token(out, token);
+        }
+        out.push(']');
+    }
+    out.push('}');
+}
+
+fn write_token(out: &mut String, token: &TranscriptToken) {
+    let _ = write!(out, "{{\"id\":{},\"text\":", token.id);
+    write_str(out, &token.text);
+    out.push_str(",\"p\":");
+    write_f32(out, token.p);
+    out.push_str(",\"plog\":");
+    write_f32(out, token.plog);
+    let _ = write!(
+        out,
+        ",\"t0\":{},\"t1\":{},\"t_dtw\":{},\"special\":{}}}",
+        token.t0, token.t1, token.t_dtw, token.special
+    );
+}
+
+/// JSON has no representation for NaN or infinities.
+fn write_f32(out: &mut String, value: f32) {
+    if value.is_finite() {
+        let _ = write!(out, "{}", value);
+    } else {
+        out.push_str("null");
+    }
+}
+
+pub(crate) fn write_str(out: &mut String, text: &str) {
+    out.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn segments_are_written_as_one_line() {
+        let mut segment = TranscriptSegment::new(0, 150, " \"Hi\"\n\u{1}");
+        segment.no_speech_probability = f32::NAN;
+        segment.speaker = Some("agent".into());
+
+        let mut out = String::new();
+        write_segment(&mut out, &segment, false);
+        assert_eq!(
+            out,
+            r#"{"start":0,"end":150,"text":" \"Hi\"\n\u0001","no_speech_probability":null,"speaker_turn_next":false,"speaker":"agent"}"#
+        );
+    }
+}
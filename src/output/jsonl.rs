@@ -0,0 +1,153 @@
+use super::json;
+use crate::TranscriptSegment;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+/// Writes segments as JSON Lines, one object per segment, as soon as they are produced.
+///
+/// Each segment is written and flushed in one go, so a job that crashes leaves every segment
+/// produced so far on disk, and at most a partial last line, which [`JsonlSink::resume`] removes.
+/// Fields are those of [`TranscriptSegment`], with times in centiseconds.
+///
+/// # Examples
+/// Write segments while [`crate::WhisperState::full`] is still running:
+/// ```no_run
+/// # use whisper_rs::{FullParams, SamplingStrategy, SegmentCallbackData};
+/// # use whisper_rs::output::JsonlSink;
+/// let mut sink = JsonlSink::new(std::fs::File::create("transcript.jsonl").unwrap());
+/// let mut params = FullParams::new(SamplingStrategy::default());
+/// params.set_segment_callback_safe_lossy(move |segment: SegmentCallbackData| {
+///     if let Err(e) = sink.write_segment(&segment.into()) {
+///         eprintln!("failed to write segment: {}", e);
+///     }
+/// });
+/// ```
+#[derive(Debug)]
+pub struct JsonlSink<W: Write> {
+    writer: W,
+    tokens: bool,
+    line: String,
+}
+
+impl<W: Write> JsonlSink<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            tokens: false,
+            line: String::new(),
+        }
+    }
+
+    /// Include the tokens of each segment, see [`TranscriptSegment::tokens`].
+    /// They make up most of the output, so only enable this if you need them.
+    ///
+    /// Defaults to false.
+    pub fn with_tokens(mut self, tokens: bool) -> Self {
+        self.tokens = tokens;
+        self
+    }
+
+    /// Write one segment and flush the writer.
+    pub fn write_segment(&mut self, segment: &TranscriptSegment) -> io::Result<()> {
+        self.line.clear();
+        json::write_segment(&mut self.line, segment, self.tokens);
+        self.line.push('\n');
+        self.writer.write_all(self.line.as_bytes())?;
+        self.writer.flush()
+    }
+
+    /// Write every segment of `segments`, such as the output of
+    /// [`crate::StreamingTranscribe::push_audio`].
+    pub fn write_segments<'s>(
+        &mut self,
+        segments: impl IntoIterator<Item = &'s TranscriptSegment>,
+    ) -> io::Result<()> {
+        segments
+            .into_iter()
+            .try_for_each(|segment| self.write_segment(segment))
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl JsonlSink<File> {
+    /// Open `path` to continue an interrupted job, creating it if it doesn't exist.
+    ///
+    /// A partial line left by a crash is removed.
+    ///
+    /// # Returns
+    /// The sink, and the end time in centiseconds of the last segment already written,
+    /// from which to resume, e.g. with [`crate::FullParams::set_offset_ms`] set to ten times it.
+    pub fn resume(path: impl AsRef<Path>) -> io::Result<(Self, Option<i64>)> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+        let mut content = Vec::new();
+        file.read_to_end(&mut content)?;
+
+        let complete = content
+            .iter()
+            .rposition(|&b| b == b'\n')
+            .map_or(0, |i| i + 1);
+        if complete < content.len() {
+            file.set_len(complete as u64)?;
+        }
+        file.seek(SeekFrom::Start(complete as u64))?;
+
+        let last_end = String::from_utf8_lossy(&content[..complete])
+            .lines()
+            .rev()
+            .find_map(end_time);
+        Ok((Self::new(file), last_end))
+    }
+}
+
+/// The `end` field of a line written by [`json::write_segment`].
+fn end_time(line: &str) -> Option<i64> {
+    let rest = &line[line.find(",\"end\":")? + 7..];
+    let len = rest
+        .find(|c: char| c != '-' && !c.is_ascii_digit())
+        .unwrap_or(rest.len());
+    rest[..len].parse().ok()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn interrupted_output_is_resumed() {
+        let path =
+            std::env::temp_dir().join(format!("whisper-rs-jsonl-{}.jsonl", std::process::id()));
+        let mut sink = JsonlSink::new(File::create(&path).unwrap());
+        sink.write_segments(&[
+            TranscriptSegment::new(0, 150, " one"),
+            TranscriptSegment::new(150, 320, " two"),
+        ])
+        .unwrap();
+        // a crash in the middle of a line
+        sink.into_inner()
+            .write_all(b"{\"start\":320,\"end\"")
+            .unwrap();
+
+        let (mut sink, last_end) = JsonlSink::resume(&path).unwrap();
+        assert_eq!(last_end, Some(320));
+        sink.write_segment(&TranscriptSegment::new(320, 400, " three"))
+            .unwrap();
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        let ends: Vec<_> = content.lines().map(|l| end_time(l).unwrap()).collect();
+        assert_eq!(ends, [150, 320, 400]);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
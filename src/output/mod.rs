@@ -0,0 +1,10 @@
+//! Writing transcripts in common file formats.
+
+mod dataset;
+pub(crate) mod json;
+mod jsonl;
+mod subtitles;
+
+pub use dataset::{DatasetExporter, DatasetLayout};
+pub use jsonl::JsonlSink;
+pub use subtitles::{cues, to_srt, to_vtt, write_srt, write_vtt, Cue, SubtitleOptions};
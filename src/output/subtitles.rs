@@ -0,0 +1,224 @@
+use crate::transcript::words::words;
+use crate::Transcript;
+use std::io::{self, Write};
+
+/// How to cut a transcript into subtitle cues.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SubtitleOptions {
+    /// Longest a cue may last, in milliseconds.
+    ///
+    /// If set, segments are re-cut into cues at word boundaries, so captions are evenly paced
+    /// regardless of how the model split its output. Word times come from DTW
+    /// (see [`crate::DtwParameters`]) or token timestamps
+    /// (see [`crate::FullParams::set_token_timestamps`]) if they were enabled, and are interpolated
+    /// from the length of each word otherwise. A single word longer than this gets a cue of its own.
+    ///
+    /// Defaults to `None`, one cue per segment.
+    pub max_cue_ms: Option<u32>,
+    /// Prefix cues with the speaker of their segment, if known.
+    ///
+    /// Defaults to false.
+    pub speakers: bool,
+}
+
+/// A single subtitle cue.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cue {
+    /// Start time in centiseconds.
+    pub start: i64,
+    /// End time in centiseconds.
+    pub end: i64,
+    pub text: String,
+    pub speaker: Option<String>,
+}
+
+/// Cut `transcript` into cues according to `options`.
+pub fn cues(transcript: &Transcript, options: &SubtitleOptions) -> Vec<Cue> {
+    let Some(max_cue_ms) = options.max_cue_ms else {
+        return transcript
+            .iter()
+            .filter(|s| !s.text.trim().is_empty())
+            .map(|s| Cue {
+                start: s.start,
+                end: s.end,
+                text: s.text.trim().to_owned(),
+                speaker: s.speaker.clone(),
+            })
+            .collect();
+    };
+    let budget = (max_cue_ms as i64 / 10).max(1);
+
+    let mut out: Vec<Cue> = Vec::new();
+    let mut current: Option<Cue> = None;
+    for segment in transcript {
+        for word in words(segment) {
+            match &mut current {
+                Some(cue) if cue.speaker == segment.speaker && word.end - cue.start <= budget => {
+                    cue.text.push(' ');
+                    cue.text.push_str(&word.text);
+                    cue.end = word.end;
+                }
+                _ => {
+                    out.extend(current.take());
+                    current = Some(Cue {
+                        start: word.start,
+                        end: word.end,
+                        text: word.text,
+                        speaker: segment.speaker.clone(),
+                    });
+                }
+            }
+        }
+    }
+    out.extend(current);
+    out
+}
+
+fn timestamp(cs: i64, separator: char) -> String {
+    let ms = cs.max(0) * 10;
+    format!(
+        "{:02}:{:02}:{:02}{}{:03}",
+        ms / 3_600_000,
+        ms / 60_000 % 60,
+        ms / 1000 % 60,
+        separator,
+        ms % 1000
+    )
+}
+
+/// Write `transcript` as SubRip (`.srt`) subtitles.
+pub fn write_srt<W: Write>(
+    mut w: W,
+    transcript: &Transcript,
+    options: &SubtitleOptions,
+) -> io::Result<()> {
+    for (i, cue) in cues(transcript, options).iter().enumerate() {
+        let text = match (&cue.speaker, options.speakers) {
+            (Some(speaker), true) => format!("{}: {}", speaker, cue.text),
+            _ => cue.text.clone(),
+        };
+        write!(
+            w,
+            "{}\n{} --> {}\n{}\n\n",
+            i + 1,
+            timestamp(cue.start, ','),
+            timestamp(cue.end, ','),
+            text
+        )?;
+    }
+    Ok(())
+}
+
+/// Write `transcript` as WebVTT (`.vtt`) subtitles. Speakers use voice tags (`<v Name>`).
+pub fn write_vtt<W: Write>(
+    mut w: W,
+    transcript: &Transcript,
+    options: &SubtitleOptions,
+) -> io::Result<()> {
+    w.write_all(b"WEBVTT\n\n")?;
+    for cue in cues(transcript, options) {
+        // cue text must not contain the "-->" arrow, or a blank line that would end the cue
+        let text = cue.text.replace("-->", "->").replace("\n\n", "\n");
+        let text = match (&cue.speaker, options.speakers) {
+            (Some(speaker), true) => format!("<v {}>{}", speaker, text),
+            _ => text,
+        };
+        write!(
+            w,
+            "{} --> {}\n{}\n\n",
+            timestamp(cue.start, '.'),
+            timestamp(cue.end, '.'),
+            text
+        )?;
+    }
+    Ok(())
+}
+
+/// [`write_srt`] into a `String`.
+pub fn to_srt(transcript: &Transcript, options: &SubtitleOptions) -> String {
+    let mut out = Vec::new();
+    write_srt(&mut out, transcript, options).expect("writing to a Vec can't fail");
+    String::from_utf8(out).expect("cue text is valid UTF-8")
+}
+
+/// [`write_vtt`] into a `String`.
+pub fn to_vtt(transcript: &Transcript, options: &SubtitleOptions) -> String {
+    let mut out = Vec::new();
+    write_vtt(&mut out, transcript, options).expect("writing to a Vec can't fail");
+    String::from_utf8(out).expect("cue text is valid UTF-8")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{TranscriptSegment, TranscriptToken};
+
+    fn token(text: &str, t0: i64, t1: i64) -> TranscriptToken {
+        TranscriptToken {
+            text: text.into(),
+            t0,
+            t1,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn segments_are_recut_by_word_timestamps() {
+        let mut segment = TranscriptSegment::new(0, 500, " one two three four");
+        segment.tokens = vec![
+            TranscriptToken {
+                special: true,
+                ..token("[_BEG_]", 0, 0)
+            },
+            token(" one", 0, 100),
+            token(" tw", 100, 150),
+            token("o", 150, 200),
+            token(" three", 250, 400),
+            token(" four", 400, 500),
+        ];
+        let transcript = Transcript::new(vec![segment]);
+        let options = SubtitleOptions {
+            max_cue_ms: Some(2500),
+            ..Default::default()
+        };
+
+        let cues: Vec<_> = cues(&transcript, &options)
+            .into_iter()
+            .map(|c| (c.start, c.end, c.text))
+            .collect();
+        assert_eq!(
+            cues,
+            vec![
+                (0, 200, "one two".to_owned()),
+                (250, 500, "three four".to_owned())
+            ]
+        );
+    }
+
+    #[test]
+    fn words_without_timestamps_are_interpolated() {
+        let transcript = Transcript::new(vec![TranscriptSegment::new(100, 300, " ab cd")]);
+        let options = SubtitleOptions {
+            max_cue_ms: Some(1000),
+            ..Default::default()
+        };
+        assert_eq!(
+            to_srt(&transcript, &options),
+            "1\n00:00:01,000 --> 00:00:02,000\nab\n\n2\n00:00:02,000 --> 00:00:03,000\ncd\n\n"
+        );
+    }
+
+    #[test]
+    fn vtt_has_header_and_voice_tags() {
+        let mut segment = TranscriptSegment::new(360_000, 360_150, " Hello.");
+        segment.speaker = Some("agent".into());
+        let options = SubtitleOptions {
+            speakers: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            to_vtt(&Transcript::new(vec![segment]), &options),
+            "WEBVTT\n\n01:00:00.000 --> 01:00:01.500\n<v agent>Hello.\n\n"
+        );
+    }
+}
@@ -0,0 +1,383 @@
+use crate::audio_pipeline::Resampler;
+use crate::{StreamingTranscribe, TranscriptSegment};
+use std::fmt;
+use std::io::{self, Read};
+
+/// Encoding of the samples of raw PCM audio, see [`PcmFormat`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SampleFormat {
+    /// Signed 16-bit little-endian integers, ffmpeg's `s16le`.
+    S16le,
+    /// Signed 32-bit little-endian integers, ffmpeg's `s32le`.
+    S32le,
+    /// 32-bit little-endian floats, ffmpeg's `f32le`.
+    F32le,
+}
+
+impl SampleFormat {
+    /// Size of one sample in bytes.
+    pub fn bytes(self) -> usize {
+        match self {
+            Self::S16le => 2,
+            Self::S32le | Self::F32le => 4,
+        }
+    }
+
+    fn decode(self, bytes: &[u8]) -> f32 {
+        match self {
+            Self::S16le => i16::from_le_bytes([bytes[0], bytes[1]]) as f32 / 32768.0,
+            Self::S32le => {
+                i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f32
+                    / 2_147_483_648.0
+            }
+            Self::F32le => f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+        }
+    }
+}
+
+/// Layout of interleaved raw PCM audio.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PcmFormat {
+    pub sample_format: SampleFormat,
+    pub sample_rate: u32,
+    pub channels: u16,
+}
+
+impl PcmFormat {
+    /// 16 kHz mono, as whisper.cpp takes it, with samples encoded as `sample_format`.
+    pub fn whisper(sample_format: SampleFormat) -> Self {
+        Self {
+            sample_format,
+            sample_rate: whisper_rs_sys::WHISPER_SAMPLE_RATE,
+            channels: 1,
+        }
+    }
+
+    /// Size of one frame, a sample for each channel, in bytes.
+    pub fn frame_bytes(&self) -> usize {
+        self.sample_format.bytes() * self.channels.max(1) as usize
+    }
+}
+
+/// Reads raw PCM or WAV audio from a pipe or file as it arrives, converted to 16 kHz mono.
+///
+/// Use it to transcribe audio from other programs, e.g. standard input fed by
+/// `ffmpeg -i talk.mp4 -f s16le -ac 1 -ar 16000 -` or a capture tool.
+///
+/// # Examples
+/// ```no_run
+/// # use whisper_rs::{FullParams, PcmFormat, PcmReader, SampleFormat, SamplingStrategy, StreamingTranscriber, WhisperContext, WhisperContextParameters};
+/// # let ctx = WhisperContext::new_with_params("model.bin", WhisperContextParameters::default()).unwrap();
+/// let params = FullParams::new(SamplingStrategy::default());
+/// let mut stream = StreamingTranscriber::new(ctx.create_state().unwrap(), params);
+/// let mut input = PcmReader::raw(std::io::stdin().lock(), PcmFormat::whisper(SampleFormat::S16le));
+/// input
+///     .transcribe_into(&mut stream, |segments| {
+///         for segment in segments {
+///             println!("{}", segment.text);
+///         }
+///     })
+///     .unwrap();
+/// ```
+pub struct PcmReader<R> {
+    reader: R,
+    format: PcmFormat,
+    /// Bytes of an incomplete frame from the last read.
+    carry: Vec<u8>,
+    /// Bytes of audio left in a WAV data chunk, if it gives its length.
+    remaining: Option<u64>,
+    /// Carries the resampling position over between reads.
+    resampler: Resampler,
+}
+
+impl<R: Read> PcmReader<R> {
+    /// Read headerless audio in `format`.
+    pub fn raw(reader: R, format: PcmFormat) -> Self {
+        Self {
+            reader,
+            format,
+            carry: Vec::new(),
+            remaining: None,
+            resampler: Resampler::new(format.sample_rate.max(1)),
+        }
+    }
+
+    /// Read a WAV stream with 16-bit or 32-bit integer or 32-bit float samples, started by
+    /// reading its header.
+    ///
+    /// Streamed WAV, such as `ffmpeg -f wav -` writes to a pipe, often has a data length of zero
+    /// or `0xFFFFFFFF`; such data, and data in the last chunk, is read to the end of the stream.
+    ///
+    /// # Returns
+    /// `Err` of kind [`io::ErrorKind::InvalidData`] if the stream isn't WAV or holds
+    /// another encoding.
+    pub fn wav(mut reader: R) -> io::Result<Self> {
+        let mut header = [0u8; 12];
+        reader.read_exact(&mut header)?;
+        if &header[0..4] != b"RIFF" || &header[8..12] != b"WAVE" {
+            return Err(invalid_data("not a WAV stream"));
+        }
+
+        let mut format = None;
+        loop {
+            let mut chunk = [0u8; 8];
+            reader.read_exact(&mut chunk)?;
+            let len = u32::from_le_bytes(chunk[4..8].try_into().unwrap());
+            if &chunk[0..4] == b"data" {
+                let format = format.ok_or_else(|| invalid_data("WAV data before format"))?;
+                let remaining = (len != 0 && len != u32::MAX).then_some(len as u64);
+                return Ok(Self {
+                    reader,
+                    format,
+                    carry: Vec::new(),
+                    remaining,
+                    resampler: Resampler::new(format.sample_rate),
+                });
+            }
+            // chunks are padded to an even length
+            let mut body = vec![0u8; len as usize + len as usize % 2];
+            reader.read_exact(&mut body)?;
+            if &chunk[0..4] == b"fmt " {
+                format = Some(wav_format(&body)?);
+            }
+        }
+    }
+
+    /// The format of the audio before conversion.
+    pub fn format(&self) -> PcmFormat {
+        self.format
+    }
+
+    /// Read whatever audio is available, waiting for at least some, converted to 16 kHz mono.
+    ///
+    /// # Returns
+    /// `Ok(None)` at the end of the stream. A trailing incomplete frame is dropped. When
+    /// resampling, the last sample or two are held back until the next read, or the end.
+    pub fn read_audio(&mut self) -> io::Result<Option<Vec<f32>>> {
+        let mut buf = [0u8; 64 * 1024];
+        let limit = self
+            .remaining
+            .map_or(buf.len(), |r| r.min(buf.len() as u64) as usize);
+        let n = loop {
+            if limit == 0 {
+                return Ok(self.finish());
+            }
+            match self.reader.read(&mut buf[..limit]) {
+                Ok(0) => return Ok(self.finish()),
+                Ok(n) => break n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        };
+        if let Some(remaining) = &mut self.remaining {
+            *remaining -= n as u64;
+        }
+        self.carry.extend_from_slice(&buf[..n]);
+        let frame = self.format.frame_bytes();
+        let whole = self.carry.len() - self.carry.len() % frame;
+        let audio = self
+            .resampler
+            .push(&to_mono(&self.carry[..whole], self.format));
+        self.carry.drain(..whole);
+        Ok(Some(audio))
+    }
+
+    /// The last samples the resampler held back, once, at the end of the stream.
+    fn finish(&mut self) -> Option<Vec<f32>> {
+        let tail = self.resampler.finish();
+        (!tail.is_empty()).then_some(tail)
+    }
+
+    /// Read the rest of the stream, converted to 16 kHz mono.
+    pub fn read_to_end(&mut self) -> io::Result<Vec<f32>> {
+        let mut audio = Vec::new();
+        while let Some(chunk) = self.read_audio()? {
+            audio.extend(chunk);
+        }
+        Ok(audio)
+    }
+
+    /// Push the audio into `stream` as it arrives until the end of the stream, passing every
+    /// batch of finalized segments to `on_segments`. At the end, the audio still buffered in
+    /// `stream` is transcribed with [`StreamingTranscribe::finish`].
+    pub fn transcribe_into<T: StreamingTranscribe>(
+        &mut self,
+        stream: &mut T,
+        mut on_segments: impl FnMut(Vec<TranscriptSegment>),
+    ) -> Result<(), PcmStreamError<T::Error>> {
+        while let Some(audio) = self.read_audio().map_err(PcmStreamError::Io)? {
+            on_segments(
+                stream
+                    .push_audio(&audio)
+                    .map_err(PcmStreamError::Transcribe)?,
+            );
+        }
+        on_segments(stream.finish().map_err(PcmStreamError::Transcribe)?);
+        Ok(())
+    }
+}
+
+/// Error from [`PcmReader::transcribe_into`].
+#[derive(Debug)]
+pub enum PcmStreamError<E> {
+    Io(io::Error),
+    Transcribe(E),
+}
+
+impl<E: fmt::Display> fmt::Display for PcmStreamError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => e.fmt(f),
+            Self::Transcribe(e) => e.fmt(f),
+        }
+    }
+}
+
+impl<E: std::error::Error + 'static> std::error::Error for PcmStreamError<E> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            Self::Transcribe(e) => Some(e),
+        }
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// The format described by the body of a WAV `fmt ` chunk.
+fn wav_format(body: &[u8]) -> io::Result<PcmFormat> {
+    if body.len() < 16 {
+        return Err(invalid_data("truncated WAV format"));
+    }
+    let mut tag = u16::from_le_bytes([body[0], body[1]]);
+    let channels = u16::from_le_bytes([body[2], body[3]]);
+    let sample_rate = u32::from_le_bytes(body[4..8].try_into().unwrap());
+    let bits = u16::from_le_bytes([body[14], body[15]]);
+    // WAVE_FORMAT_EXTENSIBLE gives the actual format in its sub-format GUID
+    if tag == 0xfffe && body.len() >= 26 {
+        tag = u16::from_le_bytes([body[24], body[25]]);
+    }
+    let sample_format = match (tag, bits) {
+        (1, 16) => SampleFormat::S16le,
+        (1, 32) => SampleFormat::S32le,
+        (3, 32) => SampleFormat::F32le,
+        _ => {
+            return Err(invalid_data(
+                "unsupported WAV encoding; use 16-bit or 32-bit PCM or 32-bit float",
+            ))
+        }
+    };
+    if channels == 0 || sample_rate == 0 {
+        return Err(invalid_data("WAV format without channels or sample rate"));
+    }
+    Ok(PcmFormat {
+        sample_format,
+        sample_rate,
+        channels,
+    })
+}
+
+/// Decode whole frames of `format` and mix them down to mono.
+fn to_mono(bytes: &[u8], format: PcmFormat) -> Vec<f32> {
+    let sample = format.sample_format.bytes();
+    let channels = format.channels.max(1) as usize;
+    bytes
+        .chunks_exact(format.frame_bytes())
+        .map(|frame| {
+            frame
+                .chunks_exact(sample)
+                .map(|s| format.sample_format.decode(s))
+                .sum::<f32>()
+                / channels as f32
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn wav(tag: u16, channels: u16, rate: u32, bits: u16, data: &[u8]) -> Vec<u8> {
+        let mut wav = b"RIFF\0\0\0\0WAVEfmt \x10\0\0\0".to_vec();
+        wav.extend_from_slice(&tag.to_le_bytes());
+        wav.extend_from_slice(&channels.to_le_bytes());
+        wav.extend_from_slice(&rate.to_le_bytes());
+        wav.extend_from_slice(&[0; 6]);
+        wav.extend_from_slice(&bits.to_le_bytes());
+        // a streamed data chunk without a length
+        wav.extend_from_slice(b"LIST\x02\0\0\0ab");
+        wav.extend_from_slice(b"data\xff\xff\xff\xff");
+        wav.extend_from_slice(data);
+        wav
+    }
+
+    #[test]
+    fn streamed_wav_is_read_to_the_end() {
+        // stereo 32 kHz: each frame is (0.5, -0.5) or (0.5, 0.5)
+        let mut data = Vec::new();
+        for i in 0..64 {
+            let right: i16 = if i % 2 == 0 { -16384 } else { 16384 };
+            data.extend_from_slice(&16384i16.to_le_bytes());
+            data.extend_from_slice(&right.to_le_bytes());
+        }
+        let bytes = wav(1, 2, 32000, 16, &data);
+        let mut reader = PcmReader::wav(&bytes[..]).unwrap();
+        assert_eq!(
+            reader.format(),
+            PcmFormat {
+                sample_format: SampleFormat::S16le,
+                sample_rate: 32000,
+                channels: 2,
+            }
+        );
+        let audio = reader.read_to_end().unwrap();
+        assert_eq!(audio.len(), 32);
+        assert_eq!(audio[0], 0.0);
+    }
+
+    #[test]
+    fn incomplete_frames_wait_for_the_next_read() {
+        let bytes: Vec<u8> = [0.25f32, -0.5]
+            .iter()
+            .flat_map(|s| s.to_le_bytes())
+            .collect();
+        let mut reader = PcmReader::raw(
+            io::Read::chain(&bytes[..3], &bytes[3..]),
+            PcmFormat::whisper(SampleFormat::F32le),
+        );
+        assert_eq!(reader.read_audio().unwrap(), Some(vec![]));
+        assert_eq!(reader.read_audio().unwrap(), Some(vec![0.25, -0.5]));
+        assert_eq!(reader.read_audio().unwrap(), None);
+
+        assert!(PcmReader::wav(&wav(2, 1, 16000, 4, &[])[..]).is_err());
+    }
+
+    #[test]
+    fn resampling_does_not_depend_on_the_reads() {
+        let data: Vec<u8> = (0..441i16).flat_map(|i| (i * 64).to_le_bytes()).collect();
+        let format = PcmFormat {
+            sample_format: SampleFormat::S16le,
+            sample_rate: 44100,
+            channels: 1,
+        };
+        let whole = PcmReader::raw(&data[..], format).read_to_end().unwrap();
+        // reads of 7 samples, which 44.1 kHz doesn't divide into whole 16 kHz samples
+        let mut reader = PcmReader::raw(Trickle(&data), format);
+        let trickled = reader.read_to_end().unwrap();
+        assert_eq!(whole.len(), 160);
+        assert_eq!(trickled, whole);
+    }
+
+    struct Trickle<'a>(&'a [u8]);
+
+    impl io::Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = buf.len().min(14).min(self.0.len());
+            buf[..n].copy_from_slice(&self.0[..n]);
+            self.0 = &self.0[n..];
+            Ok(n)
+        }
+    }
+}
@@ -0,0 +1,189 @@
+use crate::{FullParams, Transcribe, Transcript};
+use std::fmt;
+use std::sync::mpsc::{sync_channel, Receiver};
+use std::sync::{Mutex, PoisonError};
+use std::thread;
+
+/// Transcribes a batch of inputs while the next ones are fetched and decoded in the background,
+/// so a slow download doesn't leave the model idle between files.
+///
+/// Fetching runs on [`Self::fetchers`] scoped threads, transcription on the calling thread with
+/// whatever [`Transcribe`] backend it is given. At most [`Self::prefetch`] decoded inputs wait to
+/// be transcribed, bounding memory use however far ahead fetching gets, and results are reported
+/// in input order.
+///
+/// # Examples
+/// ```no_run
+/// # use whisper_rs::{FullParams, PrefetchPipeline, SamplingStrategy, WhisperContext, WhisperContextParameters};
+/// # let ctx = WhisperContext::new_with_params("model.bin", WhisperContextParameters::default()).unwrap();
+/// # fn download_and_decode(key: &str) -> std::io::Result<Vec<f32>> { unimplemented!() }
+/// let keys = vec!["a.wav", "b.wav", "c.wav"];
+/// let mut state = ctx.create_state().unwrap();
+/// let params = FullParams::new(SamplingStrategy::default());
+/// PrefetchPipeline::new().fetchers(2).run(
+///     keys,
+///     |key| download_and_decode(key),
+///     &mut state,
+///     &params,
+///     |key, result| match result {
+///         Ok(transcript) => println!("{key}: {}", transcript.text()),
+///         Err(e) => eprintln!("{key}: {e}"),
+///     },
+/// );
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PrefetchPipeline {
+    prefetch: usize,
+    fetchers: usize,
+}
+
+impl Default for PrefetchPipeline {
+    fn default() -> Self {
+        Self {
+            prefetch: 2,
+            fetchers: 1,
+        }
+    }
+}
+
+impl PrefetchPipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// How many fetched inputs may wait for transcription. At least 1.
+    ///
+    /// Defaults to 2.
+    pub fn prefetch(mut self, prefetch: usize) -> Self {
+        self.prefetch = prefetch.max(1);
+        self
+    }
+
+    /// How many inputs are fetched at the same time, for sources where a single fetch can't keep
+    /// up with transcription. At least 1.
+    ///
+    /// Defaults to 1.
+    pub fn fetchers(mut self, fetchers: usize) -> Self {
+        self.fetchers = fetchers.max(1);
+        self
+    }
+
+    /// Fetch each of `inputs` with `fetch`, which returns 16 kHz mono audio, and transcribe it
+    /// with `backend` and a copy of `params`, passing every input and its result to `on_result`
+    /// in order.
+    ///
+    /// A failure to fetch or transcribe one input is reported for it and the batch carries on.
+    /// A panic in `fetch` is resumed once the other inputs are done.
+    pub fn run<Inputs, I, E, T>(
+        &self,
+        inputs: Inputs,
+        fetch: impl Fn(&I) -> Result<Vec<f32>, E> + Sync,
+        backend: &mut T,
+        params: &FullParams<'_, '_>,
+        mut on_result: impl FnMut(I, Result<Transcript, PipelineError<E, T::Error>>),
+    ) where
+        Inputs: IntoIterator<Item = I>,
+        Inputs::IntoIter: Send,
+        I: Send,
+        E: Send,
+        T: Transcribe + ?Sized,
+    {
+        let inputs = Mutex::new(inputs.into_iter());
+        // one receiver per input, queued in input order, each getting the fetched audio
+        let (queue, ordered) = sync_channel::<Receiver<(I, Result<Vec<f32>, E>)>>(self.prefetch);
+        thread::scope(|scope| {
+            for _ in 0..self.fetchers {
+                let (inputs, fetch, queue) = (&inputs, &fetch, queue.clone());
+                scope.spawn(move || loop {
+                    let (input, done) = {
+                        let mut inputs = inputs.lock().unwrap_or_else(PoisonError::into_inner);
+                        let Some(input) = inputs.next() else { break };
+                        let (done, fetched) = sync_channel(1);
+                        // queued while holding the lock, so results stay in input order
+                        if queue.send(fetched).is_err() {
+                            break;
+                        }
+                        (input, done)
+                    };
+                    let audio = fetch(&input);
+                    let _ = done.send((input, audio));
+                });
+            }
+            drop(queue);
+
+            // a receiver without a result belongs to a fetch that panicked
+            for (input, audio) in ordered.iter().filter_map(|fetched| fetched.recv().ok()) {
+                let result = match audio {
+                    Ok(audio) => backend
+                        .transcribe(params.clone(), &audio)
+                        .map_err(PipelineError::Transcribe),
+                    Err(e) => Err(PipelineError::Fetch(e)),
+                };
+                on_result(input, result);
+            }
+        });
+    }
+}
+
+/// Error for one input of [`PrefetchPipeline::run`].
+#[derive(Debug)]
+pub enum PipelineError<F, T> {
+    Fetch(F),
+    Transcribe(T),
+}
+
+impl<F: fmt::Display, T: fmt::Display> fmt::Display for PipelineError<F, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Fetch(e) => e.fmt(f),
+            Self::Transcribe(e) => e.fmt(f),
+        }
+    }
+}
+
+impl<F, T> std::error::Error for PipelineError<F, T>
+where
+    F: std::error::Error + 'static,
+    T: std::error::Error + 'static,
+{
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Fetch(e) => Some(e),
+            Self::Transcribe(e) => Some(e),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "test-stub"))]
+mod test {
+    use super::*;
+    use crate::stub::StubContext;
+    use crate::SamplingStrategy;
+    use std::io;
+    use std::time::Duration;
+
+    #[test]
+    fn results_come_in_input_order() {
+        let ctx = StubContext::with_text(0, 100, " hello");
+        let mut state = ctx.create_state().unwrap();
+        let params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
+        let mut seen = Vec::new();
+        PrefetchPipeline::new().fetchers(3).prefetch(1).run(
+            0..8u64,
+            |&i| {
+                // later inputs finish fetching first
+                thread::sleep(Duration::from_millis(8 - i));
+                if i == 5 {
+                    return Err(io::Error::other("missing"));
+                }
+                Ok(vec![0.0; 16000])
+            },
+            &mut state,
+            &params,
+            |i, result| seen.push((i, result.is_ok())),
+        );
+        let expected: Vec<_> = (0..8).map(|i| (i, i != 5)).collect();
+        assert_eq!(seen, expected);
+        assert_eq!(ctx.runs(), 7);
+    }
+}
@@ -0,0 +1,759 @@
+use crate::transcript::words::normalize;
+use crate::{FullParams, Language, StreamingTranscribe, Transcribe, Transcript, TranscriptSegment};
+use std::collections::HashSet;
+
+/// A post-processing stage, run on every transcript by a [`ProcessingPipeline`].
+///
+/// Implemented for closures taking `&mut Transcript`, so custom stages need no type of their own.
+/// Stages that change the text of a segment should do so with [`Transcript::set_segment_text`],
+/// which keeps its tokens consistent with the new text.
+pub trait TranscriptProcessor: Send {
+    fn process(&mut self, transcript: &mut Transcript);
+}
+
+impl<F: FnMut(&mut Transcript) + Send> TranscriptProcessor for F {
+    fn process(&mut self, transcript: &mut Transcript) {
+        self(transcript)
+    }
+}
+
+/// An ordered list of [`TranscriptProcessor`]s, configured once and run on every transcript.
+///
+/// Use [`Self::wrap`] to run it automatically after each transcription of a backend.
+///
+/// # Examples
+/// ```
+/// # use whisper_rs::{CaptionConditioner, InverseTextNormalizer, ProcessingPipeline, ProfanityFilter, Transcript, TranscriptSegment};
+/// let mut pipeline = ProcessingPipeline::new()
+///     .stage(InverseTextNormalizer::new())
+///     .stage(ProfanityFilter::new(["darn"]))
+///     .stage(CaptionConditioner::new())
+///     .stage(|t: &mut Transcript| t.segments.retain(|s| s.no_speech_probability < 0.9));
+/// let mut transcript = Transcript::new(vec![TranscriptSegment::new(0, 100, " twenty  darn cats")]);
+/// pipeline.process(&mut transcript);
+/// assert_eq!(transcript.segments[0].text, " 20 d*** cats");
+/// ```
+#[derive(Default)]
+pub struct ProcessingPipeline {
+    stages: Vec<Box<dyn TranscriptProcessor>>,
+}
+
+impl ProcessingPipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a stage, run after the ones already added.
+    pub fn stage(mut self, stage: impl TranscriptProcessor + 'static) -> Self {
+        self.stages.push(Box::new(stage));
+        self
+    }
+
+    /// Number of stages.
+    pub fn len(&self) -> usize {
+        self.stages.len()
+    }
+
+    /// Whether the pipeline has no stages.
+    pub fn is_empty(&self) -> bool {
+        self.stages.is_empty()
+    }
+
+    /// Run every stage on `transcript`, in order.
+    pub fn process(&mut self, transcript: &mut Transcript) {
+        for stage in &mut self.stages {
+            stage.process(transcript);
+        }
+    }
+
+    /// Run this pipeline on every transcript `backend` produces.
+    pub fn wrap<T>(self, backend: T) -> Processed<T> {
+        Processed {
+            backend,
+            pipeline: self,
+        }
+    }
+}
+
+impl TranscriptProcessor for ProcessingPipeline {
+    fn process(&mut self, transcript: &mut Transcript) {
+        ProcessingPipeline::process(self, transcript)
+    }
+}
+
+/// A [`Transcribe`] or [`StreamingTranscribe`] backend whose output goes through
+/// a [`ProcessingPipeline`], see [`ProcessingPipeline::wrap`].
+///
+/// Streamed segments are processed in the batches they are emitted in,
+/// so stages only see the segments of one batch at a time.
+pub struct Processed<T> {
+    backend: T,
+    pipeline: ProcessingPipeline,
+}
+
+impl<T> Processed<T> {
+    /// Get the wrapped backend.
+    pub fn backend_mut(&mut self) -> &mut T {
+        &mut self.backend
+    }
+
+    /// Get the pipeline, e.g. to add stages.
+    pub fn pipeline_mut(&mut self) -> &mut ProcessingPipeline {
+        &mut self.pipeline
+    }
+
+    /// Unwrap the backend, dropping the pipeline.
+    pub fn into_inner(self) -> T {
+        self.backend
+    }
+
+    fn process_segments(&mut self, segments: Vec<TranscriptSegment>) -> Vec<TranscriptSegment> {
+        let mut transcript = Transcript::new(segments);
+        self.pipeline.process(&mut transcript);
+        transcript.segments
+    }
+}
+
+impl<T: Transcribe> Transcribe for Processed<T> {
+    type Error = T::Error;
+
+    fn transcribe(
+        &mut self,
+        params: FullParams<'_, '_>,
+        audio: &[f32],
+    ) -> Result<Transcript, Self::Error> {
+        let mut transcript = self.backend.transcribe(params, audio)?;
+        self.pipeline.process(&mut transcript);
+        Ok(transcript)
+    }
+}
+
+impl<T: StreamingTranscribe> StreamingTranscribe for Processed<T> {
+    type Error = T::Error;
+
+    fn push_audio(&mut self, audio: &[f32]) -> Result<Vec<TranscriptSegment>, Self::Error> {
+        let segments = self.backend.push_audio(audio)?;
+        Ok(self.process_segments(segments))
+    }
+
+    fn finish(&mut self) -> Result<Vec<TranscriptSegment>, Self::Error> {
+        let segments = self.backend.finish()?;
+        Ok(self.process_segments(segments))
+    }
+}
+
+/// Replace the text of every segment with the result of `f`, if it returns any.
+fn map_text(transcript: &mut Transcript, mut f: impl FnMut(&str) -> Option<String>) {
+    for i in 0..transcript.segments.len() {
+        if let Some(text) = f(&transcript.segments[i].text) {
+            // the index is in bounds
+            let _ = transcript.set_segment_text(i, text);
+        }
+    }
+}
+
+/// Byte ranges of the words of `text`: runs of letters, digits and apostrophes.
+fn word_spans(text: &str) -> Vec<(usize, usize)> {
+    let is_word = |c: char| c.is_alphanumeric() || c == '\'';
+    let mut spans = Vec::new();
+    let mut start = None;
+    for (i, c) in text.char_indices() {
+        match (start, is_word(c)) {
+            (None, true) => start = Some(i),
+            (Some(s), false) => {
+                spans.push((s, i));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    spans.extend(start.map(|s| (s, text.len())));
+    spans
+}
+
+/// Inverse text normalization for English numbers: writes spelled-out numbers as digits,
+/// e.g. "two hundred and forty-five" as "245".
+///
+/// Following common style guides, single numbers below ten stay spelled out,
+/// so "one of them" is left alone but "one hundred" becomes "100".
+/// Whisper already writes most numbers as digits; this catches the rest so output is consistent.
+#[derive(Debug, Clone, Default)]
+pub struct InverseTextNormalizer {}
+
+impl InverseTextNormalizer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Normalize the numbers in `text`.
+    pub fn normalize(&self, text: &str) -> String {
+        let spans = word_spans(text);
+        let mut out = String::with_capacity(text.len());
+        let mut copied = 0;
+        let mut i = 0;
+        while i < spans.len() {
+            match parse_number(text, &spans[i..]) {
+                Some((value, words)) => {
+                    out.push_str(&text[copied..spans[i].0]);
+                    out.push_str(&value.to_string());
+                    copied = spans[i + words - 1].1;
+                    i += words;
+                }
+                None => i += 1,
+            }
+        }
+        out.push_str(&text[copied..]);
+        out
+    }
+}
+
+impl TranscriptProcessor for InverseTextNormalizer {
+    fn process(&mut self, transcript: &mut Transcript) {
+        map_text(transcript, |text| {
+            let normalized = self.normalize(text);
+            (normalized != text).then_some(normalized)
+        });
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NumberWord {
+    Unit(u64),
+    Teen(u64),
+    Tens(u64),
+    Hundred,
+    Scale(u64),
+    And,
+}
+
+fn number_word(word: &str) -> Option<NumberWord> {
+    use NumberWord::*;
+    const UNITS: [&str; 10] = [
+        "zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine",
+    ];
+    const TEENS: [&str; 10] = [
+        "ten",
+        "eleven",
+        "twelve",
+        "thirteen",
+        "fourteen",
+        "fifteen",
+        "sixteen",
+        "seventeen",
+        "eighteen",
+        "nineteen",
+    ];
+    const TENS: [&str; 8] = [
+        "twenty", "thirty", "forty", "fifty", "sixty", "seventy", "eighty", "ninety",
+    ];
+    let word = word.to_lowercase();
+    let position = |list: &[&str]| list.iter().position(|w| *w == word).map(|i| i as u64);
+    if let Some(i) = position(&UNITS) {
+        return Some(Unit(i));
+    }
+    if let Some(i) = position(&TEENS) {
+        return Some(Teen(10 + i));
+    }
+    if let Some(i) = position(&TENS) {
+        return Some(Tens(20 + 10 * i));
+    }
+    match word.as_str() {
+        "hundred" => Some(Hundred),
+        "thousand" => Some(Scale(1_000)),
+        "million" => Some(Scale(1_000_000)),
+        "billion" => Some(Scale(1_000_000_000)),
+        "and" => Some(And),
+        _ => None,
+    }
+}
+
+/// Parse the longest number spelled out by the words at the start of `spans`,
+/// returning its value and how many words it took. Words must be separated by spaces or hyphens.
+fn parse_number(text: &str, spans: &[(usize, usize)]) -> Option<(u64, usize)> {
+    use NumberWord::*;
+    let (mut total, mut current) = (0u64, 0u64);
+    let mut last: Option<NumberWord> = None;
+    let mut last_scale = u64::MAX;
+    // value and word count of the longest valid number so far
+    let mut best = None;
+    for (n, &(start, end)) in spans.iter().enumerate() {
+        if n > 0 {
+            let gap = &text[spans[n - 1].1..start];
+            if !gap.chars().all(|c| c.is_whitespace() || c == '-') {
+                break;
+            }
+        }
+        let Some(word) = number_word(&text[start..end]) else {
+            break;
+        };
+        let allowed = match (last, word) {
+            (_, Unit(0)) => false,
+            (None, Unit(_) | Teen(_) | Tens(_)) => true,
+            (None, _) => false,
+            (Some(Unit(_) | Teen(_)), Hundred) => current < 100,
+            (Some(Tens(_)), Unit(_)) => true,
+            (Some(Unit(_) | Teen(_) | Tens(_) | Hundred), Scale(scale)) => scale < last_scale,
+            (Some(Hundred | Scale(_)), And) => true,
+            (Some(Hundred | Scale(_) | And), Unit(_) | Teen(_) | Tens(_)) => true,
+            _ => false,
+        };
+        if !allowed {
+            break;
+        }
+        match word {
+            Unit(v) | Teen(v) | Tens(v) => current += v,
+            Hundred => current *= 100,
+            Scale(scale) => {
+                total += current * scale;
+                current = 0;
+                last_scale = scale;
+            }
+            And => {}
+        }
+        last = Some(word);
+        if word != And {
+            best = Some((total + current, n + 1));
+        }
+    }
+    // single words below ten stay spelled out
+    best.filter(|&(value, words)| words > 1 || value >= 10)
+}
+
+/// Masks offensive words in the text, keeping their first letter, e.g. "d***".
+///
+/// Words are matched whole and ignoring case. No list is built in; which words to mask
+/// depends on the audience.
+#[derive(Debug, Clone, Default)]
+pub struct ProfanityFilter {
+    words: HashSet<String>,
+}
+
+impl ProfanityFilter {
+    pub fn new<I, S>(words: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        Self {
+            words: words
+                .into_iter()
+                .map(|w| w.as_ref().to_lowercase())
+                .collect(),
+        }
+    }
+
+    /// Mask the listed words in `text`.
+    pub fn filter(&self, text: &str) -> String {
+        let mut out = text.to_string();
+        for (start, end) in word_spans(text).into_iter().rev() {
+            if self.words.contains(&text[start..end].to_lowercase()) {
+                let mut chars = text[start..end].chars();
+                let masked: String = chars.next().into_iter().chain(chars.map(|_| '*')).collect();
+                out.replace_range(start..end, &masked);
+            }
+        }
+        out
+    }
+}
+
+impl TranscriptProcessor for ProfanityFilter {
+    fn process(&mut self, transcript: &mut Transcript) {
+        map_text(transcript, |text| {
+            let filtered = self.filter(text);
+            (filtered != text).then_some(filtered)
+        });
+    }
+}
+
+/// Tidies transcripts for display as captions: collapses runs of whitespace, drops segments
+/// without any text, and puts segments in order without overlaps, see [`Transcript::normalize`].
+///
+/// Segment text keeps the single leading space Whisper starts it with.
+#[derive(Debug, Clone, Default)]
+pub struct CaptionConditioner {}
+
+impl CaptionConditioner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl TranscriptProcessor for CaptionConditioner {
+    fn process(&mut self, transcript: &mut Transcript) {
+        transcript.segments.retain(|s| !s.text.trim().is_empty());
+        map_text(transcript, |text| {
+            let words: Vec<&str> = text.split_whitespace().collect();
+            let conditioned = format!(" {}", words.join(" "));
+            (conditioned != text).then_some(conditioned)
+        });
+        transcript.normalize();
+    }
+}
+
+/// Drops segments whose no-speech probability is above a threshold, see
+/// [`Transcript::retain_speech`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NoSpeechFilter {
+    threshold: f32,
+}
+
+impl NoSpeechFilter {
+    pub fn new(threshold: f32) -> Self {
+        Self { threshold }
+    }
+}
+
+/// The threshold whisper.cpp defaults to, see [`FullParams::set_no_speech_thold`].
+impl Default for NoSpeechFilter {
+    fn default() -> Self {
+        Self::new(0.6)
+    }
+}
+
+impl TranscriptProcessor for NoSpeechFilter {
+    fn process(&mut self, transcript: &mut Transcript) {
+        transcript.retain_speech(self.threshold);
+    }
+}
+
+/// What [`SilenceSuppressor`] does with a segment it believes was hallucinated over silence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SilenceAction {
+    /// Remove the segment.
+    #[default]
+    Drop,
+    /// Keep the segment and its timing, with empty text.
+    Blank,
+}
+
+/// Phrases Whisper is known to produce over silence and music, learned from the subtitles it was
+/// trained on, by language. Compared after [`normalize`]-ing each word.
+///
+/// Only whole phrases that rarely make up a segment of real speech on their own are listed;
+/// short ones such as "you" are too common to count as evidence.
+const HALLUCINATED_PHRASES: &[(Language, &[&str])] = &[
+    (
+        Language::English,
+        &[
+            "Thank you for watching.",
+            "Thanks for watching!",
+            "Please subscribe.",
+            "Subtitles by the Amara.org community",
+        ],
+    ),
+    (
+        Language::German,
+        &[
+            "Vielen Dank fürs Zuschauen.",
+            "Untertitel der Amara.org-Community",
+        ],
+    ),
+    (
+        Language::Spanish,
+        &[
+            "¡Gracias por ver!",
+            "Subtítulos realizados por la comunidad de Amara.org",
+        ],
+    ),
+    (
+        Language::French,
+        &[
+            "Merci d'avoir regardé.",
+            "Sous-titres réalisés par la communauté d'Amara.org",
+        ],
+    ),
+    (Language::Portuguese, &["Obrigado por assistir."]),
+    (Language::Japanese, &["ご視聴ありがとうございました"]),
+];
+
+/// The built-in phrases for `language`, see [`SilenceSuppressor::language`].
+fn hallucinated_phrases(language: Language) -> HashSet<String> {
+    HALLUCINATED_PHRASES
+        .iter()
+        .filter(|(l, _)| *l == language)
+        .flat_map(|(_, phrases)| phrases.iter())
+        .map(|p| normalize_phrase(p))
+        .collect()
+}
+
+/// Removes segments Whisper emitted over silence, combining the evidence available for each.
+///
+/// A segment is dropped if it barely overlaps speech found by a voice activity detector, when one
+/// was given. Otherwise it needs a no-speech probability of at least the threshold, as
+/// whisper.cpp's own rule for skipping windows does, see [`crate::FullParams::set_no_speech_thold`],
+/// and one more sign of silence:
+///
+/// * an average token log probability below the threshold
+/// * text that is one of the phrases Whisper is prone to hallucinate, such as "Thank you for
+///   watching", for the language set with [`Self::language`]
+/// * a duration below [`Self::min_duration`], if one was set
+///
+/// Segments without tokens and without a matching phrase or duration are kept, since there is
+/// nothing to judge them by beyond the no-speech probability.
+///
+/// # Examples
+/// ```
+/// # use whisper_rs::{SilenceAction, SilenceSuppressor, Transcript, TranscriptProcessor, TranscriptSegment};
+/// let mut suppressor = SilenceSuppressor::new()
+///     .speech_regions([(0, 300)])
+///     .action(SilenceAction::Blank);
+/// let mut transcript = Transcript::new(vec![
+///     TranscriptSegment::new(0, 300, " Hello there."),
+///     TranscriptSegment::new(900, 1100, " Thanks for watching!"),
+/// ]);
+/// suppressor.process(&mut transcript);
+/// assert_eq!(transcript.segments[1].text, "");
+/// ```
+#[derive(Debug, Clone)]
+pub struct SilenceSuppressor {
+    no_speech_threshold: f32,
+    logprob_threshold: f32,
+    min_duration: i64,
+    speech: Option<Vec<(i64, i64)>>,
+    min_speech_overlap: f32,
+    phrases: HashSet<String>,
+    action: SilenceAction,
+}
+
+impl Default for SilenceSuppressor {
+    fn default() -> Self {
+        Self {
+            no_speech_threshold: 0.6,
+            logprob_threshold: -1.0,
+            min_duration: 0,
+            speech: None,
+            min_speech_overlap: 0.2,
+            phrases: hallucinated_phrases(Language::English),
+            action: SilenceAction::Drop,
+        }
+    }
+}
+
+impl SilenceSuppressor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Treat segments with at least this no-speech probability as silence, if there is also
+    /// another sign of it, see [`SilenceSuppressor`].
+    ///
+    /// Defaults to 0.6.
+    pub fn no_speech_threshold(mut self, threshold: f32) -> Self {
+        self.no_speech_threshold = threshold;
+        self
+    }
+
+    /// Average token log probability below which a likely silent segment is dropped.
+    ///
+    /// Defaults to -1.0.
+    pub fn logprob_threshold(mut self, threshold: f32) -> Self {
+        self.logprob_threshold = threshold;
+        self
+    }
+
+    /// Count segments shorter than this many centiseconds as a sign of silence.
+    ///
+    /// Defaults to 0, which turns the check off.
+    pub fn min_duration(mut self, centiseconds: i64) -> Self {
+        self.min_duration = centiseconds;
+        self
+    }
+
+    /// Where speech was detected, as `(start, end)` pairs in centiseconds, e.g. from
+    /// [`crate::WhisperVadContext::segments_from_samples`]. Segments overlapping speech by less
+    /// than [`Self::min_speech_overlap`] are dropped.
+    pub fn speech_regions(mut self, regions: impl IntoIterator<Item = (i64, i64)>) -> Self {
+        self.speech = Some(regions.into_iter().collect());
+        self
+    }
+
+    /// Like [`Self::speech_regions`], taking the segments found by whisper.cpp's VAD.
+    pub fn vad_segments(
+        self,
+        segments: impl IntoIterator<Item = crate::WhisperVadSegment>,
+    ) -> Self {
+        self.speech_regions(
+            segments
+                .into_iter()
+                .map(|s| (s.start.floor() as i64, s.end.ceil() as i64)),
+        )
+    }
+
+    /// Fraction of a segment that must overlap the [`Self::speech_regions`].
+    ///
+    /// Defaults to 0.2.
+    pub fn min_speech_overlap(mut self, fraction: f32) -> Self {
+        self.min_speech_overlap = fraction;
+        self
+    }
+
+    /// Use the built-in phrases for `language`, replacing the current ones. Languages without
+    /// a built-in list get none.
+    ///
+    /// Defaults to [`Language::English`].
+    pub fn language(mut self, language: Language) -> Self {
+        self.phrases = hallucinated_phrases(language);
+        self
+    }
+
+    /// Replace the list of phrases treated as likely hallucinations.
+    pub fn phrases<I, S>(mut self, phrases: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.phrases = phrases
+            .into_iter()
+            .map(|p| normalize_phrase(p.as_ref()))
+            .collect();
+        self
+    }
+
+    /// What to do with segments over silence.
+    ///
+    /// Defaults to [`SilenceAction::Drop`].
+    pub fn action(mut self, action: SilenceAction) -> Self {
+        self.action = action;
+        self
+    }
+
+    /// Whether `segment` looks like it was hallucinated over silence.
+    pub fn is_hallucination(&self, segment: &TranscriptSegment) -> bool {
+        if segment.text.trim().is_empty() {
+            return false;
+        }
+        let duration = segment.end - segment.start;
+        if let Some(speech) = &self.speech {
+            let overlap: i64 = speech
+                .iter()
+                .map(|&(start, end)| (end.min(segment.end) - start.max(segment.start)).max(0))
+                .sum();
+            let fraction = overlap as f32 / duration.max(1) as f32;
+            if fraction < self.min_speech_overlap {
+                return true;
+            }
+        }
+
+        if segment.no_speech_probability < self.no_speech_threshold {
+            return false;
+        }
+        if duration < self.min_duration || self.phrases.contains(&normalize_phrase(&segment.text)) {
+            return true;
+        }
+        let text_tokens: Vec<_> = segment.tokens.iter().filter(|t| !t.special).collect();
+        if text_tokens.is_empty() {
+            return false;
+        }
+        let avg_logprob =
+            text_tokens.iter().map(|t| t.plog).sum::<f32>() / text_tokens.len() as f32;
+        avg_logprob < self.logprob_threshold
+    }
+}
+
+impl TranscriptProcessor for SilenceSuppressor {
+    fn process(&mut self, transcript: &mut Transcript) {
+        match self.action {
+            SilenceAction::Drop => transcript.segments.retain(|s| !self.is_hallucination(s)),
+            SilenceAction::Blank => {
+                for i in 0..transcript.segments.len() {
+                    if self.is_hallucination(&transcript.segments[i]) {
+                        let _ = transcript.set_segment_text(i, "");
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn normalize_phrase(text: &str) -> String {
+    let words: Vec<String> = text
+        .split(|c: char| c.is_whitespace() || c == '.')
+        .map(normalize)
+        .filter(|w| !w.is_empty())
+        .collect();
+    words.join(" ")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn numbers_are_written_as_digits() {
+        let itn = InverseTextNormalizer::new();
+        let cases = [
+            (" two hundred and forty-five people", " 245 people"),
+            (" one of them", " one of them"),
+            (" Twelve days and three nights", " 12 days and three nights"),
+            (" three thousand five hundred", " 3500"),
+            (" one two three", " one two three"),
+            (" twenty, thirty", " 20, 30"),
+            (" one hundred and", " 100 and"),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(itn.normalize(text), expected, "{}", text);
+        }
+    }
+
+    #[test]
+    fn stages_run_in_order() {
+        let mut pipeline = ProcessingPipeline::new()
+            .stage(ProfanityFilter::new(["Heck"]))
+            .stage(|t: &mut Transcript| {
+                let _ = t.set_segment_text(0, t.segments[0].text.to_uppercase());
+            });
+        let mut transcript =
+            Transcript::new(vec![TranscriptSegment::new(0, 100, " what the heck")]);
+        pipeline.process(&mut transcript);
+        assert_eq!(transcript.segments[0].text, " WHAT THE H***");
+    }
+
+    #[test]
+    fn silence_is_suppressed() {
+        let mut suppressor = SilenceSuppressor::new();
+        let mut speech = TranscriptSegment::new(0, 300, " Hello there.");
+        speech.no_speech_probability = 0.1;
+        let mut silence = TranscriptSegment::new(300, 900, " Thanks for watching!");
+        silence.no_speech_probability = 0.7;
+        let mut said = TranscriptSegment::new(900, 1200, " Thanks for watching!");
+        said.no_speech_probability = 0.4;
+        let mut short = TranscriptSegment::new(1200, 1230, " you");
+        short.no_speech_probability = 0.7;
+        let blip = TranscriptSegment::new(1230, 1235, " Hmm");
+
+        let mut transcript = Transcript::new(vec![speech, silence, said, short, blip]);
+        suppressor.process(&mut transcript);
+        let texts: Vec<_> = transcript.iter().map(|s| s.text.as_str()).collect();
+        assert_eq!(
+            texts,
+            [" Hello there.", " Thanks for watching!", " you", " Hmm"]
+        );
+
+        // short segments only count once asked to, and still need the no-speech probability
+        let suppressor = SilenceSuppressor::new().min_duration(50);
+        assert!(suppressor.is_hallucination(&transcript.segments[2]));
+        assert!(!suppressor.is_hallucination(&transcript.segments[3]));
+
+        let mut filtered = transcript.clone();
+        NoSpeechFilter::default().process(&mut filtered);
+        assert_eq!(filtered.len(), 3);
+
+        let suppressor = SilenceSuppressor::new().speech_regions([(0, 300)]);
+        assert!(suppressor.is_hallucination(&transcript.segments[1]));
+        assert!(!suppressor.is_hallucination(&transcript.segments[0]));
+    }
+
+    #[test]
+    fn phrases_follow_the_language() {
+        let mut segment = TranscriptSegment::new(0, 200, " Vielen Dank fürs Zuschauen.");
+        segment.no_speech_probability = 0.7;
+        assert!(!SilenceSuppressor::new().is_hallucination(&segment));
+        let german = SilenceSuppressor::new().language(Language::German);
+        assert!(german.is_hallucination(&segment));
+
+        segment.text = " Merci d'avoir regardé.".to_string();
+        assert!(!german.is_hallucination(&segment));
+        let french = SilenceSuppressor::new().language(Language::French);
+        assert!(french.is_hallucination(&segment));
+    }
+}
@@ -0,0 +1,392 @@
+use crate::{CpuTopology, FullParams, ThreadCounts};
+use std::ffi::c_int;
+use std::time::Duration;
+
+/// Battery charge, in percent, at or below which [`PowerState::LowBattery`] is reported.
+const LOW_BATTERY_PERCENT: u32 = 20;
+
+/// Where the machine is drawing power from, see [`PowerMonitor`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PowerState {
+    /// Plugged in, or a machine without a battery.
+    ExternalPower,
+    Battery,
+    /// On battery, with little charge left.
+    LowBattery,
+}
+
+impl PowerState {
+    /// Ask the operating system, see [`SystemPowerMonitor`].
+    ///
+    /// Reads `/sys/class/power_supply` on Linux and `pmset` on macOS.
+    /// Returns `None` elsewhere, or if the power source couldn't be determined.
+    pub fn detect() -> Option<Self> {
+        detect_power_state()
+    }
+}
+
+/// Reports the current [`PowerState`]. Implement it to react to the power events of a platform
+/// this crate can't query itself, e.g. from the battery APIs of a mobile OS.
+///
+/// Implemented for closures returning a [`PowerState`].
+pub trait PowerMonitor: Send {
+    fn power_state(&mut self) -> PowerState;
+}
+
+impl<F: FnMut() -> PowerState + Send> PowerMonitor for F {
+    fn power_state(&mut self) -> PowerState {
+        self()
+    }
+}
+
+/// A [`PowerMonitor`] asking the operating system, see [`PowerState::detect`].
+/// Assumes external power if it can't tell.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemPowerMonitor;
+
+impl PowerMonitor for SystemPowerMonitor {
+    fn power_state(&mut self) -> PowerState {
+        PowerState::detect().unwrap_or(PowerState::ExternalPower)
+    }
+}
+
+/// How hard to run transcription, see [`PowerProfile::for_state`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PowerProfile {
+    /// Threads to decode with, see [`FullParams::set_n_threads`].
+    pub threads: c_int,
+    /// Length of audio transcribed at once when streaming. Each run has a fixed cost of encoding
+    /// a full 30 second window, so longer chunks do less work per second of audio.
+    pub chunk_ms: u32,
+    /// Time to idle after each chunk, letting the CPU drop to its deepest sleep states
+    /// instead of running flat out until the stream is caught up.
+    pub cooldown: Duration,
+}
+
+impl PowerProfile {
+    /// Full speed, with the threads of [`ThreadCounts::for_topology`].
+    pub fn performance(topology: &CpuTopology) -> Self {
+        Self {
+            threads: ThreadCounts::for_topology(topology).encode,
+            chunk_ms: whisper_rs_sys::WHISPER_CHUNK_SIZE * 1000,
+            cooldown: Duration::ZERO,
+        }
+    }
+
+    /// Fewer threads and full 30 second chunks.
+    ///
+    /// On hybrid CPUs, as many threads as there are efficiency cores, so the OS can keep the work
+    /// on them; whisper.cpp offers no way to pin its threads to particular cores.
+    /// Elsewhere, half of the physical cores.
+    pub fn efficient(topology: &CpuTopology) -> Self {
+        let threads = topology
+            .efficiency_cores()
+            .unwrap_or(topology.physical / 2)
+            .clamp(1, 4);
+        Self {
+            threads: threads as c_int,
+            ..Self::performance(topology)
+        }
+    }
+
+    /// A single thread and a pause after every chunk, for when the battery is nearly empty.
+    /// Transcription will usually fall behind real time.
+    pub fn low_power(topology: &CpuTopology) -> Self {
+        Self {
+            threads: 1,
+            cooldown: Duration::from_secs(2),
+            ..Self::performance(topology)
+        }
+    }
+
+    /// The profile to use in `state`: [`Self::performance`] on external power,
+    /// [`Self::efficient`] on battery and [`Self::low_power`] when it runs low.
+    pub fn for_state(state: PowerState, topology: &CpuTopology) -> Self {
+        match state {
+            PowerState::ExternalPower => Self::performance(topology),
+            PowerState::Battery => Self::efficient(topology),
+            PowerState::LowBattery => Self::low_power(topology),
+        }
+    }
+
+    /// Set the thread count of `params`. Chunking and cooldown only apply to streaming,
+    /// see [`crate::PowerAwareTranscriber`].
+    pub fn apply(&self, params: &mut FullParams<'_, '_>) {
+        params.set_n_threads(self.threads);
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn detect_power_state() -> Option<PowerState> {
+    let mut battery = None;
+    for entry in std::fs::read_dir("/sys/class/power_supply").ok()?.flatten() {
+        let read = |name: &str| {
+            std::fs::read_to_string(entry.path().join(name))
+                .map(|s| s.trim().to_string())
+                .ok()
+        };
+        match read("type").as_deref() {
+            Some("Mains" | "USB") if read("online").as_deref() == Some("1") => {
+                return Some(PowerState::ExternalPower)
+            }
+            Some("Battery") => {
+                let capacity = read("capacity").and_then(|c| c.parse::<u32>().ok());
+                battery = Some(battery.unwrap_or(0).max(capacity.unwrap_or(100)));
+            }
+            _ => {}
+        }
+    }
+    Some(match battery {
+        None => PowerState::ExternalPower,
+        Some(percent) => battery_state(percent),
+    })
+}
+
+#[cfg(target_os = "macos")]
+fn detect_power_state() -> Option<PowerState> {
+    let out = std::process::Command::new("pmset")
+        .args(["-g", "batt"])
+        .output()
+        .ok()?;
+    parse_pmset(&String::from_utf8_lossy(&out.stdout))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn detect_power_state() -> Option<PowerState> {
+    None
+}
+
+fn battery_state(percent: u32) -> PowerState {
+    if percent <= LOW_BATTERY_PERCENT {
+        PowerState::LowBattery
+    } else {
+        PowerState::Battery
+    }
+}
+
+/// Parse the output of `pmset -g batt`, e.g.
+/// `Now drawing from 'Battery Power'` followed by a line with `85%;`.
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+fn parse_pmset(output: &str) -> Option<PowerState> {
+    if output.contains("'AC Power'") {
+        return Some(PowerState::ExternalPower);
+    }
+    if !output.contains("'Battery Power'") {
+        return None;
+    }
+    let percent = output
+        .split_whitespace()
+        .find_map(|word| word.strip_suffix("%;")?.parse().ok())
+        .unwrap_or(100);
+    Some(battery_state(percent))
+}
+
+#[cfg(feature = "streaming")]
+pub use transcriber::PowerAwareTranscriber;
+
+#[cfg(feature = "streaming")]
+mod transcriber {
+    use super::*;
+    use crate::{StreamingTranscribe, StreamingTranscriber, Transcribe, TranscriptSegment};
+    use std::time::Instant;
+
+    type ChangeHook = Box<dyn FnMut(PowerState, &PowerProfile) + Send>;
+
+    /// A [`StreamingTranscriber`] that adapts to the power source, for applications that
+    /// transcribe all day on laptops and mobile devices.
+    ///
+    /// The [`PowerMonitor`] is asked for the power state at most once per check interval,
+    /// and the [`PowerProfile`] for it decides the thread count, the chunk length and the
+    /// cooldown after each chunk. The cooldown blocks the caller of [`Self::push_audio`].
+    pub struct PowerAwareTranscriber<'a, 'b, T: Transcribe> {
+        stream: StreamingTranscriber<'a, 'b, T>,
+        monitor: Box<dyn PowerMonitor>,
+        profiles: [PowerProfile; 3],
+        check_interval: Duration,
+        last_check: Option<Instant>,
+        state: Option<PowerState>,
+        on_change: Option<ChangeHook>,
+    }
+
+    impl<'a, 'b, T: Transcribe> PowerAwareTranscriber<'a, 'b, T> {
+        /// Create a transcriber using [`SystemPowerMonitor`] and the profiles of
+        /// [`PowerProfile::for_state`] for this machine.
+        pub fn new(backend: T, params: FullParams<'a, 'b>) -> Self {
+            let topology = CpuTopology::detect();
+            let profile = |state| PowerProfile::for_state(state, &topology);
+            Self {
+                stream: StreamingTranscriber::new(backend, params),
+                monitor: Box::new(SystemPowerMonitor),
+                profiles: [
+                    profile(PowerState::ExternalPower),
+                    profile(PowerState::Battery),
+                    profile(PowerState::LowBattery),
+                ],
+                check_interval: Duration::from_secs(30),
+                last_check: None,
+                state: None,
+                on_change: None,
+            }
+        }
+
+        /// Get the power state from `monitor` instead of the operating system.
+        pub fn with_monitor(mut self, monitor: impl PowerMonitor + 'static) -> Self {
+            self.monitor = Box::new(monitor);
+            self.last_check = None;
+            self
+        }
+
+        /// Use `profile` in `state`.
+        pub fn with_profile(mut self, state: PowerState, profile: PowerProfile) -> Self {
+            self.profiles[state as usize] = profile;
+            self.last_check = None;
+            self
+        }
+
+        /// Set how often the power state is checked.
+        ///
+        /// Defaults to 30 seconds.
+        pub fn with_check_interval(mut self, interval: Duration) -> Self {
+            self.check_interval = interval;
+            self
+        }
+
+        /// Call `f` whenever the power state changes, including when it is first checked,
+        /// e.g. to tell the user transcription is slowing down.
+        pub fn on_power_change(
+            mut self,
+            f: impl FnMut(PowerState, &PowerProfile) + Send + 'static,
+        ) -> Self {
+            self.on_change = Some(Box::new(f));
+            self
+        }
+
+        /// The power state last reported by the monitor, or `None` before any audio was pushed.
+        pub fn power_state(&self) -> Option<PowerState> {
+            self.state
+        }
+
+        /// The underlying streaming transcriber.
+        pub fn stream_mut(&mut self) -> &mut StreamingTranscriber<'a, 'b, T> {
+            &mut self.stream
+        }
+
+        fn check_power(&mut self) {
+            let due = self
+                .last_check
+                .is_none_or(|at| at.elapsed() >= self.check_interval);
+            if !due {
+                return;
+            }
+            self.last_check = Some(Instant::now());
+            let state = self.monitor.power_state();
+            let profile = self.profiles[state as usize];
+            profile.apply(self.stream.params_mut());
+            self.stream.set_chunk_ms(profile.chunk_ms);
+            if self.state != Some(state) {
+                self.state = Some(state);
+                if let Some(on_change) = &mut self.on_change {
+                    on_change(state, &profile);
+                }
+            }
+        }
+
+        fn cooldown(&self) -> Duration {
+            self.state.map_or(Duration::ZERO, |state| {
+                self.profiles[state as usize].cooldown
+            })
+        }
+    }
+
+    impl<T: Transcribe> StreamingTranscribe for PowerAwareTranscriber<'_, '_, T> {
+        type Error = T::Error;
+
+        fn push_audio(&mut self, audio: &[f32]) -> Result<Vec<TranscriptSegment>, Self::Error> {
+            let mut out = Vec::new();
+            let mut rest = audio;
+            loop {
+                self.check_power();
+                // feed at most one chunk at a time, so there is a cooldown after each;
+                // the stream decides the chunk length, which may differ from the profile's
+                let space = self
+                    .stream
+                    .max_chunk_samples()
+                    .saturating_sub(self.stream.buffered_samples())
+                    .max(1);
+                let (piece, remaining) = rest.split_at(space.min(rest.len()));
+                rest = remaining;
+
+                let position = self.stream.position();
+                out.extend(self.stream.push_audio(piece)?);
+                if self.stream.position() != position && !self.cooldown().is_zero() {
+                    std::thread::sleep(self.cooldown());
+                }
+                if rest.is_empty() {
+                    return Ok(out);
+                }
+            }
+        }
+
+        fn finish(&mut self) -> Result<Vec<TranscriptSegment>, Self::Error> {
+            self.stream.finish()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn profiles_follow_the_power_source() {
+        let hybrid = CpuTopology {
+            logical: 20,
+            physical: 14,
+            performance: Some(6),
+        };
+        assert_eq!(
+            PowerProfile::for_state(PowerState::ExternalPower, &hybrid).threads,
+            6
+        );
+        assert_eq!(
+            PowerProfile::for_state(PowerState::Battery, &hybrid).threads,
+            4
+        );
+        let low = PowerProfile::for_state(PowerState::LowBattery, &hybrid);
+        assert_eq!((low.threads, low.cooldown), (1, Duration::from_secs(2)));
+
+        assert_eq!(
+            parse_pmset("Now drawing from 'Battery Power'\n -InternalBattery-0\t15%; discharging;"),
+            Some(PowerState::LowBattery)
+        );
+        assert_eq!(
+            parse_pmset("Now drawing from 'AC Power'"),
+            Some(PowerState::ExternalPower)
+        );
+    }
+
+    #[cfg(all(feature = "streaming", feature = "test-stub"))]
+    #[test]
+    fn short_chunks_make_progress() {
+        use crate::stub::StubContext;
+        use crate::{SamplingStrategy, StreamingTranscribe};
+
+        let ctx = StubContext::with_text(0, 100, " hello");
+        let params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
+        // the stream raises chunks under a second to one second
+        let profile = PowerProfile {
+            threads: 1,
+            chunk_ms: 500,
+            cooldown: Duration::ZERO,
+        };
+        let mut stream = PowerAwareTranscriber::new(ctx.create_state().unwrap(), params)
+            .with_monitor(|| PowerState::Battery)
+            .with_profile(PowerState::Battery, profile);
+
+        let segments = stream.push_audio(&[0.0; 40000]).unwrap();
+        assert_eq!(segments.len(), 2);
+        assert_eq!((segments[0].start, segments[1].start), (0, 100));
+        assert_eq!(stream.finish().unwrap().len(), 1);
+        assert_eq!(ctx.runs(), 3);
+    }
+}
@@ -0,0 +1,185 @@
+use crate::{FullParams, WhisperContext, WhisperVadParams};
+
+/// Recommended settings for distil-whisper models, see [`WhisperContext::distil_preset`].
+///
+/// Distilled models keep the full encoder but only a couple of decoder layers. whisper.cpp forces
+/// `no_timestamps` on most of them, which turns every 30 second window into a single segment,
+/// and conditioning on previous text makes them prone to repetition loops.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DistilPreset {
+    /// Length of audio to transcribe at once, e.g. with [`crate::StreamingTranscriber::set_chunk_ms`].
+    /// Shorter than Whisper's 30 second window, so segments stay a reasonable length.
+    pub chunk_ms: u32,
+    /// Whether the model predicts usable timestamps.
+    pub timestamps: bool,
+}
+
+impl DistilPreset {
+    /// Chunk length recommended for models without timestamp prediction.
+    pub const CHUNK_MS: u32 = 15_000;
+
+    /// Apply the decoding settings of this preset to `params`.
+    pub fn apply(&self, params: &mut FullParams) {
+        params.set_no_context(true);
+        params.set_no_timestamps(!self.timestamps);
+        params.set_single_segment(false);
+    }
+}
+
+/// Settings for narrowband telephone calls, e.g. decoded with [`crate::G711`].
+///
+/// Phone audio is mono, band-limited to 300-3400 Hz and often has line noise and hold music,
+/// which lowers the VAD's speech probabilities, while callers pause mid-sentence more than in
+/// other recordings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TelephonyPreset;
+
+impl TelephonyPreset {
+    /// VAD settings tuned for narrowband audio: a lower speech threshold, more padding, and longer
+    /// silences required to split speech.
+    pub fn vad_params(&self) -> WhisperVadParams {
+        let mut vad = WhisperVadParams::new();
+        vad.set_threshold(0.35);
+        vad.set_min_speech_duration(150);
+        vad.set_min_silence_duration(500);
+        vad.set_speech_pad(200);
+        vad.set_max_speech_duration(30.0);
+        vad
+    }
+
+    /// Apply this preset to `params`.
+    ///
+    /// This sets the VAD parameters but does not enable VAD, since that needs a VAD model,
+    /// see [`FullParams::set_vad_model_path`].
+    pub fn apply(&self, params: &mut FullParams) {
+        params.set_vad_params(self.vad_params());
+        // hold music and ringing otherwise come out as "[Music]" and similar
+        params.set_suppress_nst(true);
+        params.set_single_segment(false);
+    }
+}
+
+/// Restricts decoding to the tokens needed for a constrained answer, where open-ended decoding
+/// tends to write numbers out as words or to merge spelt letters into words.
+///
+/// [`Self::apply`] installs a logits filter that bans every text token outside the preset's
+/// character set, so the decoder can only produce what the preset allows. Timestamp and other
+/// special tokens are unaffected. Use it for answers known to have this form, e.g. after asking
+/// a caller for their account number, not for general speech.
+///
+/// # Examples
+/// ```no_run
+/// # use whisper_rs::{FullParams, SamplingStrategy, VocabularyPreset, WhisperContext, WhisperContextParameters};
+/// # let ctx = WhisperContext::new_with_params("model.bin", WhisperContextParameters::default()).unwrap();
+/// let mut params = FullParams::new(SamplingStrategy::default());
+/// VocabularyPreset::Digits.apply(&ctx, &mut params);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VocabularyPreset {
+    /// Digit strings such as phone numbers, PINs and codes: digits, with spaces, `-`, `+`, `(`
+    /// and `)` as separators.
+    Digits,
+    /// Letter-by-letter spelling, such as names or reference codes: single letters and digits,
+    /// with spaces, `-`, `,` and `.` as separators.
+    Spelling,
+}
+
+impl VocabularyPreset {
+    /// Apply this preset to `params`.
+    ///
+    /// This also disables conditioning on previous text, which would pull the decoder back
+    /// towards ordinary sentences.
+    pub fn apply(&self, ctx: &WhisperContext, params: &mut FullParams) {
+        let eot = ctx.token_eot();
+        let allowed: Vec<bool> = (0..eot)
+            .map(|id| {
+                ctx.token_to_str_lossy(id)
+                    .is_ok_and(|text| self.allows(&text))
+            })
+            .collect();
+        params.set_no_context(true);
+        params.add_logits_filter(move |_, logits| {
+            for (logit, &allowed) in logits.iter_mut().zip(&allowed) {
+                if !allowed {
+                    *logit = f32::NEG_INFINITY;
+                }
+            }
+        });
+    }
+
+    /// Whether the decoder may produce a token with this text.
+    /// Tokens of nothing but whitespace are never allowed, as they would let the decoder pad its
+    /// output with blanks instead of the characters the preset is for.
+    fn allows(&self, text: &str) -> bool {
+        if text.trim().is_empty() {
+            return false;
+        }
+        let text = text.trim_start_matches(' ');
+        match self {
+            Self::Digits => text
+                .chars()
+                .all(|c| c.is_ascii_digit() || " -+()".contains(c)),
+            Self::Spelling => {
+                let mut chars = text.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) if c.is_alphabetic() => true,
+                    _ => text
+                        .chars()
+                        .all(|c| c.is_ascii_digit() || " -,.".contains(c)),
+                }
+            }
+        }
+    }
+}
+
+impl WhisperContext {
+    /// Whether this looks like a distil-whisper model: a full encoder with a much shallower decoder.
+    ///
+    /// large-v3-turbo also has a pruned decoder, but was fine-tuned rather than distilled,
+    /// and is not counted.
+    pub fn is_distil(&self) -> bool {
+        let n_audio_layer = self.model_n_audio_layer();
+        let n_text_layer = self.model_n_text_layer();
+        let is_turbo = n_audio_layer == 32 && n_text_layer == 4;
+        n_text_layer < n_audio_layer && !is_turbo
+    }
+
+    /// Recommended settings if this is a distil-whisper model, see [`Self::is_distil`].
+    pub fn distil_preset(&self) -> Option<DistilPreset> {
+        if !self.is_distil() {
+            return None;
+        }
+        // mirrors the check whisper.cpp uses to force `no_timestamps`;
+        // distil-large-v3 (with the large-v3 vocabulary) was trained with timestamps
+        let timestamps = !(self.model_n_text_layer() == 2 && self.model_n_vocab() != 51866);
+        Some(DistilPreset {
+            chunk_ms: if timestamps {
+                whisper_rs_sys::WHISPER_CHUNK_SIZE * 1000
+            } else {
+                DistilPreset::CHUNK_MS
+            },
+            timestamps,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn vocabulary_presets_allow_their_characters() {
+        for text in ["555", " 0", "-", " (", "+"] {
+            assert!(VocabularyPreset::Digits.allows(text), "{:?}", text);
+        }
+        for text in [" five", "5a", " .", "\u{fffd}", "", " ", "  ", "\n"] {
+            assert!(!VocabularyPreset::Digits.allows(text), "{:?}", text);
+        }
+        for text in [" B", "é", " 42", ","] {
+            assert!(VocabularyPreset::Spelling.allows(text), "{:?}", text);
+        }
+        for text in [" Bee", "AB", " the", "", " ", "\t"] {
+            assert!(!VocabularyPreset::Spelling.allows(text), "{:?}", text);
+        }
+    }
+}
@@ -0,0 +1,174 @@
+use crate::{FullParams, WhisperContext, WhisperError};
+
+/// Builds an initial prompt (see [`FullParams::set_initial_prompt`]) from structured context.
+///
+/// whisper.cpp only uses the last `n_text_ctx / 2` tokens of the prompt and silently drops the
+/// rest from the front, which would cut off speaker names and glossary terms first.
+/// This builder instead keeps those and shortens the previous transcript to fit,
+/// dropping its oldest words first.
+///
+/// # Examples
+/// ```no_run
+/// # use whisper_rs::{PromptBuilder, WhisperContext, WhisperContextParameters};
+/// # let ctx = WhisperContext::new_with_params("model.bin", WhisperContextParameters::default()).unwrap();
+/// let prompt = PromptBuilder::new()
+///     .speakers(["Alice", "Bob"])
+///     .glossary(["Kubernetes", "gRPC"])
+///     .previous_text("so we moved the service over last week")
+///     .build(&ctx)
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PromptBuilder {
+    speakers: Vec<String>,
+    glossary: Vec<String>,
+    previous_text: String,
+    max_tokens: Option<usize>,
+}
+
+impl PromptBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add names of people speaking in the audio, so they are spelled consistently.
+    pub fn speakers<S: Into<String>>(mut self, speakers: impl IntoIterator<Item = S>) -> Self {
+        self.speakers.extend(speakers.into_iter().map(Into::into));
+        self
+    }
+
+    /// Add domain terms, product names or acronyms the model should prefer.
+    pub fn glossary<S: Into<String>>(mut self, terms: impl IntoIterator<Item = S>) -> Self {
+        self.glossary.extend(terms.into_iter().map(Into::into));
+        self
+    }
+
+    /// Set the transcript of the audio preceding this chunk. Only its tail is kept if space runs out.
+    pub fn previous_text(mut self, text: impl Into<String>) -> Self {
+        self.previous_text = text.into();
+        self
+    }
+
+    /// Limit the prompt to this many tokens.
+    ///
+    /// Defaults to [`WhisperContext::max_prompt_tokens`] of the model passed to [`Self::build`].
+    pub fn max_tokens(mut self, max_tokens: usize) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+
+    /// Build the prompt, counting tokens with the vocabulary of `ctx`.
+    ///
+    /// # Returns
+    /// `Err(WhisperError::NullByteInString)` if any input contains a nul byte.
+    pub fn build(&self, ctx: &WhisperContext) -> Result<String, WhisperError> {
+        let max_tokens = self.max_tokens.unwrap_or(ctx.max_prompt_tokens());
+        self.build_with(max_tokens, |text| ctx.count_tokens(text))
+    }
+
+    /// Build the prompt and set it as the initial prompt of `params`.
+    pub fn apply(&self, ctx: &WhisperContext, params: &mut FullParams) -> Result<(), WhisperError> {
+        let prompt = self.build(ctx)?;
+        params.set_initial_prompt(&prompt);
+        Ok(())
+    }
+
+    /// Build the prompt with a custom token counter, e.g. for a tokenizer outside whisper.cpp.
+    ///
+    /// Glossary terms, then speaker names, are dropped from the end if even they don't fit
+    /// in `max_tokens` on their own.
+    pub fn build_with<E>(
+        &self,
+        max_tokens: usize,
+        mut count_tokens: impl FnMut(&str) -> Result<usize, E>,
+    ) -> Result<String, E> {
+        let mut speakers = self.speakers.len();
+        let mut terms = self.glossary.len();
+        let header = loop {
+            let header = Self::header(&self.speakers[..speakers], &self.glossary[..terms]);
+            if header.is_empty() || count_tokens(&header)? <= max_tokens {
+                break header;
+            }
+            if terms > 0 {
+                terms -= 1;
+            } else {
+                speakers -= 1;
+            }
+        };
+
+        let words: Vec<&str> = self.previous_text.split_whitespace().collect();
+        let join = |n: usize| {
+            let tail = words[words.len() - n..].join(" ");
+            match (header.is_empty(), tail.is_empty()) {
+                (_, true) => header.clone(),
+                (true, false) => tail,
+                (false, false) => format!("{} {}", header, tail),
+            }
+        };
+
+        // binary search for the longest tail that still fits
+        let (mut lo, mut hi) = (0, words.len());
+        while lo < hi {
+            let mid = (lo + hi).div_ceil(2);
+            if count_tokens(&join(mid))? <= max_tokens {
+                lo = mid;
+            } else {
+                hi = mid - 1;
+            }
+        }
+        Ok(join(lo))
+    }
+
+    fn header(speakers: &[String], glossary: &[String]) -> String {
+        let mut parts = Vec::new();
+        if !speakers.is_empty() {
+            parts.push(format!("Speakers: {}.", speakers.join(", ")));
+        }
+        if !glossary.is_empty() {
+            parts.push(format!("Glossary: {}.", glossary.join(", ")));
+        }
+        parts.join(" ")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::convert::Infallible;
+
+    fn words(text: &str) -> Result<usize, Infallible> {
+        Ok(text.split_whitespace().count())
+    }
+
+    #[test]
+    fn previous_text_is_truncated_from_the_front() {
+        let builder = PromptBuilder::new()
+            .speakers(["Alice"])
+            .glossary(["gRPC"])
+            .previous_text("one two three four five");
+
+        assert_eq!(
+            builder.build_with(100, words).unwrap(),
+            "Speakers: Alice. Glossary: gRPC. one two three four five"
+        );
+        assert_eq!(
+            builder.build_with(6, words).unwrap(),
+            "Speakers: Alice. Glossary: gRPC. four five"
+        );
+    }
+
+    #[test]
+    fn glossary_is_dropped_before_speakers() {
+        let builder = PromptBuilder::new()
+            .speakers(["Alice", "Bob"])
+            .glossary(["gRPC", "Kubernetes"])
+            .previous_text("hello");
+
+        assert_eq!(
+            builder.build_with(5, words).unwrap(),
+            "Speakers: Alice, Bob. Glossary: gRPC."
+        );
+        assert_eq!(builder.build_with(0, words).unwrap(), "");
+        assert_eq!(PromptBuilder::new().build_with(10, words).unwrap(), "");
+    }
+}
@@ -0,0 +1,381 @@
+//! Opt-in recording of transcription inputs, for reproducing bugs.
+//!
+//! [`RecordingTranscriber`] writes the exact audio and parameters of every call to a bundle
+//! directory before handing them to the model, and keeps the bundle if the call fails.
+//! Since the bundle is written first, it survives crashes inside whisper.cpp as well.
+//! [`SessionBundle::load`] reads it back to replay the call.
+
+use crate::common_logging::generic_warn;
+use crate::{FullParams, Language, SamplingStrategy, Transcribe, Transcript};
+use std::ffi::{c_int, CStr};
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Raw 32 bit little-endian float samples, exactly as passed to the model.
+const AUDIO_FILE: &str = "audio.f32";
+/// One `name = value` line per parameter.
+const PARAMS_FILE: &str = "params.txt";
+/// The error the call failed with. Missing if the process crashed during the call.
+const ERROR_FILE: &str = "error.txt";
+const PARAMS_HEADER: &str = "# whisper-rs session 1";
+
+/// Wraps a [`Transcribe`] backend, recording the inputs of every call to a [`SessionBundle`]
+/// in a directory.
+///
+/// Bundles of successful calls are deleted again unless [`Self::keep_successful`] is set.
+/// Recording failures are logged and never fail the transcription itself.
+pub struct RecordingTranscriber<T> {
+    backend: T,
+    dir: PathBuf,
+    keep_successful: bool,
+}
+
+impl<T: Transcribe> RecordingTranscriber<T> {
+    /// Wrap `backend`, writing bundles into subdirectories of `dir`.
+    pub fn new(backend: T, dir: impl Into<PathBuf>) -> Self {
+        Self {
+            backend,
+            dir: dir.into(),
+            keep_successful: false,
+        }
+    }
+
+    /// Keep the bundles of calls that succeeded too, e.g. to reproduce a wrong transcript.
+    ///
+    /// Defaults to false.
+    pub fn keep_successful(mut self, keep: bool) -> Self {
+        self.keep_successful = keep;
+        self
+    }
+
+    /// The directory bundles are written to.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Consume the wrapper, returning the wrapped backend.
+    pub fn into_inner(self) -> T {
+        self.backend
+    }
+}
+
+impl<T: Transcribe> Transcribe for RecordingTranscriber<T> {
+    type Error = T::Error;
+
+    fn transcribe(
+        &mut self,
+        params: FullParams<'_, '_>,
+        audio: &[f32],
+    ) -> Result<Transcript, Self::Error> {
+        let bundle = match SessionBundle::write(&self.dir, &params, audio) {
+            Ok(bundle) => Some(bundle),
+            Err(e) => {
+                generic_warn!("failed to record session: {}", e);
+                None
+            }
+        };
+        let result = self.backend.transcribe(params, audio);
+        if let Some(bundle) = bundle {
+            let recorded = match &result {
+                Ok(_) if self.keep_successful => Ok(()),
+                Ok(_) => fs::remove_dir_all(&bundle),
+                Err(e) => fs::write(bundle.join(ERROR_FILE), e.to_string()),
+            };
+            if let Err(e) = recorded {
+                generic_warn!("failed to record session: {}", e);
+            }
+        }
+        result
+    }
+}
+
+/// The recorded inputs of a single call, see [`RecordingTranscriber`].
+///
+/// A bundle is a directory holding `audio.f32`, the raw samples, `params.txt`, the parameters
+/// as `name = value` lines, and `error.txt` if the call returned an error.
+#[derive(Debug, Clone)]
+pub struct SessionBundle {
+    /// The audio, bit for bit.
+    pub audio: Vec<f32>,
+    /// The parameters, in the order they were written.
+    pub params: Vec<(String, String)>,
+    /// The error the call failed with, if it returned one.
+    pub error: Option<String>,
+}
+
+impl SessionBundle {
+    /// Read the bundle in `dir`.
+    pub fn load(dir: impl AsRef<Path>) -> io::Result<Self> {
+        let dir = dir.as_ref();
+        let audio = fs::read(dir.join(AUDIO_FILE))?
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect();
+        let params = fs::read_to_string(dir.join(PARAMS_FILE))?
+            .lines()
+            .filter(|line| !line.starts_with('#'))
+            .filter_map(|line| line.split_once(" = "))
+            .map(|(name, value)| (name.to_string(), unescape(value)))
+            .collect();
+        let error = match fs::read_to_string(dir.join(ERROR_FILE)) {
+            Ok(error) => Some(error),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e),
+        };
+        Ok(Self {
+            audio,
+            params,
+            error,
+        })
+    }
+
+    /// Parameters with the recorded values.
+    ///
+    /// Callbacks, grammars and logit filters can't be recorded and are left unset.
+    pub fn full_params(&self) -> io::Result<FullParams<'static, 'static>> {
+        let value = |name: &str| {
+            self.params
+                .iter()
+                .find(|(n, _)| n == name)
+                .map(|(_, v)| v.as_str())
+        };
+        let invalid = |name: &str| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid value for {} in session bundle", name),
+            )
+        };
+
+        let strategy = match value("strategy") {
+            Some("beam_search") => SamplingStrategy::BeamSearch {
+                beam_size: 1,
+                patience: -1.0,
+            },
+            _ => SamplingStrategy::default(),
+        };
+        let mut params = FullParams::new(strategy);
+        let fp = &mut params.fp;
+        for (name, field) in int_fields(fp) {
+            if let Some(v) = value(name) {
+                *field = v.parse().map_err(|_| invalid(name))?;
+            }
+        }
+        for (name, field) in float_fields(fp) {
+            if let Some(v) = value(name) {
+                *field = f32::from_bits(u32::from_str_radix(v, 16).map_err(|_| invalid(name))?);
+            }
+        }
+        for (name, field) in flag_fields(fp) {
+            if let Some(v) = value(name) {
+                *field = v.parse().map_err(|_| invalid(name))?;
+            }
+        }
+
+        if let Some(language) = value("language") {
+            params.set_language(
+                language
+                    .parse::<Language>()
+                    .map_err(|_| invalid("language"))?,
+            );
+        }
+        if let Some(prompt) = value("initial_prompt") {
+            params.set_initial_prompt(prompt);
+        }
+        if let Some(regex) = value("suppress_regex") {
+            params.set_suppress_regex(regex);
+        }
+        if let Some(path) = value("vad_model_path") {
+            let vad = params.fp.vad;
+            params.set_vad_model_path(Some(path));
+            params.fp.vad = vad;
+        }
+        Ok(params)
+    }
+
+    /// Write a bundle for `params` and `audio` into a new subdirectory of `dir`.
+    ///
+    /// # Returns
+    /// The path of the bundle.
+    fn write(dir: &Path, params: &FullParams, audio: &[f32]) -> io::Result<PathBuf> {
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis());
+        let bundle = dir.join(format!(
+            "session-{}-{}-{}",
+            now,
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::create_dir_all(&bundle)?;
+
+        let bytes: Vec<u8> = audio.iter().flat_map(|s| s.to_le_bytes()).collect();
+        fs::write(bundle.join(AUDIO_FILE), bytes)?;
+        fs::write(bundle.join(PARAMS_FILE), params_text(params))?;
+        Ok(bundle)
+    }
+}
+
+fn params_text(params: &FullParams) -> String {
+    let mut fp = params.fp;
+    let mut out = format!("{}\n", PARAMS_HEADER);
+    let strategy = match params.sampling_strategy() {
+        SamplingStrategy::BeamSearch { .. } => "beam_search",
+        SamplingStrategy::Greedy { .. } => "greedy",
+    };
+    let _ = writeln!(out, "strategy = {}", strategy);
+    for (name, field) in int_fields(&mut fp) {
+        let _ = writeln!(out, "{} = {}", name, field);
+    }
+    // as bits, so the values round-trip exactly
+    for (name, field) in float_fields(&mut fp) {
+        let _ = writeln!(out, "{} = {:08x}", name, field.to_bits());
+    }
+    for (name, field) in flag_fields(&mut fp) {
+        let _ = writeln!(out, "{} = {}", name, field);
+    }
+    for (name, ptr) in [
+        ("language", fp.language),
+        ("initial_prompt", fp.initial_prompt),
+        ("suppress_regex", fp.suppress_regex),
+        ("vad_model_path", fp.vad_model_path),
+    ] {
+        if !ptr.is_null() {
+            // SAFETY: non-null strings in the params are always valid CStrings owned by or borrowed into them
+            let value = unsafe { CStr::from_ptr(ptr) }.to_string_lossy();
+            let _ = writeln!(out, "{} = {}", name, escape(&value));
+        }
+    }
+    out
+}
+
+fn int_fields(fp: &mut whisper_rs_sys::whisper_full_params) -> [(&'static str, &mut c_int); 12] {
+    [
+        ("n_threads", &mut fp.n_threads),
+        ("n_max_text_ctx", &mut fp.n_max_text_ctx),
+        ("offset_ms", &mut fp.offset_ms),
+        ("duration_ms", &mut fp.duration_ms),
+        ("max_len", &mut fp.max_len),
+        ("max_tokens", &mut fp.max_tokens),
+        ("audio_ctx", &mut fp.audio_ctx),
+        ("greedy.best_of", &mut fp.greedy.best_of),
+        ("beam_search.beam_size", &mut fp.beam_search.beam_size),
+        (
+            "vad.min_speech_duration_ms",
+            &mut fp.vad_params.min_speech_duration_ms,
+        ),
+        (
+            "vad.min_silence_duration_ms",
+            &mut fp.vad_params.min_silence_duration_ms,
+        ),
+        ("vad.speech_pad_ms", &mut fp.vad_params.speech_pad_ms),
+    ]
+}
+
+fn float_fields(fp: &mut whisper_rs_sys::whisper_full_params) -> [(&'static str, &mut f32); 14] {
+    [
+        ("thold_pt", &mut fp.thold_pt),
+        ("thold_ptsum", &mut fp.thold_ptsum),
+        ("temperature", &mut fp.temperature),
+        ("max_initial_ts", &mut fp.max_initial_ts),
+        ("length_penalty", &mut fp.length_penalty),
+        ("temperature_inc", &mut fp.temperature_inc),
+        ("entropy_thold", &mut fp.entropy_thold),
+        ("logprob_thold", &mut fp.logprob_thold),
+        ("no_speech_thold", &mut fp.no_speech_thold),
+        ("beam_search.patience", &mut fp.beam_search.patience),
+        ("grammar_penalty", &mut fp.grammar_penalty),
+        ("vad.threshold", &mut fp.vad_params.threshold),
+        (
+            "vad.max_speech_duration_s",
+            &mut fp.vad_params.max_speech_duration_s,
+        ),
+        ("vad.samples_overlap", &mut fp.vad_params.samples_overlap),
+    ]
+}
+
+fn flag_fields(fp: &mut whisper_rs_sys::whisper_full_params) -> [(&'static str, &mut bool); 12] {
+    [
+        ("translate", &mut fp.translate),
+        ("no_context", &mut fp.no_context),
+        ("no_timestamps", &mut fp.no_timestamps),
+        ("single_segment", &mut fp.single_segment),
+        ("token_timestamps", &mut fp.token_timestamps),
+        ("split_on_word", &mut fp.split_on_word),
+        ("tdrz_enable", &mut fp.tdrz_enable),
+        ("detect_language", &mut fp.detect_language),
+        ("suppress_blank", &mut fp.suppress_blank),
+        ("suppress_nst", &mut fp.suppress_nst),
+        ("print_special", &mut fp.print_special),
+        ("vad", &mut fp.vad),
+    ]
+}
+
+/// Keep values on one line.
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+fn unescape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+#[cfg(all(test, feature = "test-stub"))]
+mod test {
+    use super::*;
+    use crate::stub::StubContext;
+    use crate::WhisperError;
+
+    #[test]
+    fn failed_calls_leave_a_replayable_bundle() {
+        let dir = std::env::temp_dir().join(format!("whisper-rs-sessions-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let mut recorder =
+            RecordingTranscriber::new(StubContext::with_text(0, 100, " hello"), &dir);
+
+        let audio: Vec<f32> = (0..20_000).map(|i| (i as f32 * 0.01).sin()).collect();
+        let mut params = FullParams::new(SamplingStrategy::default());
+        params.set_initial_prompt("line one\nline two");
+        params.set_temperature(0.3);
+        recorder.transcribe(params.clone(), &audio).unwrap();
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+
+        params.set_n_threads(0);
+        assert!(matches!(
+            recorder.transcribe(params, &audio),
+            Err(WhisperError::InvalidThreadCount)
+        ));
+        let bundle = fs::read_dir(&dir).unwrap().next().unwrap().unwrap().path();
+        let bundle = SessionBundle::load(bundle).unwrap();
+        assert_eq!(bundle.audio, audio);
+        assert!(bundle.error.is_some());
+
+        let replayed = bundle.full_params().unwrap();
+        assert_eq!(replayed.fp.n_threads, 0);
+        assert_eq!(replayed.fp.temperature, 0.3);
+        let prompt = unsafe { CStr::from_ptr(replayed.fp.initial_prompt) };
+        assert_eq!(prompt.to_str().unwrap(), "line one\nline two");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,218 @@
+use crate::common_logging::generic_warn;
+use crate::{FullParams, WhisperContext, WhisperError, WhisperState};
+use std::ffi::c_int;
+use std::fmt;
+use std::thread;
+use std::time::Duration;
+
+/// Retries [`WhisperState::full`] after transient failures, see [`WhisperError::is_transient`],
+/// waiting longer after each, and optionally moves on to another model once the retries run out.
+///
+/// Errors that would only happen again, such as invalid parameters, are returned right away.
+///
+/// # Examples
+/// ```no_run
+/// # use whisper_rs::{FullParams, RetryPolicy, SamplingStrategy, WhisperContext, WhisperContextParameters};
+/// # use std::time::Duration;
+/// # let audio = vec![0.0f32; 16000];
+/// let gpu = WhisperContext::new_with_params("model.bin", WhisperContextParameters::default()).unwrap();
+/// let mut cpu_params = WhisperContextParameters::default();
+/// cpu_params.use_gpu(false);
+/// let cpu = WhisperContext::new_with_params("model.bin", cpu_params).unwrap();
+///
+/// let policy = RetryPolicy::new()
+///     .retries(3)
+///     .backoff(Duration::from_millis(200))
+///     .downgrade_to(cpu);
+/// let mut state = gpu.create_state().unwrap();
+/// let params = FullParams::new(SamplingStrategy::default());
+/// policy.full(&mut state, params, &audio).unwrap();
+/// ```
+#[derive(Clone)]
+pub struct RetryPolicy {
+    retries: u32,
+    backoff: Duration,
+    max_backoff: Duration,
+    downgrade: Option<WhisperContext>,
+}
+
+impl fmt::Debug for RetryPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RetryPolicy")
+            .field("retries", &self.retries)
+            .field("backoff", &self.backoff)
+            .field("max_backoff", &self.max_backoff)
+            .field("downgrade", &self.downgrade.is_some())
+            .finish()
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            retries: 2,
+            backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(5),
+            downgrade: None,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set how many times a failed run is retried.
+    ///
+    /// Defaults to 2.
+    pub fn retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Set how long to wait before the first retry. The wait doubles with every retry after it.
+    ///
+    /// Defaults to 100 ms.
+    pub fn backoff(mut self, backoff: Duration) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Set the longest wait between retries.
+    ///
+    /// Defaults to 5 seconds.
+    pub fn max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    /// Once the retries run out, run on a state of `ctx` instead, typically the same model
+    /// loaded without [`crate::WhisperContextParameters::use_gpu`], with as many retries again.
+    ///
+    /// Defaults to no downgrade.
+    pub fn downgrade_to(mut self, ctx: WhisperContext) -> Self {
+        self.downgrade = Some(ctx);
+        self
+    }
+
+    /// How long to wait before retry number `retry`, counting from 0.
+    fn delay(&self, retry: u32) -> Duration {
+        self.backoff
+            .saturating_mul(2u32.saturating_pow(retry))
+            .min(self.max_backoff)
+    }
+
+    /// Run [`WhisperState::full`] on `state`, retrying transient failures with a copy of `params`.
+    ///
+    /// If the run was downgraded, `state` is replaced with the state of the downgrade model that
+    /// produced the output, so the results can be read from it as usual and later runs stay on
+    /// the model that worked.
+    ///
+    /// # Returns
+    /// The result of the last attempt.
+    pub fn full(
+        &self,
+        state: &mut WhisperState,
+        params: FullParams<'_, '_>,
+        audio: &[f32],
+    ) -> Result<c_int, WhisperError> {
+        let error = match self.with_retries(state, &params, audio) {
+            Err(e) if e.is_transient() => e,
+            result => return result,
+        };
+        let Some(downgrade) = &self.downgrade else {
+            return Err(error);
+        };
+        generic_warn!(
+            "whisper-rs: still failing after {} retries ({}), downgrading",
+            self.retries,
+            error
+        );
+        let mut downgraded = downgrade.create_state()?;
+        let result = self.with_retries(&mut downgraded, &params, audio);
+        *state = downgraded;
+        result
+    }
+
+    fn with_retries(
+        &self,
+        state: &mut WhisperState,
+        params: &FullParams<'_, '_>,
+        audio: &[f32],
+    ) -> Result<c_int, WhisperError> {
+        self.retry(|| state.full(params.clone(), audio))
+    }
+
+    /// Call `run` until it succeeds, fails in a way retrying won't fix, or the retries run out.
+    fn retry<T>(
+        &self,
+        mut run: impl FnMut() -> Result<T, WhisperError>,
+    ) -> Result<T, WhisperError> {
+        let mut retry = 0;
+        loop {
+            match run() {
+                Err(e) if e.is_transient() && retry < self.retries => {
+                    let delay = self.delay(retry);
+                    generic_warn!("whisper-rs: run failed ({}), retrying in {:?}", e, delay);
+                    thread::sleep(delay);
+                    retry += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_up_to_the_limit() {
+        let policy = RetryPolicy::new()
+            .backoff(Duration::from_millis(100))
+            .max_backoff(Duration::from_millis(350));
+        let delays: Vec<_> = (0..4)
+            .map(|retry| policy.delay(retry).as_millis())
+            .collect();
+        assert_eq!(delays, [100, 200, 350, 350]);
+        assert_eq!(policy.delay(u32::MAX), Duration::from_millis(350));
+    }
+
+    #[test]
+    fn only_compute_failures_are_transient() {
+        assert!(WhisperError::FailedToEncode.is_transient());
+        assert!(WhisperError::FailedToDecode.is_transient());
+        assert!(!WhisperError::NoSamples.is_transient());
+        assert!(!WhisperError::InvalidThreadCount.is_transient());
+        assert!(!WhisperError::Aborted.is_transient());
+    }
+
+    #[cfg(feature = "test-stub")]
+    #[test]
+    fn failing_runs_are_retried_until_they_succeed() {
+        use crate::stub::StubContext;
+        use crate::SamplingStrategy;
+
+        let ctx = StubContext::with_text(0, 100, "hello");
+        let mut state = ctx.create_state().unwrap();
+        let params = FullParams::new(SamplingStrategy::default());
+        let audio = vec![0.0f32; 16000];
+        let policy = RetryPolicy::new().retries(2).backoff(Duration::ZERO);
+        let mut run = |policy: &RetryPolicy| policy.retry(|| state.full(params.clone(), &audio));
+
+        ctx.fail_next(2, WhisperError::FailedToEncode);
+        assert!(run(&policy).is_ok());
+        assert_eq!(ctx.runs(), 1);
+
+        ctx.fail_next(3, WhisperError::FailedToDecode);
+        assert!(matches!(run(&policy), Err(WhisperError::FailedToDecode)));
+
+        // a run stopped on purpose is returned right away
+        ctx.fail_next(1, WhisperError::Aborted);
+        assert!(matches!(run(&policy), Err(WhisperError::Aborted)));
+        assert!(run(&policy).is_ok());
+        assert_eq!(ctx.runs(), 2);
+    }
+}
@@ -0,0 +1,402 @@
+//! Live captions for audio played with [rodio](https://docs.rs/rodio).
+//!
+//! [`tap`] wraps a playback source, such as a `rodio::Decoder` (which decodes with Symphonia),
+//! so every sample the output device pulls is also sent to a [`CaptionFeed`]. The feed converts
+//! the samples to 16 kHz mono and pushes them into any [`StreamingTranscribe`] implementation on
+//! another thread, so a media player can show captions for a local file without decoding it twice.
+//!
+//! ```no_run
+//! # use whisper_rs::{FullParams, SamplingStrategy, StreamingTranscriber, WhisperContext, WhisperContextParameters};
+//! # fn play(_: impl rodio::Source<Item = i16> + Send + 'static) {}
+//! # let ctx = WhisperContext::new_with_params("model.bin", WhisperContextParameters::default()).unwrap();
+//! # let mut state = ctx.create_state().unwrap();
+//! # let source = rodio::buffer::SamplesBuffer::new(2, 44100, vec![0i16; 44100]);
+//! let (source, mut feed) = whisper_rs::rodio::tap(source);
+//! play(source); // e.g. `sink.append(source)`
+//!
+//! let params = FullParams::new(SamplingStrategy::default());
+//! let mut stream = StreamingTranscriber::new(&mut state, params).with_chunk_ms(5000);
+//! feed.run(&mut stream, |segments| {
+//!     for segment in segments {
+//!         println!("[{}] {}", segment.start, segment.text);
+//!     }
+//! })
+//! .unwrap();
+//! ```
+
+use crate::audio_pipeline::Resampler;
+use crate::transcribe::SAMPLES_PER_CS;
+use crate::{StreamingTranscribe, TranscriptSegment};
+use ::rodio::source::SeekError;
+use ::rodio::{Sample, Source};
+use std::sync::mpsc::{self, Receiver, SyncSender, TryRecvError};
+use std::time::Duration;
+
+/// How much audio the tap collects before sending it to the feed, in milliseconds.
+const BLOCK_MS: u32 = 100;
+
+/// Blocks that can be on their way to the feed at once, five seconds of audio.
+/// Each has a buffer allocated up front, which the feed hands back once it is done with it.
+const QUEUE_BLOCKS: usize = 50;
+
+/// Wrap `source` so the audio it plays is also sent to the returned [`CaptionFeed`].
+pub fn tap<S>(source: S) -> (CaptionTap<S>, CaptionFeed)
+where
+    S: Source,
+    S::Item: Sample,
+{
+    let (sender, receiver) = mpsc::sync_channel(QUEUE_BLOCKS);
+    let (recycle, recycled) = mpsc::sync_channel(QUEUE_BLOCKS);
+    let channels = source.channels().max(1) as usize;
+    let block_len = source.sample_rate() as usize * channels * BLOCK_MS as usize / 1000;
+    // a block is sent once it holds whole frames, so it can exceed its length by a frame
+    let buffer = || Vec::with_capacity(block_len + channels);
+    for _ in 0..QUEUE_BLOCKS {
+        let _ = recycle.try_send(buffer());
+    }
+    let tap = CaptionTap {
+        source,
+        sender,
+        recycled,
+        block: buffer(),
+        block_len,
+        seek: None,
+        skipped: Duration::ZERO,
+    };
+    let feed = CaptionFeed {
+        receiver,
+        recycle,
+        resampler: Resampler::new(whisper_rs_sys::WHISPER_SAMPLE_RATE),
+        offset: 0,
+        pushed: 0,
+    };
+    (tap, feed)
+}
+
+/// Audio played through a [`CaptionTap`], and what happened before it.
+struct Block {
+    /// Where playback was moved to, if it was since the last block.
+    seek: Option<Duration>,
+    /// Audio left out since the last block, because the feed fell behind.
+    skipped: Duration,
+    samples: Vec<f32>,
+    channels: u16,
+    sample_rate: u32,
+}
+
+/// A playback source that passes its samples through unchanged while copying them to a
+/// [`CaptionFeed`], see [`tap`].
+///
+/// The tap never blocks, and only allocates if the format of the source changes mid-stream, so
+/// it can run on the audio thread. It sends audio in blocks whose buffers are allocated by
+/// [`tap`] and reused; if the feed falls more than five seconds behind, no buffer is free and
+/// the tap skips audio until one is, rather than queueing it without bound. Captions resume
+/// after such a gap with timestamps that account for it.
+pub struct CaptionTap<S: Source>
+where
+    S::Item: Sample,
+{
+    source: S,
+    sender: SyncSender<Block>,
+    recycled: Receiver<Vec<f32>>,
+    block: Vec<f32>,
+    block_len: usize,
+    /// A seek not yet sent to the feed.
+    seek: Option<Duration>,
+    skipped: Duration,
+}
+
+impl<S: Source> CaptionTap<S>
+where
+    S::Item: Sample,
+{
+    fn flush(&mut self) {
+        if self.block.is_empty() && self.seek.is_none() {
+            return;
+        }
+        let channels = self.source.channels().max(1);
+        let sample_rate = self.source.sample_rate();
+        // no free buffer means the queue is full, or the feed is gone, in which case playback
+        // simply continues without captions
+        let Ok(next) = self.recycled.try_recv() else {
+            let frames = self.block.len() / channels as usize;
+            self.skipped += Duration::from_secs_f64(frames as f64 / sample_rate.max(1) as f64);
+            self.block.clear();
+            return;
+        };
+        let block = Block {
+            seek: self.seek.take(),
+            skipped: std::mem::take(&mut self.skipped),
+            samples: std::mem::replace(&mut self.block, next),
+            channels,
+            sample_rate,
+        };
+        let _ = self.sender.try_send(block);
+    }
+}
+
+impl<S: Source> Iterator for CaptionTap<S>
+where
+    S::Item: Sample,
+{
+    type Item = S::Item;
+
+    fn next(&mut self) -> Option<S::Item> {
+        let Some(sample) = self.source.next() else {
+            self.flush();
+            return None;
+        };
+        self.block.push(sample.to_f32());
+        // only send whole frames, so channels stay aligned
+        let channels = self.source.channels().max(1) as usize;
+        if self.block.len() >= self.block_len && self.block.len().is_multiple_of(channels) {
+            self.flush();
+        }
+        Some(sample)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.source.size_hint()
+    }
+}
+
+impl<S: Source> Source for CaptionTap<S>
+where
+    S::Item: Sample,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        self.source.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.source.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.source.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.source.total_duration()
+    }
+
+    /// Seek the wrapped source. Captions after the seek are timed from the new position.
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.source.try_seek(pos)?;
+        // audio from before the seek is still played from the output buffer, but is superseded
+        self.block.clear();
+        self.skipped = Duration::ZERO;
+        self.seek = Some(pos);
+        self.flush();
+        Ok(())
+    }
+}
+
+impl<S: Source> Drop for CaptionTap<S>
+where
+    S::Item: Sample,
+{
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+/// Receives the audio played through a [`CaptionTap`] and transcribes it, see [`tap`].
+///
+/// Segment timestamps are in centiseconds from the start of the media, also after seeking.
+/// The feed ends when the tap is dropped, e.g. once playback is finished or stopped.
+pub struct CaptionFeed {
+    receiver: Receiver<Block>,
+    /// Hands the buffers of handled blocks back to the tap.
+    recycle: SyncSender<Vec<f32>>,
+    /// Carries the resampling position over between blocks.
+    resampler: Resampler,
+    /// Media position of the start of the current stream, in centiseconds.
+    offset: i64,
+    /// Samples pushed into the current stream, at 16 kHz.
+    pushed: usize,
+}
+
+impl CaptionFeed {
+    /// Transcribe the played audio into `stream` until playback ends, passing every batch of
+    /// finalized segments to `on_segments`. Blocks, so call it on its own thread.
+    ///
+    /// When playback ends, the audio still buffered in `stream` is transcribed with
+    /// [`StreamingTranscribe::finish`].
+    pub fn run<T: StreamingTranscribe>(
+        &mut self,
+        stream: &mut T,
+        mut on_segments: impl FnMut(Vec<TranscriptSegment>),
+    ) -> Result<(), T::Error> {
+        while let Ok(block) = self.receiver.recv() {
+            on_segments(self.handle(stream, block)?);
+        }
+        on_segments(self.finish(stream)?);
+        Ok(())
+    }
+
+    /// Transcribe the audio played since the last call without waiting for more, for use from
+    /// an event loop.
+    ///
+    /// # Returns
+    /// The segments finalized by this audio, and whether playback has ended. After the end, the
+    /// buffered audio has been transcribed with [`StreamingTranscribe::finish`] and the returned
+    /// segments include it.
+    pub fn poll<T: StreamingTranscribe>(
+        &mut self,
+        stream: &mut T,
+    ) -> Result<(Vec<TranscriptSegment>, bool), T::Error> {
+        let mut segments = Vec::new();
+        loop {
+            match self.receiver.try_recv() {
+                Ok(block) => segments.extend(self.handle(stream, block)?),
+                Err(TryRecvError::Empty) => return Ok((segments, false)),
+                Err(TryRecvError::Disconnected) => {
+                    segments.extend(self.finish(stream)?);
+                    return Ok((segments, true));
+                }
+            }
+        }
+    }
+
+    fn handle<T: StreamingTranscribe>(
+        &mut self,
+        stream: &mut T,
+        mut block: Block,
+    ) -> Result<Vec<TranscriptSegment>, T::Error> {
+        let mut segments = Vec::new();
+        if let Some(pos) = block.seek {
+            // whatever was buffered was played before the seek
+            segments.extend(self.finish(stream)?);
+            self.offset = (pos.as_millis() / 10) as i64 - self.stream_position();
+        }
+        if !block.skipped.is_zero() {
+            // the audio on either side of the gap doesn't join up
+            segments.extend(self.finish(stream)?);
+            self.offset += (block.skipped.as_millis() / 10) as i64;
+        }
+        let sample_rate = block.sample_rate.max(1);
+        if sample_rate != self.resampler.input_rate() {
+            let tail = self.resampler.finish();
+            segments.extend(self.push(stream, &tail)?);
+            self.resampler = Resampler::new(sample_rate);
+        }
+        let audio = self
+            .resampler
+            .push(&to_mono(&block.samples, block.channels));
+        block.samples.clear();
+        let _ = self.recycle.try_send(block.samples);
+        segments.extend(self.push(stream, &audio)?);
+        Ok(segments)
+    }
+
+    fn push<T: StreamingTranscribe>(
+        &mut self,
+        stream: &mut T,
+        audio: &[f32],
+    ) -> Result<Vec<TranscriptSegment>, T::Error> {
+        if audio.is_empty() {
+            return Ok(Vec::new());
+        }
+        self.pushed += audio.len();
+        let segments = stream.push_audio(audio)?;
+        Ok(self.shift(segments))
+    }
+
+    /// Transcribe the rest of the current stream, including the samples the resampler held
+    /// back.
+    fn finish<T: StreamingTranscribe>(
+        &mut self,
+        stream: &mut T,
+    ) -> Result<Vec<TranscriptSegment>, T::Error> {
+        let tail = self.resampler.finish();
+        let mut segments = self.push(stream, &tail)?;
+        segments.extend(self.shift(stream.finish()?));
+        Ok(segments)
+    }
+
+    /// Position of the stream in centiseconds, as it timestamps segments.
+    fn stream_position(&self) -> i64 {
+        (self.pushed / SAMPLES_PER_CS) as i64
+    }
+
+    fn shift(&self, mut segments: Vec<TranscriptSegment>) -> Vec<TranscriptSegment> {
+        for segment in &mut segments {
+            segment.start += self.offset;
+            segment.end += self.offset;
+        }
+        segments
+    }
+}
+
+/// Mix interleaved audio down to mono.
+fn to_mono(samples: &[f32], channels: u16) -> Vec<f32> {
+    let channels = channels.max(1) as usize;
+    samples
+        .chunks_exact(channels)
+        .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ::rodio::buffer::SamplesBuffer;
+
+    /// Records what it is fed, emitting one segment per push spanning the audio so far.
+    #[derive(Default)]
+    struct Recorder {
+        samples: usize,
+    }
+
+    impl StreamingTranscribe for Recorder {
+        type Error = std::io::Error;
+
+        fn push_audio(&mut self, audio: &[f32]) -> Result<Vec<TranscriptSegment>, Self::Error> {
+            self.samples += audio.len();
+            Ok(vec![TranscriptSegment {
+                end: (self.samples / SAMPLES_PER_CS) as i64,
+                ..TranscriptSegment::default()
+            }])
+        }
+
+        fn finish(&mut self) -> Result<Vec<TranscriptSegment>, Self::Error> {
+            Ok(Vec::new())
+        }
+    }
+
+    #[test]
+    fn played_audio_is_transcribed_as_16k_mono() {
+        let source = SamplesBuffer::new(2, 32000, vec![0.5f32; 2 * 32000]);
+        let (tap, mut feed) = tap(source);
+        let played: Vec<f32> = tap.collect();
+        assert_eq!(played.len(), 2 * 32000);
+
+        let mut recorder = Recorder::default();
+        let mut last = Vec::new();
+        feed.run(&mut recorder, |segments| last.extend(segments))
+            .unwrap();
+        assert_eq!(recorder.samples, 16000);
+        assert_eq!(last.last().unwrap().end, 100);
+    }
+
+    #[test]
+    fn audio_is_skipped_while_the_feed_is_behind() {
+        let source = SamplesBuffer::new(1, 16000, vec![0.5f32; 7 * 16000]);
+        let (mut tap, mut feed) = tap(source);
+        // six seconds play without the feed keeping up: five are queued, one is skipped
+        assert_eq!(tap.by_ref().take(6 * 16000).count(), 6 * 16000);
+
+        let mut recorder = Recorder::default();
+        let (segments, ended) = feed.poll(&mut recorder).unwrap();
+        assert!(!ended);
+        assert_eq!(segments.last().unwrap().end, 500);
+
+        assert_eq!(tap.count(), 16000);
+        let mut last = Vec::new();
+        feed.run(&mut recorder, |segments| last.extend(segments))
+            .unwrap();
+        assert_eq!(recorder.samples, 6 * 16000);
+        assert_eq!(last.last().unwrap().end, 700);
+    }
+}
@@ -0,0 +1,214 @@
+use crate::{Fallback, FullParams, Observer, SamplingStrategy, Transcribe, Transcript};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+/// whisper.cpp only checks for repetition once a window has more tokens than this.
+const ENTROPY_MIN_TOKENS: usize = 32;
+
+/// One try of a [`TemperatureSchedule`].
+#[derive(Debug, Clone)]
+pub struct DecodeAttempt {
+    pub strategy: SamplingStrategy,
+    pub temperature: f32,
+}
+
+/// An explicit list of decoding attempts, each with its own sampling strategy and temperature,
+/// tried in order until one produces acceptable output.
+///
+/// whisper.cpp retries rejected windows by raising the temperature by a fixed increment
+/// (see [`FullParams::set_temperature_inc`]) with the same strategy. A schedule instead encodes
+/// policies such as "one cheap greedy try, then one expensive beam search" exactly.
+///
+/// Output is rejected by the same criteria whisper.cpp uses, with the thresholds set on the
+/// parameters: an average log probability below [`FullParams::set_logprob_thold`], or a segment
+/// whose tokens are so repetitive that their entropy is below [`FullParams::set_entropy_thold`].
+/// The whole input is retried, so this suits audio of up to 30 seconds best.
+/// Checks need token data, so backends that don't report tokens always pass.
+///
+/// # Examples
+/// ```no_run
+/// # use whisper_rs::{FullParams, SamplingStrategy, TemperatureSchedule, WhisperContext, WhisperContextParameters};
+/// # let mut ctx = WhisperContext::new_with_params("model.bin", WhisperContextParameters::default()).unwrap();
+/// # let audio = vec![0.0f32; 16000];
+/// let schedule = TemperatureSchedule::new()
+///     .then(SamplingStrategy::Greedy { best_of: 1 }, 0.0)
+///     .then(SamplingStrategy::BeamSearch { beam_size: 5, patience: -1.0 }, 0.0);
+/// let params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
+/// let result = schedule.transcribe(&mut ctx, params, &audio).unwrap();
+/// println!("accepted on attempt {}: {}", result.attempt, result.transcript.text());
+/// ```
+#[derive(Clone, Default)]
+pub struct TemperatureSchedule {
+    attempts: Vec<DecodeAttempt>,
+    observer: Option<Arc<dyn Observer>>,
+}
+
+impl fmt::Debug for TemperatureSchedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TemperatureSchedule")
+            .field("attempts", &self.attempts)
+            .field("observed", &self.observer.is_some())
+            .finish()
+    }
+}
+
+/// Result of [`TemperatureSchedule::transcribe`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScheduledTranscript {
+    pub transcript: Transcript,
+    /// Index of the attempt that produced `transcript`.
+    pub attempt: usize,
+    /// Whether `transcript` passed the checks. If no attempt did, it is the output of the last one.
+    pub accepted: bool,
+}
+
+impl TemperatureSchedule {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an attempt with `strategy` at `temperature`.
+    pub fn then(mut self, strategy: SamplingStrategy, temperature: f32) -> Self {
+        self.attempts.push(DecodeAttempt {
+            strategy,
+            temperature,
+        });
+        self
+    }
+
+    /// Report each rejected attempt to `observer`, see [`Observer::on_fallback`].
+    pub fn observe(mut self, observer: Arc<dyn Observer>) -> Self {
+        self.observer = Some(observer);
+        self
+    }
+
+    /// The attempts, in order.
+    pub fn attempts(&self) -> &[DecodeAttempt] {
+        &self.attempts
+    }
+
+    /// Transcribe `audio` with each attempt in turn until one is accepted.
+    ///
+    /// Each attempt runs with a copy of `params`, with its strategy and temperature set and
+    /// whisper.cpp's own fallback disabled. An empty schedule runs `params` once, as they are.
+    pub fn transcribe<T: Transcribe>(
+        &self,
+        backend: &mut T,
+        params: FullParams<'_, '_>,
+        audio: &[f32],
+    ) -> Result<ScheduledTranscript, T::Error> {
+        let logprob_thold = params.fp.logprob_thold;
+        let entropy_thold = params.fp.entropy_thold;
+        if self.attempts.is_empty() {
+            let transcript = backend.transcribe(params, audio)?;
+            return Ok(ScheduledTranscript {
+                transcript,
+                attempt: 0,
+                accepted: true,
+            });
+        }
+
+        let mut result = None;
+        for (i, attempt) in self.attempts.iter().enumerate() {
+            if let (Some(observer), true) = (&self.observer, i > 0) {
+                observer.on_fallback(&Fallback {
+                    attempt: i,
+                    temperature: attempt.temperature,
+                });
+            }
+            let mut params = params.clone();
+            params.set_sampling_strategy(attempt.strategy.clone());
+            params.set_temperature(attempt.temperature);
+            params.set_temperature_inc(0.0);
+
+            let transcript = backend.transcribe(params, audio)?;
+            let accepted = acceptable(&transcript, logprob_thold, entropy_thold);
+            result = Some(ScheduledTranscript {
+                transcript,
+                attempt: i,
+                accepted,
+            });
+            if accepted {
+                break;
+            }
+        }
+        Ok(result.expect("schedule has at least one attempt"))
+    }
+}
+
+fn acceptable(transcript: &Transcript, logprob_thold: f32, entropy_thold: f32) -> bool {
+    let text_tokens = || {
+        transcript
+            .iter()
+            .flat_map(|s| s.tokens.iter().filter(|t| !t.special))
+    };
+    let count = text_tokens().count();
+    if count == 0 {
+        return true;
+    }
+    let avg_logprob = text_tokens().map(|t| t.plog).sum::<f32>() / count as f32;
+    if avg_logprob < logprob_thold {
+        return false;
+    }
+
+    transcript.iter().all(|segment| {
+        let ids: Vec<_> = segment
+            .tokens
+            .iter()
+            .filter(|t| !t.special)
+            .map(|t| t.id)
+            .collect();
+        ids.len() <= ENTROPY_MIN_TOKENS
+            || entropy(&ids[ids.len() - ENTROPY_MIN_TOKENS..]) >= entropy_thold
+    })
+}
+
+/// Entropy of the token distribution in `ids`, as whisper.cpp computes it to detect repetition.
+fn entropy(ids: &[i32]) -> f32 {
+    let mut counts = HashMap::new();
+    for id in ids {
+        *counts.entry(id).or_insert(0usize) += 1;
+    }
+    let n = ids.len() as f32;
+    counts
+        .values()
+        .map(|&c| {
+            let p = c as f32 / n;
+            -p * p.ln()
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{TranscriptSegment, TranscriptToken};
+
+    fn segment(ids: impl IntoIterator<Item = i32>, plog: f32) -> TranscriptSegment {
+        let mut segment = TranscriptSegment::new(0, 100, "");
+        segment.tokens = ids
+            .into_iter()
+            .map(|id| TranscriptToken {
+                id,
+                plog,
+                ..Default::default()
+            })
+            .collect();
+        segment
+    }
+
+    #[test]
+    fn unlikely_or_repetitive_output_is_rejected() {
+        let varied = Transcript::new(vec![segment(0..40, -0.3)]);
+        assert!(acceptable(&varied, -1.0, 2.4));
+
+        let unlikely = Transcript::new(vec![segment(0..40, -1.5)]);
+        assert!(!acceptable(&unlikely, -1.0, 2.4));
+
+        let looping = Transcript::new(vec![segment((0..40).map(|i| i % 3), -0.3)]);
+        assert!(!acceptable(&looping, -1.0, 2.4));
+
+        assert!(acceptable(&Transcript::default(), -1.0, 2.4));
+    }
+}
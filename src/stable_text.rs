@@ -0,0 +1,248 @@
+use crate::{StreamedToken, TokenEvent};
+
+/// Turns the provisional tokens of [`crate::FullParams::add_token_callback`] into live captions
+/// that don't rewrite themselves.
+///
+/// The decoder often revises the last word or two of its hypothesis, and rejected windows are
+/// decoded again from scratch. Showing every token as it arrives makes captions flicker. The
+/// stabilizer holds back the trailing words of the current hypothesis, at least
+/// [`Self::min_held_words`] of them and any low-probability words before those up to
+/// [`Self::max_held_words`], and only finalizes text once it is followed by enough other words.
+///
+/// Finalized text is never taken back. When the decoder starts over on the same window, text it
+/// already finalized is matched against the new hypothesis and not repeated.
+///
+/// # Examples
+/// ```no_run
+/// # use whisper_rs::{CaptionStabilizer, FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters};
+/// # use std::sync::{Arc, Mutex};
+/// # let ctx = WhisperContext::new_with_params("model.bin", WhisperContextParameters::default()).unwrap();
+/// let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
+/// let stabilizer = Arc::new(Mutex::new(CaptionStabilizer::new()));
+/// let captions = stabilizer.clone();
+/// params.add_token_callback(&ctx, move |event| {
+///     let update = captions.lock().unwrap().push(event);
+///     print!("{}", update.finalized);
+///     // show `update.tentative` greyed out after the finalized text
+/// });
+/// ```
+#[derive(Debug, Clone)]
+pub struct CaptionStabilizer {
+    min_held_words: usize,
+    max_held_words: usize,
+    min_probability: f32,
+    /// Text tokens of the current hypothesis.
+    tokens: Vec<StreamedToken>,
+    /// How many of `tokens` have been finalized.
+    committed: usize,
+    /// The hypothesis before the last rewind, until it is known whether the decoder is retrying it.
+    previous: Option<(Vec<StreamedToken>, usize)>,
+}
+
+/// What changed after an event, see [`CaptionStabilizer::push`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StableUpdate {
+    /// Text that just became final, to append to the captions.
+    pub finalized: String,
+    /// The held-back text following all finalized text, which may still change.
+    pub tentative: String,
+}
+
+impl Default for CaptionStabilizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CaptionStabilizer {
+    pub fn new() -> Self {
+        Self {
+            min_held_words: 1,
+            max_held_words: 4,
+            min_probability: 0.5,
+            tokens: Vec::new(),
+            committed: 0,
+            previous: None,
+        }
+    }
+
+    /// Set how many trailing words are always held back, since the last word may still be
+    /// incomplete.
+    ///
+    /// Defaults to 1.
+    pub fn min_held_words(mut self, words: usize) -> Self {
+        self.min_held_words = words;
+        self.max_held_words = self.max_held_words.max(words);
+        self
+    }
+
+    /// Set the most trailing words held back for having a low probability, which bounds how far
+    /// finalized text lags behind speech.
+    ///
+    /// Defaults to 4.
+    pub fn max_held_words(mut self, words: usize) -> Self {
+        self.max_held_words = words.max(self.min_held_words);
+        self
+    }
+
+    /// Set the probability below which a word counts as uncertain and is held back.
+    /// A word's probability is the lowest of its tokens.
+    ///
+    /// Defaults to 0.5.
+    pub fn min_probability(mut self, p: f32) -> Self {
+        self.min_probability = p;
+        self
+    }
+
+    /// Process an event of [`crate::FullParams::add_token_callback`].
+    pub fn push(&mut self, event: TokenEvent) -> StableUpdate {
+        let mut finalized = String::new();
+        match event {
+            TokenEvent::Token(token) => {
+                self.add_token(token, &mut finalized);
+                let stable = self.stable_len();
+                if stable > self.committed {
+                    finalized.extend(self.tokens[self.committed..stable].iter().map(text));
+                    self.committed = stable;
+                }
+            }
+            // with no tokens since the last rewind, the earlier hypothesis is still the one to match
+            TokenEvent::Rewind if !self.tokens.is_empty() => {
+                if let Some((tokens, committed)) = self.previous.take() {
+                    finalized.extend(tokens[committed..].iter().map(text));
+                }
+                self.previous = Some((std::mem::take(&mut self.tokens), self.committed));
+                self.committed = 0;
+            }
+            TokenEvent::Rewind => {}
+        }
+        StableUpdate {
+            finalized,
+            tentative: self.tentative(),
+        }
+    }
+
+    /// Finalize all held-back text, e.g. once [`crate::WhisperState::full`] has returned.
+    pub fn finish(&mut self) -> String {
+        let mut finalized = String::new();
+        if let Some((tokens, committed)) = self.previous.take() {
+            if self.tokens.is_empty() {
+                finalized.extend(tokens[committed..].iter().map(text));
+            }
+        }
+        finalized.extend(self.tokens[self.committed..].iter().map(text));
+        self.tokens.clear();
+        self.committed = 0;
+        finalized
+    }
+
+    /// The held-back text of the current hypothesis.
+    pub fn tentative(&self) -> String {
+        match &self.previous {
+            // until the new hypothesis has caught up, the old one is the best guess
+            Some((tokens, committed)) if self.tokens.len() <= *committed => {
+                tokens[*committed..].iter().map(text).collect()
+            }
+            _ => self.tokens[self.committed..].iter().map(text).collect(),
+        }
+    }
+
+    fn add_token(&mut self, token: StreamedToken, finalized: &mut String) {
+        let i = self.tokens.len();
+        self.tokens.push(token);
+        let Some((tokens, committed)) = &self.previous else {
+            return;
+        };
+        if tokens.get(i).is_some_and(|t| t.id == self.tokens[i].id) {
+            // so far a retry of the previous hypothesis: what was finalized of it stays final
+            self.committed = self.tokens.len().min(*committed);
+            return;
+        }
+        if i == 0 {
+            // a new window: the previous hypothesis was kept, held-back tail and all
+            finalized.extend(tokens[*committed..].iter().map(text));
+        } else {
+            // a retry that diverges; text finalized from the previous attempt can't be taken back
+            self.committed = self.committed.max(i.min(*committed));
+        }
+        self.previous = None;
+    }
+
+    /// Number of tokens of the current hypothesis that are stable.
+    fn stable_len(&self) -> usize {
+        if self.previous.is_some() {
+            return self.committed;
+        }
+        // start indices of the words, each starting with a space or at the first token
+        let starts: Vec<usize> = (0..self.tokens.len())
+            .filter(|&i| i == 0 || self.tokens[i].text.starts_with(' '))
+            .collect();
+        let mut held = self.min_held_words.min(starts.len());
+        while held < self.max_held_words.min(starts.len()) {
+            let start = starts[starts.len() - held - 1];
+            let end = starts[starts.len() - held];
+            let p = self.tokens[start..end]
+                .iter()
+                .map(|t| t.p)
+                .fold(f32::INFINITY, f32::min);
+            if p >= self.min_probability {
+                break;
+            }
+            held += 1;
+        }
+        match held {
+            0 => self.tokens.len(),
+            held => starts[starts.len() - held],
+        }
+        .max(self.committed)
+    }
+}
+
+fn text(token: &StreamedToken) -> &str {
+    &token.text
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn token(id: i32, text: &str, p: f32) -> TokenEvent {
+        TokenEvent::Token(StreamedToken {
+            id,
+            text: text.to_string(),
+            p,
+            window_time: 0,
+        })
+    }
+
+    #[test]
+    fn trailing_uncertain_words_are_held_back() {
+        let mut stabilizer = CaptionStabilizer::new();
+        assert_eq!(stabilizer.push(token(1, " Hello", 0.9)).finalized, "");
+        assert_eq!(stabilizer.push(token(2, " there", 0.3)).finalized, " Hello");
+        let update = stabilizer.push(token(3, " friend", 0.9));
+        assert_eq!(update.finalized, "");
+        assert_eq!(update.tentative, " there friend");
+        // the uncertain word is settled once a certain one follows it
+        assert_eq!(
+            stabilizer.push(token(4, " again", 0.9)).finalized,
+            " there friend"
+        );
+        assert_eq!(stabilizer.finish(), " again");
+    }
+
+    #[test]
+    fn retries_do_not_repeat_finalized_text() {
+        let mut stabilizer = CaptionStabilizer::new();
+        stabilizer.push(token(1, " One", 0.9));
+        stabilizer.push(token(2, " two", 0.9));
+        stabilizer.push(TokenEvent::Rewind);
+        // the same window again, diverging after the finalized word
+        assert_eq!(stabilizer.push(token(1, " One", 0.9)).finalized, "");
+        assert_eq!(stabilizer.push(token(5, " three", 0.9)).finalized, "");
+        stabilizer.push(TokenEvent::Rewind);
+        // the next window
+        assert_eq!(stabilizer.push(token(7, " Four", 0.9)).finalized, " three");
+        assert_eq!(stabilizer.finish(), " Four");
+    }
+}
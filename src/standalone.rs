@@ -2,6 +2,8 @@
 
 use std::ffi::{c_int, CStr, CString};
 
+use crate::{Language, ParseLanguageError};
+
 /// Return the id of the specified language, returns -1 if not found
 ///
 /// # Arguments
@@ -25,6 +27,24 @@ pub fn get_lang_id(lang: &str) -> Option<c_int> {
     }
 }
 
+/// Look up a language by its code or English name, as whisper.cpp does, e.g. `"de"` or
+/// `"german"` for [`Language::German`].
+///
+/// Unlike [`get_lang_id`], strings with null bytes are rejected, not a panic. For the name to
+/// show in a user interface, see [`Language::display_name`].
+///
+/// # Returns
+/// The language, or an error if whisper.cpp doesn't know it.
+///
+/// # C++ equivalent
+/// `int whisper_lang_id(const char * lang)`
+pub fn lang_id(lang: &str) -> Result<Language, ParseLanguageError> {
+    let unknown = || ParseLanguageError(lang.to_string());
+    let c_lang = CString::new(lang).map_err(|_| unknown())?;
+    let ret = unsafe { whisper_rs_sys::whisper_lang_id(c_lang.as_ptr()) };
+    Language::from_id(ret).ok_or_else(unknown)
+}
+
 /// Return the ID of the maximum language (ie the number of languages - 1)
 ///
 /// # Returns
@@ -49,7 +69,7 @@ pub fn get_lang_str(id: i32) -> Option<&'static str> {
         None
     } else {
         let c_str = unsafe { CStr::from_ptr(c_buf) };
-        Some(c_str.to_str().unwrap())
+        c_str.to_str().ok()
     }
 }
 
@@ -66,7 +86,7 @@ pub fn get_lang_str_full(id: i32) -> Option<&'static str> {
         None
     } else {
         let c_str = unsafe { CStr::from_ptr(c_buf) };
-        Some(c_str.to_str().unwrap())
+        c_str.to_str().ok()
     }
 }
 
@@ -0,0 +1,193 @@
+use crate::common_logging::generic_warn;
+use crate::transcribe::{ms_to_samples, SAMPLES_PER_CS};
+use crate::{
+    AdaptiveChunking, FullParams, StreamingTranscribe, Transcribe, TranscriptSegment,
+    WhisperVadContext,
+};
+
+/// A [`StreamingTranscribe`] implementation on top of any [`Transcribe`] backend.
+///
+/// Incoming audio is buffered and transcribed in consecutive chunks of a fixed length,
+/// or of a length chosen from voice activity with [`Self::with_adaptive_chunking`].
+/// Segments are emitted once their chunk has been transcribed, with timestamps shifted to be
+/// relative to the start of the stream.
+pub struct StreamingTranscriber<'a, 'b, T: Transcribe> {
+    backend: T,
+    params: FullParams<'a, 'b>,
+    chunk_samples: usize,
+    adaptive: Option<(WhisperVadContext, AdaptiveChunking)>,
+    buffer: Vec<f32>,
+    /// Number of samples already transcribed and dropped from the front of `buffer`.
+    consumed: usize,
+}
+
+impl<'a, 'b, T: Transcribe> StreamingTranscriber<'a, 'b, T> {
+    /// Default chunk length: the 30 second window Whisper was trained on.
+    pub const DEFAULT_CHUNK_MS: u32 = whisper_rs_sys::WHISPER_CHUNK_SIZE * 1000;
+
+    /// Create a new streaming transcriber.
+    ///
+    /// `params` are cloned for every chunk.
+    pub fn new(backend: T, params: FullParams<'a, 'b>) -> Self {
+        Self {
+            backend,
+            params,
+            chunk_samples: ms_to_samples(Self::DEFAULT_CHUNK_MS),
+            adaptive: None,
+            buffer: Vec::new(),
+            consumed: 0,
+        }
+    }
+
+    /// Set the length of audio transcribed at once.
+    /// Shorter chunks lower latency at the cost of accuracy, since the model sees less context.
+    ///
+    /// Defaults to [`Self::DEFAULT_CHUNK_MS`]. Values below one second are raised to one second,
+    /// as whisper.cpp refuses shorter input.
+    pub fn set_chunk_ms(&mut self, chunk_ms: u32) {
+        self.chunk_samples = ms_to_samples(chunk_ms.max(1000));
+    }
+
+    /// Builder-style variant of [`Self::set_chunk_ms`].
+    pub fn with_chunk_ms(mut self, chunk_ms: u32) -> Self {
+        self.set_chunk_ms(chunk_ms);
+        self
+    }
+
+    /// Cut chunks where `vad` finds pauses, shorter in dense speech and longer across silence,
+    /// instead of every [`Self::set_chunk_ms`], see [`AdaptiveChunking`].
+    ///
+    /// If the VAD fails on some audio, that chunk is cut at its maximum length with a warning.
+    pub fn with_adaptive_chunking(
+        mut self,
+        vad: WhisperVadContext,
+        chunking: AdaptiveChunking,
+    ) -> Self {
+        self.adaptive = Some((vad, chunking));
+        self
+    }
+
+    /// The parameters used for each chunk.
+    pub fn params_mut(&mut self) -> &mut FullParams<'a, 'b> {
+        &mut self.params
+    }
+
+    /// The wrapped backend.
+    pub fn backend_mut(&mut self) -> &mut T {
+        &mut self.backend
+    }
+
+    /// Consume the transcriber, returning the wrapped backend. Buffered audio and the VAD of
+    /// [`Self::with_adaptive_chunking`] are discarded.
+    pub fn into_inner(self) -> T {
+        self.backend
+    }
+
+    /// Number of samples received but not yet transcribed.
+    pub fn buffered_samples(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Position of the start of the buffered audio, in centiseconds from the start of the stream.
+    pub fn position(&self) -> i64 {
+        (self.consumed / SAMPLES_PER_CS) as i64
+    }
+
+    /// Most samples a chunk holds.
+    pub(crate) fn max_chunk_samples(&self) -> usize {
+        match &self.adaptive {
+            Some((_, chunking)) => chunking.max_samples(),
+            None => self.chunk_samples,
+        }
+    }
+
+    /// Length of the next chunk, out of a buffer of at least [`Self::max_chunk_samples`].
+    fn next_chunk_len(&mut self) -> usize {
+        let max = self.max_chunk_samples();
+        let Some((vad, chunking)) = &mut self.adaptive else {
+            return max;
+        };
+        match vad.detect_speech(&self.buffer[..max]) {
+            Ok(()) => chunking.cut(vad.probabilities()).clamp(1, max),
+            Err(e) => {
+                generic_warn!(
+                    "whisper-rs: voice activity detection failed, cutting a fixed chunk: {}",
+                    e
+                );
+                max
+            }
+        }
+    }
+
+    fn transcribe_chunk(&mut self, len: usize) -> Result<Vec<TranscriptSegment>, T::Error> {
+        let offset = self.position();
+        let transcript = self
+            .backend
+            .transcribe(self.params.clone(), &self.buffer[..len])?;
+
+        self.buffer.drain(..len);
+        self.consumed += len;
+
+        Ok(transcript
+            .segments
+            .into_iter()
+            .map(|mut segment| {
+                segment.start += offset;
+                segment.end += offset;
+                segment
+            })
+            .collect())
+    }
+}
+
+impl<T: Transcribe> StreamingTranscribe for StreamingTranscriber<'_, '_, T> {
+    type Error = T::Error;
+
+    fn push_audio(&mut self, audio: &[f32]) -> Result<Vec<TranscriptSegment>, Self::Error> {
+        self.buffer.extend_from_slice(audio);
+
+        let mut out = Vec::new();
+        while self.buffer.len() >= self.max_chunk_samples() {
+            let len = self.next_chunk_len();
+            out.extend(self.transcribe_chunk(len)?);
+        }
+        Ok(out)
+    }
+
+    fn finish(&mut self) -> Result<Vec<TranscriptSegment>, Self::Error> {
+        let mut out = Vec::new();
+        while self.buffer.len() > self.max_chunk_samples() {
+            let len = self.next_chunk_len();
+            out.extend(self.transcribe_chunk(len)?);
+        }
+        if !self.buffer.is_empty() {
+            out.extend(self.transcribe_chunk(self.buffer.len())?);
+        }
+        Ok(out)
+    }
+}
+
+#[cfg(all(test, feature = "test-stub"))]
+mod test {
+    use super::*;
+    use crate::stub::StubContext;
+    use crate::SamplingStrategy;
+
+    #[test]
+    fn segments_are_offset_by_chunk_position() {
+        let ctx = StubContext::with_text(0, 100, " hello");
+        let params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
+        let mut stream =
+            StreamingTranscriber::new(ctx.create_state().unwrap(), params).with_chunk_ms(2000);
+
+        // 4.5 seconds in total: two whole chunks, then half a second left for finish()
+        assert!(stream.push_audio(&[0.0; 16000]).unwrap().is_empty());
+        let segments = stream.push_audio(&[0.0; 56000]).unwrap();
+        assert_eq!(segments.len(), 2);
+        assert_eq!((segments[0].start, segments[1].start), (0, 200));
+
+        let rest = stream.finish().unwrap();
+        assert_eq!((rest[0].start, rest[0].end), (400, 500));
+        assert_eq!(ctx.runs(), 3);
+    }
+}
@@ -0,0 +1,234 @@
+//! Fake contexts and states that return canned transcripts.
+//!
+//! These mirror the parts of [`crate::WhisperContext`] and [`crate::WhisperState`] used to run a
+//! transcription, so code that is generic over them can be unit tested without a model file,
+//! a GPU, or waiting on real inference. Nothing in this module calls into whisper.cpp while running,
+//! but building [`FullParams`] still goes through whisper.cpp to fetch its defaults.
+
+use crate::observer::Observers;
+use crate::{FullParams, Observer, RunEnd, RunStart, Transcript, TranscriptSegment, WhisperError};
+use std::collections::VecDeque;
+use std::ffi::c_int;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Audio context size reported by stub models, the same as every official Whisper model.
+const STUB_N_AUDIO_CTX: c_int = 1500;
+
+#[derive(Debug, Default)]
+struct StubInner {
+    default: Transcript,
+    queued: Mutex<VecDeque<Transcript>>,
+    failures: Mutex<VecDeque<WhisperError>>,
+    runs: AtomicUsize,
+    observers: Observers,
+}
+
+/// A stand-in for [`crate::WhisperContext`].
+///
+/// Cloning is cheap and all clones share the same canned responses and run counter.
+#[derive(Debug, Clone, Default)]
+pub struct StubContext {
+    inner: Arc<StubInner>,
+}
+
+impl StubContext {
+    /// Create a context whose states return `transcript` from every run.
+    pub fn new(transcript: Transcript) -> Self {
+        Self {
+            inner: Arc::new(StubInner {
+                default: transcript,
+                ..Default::default()
+            }),
+        }
+    }
+
+    /// Create a context whose states return each of `responses` in turn, one per run,
+    /// across all states. Once they are used up, runs return an empty transcript.
+    pub fn with_responses(responses: impl IntoIterator<Item = Transcript>) -> Self {
+        Self {
+            inner: Arc::new(StubInner {
+                queued: Mutex::new(responses.into_iter().collect()),
+                ..Default::default()
+            }),
+        }
+    }
+
+    /// Create a context whose states return a single segment with `text`, spanning `start..end` centiseconds.
+    pub fn with_text(start: i64, end: i64, text: &str) -> Self {
+        Self::new(Transcript::new(vec![TranscriptSegment::new(
+            start, end, text,
+        )]))
+    }
+
+    /// Create a new state, as [`crate::WhisperContext::create_state`] would.
+    pub fn create_state(&self) -> Result<StubState, WhisperError> {
+        Ok(StubState {
+            ctx: self.clone(),
+            result: Transcript::default(),
+        })
+    }
+
+    /// Notify `observer` of the start and end of every run, as
+    /// [`crate::WhisperContext::add_observer`] would. Stub segments aren't reported to
+    /// [`Observer::on_segment`], which takes segments of a real state.
+    pub fn add_observer(&self, observer: Arc<dyn Observer>) {
+        self.inner.observers.add(observer);
+    }
+
+    /// Total number of successful runs across all states created from this context.
+    pub fn runs(&self) -> usize {
+        self.inner.runs.load(Ordering::Relaxed)
+    }
+
+    /// Make the next `runs` runs across all states fail with `error` once their input and
+    /// parameters have been checked, e.g. to test retries.
+    pub fn fail_next(&self, runs: usize, error: WhisperError) {
+        self.inner
+            .failures
+            .lock()
+            .expect("stub failure queue poisoned")
+            .extend(std::iter::repeat_n(error, runs));
+    }
+
+    fn next_failure(&self) -> Option<WhisperError> {
+        self.inner
+            .failures
+            .lock()
+            .expect("stub failure queue poisoned")
+            .pop_front()
+    }
+
+    fn next_response(&self) -> Transcript {
+        self.inner.runs.fetch_add(1, Ordering::Relaxed);
+        self.inner
+            .queued
+            .lock()
+            .expect("stub response queue poisoned")
+            .pop_front()
+            .unwrap_or_else(|| self.inner.default.clone())
+    }
+}
+
+/// A stand-in for [`crate::WhisperState`], created with [`StubContext::create_state`].
+#[derive(Debug)]
+pub struct StubState {
+    ctx: StubContext,
+    result: Transcript,
+}
+
+impl StubState {
+    /// Pretend to run the model on `data`, storing the next canned transcript as the result.
+    ///
+    /// Input and parameters are checked the same way as [`crate::WhisperState::full`],
+    /// so tests still catch empty buffers and out-of-range parameters.
+    pub fn full(&mut self, params: FullParams, data: &[f32]) -> Result<c_int, WhisperError> {
+        let observers = self.ctx.inner.observers.get();
+        let run = RunStart {
+            samples: data.len(),
+            n_threads: params.fp.n_threads,
+        };
+        observers.iter().for_each(|o| o.on_run_start(&run));
+        let started = Instant::now();
+        let result = self.run_full(params, data);
+
+        let end = RunEnd {
+            duration: started.elapsed(),
+            audio_duration: Duration::from_secs_f64(
+                data.len() as f64 / whisper_rs_sys::WHISPER_SAMPLE_RATE as f64,
+            ),
+            segments: if result.is_ok() {
+                self.full_n_segments()
+            } else {
+                0
+            },
+            error: result.err(),
+        };
+        observers.iter().for_each(|o| o.on_run_end(&end));
+        result
+    }
+
+    fn run_full(&mut self, mut params: FullParams, data: &[f32]) -> Result<c_int, WhisperError> {
+        if data.is_empty() {
+            return Err(WhisperError::NoSamples);
+        }
+        params.validate(STUB_N_AUDIO_CTX, data.len())?;
+        crate::transcribe::pad_short_input(&mut params, data)?;
+        if let Some(error) = self.ctx.next_failure() {
+            return Err(error);
+        }
+
+        self.result = self.ctx.next_response();
+        Ok(0)
+    }
+
+    /// Number of segments in the last result.
+    pub fn full_n_segments(&self) -> c_int {
+        self.result.len() as c_int
+    }
+
+    /// Get a segment of the last result.
+    pub fn get_segment(&self, segment: c_int) -> Option<&TranscriptSegment> {
+        usize::try_from(segment)
+            .ok()
+            .and_then(|i| self.result.segments.get(i))
+    }
+
+    /// Copy the last result, as [`crate::WhisperState::transcript`] would.
+    pub fn transcript(&self) -> Result<Transcript, WhisperError> {
+        Ok(self.result.clone())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn responses_are_returned_in_order() {
+        let ctx = StubContext::with_responses([
+            Transcript::new(vec![TranscriptSegment::new(0, 100, " one")]),
+            Transcript::new(vec![TranscriptSegment::new(0, 100, " two")]),
+        ]);
+        let other = ctx.clone();
+
+        assert_eq!(ctx.next_response().text(), " one");
+        assert_eq!(other.next_response().text(), " two");
+        assert!(ctx.next_response().is_empty());
+        assert_eq!(other.runs(), 3);
+    }
+
+    #[derive(Default)]
+    struct Ends(Mutex<Vec<(usize, c_int, Option<String>)>>);
+
+    impl Observer for Ends {
+        fn on_run_end(&self, run: &RunEnd) {
+            let error = run.error.map(|e| e.to_string());
+            let samples = (run.audio_duration.as_secs_f64() * 16000.0).round() as usize;
+            self.0.lock().unwrap().push((samples, run.segments, error));
+        }
+    }
+
+    #[test]
+    fn rejected_runs_are_observed() {
+        let ctx = StubContext::with_text(0, 100, " hello");
+        let ends = Arc::new(Ends::default());
+        ctx.add_observer(ends.clone());
+        let mut state = ctx.create_state().unwrap();
+
+        let mut params = FullParams::new(crate::SamplingStrategy::Greedy { best_of: 1 });
+        state.full(params.clone(), &[0.0; 16000]).unwrap();
+        params.set_n_threads(0);
+        assert!(state.full(params.clone(), &[0.0; 16000]).is_err());
+        assert!(state.full(params, &[]).is_err());
+
+        let ends = ends.0.lock().unwrap();
+        assert_eq!(ends.len(), 3);
+        assert_eq!(ends[0], (16000, 1, None));
+        assert_eq!((ends[1].0, ends[1].1), (16000, 0));
+        assert!(ends[1].2.is_some());
+        assert_eq!(ends[2].0, 0);
+        assert_eq!(ctx.runs(), 1);
+    }
+}
@@ -0,0 +1,41 @@
+//! The generated whisper.cpp bindings, for calling functions whisper-rs doesn't wrap yet.
+//!
+//! Everything here is exactly what `whisper-rs-sys` generates from the headers of the
+//! whisper.cpp version in [`WHISPER_CPP_VERSION`], re-exported at a path that stays the same
+//! across releases. The contents are not covered by semver: any release of whisper-rs may
+//! move to a newer whisper.cpp, and these bindings change with it. Pin whisper-rs with `=` and
+//! check [`WHISPER_CPP_VERSION`] when upgrading if you depend on them.
+//!
+//! Use [`crate::WhisperContext::as_raw_ptr`] and [`crate::WhisperState::as_raw_ptr`] to call
+//! these on objects created through the safe API.
+//!
+//! # Examples
+//! ```no_run
+//! # use whisper_rs::{WhisperContext, WhisperContextParameters};
+//! # let ctx = WhisperContext::new_with_params("model.bin", WhisperContextParameters::default()).unwrap();
+//! let n_vocab = unsafe { whisper_rs::sys::whisper_model_n_vocab(ctx.as_raw_ptr()) };
+//! ```
+
+pub use crate::WHISPER_CPP_VERSION;
+pub use whisper_rs_sys::*;
+
+impl crate::WhisperContext {
+    /// The whisper.cpp context of this model, see [`crate::sys`].
+    ///
+    /// The pointer is valid as long as this context or any clone or state of it is alive. It is
+    /// shared by all of them, so don't free it, and don't call functions that use the context's
+    /// built-in state, which whisper-rs never creates.
+    pub fn as_raw_ptr(&self) -> *mut whisper_context {
+        self.inner().ctx
+    }
+}
+
+impl crate::WhisperState {
+    /// The whisper.cpp state, see [`crate::sys`].
+    ///
+    /// The pointer is valid as long as this state is alive. Don't free it, and don't use it
+    /// while this state is running.
+    pub fn as_raw_ptr(&mut self) -> *mut whisper_state {
+        self.ptr
+    }
+}
@@ -0,0 +1,137 @@
+//! Decoding G.711 telephony audio: 8 kHz, 8 bit µ-law (North America, Japan) or A-law (elsewhere),
+//! as delivered by SIP trunks, call-center recorders and `audio/basic` files.
+
+use crate::{resample_linear, WhisperError};
+
+const TELEPHONY_SAMPLE_RATE: u32 = 8000;
+
+const fn build_mulaw_table() -> [i16; 256] {
+    let mut table = [0i16; 256];
+    let mut i = 0;
+    while i < 256 {
+        let byte = !(i as u8);
+        let exponent = (byte >> 4) & 0x07;
+        let mantissa = (byte & 0x0f) as i16;
+        let magnitude = (((mantissa << 3) + 0x84) << exponent) - 0x84;
+        table[i] = if byte & 0x80 != 0 {
+            -magnitude
+        } else {
+            magnitude
+        };
+        i += 1;
+    }
+    table
+}
+
+const fn build_alaw_table() -> [i16; 256] {
+    let mut table = [0i16; 256];
+    let mut i = 0;
+    while i < 256 {
+        let byte = (i as u8) ^ 0x55;
+        let exponent = (byte >> 4) & 0x07;
+        let mantissa = (byte & 0x0f) as i16;
+        let magnitude = if exponent == 0 {
+            (mantissa << 4) + 8
+        } else {
+            ((mantissa << 4) + 0x108) << (exponent - 1)
+        };
+        // unlike µ-law, a set sign bit means positive
+        table[i] = if byte & 0x80 != 0 {
+            magnitude
+        } else {
+            -magnitude
+        };
+        i += 1;
+    }
+    table
+}
+
+static MULAW_TABLE: [i16; 256] = build_mulaw_table();
+static ALAW_TABLE: [i16; 256] = build_alaw_table();
+
+/// A G.711 companding law.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum G711 {
+    /// G.711 µ-law (PCMU, RTP payload type 0).
+    MuLaw,
+    /// G.711 A-law (PCMA, RTP payload type 8).
+    ALaw,
+}
+
+impl G711 {
+    fn table(self) -> &'static [i16; 256] {
+        match self {
+            G711::MuLaw => &MULAW_TABLE,
+            G711::ALaw => &ALAW_TABLE,
+        }
+    }
+
+    /// Decode G.711 bytes to 32 bit floating point samples, keeping the 8 kHz sample rate.
+    ///
+    /// # Arguments
+    /// * `input` - The encoded bytes, one per sample.
+    /// * `output` - Where to write the decoded samples.
+    ///
+    /// # Errors
+    /// * if `input.len() != output.len()` ([`WhisperError::InputOutputLengthMismatch`])
+    pub fn decode(self, input: &[u8], output: &mut [f32]) -> Result<(), WhisperError> {
+        if input.len() != output.len() {
+            return Err(WhisperError::InputOutputLengthMismatch {
+                input_len: input.len(),
+                output_len: output.len(),
+            });
+        }
+        let table = self.table();
+        for (byte, out) in input.iter().zip(output) {
+            *out = table[*byte as usize] as f32 / 32768.0;
+        }
+        Ok(())
+    }
+
+    /// Decode 8 kHz G.711 bytes and upsample them to the 16 kHz Whisper expects.
+    ///
+    /// # Examples
+    /// ```
+    /// # use whisper_rs::G711;
+    /// let call = [0xffu8; 8000];
+    /// let audio = G711::MuLaw.decode_to_16k(&call);
+    /// assert_eq!(audio.len(), 16000);
+    /// ```
+    pub fn decode_to_16k(self, input: &[u8]) -> Vec<f32> {
+        let mut narrowband = vec![0.0; input.len()];
+        self.decode(input, &mut narrowband)
+            .expect("output is sized to match the input");
+        resample_linear(
+            &narrowband,
+            TELEPHONY_SAMPLE_RATE,
+            whisper_rs_sys::WHISPER_SAMPLE_RATE,
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn known_code_points() {
+        assert_eq!(MULAW_TABLE[0xff], 0);
+        assert_eq!(MULAW_TABLE[0x7f], 0);
+        assert_eq!(MULAW_TABLE[0x00], -32124);
+        assert_eq!(MULAW_TABLE[0x80], 32124);
+        assert_eq!(ALAW_TABLE[0xd5], 8);
+        assert_eq!(ALAW_TABLE[0x55], -8);
+        assert_eq!(ALAW_TABLE[0xaa], 32256);
+        assert_eq!(ALAW_TABLE[0x2a], -32256);
+    }
+
+    #[test]
+    fn decoding_checks_lengths() {
+        let mut output = [0.0; 2];
+        assert!(matches!(
+            G711::ALaw.decode(&[0xd5; 3], &mut output),
+            Err(WhisperError::InputOutputLengthMismatch { .. })
+        ));
+        assert_eq!(G711::MuLaw.decode_to_16k(&[0xff; 80]), vec![0.0; 160]);
+    }
+}
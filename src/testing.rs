@@ -0,0 +1,297 @@
+//! Helpers for testing code built on top of whisper-rs.
+//!
+//! This module provides deterministic synthetic audio generators, a way to write small fixture WAV files
+//! without any extra dependencies, and assertion helpers that compare transcripts loosely,
+//! since the exact output of a model is rarely stable across versions and backends.
+//!
+//! All generated audio is 32 bit floating point, mono, at 16 kHz, which is what [`crate::WhisperState::full`] expects.
+
+pub use crate::wav::write_wav;
+use crate::{Transcript, TranscriptSegment};
+use std::f32::consts::TAU;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Sample rate of all generated audio.
+pub const SAMPLE_RATE: u32 = whisper_rs_sys::WHISPER_SAMPLE_RATE;
+
+fn samples_for(duration_ms: u32) -> usize {
+    (duration_ms as u64 * SAMPLE_RATE as u64 / 1000) as usize
+}
+
+/// Small xorshift generator, so the output of the generators is identical on every platform.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        // xorshift gets stuck on 0, so mix the seed first
+        Self(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.0 = x;
+        x
+    }
+
+    /// Uniform float in `[0, 1)`.
+    fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    fn range(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.next_f32()
+    }
+}
+
+/// Generate `duration_ms` of digital silence.
+pub fn silence(duration_ms: u32) -> Vec<f32> {
+    vec![0.0; samples_for(duration_ms)]
+}
+
+/// Generate a pure sine tone.
+///
+/// # Arguments
+/// * frequency_hz: Frequency of the tone.
+/// * duration_ms: Length of the output.
+/// * amplitude: Peak amplitude, in the range `0.0..=1.0`.
+pub fn sine_tone(frequency_hz: f32, duration_ms: u32, amplitude: f32) -> Vec<f32> {
+    (0..samples_for(duration_ms))
+        .map(|i| amplitude * (TAU * frequency_hz * i as f32 / SAMPLE_RATE as f32).sin())
+        .collect()
+}
+
+/// Generate uniform white noise. The same `seed` always produces the same samples.
+pub fn white_noise(duration_ms: u32, amplitude: f32, seed: u64) -> Vec<f32> {
+    let mut rng = Rng::new(seed);
+    (0..samples_for(duration_ms))
+        .map(|_| amplitude * rng.range(-1.0, 1.0))
+        .collect()
+}
+
+/// Generate a signal with roughly the spectral and temporal shape of speech.
+///
+/// The output is a sequence of voiced "syllables" (a harmonic series with a wandering pitch,
+/// shaped by formant-like peaks) separated by short pauses.
+/// It will not produce meaningful words, but it is useful for exercising VAD, chunking and
+/// streaming code paths with something that is neither silence nor a pure tone.
+///
+/// The output is normalized to peak at half of full scale, whatever the harmonics add up to.
+/// The same `seed` always produces the same samples.
+pub fn speech_like(duration_ms: u32, seed: u64) -> Vec<f32> {
+    const FORMANTS: [[f32; 2]; 5] = [
+        [730.0, 1090.0],
+        [270.0, 2290.0],
+        [530.0, 1840.0],
+        [570.0, 840.0],
+        [300.0, 870.0],
+    ];
+
+    let mut rng = Rng::new(seed);
+    let total = samples_for(duration_ms);
+    let mut out = Vec::with_capacity(total);
+
+    while out.len() < total {
+        let syllable = samples_for(rng.range(120.0, 280.0) as u32).min(total - out.len());
+        let pitch = rng.range(90.0, 220.0);
+        let formants = FORMANTS[(rng.next_u64() % FORMANTS.len() as u64) as usize];
+
+        let mut phase = 0.0f32;
+        for i in 0..syllable {
+            let t = i as f32 / syllable as f32;
+            // slight falling intonation inside each syllable
+            let f0 = pitch * (1.0 - 0.15 * t);
+            phase += TAU * f0 / SAMPLE_RATE as f32;
+
+            let mut sample = 0.0;
+            for harmonic in 1..=20 {
+                let freq = f0 * harmonic as f32;
+                let gain: f32 = formants
+                    .iter()
+                    .map(|f| 1.0 / (1.0 + ((freq - f) / 120.0).powi(2)))
+                    .sum();
+                sample += gain * (phase * harmonic as f32).sin() / harmonic as f32;
+            }
+            let envelope = (std::f32::consts::PI * t).sin();
+            out.push(envelope * sample);
+        }
+
+        let pause = samples_for(rng.range(30.0, 150.0) as u32).min(total - out.len());
+        out.resize(out.len() + pause, 0.0);
+    }
+
+    let peak = out.iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+    if peak > 0.0 {
+        let gain = 0.5 / peak;
+        out.iter_mut().for_each(|s| *s *= gain);
+    }
+    out
+}
+
+/// Encode samples as an in-memory WAV file. See [`write_wav`].
+pub fn wav_bytes(samples: &[f32]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(44 + samples.len() * 2);
+    write_wav(&mut out, samples).expect("writing to a Vec cannot fail");
+    out
+}
+
+/// Write a small set of fixture WAV files into `dir`, returning their paths.
+///
+/// The fixtures are:
+/// * `silence_1s.wav`: one second of silence
+/// * `tone_440hz_1s.wav`: one second of a 440 Hz tone
+/// * `speech_like_3s.wav`: three seconds of [`speech_like`] audio
+pub fn write_fixture_wavs(dir: impl AsRef<Path>) -> io::Result<Vec<PathBuf>> {
+    let dir = dir.as_ref();
+    std::fs::create_dir_all(dir)?;
+
+    let fixtures = [
+        ("silence_1s.wav", silence(1000)),
+        ("tone_440hz_1s.wav", sine_tone(440.0, 1000, 0.5)),
+        ("speech_like_3s.wav", speech_like(3000, 0)),
+    ];
+
+    let mut paths = Vec::with_capacity(fixtures.len());
+    for (name, samples) in fixtures {
+        let path = dir.join(name);
+        write_wav(io::BufWriter::new(std::fs::File::create(&path)?), &samples)?;
+        paths.push(path);
+    }
+    Ok(paths)
+}
+
+fn normalized_words(text: &str) -> Vec<String> {
+    text.split_whitespace()
+        .map(crate::transcript::words::normalize)
+        .filter(|w| !w.is_empty())
+        .collect()
+}
+
+/// Word error rate of `hypothesis` against `reference`.
+///
+/// Both strings are lowercased and stripped of punctuation before comparing,
+/// so `"Hello, world."` and `"hello world"` are considered identical.
+/// Returns `0.0` when both are empty, and `1.0` for any hypothesis against an empty reference.
+pub fn word_error_rate(reference: &str, hypothesis: &str) -> f32 {
+    let reference = normalized_words(reference);
+    let hypothesis = normalized_words(hypothesis);
+
+    if reference.is_empty() {
+        return if hypothesis.is_empty() { 0.0 } else { 1.0 };
+    }
+
+    // single row Levenshtein distance over words
+    let mut row: Vec<usize> = (0..=hypothesis.len()).collect();
+    for (i, r) in reference.iter().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, h) in hypothesis.iter().enumerate() {
+            let substitution = diagonal + usize::from(r != h);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(row[j + 1] + 1);
+        }
+    }
+
+    row[hypothesis.len()] as f32 / reference.len() as f32
+}
+
+/// Assert that the text of `transcript` matches `expected` with a word error rate of at most `max_wer`.
+///
+/// See [`word_error_rate`] for how the texts are compared.
+#[track_caller]
+pub fn assert_transcript_matches(transcript: &Transcript, expected: &str, max_wer: f32) {
+    assert_text_matches(&transcript.text(), expected, max_wer);
+}
+
+/// Assert that `actual` matches `expected` with a word error rate of at most `max_wer`.
+#[track_caller]
+pub fn assert_text_matches(actual: &str, expected: &str, max_wer: f32) {
+    let wer = word_error_rate(expected, actual);
+    assert!(
+        wer <= max_wer,
+        "transcript does not match: word error rate {:.3} > {:.3}\n  expected: {:?}\n    actual: {:?}",
+        wer,
+        max_wer,
+        expected.trim(),
+        actual.trim()
+    );
+}
+
+/// Assert that both timestamps of `segment` are within `tolerance` of the expected ones.
+///
+/// All values are in centiseconds, like the timestamps of [`TranscriptSegment`].
+#[track_caller]
+pub fn assert_segment_timing(
+    segment: &TranscriptSegment,
+    expected_start: i64,
+    expected_end: i64,
+    tolerance: i64,
+) {
+    assert!(
+        (segment.start - expected_start).abs() <= tolerance
+            && (segment.end - expected_end).abs() <= tolerance,
+        "segment timing out of tolerance (±{}): expected {}..{}, got {}..{} ({:?})",
+        tolerance,
+        expected_start,
+        expected_end,
+        segment.start,
+        segment.end,
+        segment.text
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn generators_are_deterministic() {
+        assert_eq!(silence(250).len(), 4000);
+        assert_eq!(sine_tone(440.0, 1000, 0.5).len(), 16000);
+        assert_eq!(white_noise(100, 0.1, 7), white_noise(100, 0.1, 7));
+        assert_ne!(white_noise(100, 0.1, 7), white_noise(100, 0.1, 8));
+
+        let speech = speech_like(2000, 42);
+        assert_eq!(speech.len(), 32000);
+        assert_eq!(speech, speech_like(2000, 42));
+        let peak = speech.iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+        assert!((peak - 0.5).abs() < 1e-6);
+        for seed in 0..20 {
+            assert!(speech_like(1000, seed)
+                .iter()
+                .all(|s| s.abs() <= 0.5 + 1e-6));
+        }
+    }
+
+    #[test]
+    fn wav_header_is_valid() {
+        let bytes = wav_bytes(&sine_tone(440.0, 10, 1.0));
+        assert_eq!(&bytes[0..4], b"RIFF");
+        assert_eq!(&bytes[8..16], b"WAVEfmt ");
+        assert_eq!(u32::from_le_bytes(bytes[24..28].try_into().unwrap()), 16000);
+        assert_eq!(u32::from_le_bytes(bytes[40..44].try_into().unwrap()), 320);
+        assert_eq!(bytes.len(), 44 + 320);
+    }
+
+    #[test]
+    fn word_error_rate_ignores_case_and_punctuation() {
+        assert_eq!(word_error_rate("Hello, world.", " hello world"), 0.0);
+        assert_eq!(word_error_rate("a b c d", "a x c d"), 0.25);
+        assert_eq!(word_error_rate("a b", "a b c d"), 1.0);
+        assert_eq!(word_error_rate("", ""), 0.0);
+
+        let transcript = Transcript::new(vec![
+            TranscriptSegment::new(0, 150, " The quick brown fox"),
+            TranscriptSegment::new(150, 300, " jumps over the lazy dog."),
+        ]);
+        assert_transcript_matches(
+            &transcript,
+            "the quick brown fox jumped over the lazy dog",
+            0.2,
+        );
+        assert_segment_timing(&transcript.segments[1], 160, 290, 10);
+    }
+}
@@ -0,0 +1,208 @@
+use crate::FullParams;
+use std::collections::HashSet;
+use std::ffi::c_int;
+
+/// Beyond this many threads the encoder stops getting faster and starts contending for memory bandwidth.
+const MAX_ENCODE_THREADS: usize = 8;
+/// Decoding runs one token at a time, and is memory bound well before this.
+const MAX_DECODE_THREADS: usize = 4;
+
+/// What is known about the CPUs of this machine, see [`CpuTopology::detect`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CpuTopology {
+    /// Number of hardware threads, as reported by [`std::thread::available_parallelism`].
+    pub logical: usize,
+    /// Number of physical cores. Equal to `logical` if it couldn't be determined.
+    pub physical: usize,
+    /// Number of physical performance cores on hybrid CPUs (Apple Silicon, Intel Alder Lake and later).
+    /// `None` if the CPU isn't hybrid or it couldn't be determined.
+    pub performance: Option<usize>,
+}
+
+impl CpuTopology {
+    /// Inspect the current machine.
+    ///
+    /// Physical and performance core counts are read from `/proc/cpuinfo` and `/sys/devices/cpu_core`
+    /// on Linux, and from `sysctl` on macOS. Elsewhere, only the logical count is known.
+    pub fn detect() -> Self {
+        let logical = std::thread::available_parallelism().map_or(1, |n| n.get());
+        let (physical, performance) = detect_cores();
+        Self {
+            logical,
+            physical: physical.filter(|&n| n > 0).unwrap_or(logical),
+            performance: performance.filter(|&n| n > 0),
+        }
+    }
+
+    /// Number of physical efficiency cores on hybrid CPUs, `None` if [`Self::performance`] is.
+    pub fn efficiency_cores(&self) -> Option<usize> {
+        self.performance.map(|p| self.physical.saturating_sub(p))
+    }
+}
+
+/// Thread counts for the encoder and decoder, see [`ThreadCounts::auto`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ThreadCounts {
+    /// Threads for [`crate::WhisperState::encode`] and [`crate::WhisperState::full`].
+    pub encode: c_int,
+    /// Threads for [`crate::WhisperState::decode`].
+    pub decode: c_int,
+}
+
+impl ThreadCounts {
+    /// Pick thread counts for the current machine.
+    pub fn auto() -> Self {
+        Self::for_topology(&CpuTopology::detect())
+    }
+
+    /// Pick thread counts for `topology`.
+    ///
+    /// Only performance cores are used on hybrid CPUs, since ggml splits work evenly across threads
+    /// and a thread on an efficiency core holds up the whole step. Hyper-threads are not counted either,
+    /// as they share the vector units the encoder saturates.
+    pub fn for_topology(topology: &CpuTopology) -> Self {
+        let cores = topology.performance.unwrap_or(topology.physical).max(1);
+        Self {
+            encode: cores.min(MAX_ENCODE_THREADS) as c_int,
+            decode: cores.min(MAX_DECODE_THREADS) as c_int,
+        }
+    }
+}
+
+impl FullParams<'_, '_> {
+    /// Set the number of threads from [`ThreadCounts::auto`].
+    ///
+    /// whisper.cpp uses the same thread count for encoding and decoding in [`crate::WhisperState::full`],
+    /// so this uses the encoder count, which dominates the run time.
+    pub fn set_auto_threads(&mut self) {
+        self.set_n_threads(ThreadCounts::auto().encode);
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn detect_cores() -> (Option<usize>, Option<usize>) {
+    let Ok(cpuinfo) = std::fs::read_to_string("/proc/cpuinfo") else {
+        return (None, None);
+    };
+    let cores = parse_cpuinfo(&cpuinfo);
+    let count = |cpus: Option<&HashSet<usize>>| {
+        let distinct: HashSet<_> = cores
+            .iter()
+            .filter(|(cpu, _)| match cpus {
+                Some(set) => set.contains(cpu),
+                None => true,
+            })
+            .map(|(_, core)| core)
+            .collect();
+        Some(distinct.len()).filter(|&n| n > 0)
+    };
+
+    // only present on hybrid Intel CPUs, listing the logical CPUs of the performance cores
+    let performance = std::fs::read_to_string("/sys/devices/cpu_core/cpus")
+        .ok()
+        .map(|list| parse_cpu_list(&list))
+        .and_then(|cpus| count(Some(&cpus)));
+    (count(None), performance)
+}
+
+#[cfg(target_os = "macos")]
+fn detect_cores() -> (Option<usize>, Option<usize>) {
+    fn sysctl(name: &str) -> Option<usize> {
+        let out = std::process::Command::new("sysctl")
+            .args(["-n", name])
+            .output()
+            .ok()?;
+        String::from_utf8(out.stdout).ok()?.trim().parse().ok()
+    }
+    // perflevel0 is the fastest core type; perflevel1 only exists on hybrid chips
+    let performance =
+        sysctl("hw.perflevel1.physicalcpu").and_then(|_| sysctl("hw.perflevel0.physicalcpu"));
+    (sysctl("hw.physicalcpu"), performance)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn detect_cores() -> (Option<usize>, Option<usize>) {
+    (None, None)
+}
+
+/// Map each logical CPU to its `(physical id, core id)`. Architectures that don't report these
+/// (most ARM kernels) produce an empty list.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_cpuinfo(cpuinfo: &str) -> Vec<(usize, (usize, usize))> {
+    let mut out = Vec::new();
+    for block in cpuinfo.split("\n\n") {
+        let field = |name: &str| {
+            block.lines().find_map(|line| {
+                let (key, value) = line.split_once(':')?;
+                if key.trim() == name {
+                    value.trim().parse::<usize>().ok()
+                } else {
+                    None
+                }
+            })
+        };
+        if let (Some(cpu), Some(package), Some(core)) =
+            (field("processor"), field("physical id"), field("core id"))
+        {
+            out.push((cpu, (package, core)));
+        }
+    }
+    out
+}
+
+/// Parse a kernel CPU list such as `0-3,8,10-11`.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_cpu_list(list: &str) -> HashSet<usize> {
+    let mut out = HashSet::new();
+    for part in list.trim().split(',').filter(|p| !p.is_empty()) {
+        let (start, end) = part.split_once('-').unwrap_or((part, part));
+        if let (Ok(start), Ok(end)) = (start.parse::<usize>(), end.parse::<usize>()) {
+            out.extend(start..=end);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn hybrid_cpus_use_performance_cores_only() {
+        let hybrid = CpuTopology {
+            logical: 20,
+            physical: 14,
+            performance: Some(6),
+        };
+        assert_eq!(
+            ThreadCounts::for_topology(&hybrid),
+            ThreadCounts {
+                encode: 6,
+                decode: 4
+            }
+        );
+
+        let server = CpuTopology {
+            logical: 64,
+            physical: 32,
+            performance: None,
+        };
+        assert_eq!(ThreadCounts::for_topology(&server).encode, 8);
+    }
+
+    #[test]
+    fn parses_kernel_cpu_info() {
+        assert_eq!(
+            parse_cpu_list("0-3,8,10-11\n"),
+            HashSet::from([0, 1, 2, 3, 8, 10, 11])
+        );
+
+        let cpuinfo = "processor\t: 0\nphysical id\t: 0\ncore id\t\t: 0\n\n\
+                       processor\t: 1\nphysical id\t: 0\ncore id\t\t: 0\n\n\
+                       processor\t: 2\nphysical id\t: 0\ncore id\t\t: 1\n";
+        assert_eq!(
+            parse_cpuinfo(cpuinfo),
+            vec![(0, (0, 0)), (1, (0, 0)), (2, (0, 1))]
+        );
+    }
+}
@@ -0,0 +1,144 @@
+use crate::{FullParams, WhisperContext, WhisperTokenId};
+
+/// A token as it is decoded, see [`FullParams::add_token_callback`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamedToken {
+    pub id: WhisperTokenId,
+    /// The text of this token. Tokens that split a multi-byte character contain the replacement character.
+    pub text: String,
+    /// Probability of this token.
+    pub p: f32,
+    /// Estimated time in centiseconds, relative to the start of the 30 second window being decoded:
+    /// the time of the most recent timestamp token, or 0 before the first one.
+    pub window_time: i64,
+}
+
+/// Progress of the decoder, see [`FullParams::add_token_callback`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenEvent {
+    /// A token was appended to the text of the current window.
+    Token(StreamedToken),
+    /// The decoder started over: on the next window, in which case the tokens since the previous
+    /// rewind are reported again as segments, or on the same window after its output was rejected
+    /// (see [`FullParams::set_temperature_inc`]), in which case they were discarded.
+    /// Either way, provisional text shown since the previous rewind should be replaced.
+    Rewind,
+}
+
+/// Turns the token sequences seen by a logits filter into events.
+#[derive(Debug)]
+struct TokenTracker {
+    /// Timestamp tokens start at this id.
+    token_beg: WhisperTokenId,
+    /// Special tokens start at this id.
+    token_eot: WhisperTokenId,
+    seen: Vec<WhisperTokenId>,
+    window_time: i64,
+}
+
+impl TokenTracker {
+    fn update(&mut self, tokens: &[(WhisperTokenId, f32)], mut emit: impl FnMut(TokenEvent)) {
+        let continues = tokens.len() >= self.seen.len()
+            && tokens
+                .iter()
+                .zip(&self.seen)
+                .all(|((id, _), seen)| id == seen);
+        if !continues {
+            if !self.seen.is_empty() {
+                emit(TokenEvent::Rewind);
+            }
+            self.seen.clear();
+            self.window_time = 0;
+        }
+
+        for &(id, p) in &tokens[self.seen.len()..] {
+            self.seen.push(id);
+            if id >= self.token_beg {
+                // timestamp tokens are 20 ms apart
+                self.window_time = (id - self.token_beg) as i64 * 2;
+            } else if id < self.token_eot {
+                emit(TokenEvent::Token(StreamedToken {
+                    id,
+                    text: String::new(),
+                    p,
+                    window_time: self.window_time,
+                }));
+            }
+        }
+    }
+}
+
+impl FullParams<'_, '_> {
+    /// Call `callback` with every text token as soon as it is decoded, so the text can be shown
+    /// word by word while [`crate::WhisperState::full`] is running.
+    ///
+    /// Tokens are reported before whisper.cpp has decided whether to keep them, so consumers should
+    /// treat them as provisional until [`TokenEvent::Rewind`]. Use greedy sampling with `best_of` 1,
+    /// as with several decoders their tokens are interleaved and rewind constantly.
+    ///
+    /// Implemented as a logits filter, see [`Self::add_logits_filter`].
+    pub fn add_token_callback<F>(&mut self, ctx: &WhisperContext, mut callback: F)
+    where
+        F: FnMut(TokenEvent) + Send + 'static,
+    {
+        let ctx = ctx.clone();
+        let mut tracker = TokenTracker {
+            token_beg: ctx.token_beg(),
+            token_eot: ctx.token_eot(),
+            seen: Vec::new(),
+            window_time: 0,
+        };
+        let mut ids = Vec::new();
+        self.add_logits_filter(move |tokens, _| {
+            ids.clear();
+            ids.extend(tokens.iter().map(|t| (t.id, t.p)));
+            tracker.update(&ids, |mut event| {
+                if let TokenEvent::Token(token) = &mut event {
+                    token.text = ctx
+                        .token_to_str_lossy(token.id)
+                        .map(|text| text.into_owned())
+                        .unwrap_or_default();
+                }
+                callback(event);
+            });
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn new_tokens_and_restarts_are_reported() {
+        let mut tracker = TokenTracker {
+            token_beg: 100,
+            token_eot: 50,
+            seen: Vec::new(),
+            window_time: 0,
+        };
+        let mut events = Vec::new();
+        let mut run = |tokens: &[(WhisperTokenId, f32)]| {
+            tracker.update(tokens, |event| events.push(event));
+        };
+
+        run(&[]);
+        run(&[(150, 0.9)]);
+        run(&[(150, 0.9), (7, 0.8)]);
+        // fallback: the same window decoded again
+        run(&[(100, 0.9)]);
+
+        assert_eq!(
+            events,
+            vec![
+                TokenEvent::Token(StreamedToken {
+                    id: 7,
+                    text: String::new(),
+                    p: 0.8,
+                    window_time: 100
+                }),
+                TokenEvent::Rewind
+            ]
+        );
+    }
+}
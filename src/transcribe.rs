@@ -0,0 +1,185 @@
+use crate::{
+    FullParams, Transcript, TranscriptSegment, WhisperContext, WhisperError, WhisperState,
+};
+use std::borrow::Cow;
+use std::ffi::c_int;
+
+/// Number of samples per centisecond at 16 kHz, the unit of Whisper timestamps.
+pub(crate) const SAMPLES_PER_CS: usize = whisper_rs_sys::WHISPER_SAMPLE_RATE as usize / 100;
+
+/// whisper.cpp skips input shorter than this, so short audio is padded with silence up to it.
+pub(crate) const MIN_INPUT_MS: u32 = 1010;
+
+pub(crate) fn ms_to_samples(ms: u32) -> usize {
+    ms as usize * (whisper_rs_sys::WHISPER_SAMPLE_RATE as usize / 1000)
+}
+
+/// The audio to hand to whisper.cpp: `audio` itself, or padded with silence if the part selected
+/// by the offset and duration of `params` is too short, lengthening the duration to match.
+///
+/// Returns [`WhisperError::AudioTooShort`] instead if padding was turned off,
+/// see [`FullParams::set_pad_short_audio`].
+pub(crate) fn pad_short_input<'a>(
+    params: &mut FullParams<'_, '_>,
+    audio: &'a [f32],
+) -> Result<Cow<'a, [f32]>, WhisperError> {
+    let min_samples = ms_to_samples(MIN_INPUT_MS);
+    let offset = ms_to_samples(params.fp.offset_ms.max(0) as u32);
+    let available = audio.len().saturating_sub(offset);
+    let samples = match params.fp.duration_ms {
+        duration if duration > 0 => ms_to_samples(duration as u32).min(available),
+        _ => available,
+    };
+    if samples >= min_samples {
+        return Ok(Cow::Borrowed(audio));
+    }
+    if !params.pad_short_audio {
+        return Err(WhisperError::AudioTooShort {
+            samples,
+            min_samples,
+        });
+    }
+    if params.fp.duration_ms > 0 {
+        params.fp.duration_ms = MIN_INPUT_MS as c_int;
+    }
+    let mut padded = audio.to_vec();
+    padded.resize(audio.len().max(offset + min_samples), 0.0);
+    Ok(Cow::Owned(padded))
+}
+
+/// Something that can turn a buffer of audio into a [`Transcript`].
+///
+/// Implemented by [`WhisperState`] and [`WhisperContext`] (and the test stubs, with the `test-stub` feature),
+/// so application code can be written against this trait and swap in a different model,
+/// a fake for tests, or a remote service without further changes.
+pub trait Transcribe {
+    type Error: std::error::Error;
+
+    /// Transcribe `audio`: 32 bit floating point PCM, 1 channel, at a sample rate of 16 kHz.
+    fn transcribe(
+        &mut self,
+        params: FullParams<'_, '_>,
+        audio: &[f32],
+    ) -> Result<Transcript, Self::Error>;
+}
+
+/// Something that accepts audio incrementally and emits segments as they become available.
+///
+/// See [`crate::StreamingTranscriber`] for an implementation on top of any [`Transcribe`].
+pub trait StreamingTranscribe {
+    type Error: std::error::Error;
+
+    /// Feed more audio (32 bit floating point PCM, 1 channel, 16 kHz).
+    ///
+    /// # Returns
+    /// Any segments that were finalized because of this audio. Timestamps are relative to the start of the stream.
+    fn push_audio(&mut self, audio: &[f32]) -> Result<Vec<TranscriptSegment>, Self::Error>;
+
+    /// Signal the end of the stream, transcribing whatever audio is still buffered.
+    fn finish(&mut self) -> Result<Vec<TranscriptSegment>, Self::Error>;
+}
+
+impl Transcribe for WhisperState {
+    type Error = WhisperError;
+
+    fn transcribe(
+        &mut self,
+        params: FullParams<'_, '_>,
+        audio: &[f32],
+    ) -> Result<Transcript, Self::Error> {
+        self.full(params, audio)?;
+        self.transcript()
+    }
+}
+
+/// Creates a new [`WhisperState`] for every call.
+/// Keep a state around and use it directly instead if you're transcribing many short buffers.
+impl Transcribe for WhisperContext {
+    type Error = WhisperError;
+
+    fn transcribe(
+        &mut self,
+        params: FullParams<'_, '_>,
+        audio: &[f32],
+    ) -> Result<Transcript, Self::Error> {
+        self.create_state()?.transcribe(params, audio)
+    }
+}
+
+impl<T: Transcribe + ?Sized> Transcribe for &mut T {
+    type Error = T::Error;
+
+    fn transcribe(
+        &mut self,
+        params: FullParams<'_, '_>,
+        audio: &[f32],
+    ) -> Result<Transcript, Self::Error> {
+        (**self).transcribe(params, audio)
+    }
+}
+
+impl<T: Transcribe + ?Sized> Transcribe for Box<T> {
+    type Error = T::Error;
+
+    fn transcribe(
+        &mut self,
+        params: FullParams<'_, '_>,
+        audio: &[f32],
+    ) -> Result<Transcript, Self::Error> {
+        (**self).transcribe(params, audio)
+    }
+}
+
+#[cfg(feature = "test-stub")]
+impl Transcribe for crate::stub::StubState {
+    type Error = WhisperError;
+
+    fn transcribe(
+        &mut self,
+        params: FullParams<'_, '_>,
+        audio: &[f32],
+    ) -> Result<Transcript, Self::Error> {
+        self.full(params, audio)?;
+        self.transcript()
+    }
+}
+
+#[cfg(feature = "test-stub")]
+impl Transcribe for crate::stub::StubContext {
+    type Error = WhisperError;
+
+    fn transcribe(
+        &mut self,
+        params: FullParams<'_, '_>,
+        audio: &[f32],
+    ) -> Result<Transcript, Self::Error> {
+        self.create_state()?.transcribe(params, audio)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::SamplingStrategy;
+
+    #[test]
+    fn short_audio_is_padded_or_rejected() {
+        let audio = vec![0.5; ms_to_samples(300)];
+        let mut params = FullParams::new(SamplingStrategy::default());
+        let padded = pad_short_input(&mut params, &audio).unwrap();
+        assert_eq!(padded.len(), ms_to_samples(MIN_INPUT_MS));
+        assert_eq!(&padded[..audio.len()], &audio[..]);
+
+        params.set_pad_short_audio(false);
+        assert!(matches!(
+            pad_short_input(&mut params, &audio),
+            Err(WhisperError::AudioTooShort { samples, .. }) if samples == audio.len()
+        ));
+
+        let long = vec![0.0; ms_to_samples(2000)];
+        assert!(matches!(
+            pad_short_input(&mut params, &long),
+            Ok(Cow::Borrowed(_))
+        ));
+    }
+}
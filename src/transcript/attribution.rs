@@ -0,0 +1,169 @@
+use super::edit::token_text;
+use super::{Transcript, TranscriptSegment};
+use std::ops::Range;
+
+/// The tokens and audio behind a range of [`Transcript::text`], see [`Transcript::attribute`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TextAttribution {
+    /// The `(segment, token)` indices of the text tokens overlapping the range, in order.
+    ///
+    /// Segments whose tokens don't spell out their text contribute no tokens, e.g. after
+    /// [`TranscriptSegment::text`] was edited or a token split a multi-byte character.
+    pub tokens: Vec<(usize, usize)>,
+    /// Start of the audio in centiseconds.
+    pub start: i64,
+    /// End of the audio in centiseconds.
+    pub end: i64,
+}
+
+impl Transcript {
+    /// Find the tokens and audio time range behind the bytes `range` of [`Self::text`], e.g. to
+    /// play back a word the user clicked on. An empty range finds the token at its position.
+    ///
+    /// Times come from the token timestamps when every token in the range has them, and are
+    /// interpolated over the segment by text length otherwise.
+    ///
+    /// # Returns
+    /// `None` if `range` is out of bounds of the text or reversed.
+    pub fn attribute(&self, range: Range<usize>) -> Option<TextAttribution> {
+        let len: usize = self.segments.iter().map(|s| s.text.len()).sum();
+        if range.start > range.end || range.end > len || self.segments.is_empty() {
+            return None;
+        }
+        // an empty range selects whatever it points into
+        let range = if range.is_empty() {
+            range.start.min(len.saturating_sub(1))..range.start.min(len.saturating_sub(1)) + 1
+        } else {
+            range
+        };
+
+        let mut attribution: Option<TextAttribution> = None;
+        let mut offset = 0;
+        for (index, segment) in self.segments.iter().enumerate() {
+            let segment_range = offset..offset + segment.text.len();
+            offset = segment_range.end;
+            let (start, end) = (
+                range.start.max(segment_range.start),
+                range.end.min(segment_range.end),
+            );
+            if start >= end {
+                continue;
+            }
+            let (tokens, times) = attribute_segment(
+                segment,
+                start - segment_range.start..end - segment_range.start,
+            );
+            let attribution = attribution.get_or_insert(TextAttribution {
+                tokens: Vec::new(),
+                start: times.0,
+                end: times.1,
+            });
+            attribution
+                .tokens
+                .extend(tokens.into_iter().map(|token| (index, token)));
+            attribution.start = attribution.start.min(times.0);
+            attribution.end = attribution.end.max(times.1);
+        }
+        // a transcript of only empty segments has no text to point into
+        Some(attribution.unwrap_or_else(|| {
+            let segment = &self.segments[0];
+            TextAttribution {
+                tokens: Vec::new(),
+                start: segment.start,
+                end: segment.end.max(segment.start),
+            }
+        }))
+    }
+}
+
+/// The indices of the text tokens of `segment` overlapping the non-empty bytes `range` of its
+/// text, and the start and end time of that range.
+fn attribute_segment(segment: &TranscriptSegment, range: Range<usize>) -> (Vec<usize>, (i64, i64)) {
+    let (segment_start, segment_end) = (segment.start, segment.end.max(segment.start));
+    let mut tokens = Vec::new();
+    if token_text(&segment.tokens) == segment.text {
+        let mut offset = 0;
+        for (index, token) in segment.tokens.iter().enumerate() {
+            if token.special {
+                continue;
+            }
+            let token_range = offset..offset + token.text.len();
+            offset = token_range.end;
+            if token_range.start < range.end && range.start < token_range.end {
+                tokens.push(index);
+            }
+        }
+    }
+
+    let timed = tokens
+        .iter()
+        .map(|&i| &segment.tokens[i])
+        .filter(|t| t.has_timestamps())
+        .count();
+    if !tokens.is_empty() && timed == tokens.len() {
+        let start = tokens
+            .iter()
+            .map(|&i| segment.tokens[i].t0)
+            .min()
+            .unwrap_or(segment_start)
+            .clamp(segment_start, segment_end);
+        let end = tokens
+            .iter()
+            .map(|&i| segment.tokens[i].t1)
+            .max()
+            .unwrap_or(segment_end)
+            .clamp(start, segment_end);
+        return (tokens, (start, end));
+    }
+
+    let len = segment.text.len().max(1) as i64;
+    let duration = segment_end - segment_start;
+    let at = |byte: usize| segment_start + duration * byte as i64 / len;
+    (tokens, (at(range.start), at(range.end)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::TranscriptToken;
+
+    fn token(text: &str, t0: i64, t1: i64) -> TranscriptToken {
+        TranscriptToken {
+            text: text.to_string(),
+            t0,
+            t1,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn ranges_map_to_tokens_and_times() {
+        let mut first = TranscriptSegment::new(0, 100, " Hello world");
+        first.tokens = vec![
+            TranscriptToken {
+                special: true,
+                ..token("[_BEG_]", 0, 0)
+            },
+            token(" Hello", 10, 40),
+            token(" world", 50, 90),
+        ];
+        let second = TranscriptSegment::new(100, 200, " again");
+        let transcript = Transcript::new(vec![first, second]);
+
+        let world = transcript.text().find("world").unwrap();
+        let attribution = transcript.attribute(world..world + 5).unwrap();
+        assert_eq!(attribution.tokens, vec![(0, 2)]);
+        assert_eq!((attribution.start, attribution.end), (50, 90));
+
+        assert_eq!(transcript.attribute(3..3).unwrap().tokens, vec![(0, 1)]);
+
+        // the untokenized segment is interpolated
+        let attribution = transcript
+            .attribute(world..transcript.text().len())
+            .unwrap();
+        assert_eq!(attribution.tokens, vec![(0, 2)]);
+        assert_eq!((attribution.start, attribution.end), (50, 200));
+
+        assert_eq!(transcript.attribute(0..100), None);
+    }
+}
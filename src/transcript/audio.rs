@@ -0,0 +1,65 @@
+use super::Transcript;
+use crate::transcribe::SAMPLES_PER_CS;
+use crate::wav::write_wav;
+use std::io::{self, Write};
+
+impl Transcript {
+    /// The span of `audio` behind one segment, e.g. to play it back in a review UI or cut a
+    /// dataset into utterances.
+    ///
+    /// # Arguments
+    /// * index: The segment.
+    /// * audio: The full audio this transcript was produced from, 16 kHz mono.
+    ///
+    /// # Returns
+    /// The samples from the start to the end of the segment, clamped to `audio`, so a
+    /// segment reaching past the end of the audio gets a shorter or empty span.
+    ///
+    /// # Panics
+    /// If `index` is out of bounds.
+    pub fn segment_audio<'a>(&self, index: usize, audio: &'a [f32]) -> &'a [f32] {
+        let segment = &self.segments[index];
+        let (start, end) = (segment.start, segment.end.max(segment.start));
+        let from = (start.max(0) as usize * SAMPLES_PER_CS).min(audio.len());
+        let to = (end.max(0) as usize * SAMPLES_PER_CS).clamp(from, audio.len());
+        &audio[from..to]
+    }
+
+    /// Write the [`Self::segment_audio`] of one segment to `writer` as a 16 bit PCM mono WAV
+    /// file.
+    ///
+    /// # Panics
+    /// If `index` is out of bounds.
+    pub fn write_segment_wav<W: Write>(
+        &self,
+        index: usize,
+        audio: &[f32],
+        writer: W,
+    ) -> io::Result<()> {
+        write_wav(writer, self.segment_audio(index, audio))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Transcript, TranscriptSegment};
+
+    #[test]
+    fn segments_map_to_their_samples() {
+        let audio: Vec<f32> = (0..32000).map(|i| i as f32).collect();
+        let transcript = Transcript::new(vec![
+            TranscriptSegment::new(50, 100, " one"),
+            TranscriptSegment::new(150, 300, " two"),
+            TranscriptSegment::new(400, 390, " three"),
+        ]);
+        let one = transcript.segment_audio(0, &audio);
+        assert_eq!((one.len(), one[0]), (8000, 8000.0));
+        // clamped to the end of the audio
+        assert_eq!(transcript.segment_audio(1, &audio).len(), 8000);
+        assert!(transcript.segment_audio(2, &audio).is_empty());
+
+        let mut wav = Vec::new();
+        transcript.write_segment_wav(0, &audio, &mut wav).unwrap();
+        assert_eq!(wav.len(), 44 + 8000 * 2);
+    }
+}
@@ -0,0 +1,95 @@
+use crate::{TokenData, TranscriptSegment, TranscriptToken, WhisperSegment};
+
+/// How confident the model was in the text of a segment, see [`TranscriptSegment::confidence`].
+///
+/// Computed from the text tokens only, since the probabilities of timestamp and other
+/// special tokens say nothing about the words.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Confidence {
+    /// Geometric mean of the token probabilities, the exponent of the mean log probability.
+    /// Unlike the arithmetic mean, a single very unlikely token pulls it down noticeably.
+    pub mean: f32,
+    /// Probability of the least likely token, e.g. to flag a segment for review.
+    pub min: f32,
+    /// Mean log probability of the tokens, as compared against
+    /// [`crate::FullParams::set_logprob_thold`].
+    pub mean_logprob: f32,
+}
+
+impl Confidence {
+    /// The confidence of a sequence of `(probability, log probability)` pairs,
+    /// `None` if it is empty.
+    fn of(tokens: impl Iterator<Item = (f32, f32)>) -> Option<Self> {
+        let (mut n, mut logprob_sum, mut min) = (0usize, 0.0f32, f32::INFINITY);
+        for (p, plog) in tokens {
+            n += 1;
+            logprob_sum += plog;
+            min = min.min(p);
+        }
+        (n > 0).then(|| {
+            let mean_logprob = logprob_sum / n as f32;
+            Self {
+                mean: mean_logprob.exp(),
+                min,
+                mean_logprob,
+            }
+        })
+    }
+}
+
+impl TranscriptSegment {
+    /// The confidence of the text tokens of this segment, `None` if it has none.
+    pub fn confidence(&self) -> Option<Confidence> {
+        Confidence::of(
+            self.tokens
+                .iter()
+                .filter(|t| !t.special)
+                .map(|t| (t.p, t.plog)),
+        )
+    }
+}
+
+impl WhisperSegment<'_> {
+    /// The confidence of the text tokens of this segment, `None` if it has none.
+    /// See [`TranscriptSegment::confidence`].
+    pub fn confidence(&self) -> Option<Confidence> {
+        Confidence::of(self.text_tokens().map(|t| (t.p, t.plog)))
+    }
+}
+
+impl TranscriptToken {
+    /// The probability of this token, between 0 and 1.
+    pub fn confidence(&self) -> f32 {
+        self.p
+    }
+}
+
+impl TokenData<'_> {
+    /// The probability of this token, between 0 and 1.
+    pub fn confidence(&self) -> f32 {
+        self.p
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn confidence_ignores_special_tokens() {
+        let token = |p: f32, special| TranscriptToken {
+            p,
+            plog: p.ln(),
+            special,
+            ..Default::default()
+        };
+        let mut segment = TranscriptSegment::new(0, 100, " hi there");
+        assert_eq!(segment.confidence(), None);
+
+        segment.tokens = vec![token(0.01, true), token(0.9, false), token(0.4, false)];
+        let confidence = segment.confidence().unwrap();
+        assert_eq!(confidence.min, 0.4);
+        assert!((confidence.mean - 0.6).abs() < 1e-6);
+        assert!((confidence.mean_logprob - 0.36f32.ln() / 2.0).abs() < 1e-6);
+    }
+}
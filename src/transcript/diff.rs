@@ -0,0 +1,217 @@
+use super::words::{normalize, words, Word};
+use super::Transcript;
+use std::fmt;
+
+/// A word of one of the transcripts in a [`TranscriptDiff`], with its timing in centiseconds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffWord {
+    pub text: String,
+    pub start: i64,
+    pub end: i64,
+}
+
+/// A difference between two transcripts, see [`Transcript::diff`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WordChange {
+    /// A word only the other transcript has.
+    Inserted(DiffWord),
+    /// A word only this transcript has.
+    Deleted(DiffWord),
+    /// A word this transcript has where the other has a different one.
+    Substituted { before: DiffWord, after: DiffWord },
+}
+
+impl WordChange {
+    /// Start of the change, in centiseconds.
+    pub fn start(&self) -> i64 {
+        match self {
+            Self::Inserted(word) | Self::Deleted(word) => word.start,
+            Self::Substituted { before, .. } => before.start,
+        }
+    }
+}
+
+/// The word-level differences between two transcripts of the same audio.
+///
+/// Displays as one line per change, e.g. `12.40 ~ their -> there`, with `+` for insertions
+/// and `-` for deletions, for reviewing where two configurations disagree.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TranscriptDiff {
+    /// The changes, in order of the words they affect.
+    pub changes: Vec<WordChange>,
+    /// Number of words of this transcript, the reference for [`Self::word_error_rate`].
+    pub words: usize,
+}
+
+impl TranscriptDiff {
+    /// The number of changes relative to the number of words of this transcript,
+    /// the word error rate of the other transcript if this one is correct.
+    pub fn word_error_rate(&self) -> f32 {
+        match (self.words, self.changes.len()) {
+            (_, 0) => 0.0,
+            (0, _) => 1.0,
+            (words, changes) => changes as f32 / words as f32,
+        }
+    }
+
+    /// Whether the transcripts have the same words.
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+impl fmt::Display for TranscriptDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for change in &self.changes {
+            let start = change.start() as f64 / 100.0;
+            match change {
+                WordChange::Inserted(word) => writeln!(f, "{:.2} + {}", start, word.text)?,
+                WordChange::Deleted(word) => writeln!(f, "{:.2} - {}", start, word.text)?,
+                WordChange::Substituted { before, after } => {
+                    writeln!(f, "{:.2} ~ {} -> {}", start, before.text, after.text)?
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Transcript {
+    /// Align this transcript with `other` word by word, and list where they differ.
+    ///
+    /// Words are compared ignoring case and punctuation, the same way as
+    /// `testing::word_error_rate`, and aligned with the fewest changes. Word timings come
+    /// from token timestamps if they were enabled, and are interpolated otherwise, see
+    /// [`crate::FullParams::set_token_timestamps`].
+    ///
+    /// Memory use is linear in the number of words, time is quadratic.
+    pub fn diff(&self, other: &Transcript) -> TranscriptDiff {
+        let before = transcript_words(self);
+        let after = transcript_words(other);
+        let a: Vec<&str> = before.iter().map(|(key, _)| key.as_str()).collect();
+        let b: Vec<&str> = after.iter().map(|(key, _)| key.as_str()).collect();
+
+        let mut ops = Vec::new();
+        align(&a, &b, 0, 0, &mut ops);
+
+        let word = |(_, word): &(String, Word)| DiffWord {
+            text: word.text.clone(),
+            start: word.start,
+            end: word.end,
+        };
+        let changes = ops
+            .into_iter()
+            .filter_map(|op| match op {
+                Op::Equal(..) => None,
+                Op::Substitute(i, j) => Some(WordChange::Substituted {
+                    before: word(&before[i]),
+                    after: word(&after[j]),
+                }),
+                Op::Delete(i) => Some(WordChange::Deleted(word(&before[i]))),
+                Op::Insert(j) => Some(WordChange::Inserted(word(&after[j]))),
+            })
+            .collect();
+        TranscriptDiff {
+            changes,
+            words: before.len(),
+        }
+    }
+}
+
+/// Every word of `transcript` that isn't only punctuation, with its normalized form.
+fn transcript_words(transcript: &Transcript) -> Vec<(String, Word)> {
+    transcript
+        .iter()
+        .flat_map(words)
+        .map(|word| (normalize(&word.text), word))
+        .filter(|(key, _)| !key.is_empty())
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Equal(usize, usize),
+    Substitute(usize, usize),
+    Delete(usize),
+    Insert(usize),
+}
+
+/// Minimal edit script from `a` to `b`, by Hirschberg's algorithm.
+/// `i` and `j` are the offsets of `a` and `b` in the full sequences.
+fn align(a: &[&str], b: &[&str], i: usize, j: usize, ops: &mut Vec<Op>) {
+    match (a.len(), b.len()) {
+        (0, _) => ops.extend((0..b.len()).map(|k| Op::Insert(j + k))),
+        (_, 0) => ops.extend((0..a.len()).map(|k| Op::Delete(i + k))),
+        (1, _) => {
+            let (matched, op) = match b.iter().position(|w| *w == a[0]) {
+                Some(k) => (k, Op::Equal(i, j + k)),
+                None => (0, Op::Substitute(i, j)),
+            };
+            ops.extend((0..matched).map(|k| Op::Insert(j + k)));
+            ops.push(op);
+            ops.extend((matched + 1..b.len()).map(|k| Op::Insert(j + k)));
+        }
+        _ => {
+            let mid = a.len() / 2;
+            let forward = last_row(a[..mid].iter(), b.iter());
+            let backward = last_row(a[mid..].iter().rev(), b.iter().rev());
+            let split = (0..=b.len())
+                .min_by_key(|&k| forward[k] + backward[b.len() - k])
+                .unwrap_or(0);
+            align(&a[..mid], &b[..split], i, j, ops);
+            align(&a[mid..], &b[split..], i + mid, j + split, ops);
+        }
+    }
+}
+
+/// Last row of the Levenshtein distance table of `a` against `b`:
+/// the distance from all of `a` to each prefix of `b`.
+fn last_row<'a>(
+    a: impl Iterator<Item = &'a &'a str>,
+    b: impl Iterator<Item = &'a &'a str> + Clone,
+) -> Vec<usize> {
+    let mut row: Vec<usize> = (0..=b.clone().count()).collect();
+    for (i, x) in a.enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (k, y) in b.clone().enumerate() {
+            let substitution = diagonal + usize::from(x != y);
+            diagonal = row[k + 1];
+            row[k + 1] = substitution.min(row[k] + 1).min(row[k + 1] + 1);
+        }
+    }
+    row
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::TranscriptSegment;
+
+    #[test]
+    fn differences_are_aligned_by_word() {
+        let before = Transcript::new(vec![
+            TranscriptSegment::new(0, 400, " The cat sat on the mat."),
+            TranscriptSegment::new(400, 600, " It purred."),
+        ]);
+        let after = Transcript::new(vec![TranscriptSegment::new(
+            0,
+            600,
+            " the cat sat on a mat, it purred and slept",
+        )]);
+        let diff = before.diff(&after);
+        assert_eq!(diff.words, 8);
+        assert_eq!(diff.changes.len(), 3);
+        assert!(matches!(
+            &diff.changes[0],
+            WordChange::Substituted { before, after } if before.text == "the" && after.text == "a"
+        ));
+        assert!(matches!(&diff.changes[2], WordChange::Inserted(word) if word.text == "slept"));
+        assert_eq!(diff.word_error_rate(), 0.375);
+        assert_eq!(
+            diff.to_string(),
+            "2.44 ~ the -> a\n4.50 + and\n5.06 + slept\n"
+        );
+        assert!(before.diff(&before).is_empty());
+    }
+}
@@ -0,0 +1,242 @@
+use super::{Transcript, TranscriptSegment};
+use crate::WhisperVadSegment;
+
+/// Assumed speaking rate when a stuck segment has to be given a length from its text alone,
+/// in centiseconds per character (about 15 characters per second).
+const CS_PER_CHAR: i64 = 6;
+
+/// Repairs timestamps of long transcriptions, where whisper.cpp is prone to two failure modes:
+/// timestamps that run backwards or overlap across window boundaries, and runs of "stuck"
+/// segments that all share the same timestamp.
+///
+/// [`Self::apply`] works in three passes:
+/// 1. Timestamps are clamped to the length of the audio, and made monotonic so every segment
+///    starts no earlier than the previous one ended.
+/// 2. Runs of zero-length segments are spread over the gap up to the next segment,
+///    proportionally to the length of their text.
+/// 3. If speech regions from a VAD are known, segment boundaries within [`Self::max_snap`]
+///    of a speech boundary are moved onto it, re-anchoring timestamps that drifted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DriftCorrector {
+    audio_end: Option<i64>,
+    speech: Vec<(i64, i64)>,
+    max_snap: i64,
+}
+
+/// What [`DriftCorrector::apply`] changed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DriftReport {
+    /// Segments moved to keep timestamps monotonic or within the audio.
+    pub reordered: usize,
+    /// Zero-length segments given a length.
+    pub unstuck: usize,
+    /// Segment boundaries moved onto a speech boundary.
+    pub snapped: usize,
+}
+
+impl Default for DriftCorrector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DriftCorrector {
+    pub fn new() -> Self {
+        Self {
+            audio_end: None,
+            speech: Vec::new(),
+            max_snap: 100,
+        }
+    }
+
+    /// Set the length of the transcribed audio in centiseconds. Timestamps past it are clamped.
+    ///
+    /// Defaults to no limit.
+    pub fn audio_end(mut self, audio_end: i64) -> Self {
+        self.audio_end = Some(audio_end);
+        self
+    }
+
+    /// Set speech regions detected by a VAD, such as the output of
+    /// [`crate::WhisperVadContext::segments_from_samples`].
+    pub fn speech_segments(self, segments: impl IntoIterator<Item = WhisperVadSegment>) -> Self {
+        self.speech_spans(
+            segments
+                .into_iter()
+                .map(|s| (s.start.round() as i64, s.end.round() as i64)),
+        )
+    }
+
+    /// Set speech regions as `(start, end)` pairs in centiseconds.
+    pub fn speech_spans(mut self, spans: impl IntoIterator<Item = (i64, i64)>) -> Self {
+        self.speech = spans.into_iter().filter(|(s, e)| e > s).collect();
+        self.speech.sort_unstable();
+        self
+    }
+
+    /// Set how far in centiseconds a segment boundary may be moved to meet a speech boundary.
+    ///
+    /// Defaults to 100 (one second).
+    pub fn max_snap(mut self, max_snap: i64) -> Self {
+        self.max_snap = max_snap.max(0);
+        self
+    }
+
+    /// Correct the timestamps of `transcript` in place.
+    pub fn apply(&self, transcript: &mut Transcript) -> DriftReport {
+        let segments = &mut transcript.segments;
+        let mut report = DriftReport {
+            reordered: self.make_monotonic(segments),
+            ..Default::default()
+        };
+        report.unstuck = self.unstick(segments);
+        if !self.speech.is_empty() {
+            report.snapped = self.snap(segments);
+            // snapping both ends independently can make neighbours overlap again
+            self.make_monotonic(segments);
+        }
+        report
+    }
+
+    fn make_monotonic(&self, segments: &mut [TranscriptSegment]) -> usize {
+        let limit = self.audio_end.unwrap_or(i64::MAX);
+        let mut prev_end = 0;
+        let mut changed = 0;
+        for segment in segments {
+            let start = segment.start.clamp(prev_end.min(limit), limit);
+            let end = segment.end.clamp(start, limit);
+            if (start, end) != (segment.start, segment.end) {
+                segment.start = start;
+                segment.end = end;
+                changed += 1;
+            }
+            prev_end = end;
+        }
+        changed
+    }
+
+    fn unstick(&self, segments: &mut [TranscriptSegment]) -> usize {
+        let mut fixed = 0;
+        let mut i = 0;
+        while i < segments.len() {
+            if segments[i].duration() > 0 {
+                i += 1;
+                continue;
+            }
+            let run_end = (i..segments.len())
+                .find(|&j| segments[j].duration() > 0)
+                .unwrap_or(segments.len());
+            let run = &segments[i..run_end];
+            let chars: Vec<i64> = run
+                .iter()
+                .map(|s| s.text.trim().chars().count().max(1) as i64)
+                .collect();
+            let total_chars: i64 = chars.iter().sum();
+
+            let start = run[0].start;
+            let next_start = segments.get(run_end).map(|s| s.start);
+            let mut window_end = next_start
+                .filter(|&next| next > start)
+                .unwrap_or(start + total_chars * CS_PER_CHAR);
+            if let Some(limit) = self.audio_end {
+                window_end = window_end.min(limit);
+            }
+            if window_end <= start {
+                // nowhere to put them, e.g. stuck at the very end of the audio
+                i = run_end;
+                continue;
+            }
+
+            let window = window_end - start;
+            let mut seen = 0;
+            for (segment, n) in segments[i..run_end].iter_mut().zip(&chars) {
+                segment.start = start + window * seen / total_chars;
+                seen += n;
+                segment.end = start + window * seen / total_chars;
+            }
+            fixed += run_end - i;
+            i = run_end;
+        }
+        fixed
+    }
+
+    fn snap(&self, segments: &mut [TranscriptSegment]) -> usize {
+        let nearest = |t: i64, boundary: fn(&(i64, i64)) -> i64| {
+            self.speech
+                .iter()
+                .map(boundary)
+                .filter(|b| (b - t).abs() <= self.max_snap)
+                .min_by_key(|b| (b - t).abs())
+        };
+
+        let mut snapped = 0;
+        for segment in segments {
+            if let Some(start) = nearest(segment.start, |s| s.0) {
+                if start != segment.start && start < segment.end {
+                    segment.start = start;
+                    snapped += 1;
+                }
+            }
+            if let Some(end) = nearest(segment.end, |s| s.1) {
+                if end != segment.end && end > segment.start {
+                    segment.end = end;
+                    snapped += 1;
+                }
+            }
+        }
+        snapped
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn times(transcript: &Transcript) -> Vec<(i64, i64)> {
+        transcript.iter().map(|s| (s.start, s.end)).collect()
+    }
+
+    #[test]
+    fn backwards_and_stuck_timestamps_are_repaired() {
+        let mut transcript = Transcript::new(vec![
+            TranscriptSegment::new(0, 500, " first"),
+            TranscriptSegment::new(400, 900, " overlaps"),
+            TranscriptSegment::new(900, 900, " ab"),
+            TranscriptSegment::new(900, 900, " abcdef"),
+            TranscriptSegment::new(1700, 2000, " last"),
+            TranscriptSegment::new(2500, 2600, " past the end"),
+        ]);
+
+        let report = DriftCorrector::new().audio_end(2200).apply(&mut transcript);
+
+        assert_eq!(
+            times(&transcript),
+            vec![
+                (0, 500),
+                (500, 900),
+                (900, 1100),
+                (1100, 1700),
+                (1700, 2000),
+                (2200, 2200)
+            ]
+        );
+        assert_eq!(report.reordered, 2);
+        assert_eq!(report.unstuck, 2);
+    }
+
+    #[test]
+    fn boundaries_snap_to_nearby_speech() {
+        let mut transcript = Transcript::new(vec![
+            TranscriptSegment::new(30, 480, " one"),
+            TranscriptSegment::new(600, 1000, " two"),
+        ]);
+
+        let report = DriftCorrector::new()
+            .speech_spans([(0, 450), (800, 1100)])
+            .max_snap(50)
+            .apply(&mut transcript);
+
+        assert_eq!(times(&transcript), vec![(0, 450), (600, 1000)]);
+        assert_eq!(report.snapped, 2);
+    }
+}
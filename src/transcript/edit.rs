@@ -0,0 +1,238 @@
+use super::{Transcript, TranscriptSegment, TranscriptToken};
+use std::fmt;
+
+/// Why an edit of a [`Transcript`] was refused. The transcript is left unchanged.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TranscriptEditError {
+    /// There is no segment at `index`.
+    OutOfBounds { index: usize, len: usize },
+    /// The segments to merge have different speakers.
+    DifferentSpeakers,
+    /// The split position is not between two words of the segment text: it must be a byte offset
+    /// inside the text at which a word starts, with the whitespace before it.
+    NotAWordBoundary { at: usize },
+}
+
+impl fmt::Display for TranscriptEditError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::OutOfBounds { index, len } => write!(
+                f,
+                "no segment at index {} in a transcript of {} segments",
+                index, len
+            ),
+            Self::DifferentSpeakers => write!(f, "segments of different speakers can't be merged"),
+            Self::NotAWordBoundary { at } => {
+                write!(f, "byte {} is not at the start of a word", at)
+            }
+        }
+    }
+}
+
+impl std::error::Error for TranscriptEditError {}
+
+impl Transcript {
+    /// Merge the segment at `index` with the one after it.
+    ///
+    /// The merged segment spans both, with their text and tokens joined. It counts as speech if
+    /// either did, so its no-speech probability is the lower of the two.
+    pub fn merge_with_next(&mut self, index: usize) -> Result<(), TranscriptEditError> {
+        self.check_index(index + 1)?;
+        if self.segments[index].speaker != self.segments[index + 1].speaker {
+            return Err(TranscriptEditError::DifferentSpeakers);
+        }
+
+        let next = self.segments.remove(index + 1);
+        let segment = &mut self.segments[index];
+        segment.start = segment.start.min(next.start);
+        segment.end = segment.end.max(next.end);
+        segment.text.push_str(&next.text);
+        segment.no_speech_probability = segment
+            .no_speech_probability
+            .min(next.no_speech_probability);
+        segment.speaker_turn_next = next.speaker_turn_next;
+        segment.tokens.extend(next.tokens);
+        Ok(())
+    }
+
+    /// Split the segment at `index` in two, where its text reaches byte `at`.
+    ///
+    /// The second segment starts at the first token after the split if token timestamps are known,
+    /// otherwise at a time proportional to the length of the text before it.
+    /// Tokens are divided between the two if they make up the text; if the text was edited
+    /// they no longer do, and are dropped.
+    pub fn split_segment(&mut self, index: usize, at: usize) -> Result<(), TranscriptEditError> {
+        self.check_index(index)?;
+        let segment = &self.segments[index];
+        let text = &segment.text;
+        let at_word = at > 0
+            && at < text.len()
+            && text.is_char_boundary(at)
+            && text[at..].starts_with(char::is_whitespace)
+            && !text[..at].ends_with(char::is_whitespace)
+            && !text[..at].trim().is_empty()
+            && !text[at..].trim().is_empty();
+        if !at_word {
+            return Err(TranscriptEditError::NotAWordBoundary { at });
+        }
+
+        let (first_tokens, second_tokens) = match split_tokens(segment, at) {
+            Some((first, second)) => (first, second),
+            None => (Vec::new(), Vec::new()),
+        };
+        let (start, end) = (segment.start, segment.end.max(segment.start));
+        let split_time = second_tokens
+            .iter()
+            .find(|t| !t.special && t.has_timestamps())
+            .map(|t| t.t0)
+            .unwrap_or_else(|| {
+                let chars_before = text[..at].chars().count() as i64;
+                let chars = text.chars().count() as i64;
+                start + (end - start) * chars_before / chars
+            })
+            .clamp(start, end);
+
+        let mut first = segment.clone();
+        first.end = split_time;
+        first.text = text[..at].to_string();
+        first.speaker_turn_next = false;
+        first.tokens = first_tokens;
+
+        let mut second = segment.clone();
+        second.start = split_time;
+        second.text = text[at..].to_string();
+        second.tokens = second_tokens;
+
+        self.segments.splice(index..=index, [first, second]);
+        Ok(())
+    }
+
+    /// Replace the text of the segment at `index`, keeping its timing.
+    ///
+    /// Tokens are kept only if they still spell out the text, so token-level data never
+    /// contradicts it.
+    pub fn set_segment_text(
+        &mut self,
+        index: usize,
+        text: impl Into<String>,
+    ) -> Result<(), TranscriptEditError> {
+        self.check_index(index)?;
+        let segment = &mut self.segments[index];
+        segment.text = text.into();
+        if token_text(&segment.tokens) != segment.text {
+            segment.tokens.clear();
+        }
+        Ok(())
+    }
+
+    /// Put segments back in order of their start time, and shorten any segment that overlaps
+    /// the next, so indices and subtitle numbering follow the timeline again after edits.
+    pub fn normalize(&mut self) {
+        self.segments.sort_by_key(|s| s.start);
+        for i in 1..self.segments.len() {
+            let next_start = self.segments[i].start;
+            let previous = &mut self.segments[i - 1];
+            previous.end = previous
+                .end
+                .clamp(previous.start, next_start.max(previous.start));
+        }
+    }
+
+    fn check_index(&self, index: usize) -> Result<(), TranscriptEditError> {
+        if index < self.segments.len() {
+            Ok(())
+        } else {
+            Err(TranscriptEditError::OutOfBounds {
+                index,
+                len: self.segments.len(),
+            })
+        }
+    }
+}
+
+/// The text spelled out by the non-special tokens.
+pub(super) fn token_text(tokens: &[TranscriptToken]) -> String {
+    tokens
+        .iter()
+        .filter(|t| !t.special)
+        .map(|t| t.text.as_str())
+        .collect()
+}
+
+/// Divide the tokens of `segment` where its text reaches byte `at`,
+/// or `None` if they don't spell out the text or no token ends there.
+fn split_tokens(
+    segment: &TranscriptSegment,
+    at: usize,
+) -> Option<(Vec<TranscriptToken>, Vec<TranscriptToken>)> {
+    if token_text(&segment.tokens) != segment.text {
+        return None;
+    }
+    let mut len = 0;
+    let mut split = None;
+    for (i, token) in segment.tokens.iter().enumerate() {
+        if len == at && !token.special {
+            split = Some(i);
+            break;
+        }
+        if !token.special {
+            len += token.text.len();
+        }
+    }
+    let split = split?;
+    Some((
+        segment.tokens[..split].to_vec(),
+        segment.tokens[split..].to_vec(),
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn token(text: &str, t0: i64, t1: i64) -> TranscriptToken {
+        TranscriptToken {
+            text: text.to_string(),
+            t0,
+            t1,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn segments_are_split_and_merged() {
+        let mut segment = TranscriptSegment::new(0, 300, " hello there world");
+        segment.tokens = vec![
+            token(" hello", 0, 80),
+            token(" the", 120, 160),
+            token("re", 160, 200),
+            token(" world", 210, 300),
+        ];
+        let original = Transcript::new(vec![segment]);
+        let mut transcript = original.clone();
+
+        assert_eq!(
+            transcript.split_segment(0, 9),
+            Err(TranscriptEditError::NotAWordBoundary { at: 9 })
+        );
+        transcript.split_segment(0, 6).unwrap();
+        assert_eq!(transcript.segments[0].text, " hello");
+        assert_eq!(transcript.segments[0].end, 120);
+        assert_eq!(transcript.segments[1].start, 120);
+        assert_eq!(transcript.segments[1].tokens.len(), 3);
+
+        transcript.merge_with_next(0).unwrap();
+        assert_eq!(transcript, original);
+        assert_eq!(
+            transcript.merge_with_next(0),
+            Err(TranscriptEditError::OutOfBounds { index: 1, len: 1 })
+        );
+
+        transcript
+            .set_segment_text(0, " hello their world")
+            .unwrap();
+        assert!(transcript.segments[0].tokens.is_empty());
+        transcript.split_segment(0, 12).unwrap();
+        assert_eq!(transcript.segments[1].start, 200);
+    }
+}
@@ -0,0 +1,264 @@
+mod attribution;
+mod audio;
+mod confidence;
+mod diff;
+mod drift;
+mod edit;
+mod retranscribe;
+#[cfg(feature = "output-formats")]
+mod store;
+mod timeline;
+pub(crate) mod words;
+
+pub use attribution::TextAttribution;
+pub use confidence::Confidence;
+pub use diff::{DiffWord, TranscriptDiff, WordChange};
+pub use drift::{DriftCorrector, DriftReport};
+pub use edit::TranscriptEditError;
+#[cfg(feature = "output-formats")]
+pub use store::TranscriptStore;
+pub use timeline::EditList;
+
+use crate::{
+    SegmentCallbackData, TokenData, WhisperError, WhisperSegment, WhisperState, WhisperToken,
+    WhisperTokenId,
+};
+
+/// An owned copy of the result of a transcription run.
+///
+/// Unlike [`WhisperSegment`], this does not borrow the [`WhisperState`] it came from,
+/// so it can be stored, sent between threads, or compared after the state has been reused.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Transcript {
+    pub segments: Vec<TranscriptSegment>,
+}
+
+/// A single owned segment of a [`Transcript`].
+///
+/// Timestamps are in centiseconds (10s of milliseconds), the same unit used by [`WhisperSegment`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TranscriptSegment {
+    pub start: i64,
+    pub end: i64,
+    pub text: String,
+    pub no_speech_probability: f32,
+    pub speaker_turn_next: bool,
+    /// Who is speaking, if known, e.g. from the channel of a call recording.
+    /// Whisper itself never sets this.
+    pub speaker: Option<String>,
+    /// The tokens making up this segment, including special tokens.
+    pub tokens: Vec<TranscriptToken>,
+}
+
+/// A single owned token of a [`TranscriptSegment`].
+///
+/// The default token has no timestamps, with all of them set to -1.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TranscriptToken {
+    pub id: WhisperTokenId,
+    /// The text of this token. Tokens that split a multi-byte character contain the replacement character.
+    pub text: String,
+    /// The raw bytes of this token, which may start or end inside a multi-byte character.
+    /// Empty if unknown, in which case [`Self::text`] stands in for them.
+    pub bytes: Vec<u8>,
+    /// Probability of this token.
+    pub p: f32,
+    /// Log probability of this token.
+    pub plog: f32,
+    /// Start time in centiseconds. Only set if token timestamps were enabled, otherwise -1.
+    pub t0: i64,
+    /// End time in centiseconds. Only set if token timestamps were enabled, otherwise -1.
+    pub t1: i64,
+    /// Time in centiseconds from DTW alignment. Only set if DTW was enabled, otherwise -1.
+    pub t_dtw: i64,
+    /// Whether this is a special token rather than text, see [`WhisperToken::is_special`].
+    pub special: bool,
+}
+
+impl Transcript {
+    pub fn new(segments: Vec<TranscriptSegment>) -> Self {
+        Self { segments }
+    }
+
+    /// Number of segments in this transcript.
+    pub fn len(&self) -> usize {
+        self.segments.len()
+    }
+
+    /// Whether this transcript has no segments.
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    /// Iterate over the segments of this transcript.
+    pub fn iter(&self) -> std::slice::Iter<'_, TranscriptSegment> {
+        self.segments.iter()
+    }
+
+    /// The text of all segments concatenated together.
+    ///
+    /// Whisper usually emits a leading space on each segment, so no separator is added.
+    pub fn text(&self) -> String {
+        self.segments.iter().map(|s| s.text.as_str()).collect()
+    }
+
+    /// Start time of the first segment, in centiseconds.
+    pub fn start(&self) -> Option<i64> {
+        self.segments.first().map(|s| s.start)
+    }
+
+    /// End time of the last segment, in centiseconds.
+    pub fn end(&self) -> Option<i64> {
+        self.segments.last().map(|s| s.end)
+    }
+
+    /// Remove segments whose no-speech probability is above `threshold`, which are almost always
+    /// hallucinations over silence. See [`crate::SilenceSuppressor`] for a more thorough check.
+    ///
+    /// # Returns
+    /// The number of segments removed.
+    pub fn retain_speech(&mut self, threshold: f32) -> usize {
+        let len = self.segments.len();
+        self.segments
+            .retain(|s| s.no_speech_probability <= threshold);
+        len - self.segments.len()
+    }
+}
+
+impl<'a> IntoIterator for &'a Transcript {
+    type Item = &'a TranscriptSegment;
+    type IntoIter = std::slice::Iter<'a, TranscriptSegment>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.segments.iter()
+    }
+}
+
+impl FromIterator<TranscriptSegment> for Transcript {
+    fn from_iter<T: IntoIterator<Item = TranscriptSegment>>(iter: T) -> Self {
+        Self::new(iter.into_iter().collect())
+    }
+}
+
+impl TranscriptSegment {
+    /// Create a segment from its text and start/end time in centiseconds.
+    pub fn new(start: i64, end: i64, text: impl Into<String>) -> Self {
+        Self {
+            start,
+            end,
+            text: text.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Length of this segment in centiseconds.
+    pub fn duration(&self) -> i64 {
+        self.end - self.start
+    }
+}
+
+impl Default for TranscriptToken {
+    fn default() -> Self {
+        Self {
+            id: 0,
+            text: String::new(),
+            bytes: Vec::new(),
+            p: 0.0,
+            plog: 0.0,
+            t0: -1,
+            t1: -1,
+            t_dtw: -1,
+            special: false,
+        }
+    }
+}
+
+impl TranscriptToken {
+    /// Whether [`Self::t0`] and [`Self::t1`] hold real timestamps.
+    pub fn has_timestamps(&self) -> bool {
+        self.t0 >= 0 && self.t1 >= self.t0
+    }
+
+    /// [`Self::bytes`], or the bytes of [`Self::text`] if they are unknown.
+    pub(crate) fn raw_bytes(&self) -> &[u8] {
+        if self.bytes.is_empty() {
+            self.text.as_bytes()
+        } else {
+            &self.bytes
+        }
+    }
+}
+
+impl From<TokenData<'_>> for TranscriptToken {
+    fn from(data: TokenData<'_>) -> Self {
+        Self {
+            id: data.id,
+            text: data.text.into_owned(),
+            bytes: Vec::new(),
+            p: data.p,
+            plog: data.plog,
+            t0: data.t0,
+            t1: data.t1,
+            t_dtw: data.t_dtw,
+            special: data.special,
+        }
+    }
+}
+
+impl TryFrom<&WhisperToken<'_, '_>> for TranscriptToken {
+    type Error = WhisperError;
+
+    fn try_from(token: &WhisperToken<'_, '_>) -> Result<Self, Self::Error> {
+        let data = token.token_data();
+        Ok(Self {
+            id: data.id,
+            text: token.to_str_lossy()?.into_owned(),
+            bytes: token.to_bytes()?.to_vec(),
+            p: data.p,
+            plog: data.plog,
+            t0: data.t0,
+            t1: data.t1,
+            t_dtw: data.t_dtw,
+            special: token.is_special(),
+        })
+    }
+}
+
+impl TryFrom<&WhisperSegment<'_>> for TranscriptSegment {
+    type Error = WhisperError;
+
+    fn try_from(segment: &WhisperSegment<'_>) -> Result<Self, Self::Error> {
+        let tokens = (0..segment.n_tokens())
+            .filter_map(|i| segment.get_token(i))
+            .map(|token| TranscriptToken::try_from(&token))
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            start: segment.start_timestamp(),
+            end: segment.end_timestamp(),
+            text: segment.to_str_lossy()?.into_owned(),
+            no_speech_probability: segment.no_speech_probability(),
+            speaker_turn_next: segment.next_segment_speaker_turn(),
+            speaker: None,
+            tokens,
+        })
+    }
+}
+
+/// Only the text and timestamps are known from a segment callback, everything else is left at its default.
+impl From<SegmentCallbackData> for TranscriptSegment {
+    fn from(data: SegmentCallbackData) -> Self {
+        Self::new(data.start_timestamp, data.end_timestamp, data.text)
+    }
+}
+
+impl TryFrom<&WhisperState> for Transcript {
+    type Error = WhisperError;
+
+    fn try_from(state: &WhisperState) -> Result<Self, Self::Error> {
+        state
+            .as_iter()
+            .map(|segment| TranscriptSegment::try_from(&segment))
+            .collect::<Result<Vec<_>, _>>()
+            .map(Self::new)
+    }
+}
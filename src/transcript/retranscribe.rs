@@ -0,0 +1,92 @@
+use super::Transcript;
+use crate::transcribe::{ms_to_samples, MIN_INPUT_MS};
+use crate::{FullParams, Transcribe};
+use std::ops::Range;
+
+impl Transcript {
+    /// Re-run the audio span of one segment with different parameters (a bigger beam, another
+    /// language, a prompt, ...) and replace the segment with the result.
+    ///
+    /// # Arguments
+    /// * backend: Used to run the new transcription.
+    /// * index: The segment to replace.
+    /// * audio: The full audio this transcript was produced from, 16 kHz mono.
+    /// * params: Parameters for the new run.
+    ///
+    /// # Returns
+    /// The indices of the segments that replaced the old one. Their timestamps are relative to the
+    /// start of `audio` and kept within the old segment. The range is empty if nothing was recognised,
+    /// in which case the segment is removed.
+    ///
+    /// # Panics
+    /// If `index` is out of bounds.
+    pub fn retranscribe_segment<T: Transcribe>(
+        &mut self,
+        backend: &mut T,
+        index: usize,
+        audio: &[f32],
+        params: FullParams<'_, '_>,
+    ) -> Result<Range<usize>, T::Error> {
+        let old = &self.segments[index];
+        let (start, end) = (old.start, old.end.max(old.start));
+
+        let mut span = self.segment_audio(index, audio).to_vec();
+        let min_len = ms_to_samples(MIN_INPUT_MS);
+        if span.len() < min_len {
+            span.resize(min_len, 0.0);
+        }
+
+        let replacement: Vec<_> = backend
+            .transcribe(params, &span)?
+            .segments
+            .into_iter()
+            .map(|mut segment| {
+                segment.start = (segment.start + start).clamp(start, end);
+                segment.end = (segment.end + start).clamp(segment.start, end);
+                segment
+            })
+            .collect();
+
+        let range = index..index + replacement.len();
+        self.segments.splice(index..=index, replacement);
+        Ok(range)
+    }
+}
+
+#[cfg(all(test, feature = "test-stub"))]
+mod test {
+    use crate::stub::StubContext;
+    use crate::{FullParams, SamplingStrategy, Transcript, TranscriptSegment};
+
+    #[test]
+    fn result_is_spliced_in_place_of_the_segment() {
+        let mut transcript = Transcript::new(vec![
+            TranscriptSegment::new(0, 200, " one"),
+            TranscriptSegment::new(200, 300, " tow"),
+            TranscriptSegment::new(300, 500, " three"),
+        ]);
+        let ctx = StubContext::new(Transcript::new(vec![
+            TranscriptSegment::new(0, 50, " two"),
+            TranscriptSegment::new(50, 150, " too"),
+        ]));
+        let params = FullParams::new(SamplingStrategy::BeamSearch {
+            beam_size: 5,
+            patience: -1.0,
+        });
+
+        let range = transcript
+            .retranscribe_segment(&mut ctx.clone(), 1, &[0.0; 80000], params)
+            .unwrap();
+
+        assert_eq!(range, 1..3);
+        assert_eq!(transcript.text(), " one two too three");
+        assert_eq!(
+            (transcript.segments[1].start, transcript.segments[1].end),
+            (200, 250)
+        );
+        assert_eq!(
+            (transcript.segments[2].start, transcript.segments[2].end),
+            (250, 300)
+        );
+    }
+}
@@ -0,0 +1,162 @@
+use super::TranscriptSegment;
+use crate::output::JsonlSink;
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// A transcript of unbounded length with bounded memory use, for live captioning that runs for days.
+///
+/// Every segment pushed is appended to a JSON Lines file, see [`crate::output::JsonlSink`], and only the most
+/// recent segments are kept in memory, e.g. for display or as a prompt.
+/// The file is only ever appended to, so it can be tailed while in use.
+///
+/// # Examples
+/// ```no_run
+/// # use whisper_rs::{StreamingTranscribe, TranscriptStore};
+/// # fn run(stream: &mut impl StreamingTranscribe, audio: &[f32]) {
+/// let mut store = TranscriptStore::open("captions.jsonl").unwrap().window_segments(50);
+/// store.extend(stream.push_audio(audio).unwrap()).unwrap();
+/// for segment in store.recent() {
+///     println!("{}", segment.text);
+/// }
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct TranscriptStore {
+    path: PathBuf,
+    sink: JsonlSink<File>,
+    recent: VecDeque<TranscriptSegment>,
+    window_segments: usize,
+    window_duration: Option<i64>,
+    written: u64,
+}
+
+impl TranscriptStore {
+    /// Open `path` for appending, creating it if it doesn't exist.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        Ok(Self {
+            path,
+            sink: JsonlSink::new(file),
+            recent: VecDeque::new(),
+            window_segments: 100,
+            window_duration: None,
+            written: 0,
+        })
+    }
+
+    /// Set the most segments to keep in memory.
+    ///
+    /// Defaults to 100.
+    pub fn window_segments(mut self, segments: usize) -> Self {
+        self.window_segments = segments;
+        self.trim();
+        self
+    }
+
+    /// Also drop segments from memory that ended more than `duration` centiseconds before the
+    /// end of the newest one.
+    ///
+    /// Defaults to no limit.
+    pub fn window_duration(mut self, duration: i64) -> Self {
+        self.window_duration = Some(duration);
+        self.trim();
+        self
+    }
+
+    /// Append a finalized segment to the file and the in-memory window.
+    ///
+    /// Each segment is written out before returning, so a crash loses nothing already pushed.
+    pub fn push(&mut self, segment: TranscriptSegment) -> io::Result<()> {
+        self.sink.write_segment(&segment)?;
+        self.written += 1;
+
+        self.recent.push_back(segment);
+        self.trim();
+        Ok(())
+    }
+
+    /// [`Self::push`] every segment of `segments`, such as the output of
+    /// [`crate::StreamingTranscribe::push_audio`].
+    pub fn extend(
+        &mut self,
+        segments: impl IntoIterator<Item = TranscriptSegment>,
+    ) -> io::Result<()> {
+        segments
+            .into_iter()
+            .try_for_each(|segment| self.push(segment))
+    }
+
+    /// The segments still in memory, oldest first.
+    pub fn recent(&self) -> impl ExactSizeIterator<Item = &TranscriptSegment> + '_ {
+        self.recent.iter()
+    }
+
+    /// The text of the segments still in memory.
+    pub fn recent_text(&self) -> String {
+        self.recent.iter().map(|s| s.text.as_str()).collect()
+    }
+
+    /// Number of segments written since the store was opened, including those no longer in memory.
+    pub fn written(&self) -> u64 {
+        self.written
+    }
+
+    /// The file segments are appended to.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Wait until everything written has reached the disk.
+    pub fn sync(&self) -> io::Result<()> {
+        self.sink.get_ref().sync_data()
+    }
+
+    fn trim(&mut self) {
+        while self.recent.len() > self.window_segments {
+            self.recent.pop_front();
+        }
+        if let (Some(duration), Some(newest)) = (self.window_duration, self.recent.back()) {
+            let cutoff = newest.end - duration;
+            while self.recent.front().is_some_and(|s| s.end < cutoff) {
+                self.recent.pop_front();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn old_segments_only_remain_on_disk() {
+        let path =
+            std::env::temp_dir().join(format!("whisper-rs-store-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let mut store = TranscriptStore::open(&path)
+            .unwrap()
+            .window_segments(3)
+            .window_duration(1000);
+        store
+            .extend(
+                (0..5).map(|i| TranscriptSegment::new(i * 100, i * 100 + 100, format!(" {}", i))),
+            )
+            .unwrap();
+        assert_eq!(store.recent_text(), " 2 3 4");
+
+        store
+            .push(TranscriptSegment::new(2000, 2100, " 5"))
+            .unwrap();
+        assert_eq!(store.recent_text(), " 5");
+        assert_eq!(store.written(), 6);
+
+        let lines = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(lines.lines().count(), 6);
+        assert!(lines.starts_with("{\"start\":0,\"end\":100,\"text\":\" 0\""));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,187 @@
+use super::edit::token_text;
+use super::{Transcript, TranscriptSegment, TranscriptToken};
+
+/// Cuts and insertions applied to the audio of a transcript, such as the edit decision list of a
+/// podcast editor, to carry the transcript over to the edited audio with [`Transcript::apply_edits`].
+///
+/// All times are in centiseconds of the original, unedited audio, regardless of the order in
+/// which the edits are added: a cut removes that stretch of the original, and an insertion adds
+/// new material (an intro, an ad) before what was at that time in the original.
+///
+/// # Examples
+/// ```
+/// # use whisper_rs::{EditList, Transcript, TranscriptSegment};
+/// let mut transcript = Transcript::new(vec![
+///     TranscriptSegment::new(0, 200, " Um, so."),
+///     TranscriptSegment::new(200, 500, " Welcome to the show."),
+/// ]);
+/// let edits = EditList::new().cut(0, 200).insert(0, 1000);
+/// transcript.apply_edits(&edits);
+/// assert_eq!(transcript.segments.len(), 1);
+/// assert_eq!((transcript.segments[0].start, transcript.segments[0].end), (1000, 1300));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EditList {
+    /// Sorted, non-overlapping `(start, end)` ranges.
+    cuts: Vec<(i64, i64)>,
+    /// `(at, duration)`, sorted by position.
+    inserts: Vec<(i64, i64)>,
+}
+
+impl EditList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Remove the audio from `start` to `end`. Overlapping cuts are merged.
+    pub fn cut(mut self, start: i64, end: i64) -> Self {
+        if end <= start {
+            return self;
+        }
+        let (mut start, mut end) = (start, end);
+        self.cuts.retain(|&(s, e)| {
+            let overlaps = s <= end && e >= start;
+            if overlaps {
+                start = start.min(s);
+                end = end.max(e);
+            }
+            !overlaps
+        });
+        let i = self.cuts.partition_point(|&(s, _)| s < start);
+        self.cuts.insert(i, (start, end));
+        self
+    }
+
+    /// Insert `duration` of new audio before the original audio at `at`.
+    pub fn insert(mut self, at: i64, duration: i64) -> Self {
+        if duration > 0 {
+            let i = self.inserts.partition_point(|&(a, _)| a <= at);
+            self.inserts.insert(i, (at, duration));
+        }
+        self
+    }
+
+    /// Where the original time `t` ends up in the edited audio. Times within a cut move to
+    /// where the cut was made; times at an insertion move after the inserted audio.
+    pub fn map_time(&self, t: i64) -> i64 {
+        self.map(t, true)
+    }
+
+    /// Map the start (`start` true) or end of something; an end at an insertion stays before it.
+    fn map(&self, t: i64, start: bool) -> i64 {
+        let mut shift = 0;
+        for &(s, e) in &self.cuts {
+            if t >= e {
+                shift -= e - s;
+            } else if t > s {
+                shift -= t - s;
+            }
+        }
+        for &(at, duration) in &self.inserts {
+            if at < t || (start && at == t) {
+                shift += duration;
+            }
+        }
+        t + shift
+    }
+
+    /// Whether all of `start..end` is cut. Zero-length ranges count if they lie inside a cut.
+    fn is_cut(&self, start: i64, end: i64) -> bool {
+        self.cuts
+            .iter()
+            .any(|&(s, e)| s <= start && end <= e && (start < end || (s < start && start < e)))
+    }
+}
+
+impl Transcript {
+    /// Move every segment and token timestamp onto the timeline of the audio after `edits`.
+    ///
+    /// Segments that lie entirely within a cut are removed, and segments cut in part are shortened.
+    /// With token timestamps, tokens within a cut are removed too, and the segment text is
+    /// rebuilt from the remaining tokens if they spelled it out before.
+    ///
+    /// # Returns
+    /// The number of segments removed.
+    pub fn apply_edits(&mut self, edits: &EditList) -> usize {
+        let len = self.segments.len();
+        self.segments
+            .retain(|segment| !edits.is_cut(segment.start, segment.end.max(segment.start)));
+        for segment in &mut self.segments {
+            remap_segment(segment, edits);
+        }
+        len - self.segments.len()
+    }
+}
+
+fn remap_segment(segment: &mut TranscriptSegment, edits: &EditList) {
+    let spelled_out = token_text(&segment.tokens) == segment.text;
+    let tokens = segment.tokens.len();
+    segment.tokens.retain(|token| {
+        token.special || !token.has_timestamps() || !edits.is_cut(token.t0, token.t1)
+    });
+    for token in &mut segment.tokens {
+        remap_token(token, edits);
+    }
+    if spelled_out && segment.tokens.len() != tokens {
+        segment.text = token_text(&segment.tokens);
+    }
+
+    segment.start = edits.map(segment.start, true);
+    segment.end = edits.map(segment.end, false).max(segment.start);
+}
+
+fn remap_token(token: &mut TranscriptToken, edits: &EditList) {
+    if token.t0 >= 0 {
+        token.t0 = edits.map(token.t0, true);
+    }
+    if token.t1 >= 0 {
+        token.t1 = edits.map(token.t1, false).max(token.t0);
+    }
+    if token.t_dtw >= 0 {
+        token.t_dtw = edits.map(token.t_dtw, true);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn token(text: &str, t0: i64, t1: i64) -> TranscriptToken {
+        TranscriptToken {
+            text: text.to_string(),
+            t0,
+            t1,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn cuts_remove_words_and_shift_later_ones() {
+        let mut segment = TranscriptSegment::new(0, 300, " So um yes");
+        segment.tokens = vec![
+            token(" So", 0, 100),
+            token(" um", 100, 200),
+            token(" yes", 200, 300),
+        ];
+        let mut transcript =
+            Transcript::new(vec![segment, TranscriptSegment::new(400, 500, " No")]);
+
+        let edits = EditList::new().cut(100, 200).insert(400, 50);
+        assert_eq!(transcript.apply_edits(&edits), 0);
+
+        let first = &transcript.segments[0];
+        assert_eq!((first.start, first.end), (0, 200));
+        assert_eq!(first.text, " So yes");
+        assert_eq!((first.tokens[1].t0, first.tokens[1].t1), (100, 200));
+        let second = &transcript.segments[1];
+        assert_eq!((second.start, second.end), (350, 450));
+    }
+
+    #[test]
+    fn overlapping_cuts_are_merged() {
+        let edits = EditList::new().cut(100, 200).cut(150, 300).cut(500, 600);
+        assert_eq!(edits.cuts, [(100, 300), (500, 600)]);
+        assert_eq!(edits.map_time(250), 100);
+        assert_eq!(edits.map_time(700), 400);
+    }
+}
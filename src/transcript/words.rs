@@ -0,0 +1,153 @@
+use super::TranscriptSegment;
+
+/// A word and its timing, in centiseconds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Word {
+    pub(crate) text: String,
+    pub(crate) start: i64,
+    pub(crate) end: i64,
+}
+
+/// Split a segment into words. A word starts at every token beginning with a space.
+///
+/// Word times come from DTW if every token has a DTW timestamp, then from token timestamps,
+/// and are interpolated otherwise.
+pub(crate) fn words(segment: &TranscriptSegment) -> Vec<Word> {
+    let text_tokens: Vec<_> = segment.tokens.iter().filter(|t| !t.special).collect();
+    if text_tokens.is_empty() {
+        let words: Vec<&str> = segment.text.split_whitespace().collect();
+        return interpolate(segment, words.into_iter().map(str::to_owned).collect());
+    }
+
+    // (bytes, start, end, DTW time) per word, with times of its first and last token. The bytes
+    // are only decoded once the word is complete, as tokens can split a multi-byte character
+    let mut grouped: Vec<(Vec<u8>, i64, i64, i64)> = Vec::new();
+    for token in &text_tokens {
+        let bytes = token.raw_bytes();
+        match grouped.last_mut() {
+            Some(word) if !bytes.starts_with(b" ") => {
+                word.0.extend_from_slice(bytes);
+                word.2 = token.t1;
+            }
+            _ => grouped.push((bytes.to_vec(), token.t0, token.t1, token.t_dtw)),
+        }
+    }
+    let mut grouped: Vec<(String, i64, i64, i64)> = grouped
+        .into_iter()
+        .map(|(bytes, start, end, dtw)| {
+            let text = String::from_utf8_lossy(&bytes).into_owned();
+            (text, start, end, dtw)
+        })
+        .collect();
+    grouped.retain(|(text, ..)| !text.trim().is_empty());
+
+    let (segment_start, segment_end) = (segment.start, segment.end.max(segment.start));
+    if text_tokens.iter().all(|t| t.t_dtw >= 0) {
+        // DTW gives the time each token is spoken at; a word lasts until the next one starts
+        let starts: Vec<i64> = grouped
+            .iter()
+            .map(|word| word.3.clamp(segment_start, segment_end))
+            .collect();
+        grouped
+            .into_iter()
+            .enumerate()
+            .map(|(i, (text, ..))| Word {
+                text: text.trim().to_owned(),
+                start: starts[i],
+                end: starts
+                    .get(i + 1)
+                    .copied()
+                    .unwrap_or(segment_end)
+                    .max(starts[i]),
+            })
+            .collect()
+    } else if text_tokens.iter().all(|t| t.has_timestamps()) {
+        grouped
+            .into_iter()
+            .map(|(text, start, end, _)| Word {
+                text: text.trim().to_owned(),
+                start: start.max(segment_start),
+                end: end.clamp(start.max(segment_start), segment_end),
+            })
+            .collect()
+    } else {
+        interpolate(
+            segment,
+            grouped
+                .into_iter()
+                .map(|(text, ..)| text.trim().to_owned())
+                .collect(),
+        )
+    }
+}
+
+/// Spread `words` over the segment proportionally to their length.
+fn interpolate(segment: &TranscriptSegment, words: Vec<String>) -> Vec<Word> {
+    let lengths: Vec<i64> = words
+        .iter()
+        .map(|w| w.chars().count().max(1) as i64)
+        .collect();
+    let total: i64 = lengths.iter().sum();
+    let duration = segment.duration().max(0);
+    let mut seen = 0;
+    words
+        .into_iter()
+        .zip(lengths)
+        .map(|(text, len)| {
+            let start = segment.start + duration * seen / total;
+            seen += len;
+            Word {
+                text,
+                start,
+                end: segment.start + duration * seen / total,
+            }
+        })
+        .collect()
+}
+
+/// Lowercase `word` and strip it of punctuation, for comparing words regardless of formatting.
+pub(crate) fn normalize(word: &str) -> String {
+    word.chars()
+        .filter(|c| c.is_alphanumeric() || *c == '\'')
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::TranscriptToken;
+
+    #[test]
+    fn dtw_times_are_preferred() {
+        let token = |text: &str, t_dtw| TranscriptToken {
+            text: text.to_string(),
+            t0: 0,
+            t1: 100,
+            t_dtw,
+            ..Default::default()
+        };
+        let mut segment = TranscriptSegment::new(0, 100, " one two");
+        segment.tokens = vec![token(" one", 10), token(" tw", 55), token("o", 60)];
+        let times: Vec<_> = words(&segment).iter().map(|w| (w.start, w.end)).collect();
+        assert_eq!(times, [(10, 55), (55, 100)]);
+    }
+
+    #[test]
+    fn characters_split_across_tokens_are_joined() {
+        let token = |bytes: &[u8]| TranscriptToken {
+            text: String::from_utf8_lossy(bytes).into_owned(),
+            bytes: bytes.to_vec(),
+            ..Default::default()
+        };
+        // "日本" with the second character split over two tokens
+        let mut segment = TranscriptSegment::new(0, 100, " 日本");
+        segment.tokens = vec![
+            token(" 日".as_bytes()),
+            token(&[0xe6, 0x9c]),
+            token(&[0xac]),
+        ];
+        let words: Vec<_> = words(&segment).into_iter().map(|w| w.text).collect();
+        assert_eq!(words, ["日本"]);
+    }
+}
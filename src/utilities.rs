@@ -73,6 +73,108 @@ pub fn convert_stereo_to_mono_audio(input: &[f32], output: &mut [f32]) -> Result
     Ok(())
 }
 
+/// Split interleaved 32-bit floating point stereo PCM audio into its left and right channels.
+///
+/// # Arguments
+/// * `input` - The array of 32-bit floating point stereo PCM audio samples.
+///
+/// # Errors
+/// * if `input.len()` is odd ([`WhisperError::HalfSampleMissing`])
+///
+/// # Returns
+/// The left and right channel, in that order.
+///
+/// # Examples
+/// ```
+/// # use whisper_rs::split_stereo_audio;
+/// let (left, right) = split_stereo_audio(&[0.1, 0.2, 0.3, 0.4]).expect("should be no half samples missing");
+/// assert_eq!(left, [0.1, 0.3]);
+/// assert_eq!(right, [0.2, 0.4]);
+/// ```
+pub fn split_stereo_audio(input: &[f32]) -> Result<(Vec<f32>, Vec<f32>), WhisperError> {
+    let (frames, []) = input.as_chunks::<2>() else {
+        return Err(WhisperError::HalfSampleMissing(input.len()));
+    };
+    Ok(frames.iter().map(|[left, right]| (*left, *right)).unzip())
+}
+
+/// Split interleaved 32-bit floating point PCM audio with any number of channels into one buffer per channel.
+///
+/// # Arguments
+/// * `input` - The array of interleaved 32-bit floating point PCM audio samples.
+/// * `channels` - The number of channels in `input`.
+///
+/// # Errors
+/// * if `channels` is 0, or `input.len()` is not a multiple of it ([`WhisperError::IncompleteFrame`])
+///
+/// # Returns
+/// The channels, in the order they are interleaved.
+///
+/// # Examples
+/// ```
+/// # use whisper_rs::split_channels;
+/// let tracks = split_channels(&[0.1, 0.2, 0.3, 0.4, 0.5, 0.6], 3).expect("should be whole frames");
+/// assert_eq!(tracks, [[0.1, 0.4], [0.2, 0.5], [0.3, 0.6]]);
+/// ```
+pub fn split_channels(input: &[f32], channels: usize) -> Result<Vec<Vec<f32>>, WhisperError> {
+    if channels == 0 || !input.len().is_multiple_of(channels) {
+        return Err(WhisperError::IncompleteFrame {
+            samples: input.len(),
+            channels,
+        });
+    }
+    let mut out = vec![Vec::with_capacity(input.len() / channels); channels];
+    for frame in input.chunks_exact(channels) {
+        for (track, &sample) in out.iter_mut().zip(frame) {
+            track.push(sample);
+        }
+    }
+    Ok(out)
+}
+
+/// Resample mono audio with linear interpolation.
+///
+/// Good enough for upsampling narrowband sources such as 8 kHz telephony to the 16 kHz Whisper expects.
+/// Downsampling does not filter out frequencies above the new Nyquist rate, so prefer a proper resampler for that.
+///
+/// # Arguments
+/// * `input` - The samples to resample.
+/// * `from_rate` - The sample rate of `input`, in Hz.
+/// * `to_rate` - The sample rate to convert to, in Hz.
+///
+/// # Panics
+/// * if either rate is 0
+///
+/// # Examples
+/// ```
+/// # use whisper_rs::resample_linear;
+/// let narrowband = [0.0f32, 0.5, 1.0];
+/// assert_eq!(resample_linear(&narrowband, 8000, 16000), [0.0, 0.25, 0.5, 0.75, 1.0, 1.0]);
+/// ```
+#[cfg(feature = "audio-utils")]
+pub fn resample_linear(input: &[f32], from_rate: u32, to_rate: u32) -> Vec<f32> {
+    assert!(
+        from_rate > 0 && to_rate > 0,
+        "sample rates must be non-zero"
+    );
+    if from_rate == to_rate || input.is_empty() {
+        return input.to_vec();
+    }
+
+    let out_len = (input.len() as u64 * to_rate as u64 / from_rate as u64) as usize;
+    let step = from_rate as f64 / to_rate as f64;
+    let last = input.len() - 1;
+    (0..out_len)
+        .map(|i| {
+            let pos = i as f64 * step;
+            let idx = (pos as usize).min(last);
+            let frac = (pos - idx as f64) as f32;
+            let next = input[(idx + 1).min(last)];
+            input[idx] + (next - input[idx]) * frac
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -134,6 +236,18 @@ mod test {
         );
     }
 
+    #[test]
+    #[cfg(feature = "audio-utils")]
+    pub fn assert_resample_linear_lengths() {
+        let samples = vec![0.25f32; 8000];
+        assert_eq!(resample_linear(&samples, 8000, 16000).len(), 16000);
+        assert_eq!(resample_linear(&samples, 48000, 16000).len(), 2666);
+        assert!(resample_linear(&samples, 8000, 16000)
+            .iter()
+            .all(|&s| s == 0.25));
+        assert!(resample_linear(&[], 8000, 16000).is_empty());
+    }
+
     #[bench]
     pub fn bench_stereo_to_mono(b: &mut test::Bencher) {
         let samples = random_sample_data::<f32>();
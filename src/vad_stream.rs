@@ -0,0 +1,342 @@
+use crate::adaptive_chunks::FRAME_SAMPLES;
+use crate::transcribe::{ms_to_samples, MIN_INPUT_MS, SAMPLES_PER_CS};
+use crate::{
+    FullParams, StreamingTranscribe, Transcribe, TranscriptSegment, WhisperError, WhisperVadContext,
+};
+use std::collections::VecDeque;
+use std::fmt;
+
+/// Audio already classified that is passed to the VAD again with every new buffer, so its
+/// recurrent state has warmed up by the time it reaches new audio. About one second.
+const CONTEXT_FRAMES: usize = 32;
+
+/// A [`StreamingTranscribe`] implementation that only transcribes speech.
+///
+/// Incoming audio is classified with a VAD model, and each stretch of speech is transcribed
+/// on its own as soon as it ends, with timestamps relative to the start of the stream.
+/// Silence never reaches the model, which saves work and avoids the text Whisper tends to
+/// hallucinate from it.
+///
+/// A VAD reports speech a little after it starts, so gating on its output alone clips the first
+/// sound of each utterance. [`Self::with_pre_roll_ms`] keeps some audio from before the detected
+/// start to prevent this, and [`Self::with_post_roll_ms`] does the same for trailing sounds.
+pub struct VadGatedTranscriber<'a, 'b, T: Transcribe> {
+    backend: T,
+    params: FullParams<'a, 'b>,
+    vad: WhisperVadContext,
+    threshold: f32,
+    gate: SpeechGate,
+    /// Samples not yet making up a whole frame.
+    pending: Vec<f32>,
+    /// The last frames passed to the VAD, see [`CONTEXT_FRAMES`].
+    context: Vec<f32>,
+}
+
+/// Error from a [`VadGatedTranscriber`].
+#[derive(Debug)]
+pub enum VadStreamError<E> {
+    Vad(WhisperError),
+    Transcribe(E),
+}
+
+impl<E: fmt::Display> fmt::Display for VadStreamError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Vad(e) => write!(f, "voice activity detection failed: {}", e),
+            Self::Transcribe(e) => e.fmt(f),
+        }
+    }
+}
+
+impl<E: std::error::Error + 'static> std::error::Error for VadStreamError<E> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Vad(e) => Some(e),
+            Self::Transcribe(e) => Some(e),
+        }
+    }
+}
+
+impl<'a, 'b, T: Transcribe> VadGatedTranscriber<'a, 'b, T> {
+    /// Create a new gated transcriber. `params` are cloned for every stretch of speech.
+    pub fn new(backend: T, params: FullParams<'a, 'b>, vad: WhisperVadContext) -> Self {
+        Self {
+            backend,
+            params,
+            vad,
+            threshold: 0.5,
+            gate: SpeechGate::new(),
+            pending: Vec::new(),
+            context: Vec::new(),
+        }
+    }
+
+    /// Set the probability above which a frame is considered speech.
+    ///
+    /// Defaults to 0.5.
+    pub fn with_threshold(mut self, threshold: f32) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Set how much audio from before the detected start of speech is transcribed with it.
+    ///
+    /// Defaults to 300 ms.
+    pub fn with_pre_roll_ms(mut self, pre_roll_ms: u32) -> Self {
+        self.gate.pre_roll = ms_to_samples(pre_roll_ms);
+        self
+    }
+
+    /// Set how much audio from after the detected end of speech is transcribed with it.
+    /// Limited to [`Self::with_min_silence_ms`], as any more would be the next utterance.
+    ///
+    /// Defaults to 300 ms.
+    pub fn with_post_roll_ms(mut self, post_roll_ms: u32) -> Self {
+        self.gate.post_roll = ms_to_samples(post_roll_ms);
+        self
+    }
+
+    /// Set how long a pause must last to end a stretch of speech.
+    /// Shorter pauses are transcribed along with the speech around them.
+    ///
+    /// Defaults to 500 ms.
+    pub fn with_min_silence_ms(mut self, min_silence_ms: u32) -> Self {
+        self.gate.min_silence = ms_to_samples(min_silence_ms);
+        self
+    }
+
+    /// Set the longest stretch of speech transcribed at once. Longer ones are cut.
+    ///
+    /// Defaults to 30 seconds, the window Whisper was trained on.
+    pub fn with_max_speech_ms(mut self, max_speech_ms: u32) -> Self {
+        self.gate.max_speech = ms_to_samples(max_speech_ms.max(1000));
+        self
+    }
+
+    /// The parameters used for each stretch of speech.
+    pub fn params_mut(&mut self) -> &mut FullParams<'a, 'b> {
+        &mut self.params
+    }
+
+    /// Consume the transcriber, returning the wrapped backend and VAD. Buffered audio is discarded.
+    pub fn into_inner(self) -> (T, WhisperVadContext) {
+        (self.backend, self.vad)
+    }
+
+    fn classify(&mut self, frames: usize) -> Result<Vec<bool>, WhisperError> {
+        let new = &self.pending[..frames * FRAME_SAMPLES];
+        self.context.extend_from_slice(new);
+        self.vad.detect_speech(&self.context)?;
+        let probs = self.vad.probabilities();
+        let speech = probs[probs.len().saturating_sub(frames)..]
+            .iter()
+            .map(|&p| p >= self.threshold)
+            .collect();
+
+        let keep = CONTEXT_FRAMES * FRAME_SAMPLES;
+        if self.context.len() > keep {
+            self.context.drain(..self.context.len() - keep);
+        }
+        Ok(speech)
+    }
+
+    fn transcribe(
+        &mut self,
+        utterances: Vec<Utterance>,
+    ) -> Result<Vec<TranscriptSegment>, T::Error> {
+        let mut out = Vec::new();
+        for Utterance { start, mut audio } in utterances {
+            let offset = (start / SAMPLES_PER_CS) as i64;
+            let end = offset + (audio.len() / SAMPLES_PER_CS) as i64;
+            let min_len = ms_to_samples(MIN_INPUT_MS);
+            if audio.len() < min_len {
+                audio.resize(min_len, 0.0);
+            }
+            let transcript = self.backend.transcribe(self.params.clone(), &audio)?;
+            out.extend(transcript.segments.into_iter().map(|mut segment| {
+                segment.start = (segment.start + offset).min(end);
+                segment.end = (segment.end + offset).clamp(segment.start, end);
+                segment
+            }));
+        }
+        Ok(out)
+    }
+}
+
+impl<T: Transcribe> StreamingTranscribe for VadGatedTranscriber<'_, '_, T>
+where
+    T::Error: 'static,
+{
+    type Error = VadStreamError<T::Error>;
+
+    fn push_audio(&mut self, audio: &[f32]) -> Result<Vec<TranscriptSegment>, Self::Error> {
+        self.pending.extend_from_slice(audio);
+        let frames = self.pending.len() / FRAME_SAMPLES;
+        if frames == 0 {
+            return Ok(Vec::new());
+        }
+
+        let speech = self.classify(frames).map_err(VadStreamError::Vad)?;
+        let mut utterances = Vec::new();
+        for (frame, speech) in self.pending.chunks_exact(FRAME_SAMPLES).zip(speech) {
+            utterances.extend(self.gate.push(frame, speech));
+        }
+        self.pending.drain(..frames * FRAME_SAMPLES);
+        self.transcribe(utterances)
+            .map_err(VadStreamError::Transcribe)
+    }
+
+    fn finish(&mut self) -> Result<Vec<TranscriptSegment>, Self::Error> {
+        let rest = std::mem::take(&mut self.pending);
+        // the last partial frame is too short for the VAD; it belongs to whatever came before it
+        let speech = self.gate.speaking();
+        let mut utterances: Vec<_> = self.gate.push(&rest, speech).into_iter().collect();
+        utterances.extend(self.gate.finish());
+        self.context.clear();
+        self.transcribe(utterances)
+            .map_err(VadStreamError::Transcribe)
+    }
+}
+
+/// A stretch of speech, with the position of its first sample in the stream.
+#[derive(Debug, PartialEq)]
+struct Utterance {
+    start: usize,
+    audio: Vec<f32>,
+}
+
+/// Cuts a stream of classified frames into utterances, with pre- and post-roll.
+#[derive(Debug)]
+struct SpeechGate {
+    pre_roll: usize,
+    post_roll: usize,
+    min_silence: usize,
+    max_speech: usize,
+    /// Samples seen so far.
+    position: usize,
+    /// The most recent audio while not in an utterance, up to `pre_roll` samples.
+    history: VecDeque<f32>,
+    current: Option<Utterance>,
+    /// Samples of silence at the end of `current`.
+    silence: usize,
+}
+
+impl SpeechGate {
+    fn new() -> Self {
+        Self {
+            pre_roll: ms_to_samples(300),
+            post_roll: ms_to_samples(300),
+            min_silence: ms_to_samples(500),
+            max_speech: ms_to_samples(whisper_rs_sys::WHISPER_CHUNK_SIZE * 1000),
+            position: 0,
+            history: VecDeque::new(),
+            current: None,
+            silence: 0,
+        }
+    }
+
+    fn speaking(&self) -> bool {
+        self.current.is_some() && self.silence == 0
+    }
+
+    fn push(&mut self, frame: &[f32], speech: bool) -> Option<Utterance> {
+        let start = self.position;
+        self.position += frame.len();
+
+        let Some(current) = &mut self.current else {
+            if speech {
+                let mut audio: Vec<f32> = self.history.drain(..).collect();
+                audio.extend_from_slice(frame);
+                self.current = Some(Utterance {
+                    start: start - (audio.len() - frame.len()),
+                    audio,
+                });
+                self.silence = 0;
+            } else {
+                self.history.extend(frame);
+                let excess = self.history.len().saturating_sub(self.pre_roll);
+                self.history.drain(..excess);
+            }
+            return None;
+        };
+
+        current.audio.extend_from_slice(frame);
+        self.silence = if speech {
+            0
+        } else {
+            self.silence + frame.len()
+        };
+        if self.silence >= self.min_silence {
+            return self.finish();
+        }
+        if current.audio.len() >= self.max_speech {
+            let done = self.current.take();
+            if speech {
+                self.current = Some(Utterance {
+                    start: self.position,
+                    audio: Vec::new(),
+                });
+            }
+            return done;
+        }
+        None
+    }
+
+    fn finish(&mut self) -> Option<Utterance> {
+        let mut utterance = self.current.take()?;
+        let trailing = self.silence;
+        self.silence = 0;
+
+        // the trailing silence is the pre-roll of whatever comes next
+        let tail = &utterance.audio[utterance.audio.len() - trailing.min(self.pre_roll)..];
+        self.history = tail.iter().copied().collect();
+
+        let keep = utterance.audio.len() - trailing + trailing.min(self.post_roll);
+        utterance.audio.truncate(keep);
+        Some(utterance).filter(|u| !u.audio.is_empty())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Feed `pattern` to `gate` one frame per character, `#` for speech and `.` for silence,
+    /// with every sample set to the index of its frame.
+    fn run(gate: &mut SpeechGate, pattern: &str) -> Vec<(usize, usize)> {
+        let mut out = Vec::new();
+        for (i, c) in pattern.chars().enumerate() {
+            let frame = [i as f32; 4];
+            out.extend(gate.push(&frame, c == '#'));
+        }
+        out.extend(gate.finish());
+        out.iter()
+            .map(|u| (u.start / 4, u.audio.len() / 4))
+            .collect()
+    }
+
+    #[test]
+    fn speech_is_padded_with_pre_and_post_roll() {
+        let mut gate = SpeechGate {
+            pre_roll: 8,
+            post_roll: 4,
+            min_silence: 12,
+            max_speech: 400,
+            ..SpeechGate::new()
+        };
+        // (first frame, number of frames)
+        assert_eq!(run(&mut gate, ".....##.#.....###"), vec![(3, 7), (12, 5)]);
+    }
+
+    #[test]
+    fn long_speech_is_cut() {
+        let mut gate = SpeechGate {
+            pre_roll: 0,
+            post_roll: 0,
+            min_silence: 4,
+            max_speech: 12,
+            ..SpeechGate::new()
+        };
+        assert_eq!(run(&mut gate, "#####"), vec![(0, 3), (3, 2)]);
+    }
+}
@@ -0,0 +1,259 @@
+use crate::{WhisperContext, WhisperError, WhisperTokenId};
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+
+/// How far ahead of the tokens read so far an id in a `.tiktoken` file may be, see
+/// [`Vocabulary::read_tiktoken`]. Ids are checked as they are read, so a corrupt id can't
+/// allocate room for billions of tokens.
+const MAX_ID_AHEAD: usize = 1024;
+
+/// The tokens of a model: the bytes each token id stands for, see [`WhisperContext::vocabulary`].
+///
+/// whisper.cpp reads the vocabulary from the model file and has no other, so this is exactly the
+/// tokenization the loaded model decodes with. The text tokens can be exported in the
+/// `.tiktoken` format OpenAI ships the Whisper vocabularies in, one base64 encoded piece and its
+/// id per line, for search indexing or alignment tools, and read back to check that another
+/// model tokenizes the same way.
+///
+/// Pieces are byte-level BPE: a token may hold part of a UTF-8 character, so decode the bytes
+/// of consecutive tokens together.
+///
+/// # Examples
+/// ```no_run
+/// # use whisper_rs::{Vocabulary, WhisperContext, WhisperContextParameters};
+/// # let ctx = WhisperContext::new_with_params("model.bin", WhisperContextParameters::default()).unwrap();
+/// let vocabulary = ctx.vocabulary().unwrap();
+/// vocabulary.write_tiktoken(std::fs::File::create("model.tiktoken").unwrap()).unwrap();
+///
+/// let file = std::io::BufReader::new(std::fs::File::open("model.tiktoken").unwrap());
+/// let exported = Vocabulary::read_tiktoken(file).unwrap();
+/// assert_eq!(exported.text_pieces(), vocabulary.text_pieces());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Vocabulary {
+    pieces: Vec<Vec<u8>>,
+    /// Number of text tokens, which come before the special tokens.
+    n_text: usize,
+    ids: HashMap<Vec<u8>, WhisperTokenId>,
+}
+
+impl Vocabulary {
+    fn new(pieces: Vec<Vec<u8>>, n_text: usize) -> Self {
+        let ids = pieces[..n_text]
+            .iter()
+            .enumerate()
+            .map(|(id, piece)| (piece.clone(), id as WhisperTokenId))
+            .collect();
+        Self {
+            pieces,
+            n_text,
+            ids,
+        }
+    }
+
+    /// Number of tokens, special tokens included.
+    pub fn len(&self) -> usize {
+        self.pieces.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pieces.is_empty()
+    }
+
+    /// The bytes of token `id`, or `None` if the vocabulary has no such token.
+    ///
+    /// Special tokens have whisper.cpp's names, such as `[_SOT_]` or `[_TT_150]`.
+    pub fn piece(&self, id: WhisperTokenId) -> Option<&[u8]> {
+        let index = usize::try_from(id).ok()?;
+        self.pieces.get(index).map(Vec::as_slice)
+    }
+
+    /// The id of the text token spelled exactly `piece`, if there is one.
+    pub fn id(&self, piece: &[u8]) -> Option<WhisperTokenId> {
+        self.ids.get(piece).copied()
+    }
+
+    /// Whether `id` is a special token: end of text, start of transcript, a language, task or
+    /// timestamp token and so on, rather than a piece of text.
+    pub fn is_special(&self, id: WhisperTokenId) -> bool {
+        usize::try_from(id).is_ok_and(|index| index >= self.n_text && index < self.pieces.len())
+    }
+
+    /// The pieces of the text tokens, indexed by token id.
+    pub fn text_pieces(&self) -> &[Vec<u8>] {
+        &self.pieces[..self.n_text]
+    }
+
+    /// Every token id with its bytes, in order.
+    pub fn iter(&self) -> impl Iterator<Item = (WhisperTokenId, &[u8])> {
+        self.pieces
+            .iter()
+            .enumerate()
+            .map(|(id, piece)| (id as WhisperTokenId, piece.as_slice()))
+    }
+
+    /// Write the text tokens in the `.tiktoken` format: the base64 encoded piece, a space and
+    /// the token id on each line.
+    ///
+    /// The special tokens are left out, as in OpenAI's files; their ids start right after the
+    /// last line, see [`WhisperContext::token_eot`].
+    pub fn write_tiktoken<W: Write>(&self, writer: W) -> io::Result<()> {
+        let mut writer = io::BufWriter::new(writer);
+        for (id, piece) in self.text_pieces().iter().enumerate() {
+            writeln!(writer, "{} {}", base64_encode(piece), id)?;
+        }
+        writer.flush()
+    }
+
+    /// Read a vocabulary in the `.tiktoken` format, as written by [`Self::write_tiktoken`].
+    ///
+    /// All tokens read are text tokens; the ids must run from 0 without gaps. They may be out of
+    /// order, but no id may come more than 1024 tokens early.
+    ///
+    /// # Returns
+    /// An error of kind [`io::ErrorKind::InvalidData`] if a line isn't a base64 piece and an id,
+    /// or ids are missing, repeated or too far ahead.
+    pub fn read_tiktoken<R: BufRead>(reader: R) -> io::Result<Self> {
+        let invalid = |line: usize, message: &str| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("line {}: {}", line + 1, message),
+            )
+        };
+        let mut pieces: Vec<Option<Vec<u8>>> = Vec::new();
+        let mut read = 0;
+        for (number, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let (piece, id) = line
+                .split_once(' ')
+                .ok_or_else(|| invalid(number, "expected a piece and an id"))?;
+            let piece = base64_decode(piece).ok_or_else(|| invalid(number, "invalid base64"))?;
+            let id: usize = id
+                .trim()
+                .parse()
+                .map_err(|_| invalid(number, "invalid token id"))?;
+            if id > read + MAX_ID_AHEAD {
+                return Err(invalid(number, "token id too far ahead"));
+            }
+            read += 1;
+            if id >= pieces.len() {
+                pieces.resize(id + 1, None);
+            }
+            if pieces[id].replace(piece).is_some() {
+                return Err(invalid(number, "repeated token id"));
+            }
+        }
+        let pieces = pieces
+            .into_iter()
+            .enumerate()
+            .map(|(id, piece)| {
+                piece.ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("token id {} is missing", id),
+                    )
+                })
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+        let n_text = pieces.len();
+        Ok(Self::new(pieces, n_text))
+    }
+}
+
+impl WhisperContext {
+    /// The vocabulary of the loaded model, see [`Vocabulary`].
+    ///
+    /// whisper.cpp hands out token pieces as C strings, so the piece of the token for a single
+    /// NUL byte comes out empty.
+    ///
+    /// # C++ equivalent
+    /// `int whisper_n_vocab(struct whisper_context * ctx)` and
+    /// `const char * whisper_token_to_str(struct whisper_context * ctx, whisper_token token)`
+    pub fn vocabulary(&self) -> Result<Vocabulary, WhisperError> {
+        let n_vocab = self.n_vocab().max(0);
+        let pieces = (0..n_vocab)
+            .map(|id| self.token_to_bytes(id).map(<[u8]>::to_vec))
+            .collect::<Result<Vec<_>, _>>()?;
+        let n_text = (self.token_eot().max(0) as usize).min(pieces.len());
+        Ok(Vocabulary::new(pieces, n_text))
+    }
+}
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn base64_encode(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(BASE64[(n >> (18 - 6 * i)) as usize & 63] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+fn base64_decode(text: &str) -> Option<Vec<u8>> {
+    let text = text.as_bytes();
+    if !text.len().is_multiple_of(4) {
+        return None;
+    }
+    let mut out = Vec::with_capacity(text.len() / 4 * 3);
+    for chunk in text.chunks(4) {
+        let padding = chunk.iter().rev().take_while(|&&c| c == b'=').count();
+        if padding > 2 {
+            return None;
+        }
+        let mut n = 0u32;
+        for (i, &c) in chunk[..4 - padding].iter().enumerate() {
+            let value = BASE64.iter().position(|&b| b == c)? as u32;
+            n |= value << (18 - 6 * i);
+        }
+        out.extend((0..3 - padding).map(|i| (n >> (16 - 8 * i)) as u8));
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn tiktoken_files_round_trip() {
+        let pieces = vec![
+            b"!".to_vec(),
+            b" the".to_vec(),
+            vec![0xe4, 0xbd],
+            b"[_EOT_]".to_vec(),
+        ];
+        let vocabulary = Vocabulary::new(pieces, 3);
+        assert_eq!(vocabulary.id(b" the"), Some(1));
+        assert_eq!(vocabulary.id(b"[_EOT_]"), None);
+        assert!(vocabulary.is_special(3) && !vocabulary.is_special(2));
+
+        let mut file = Vec::new();
+        vocabulary.write_tiktoken(&mut file).unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(&file),
+            "IQ== 0\nIHRoZQ== 1\n5L0= 2\n"
+        );
+        let read = Vocabulary::read_tiktoken(&file[..]).unwrap();
+        assert_eq!(read.text_pieces(), vocabulary.text_pieces());
+        assert_eq!(read.len(), 3);
+
+        let error = Vocabulary::read_tiktoken(&b"IQ== 0\n5L0= 2\n"[..]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(Vocabulary::read_tiktoken(&b"I*== 0\n"[..]).is_err());
+        let error = Vocabulary::read_tiktoken(&b"IQ== 4000000000\n"[..]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -1,4 +1,9 @@
-use std::{ffi::CStr, os::raw::c_int};
+use std::{
+    ffi::CStr,
+    io,
+    os::raw::c_int,
+    path::{Path, PathBuf},
+};
 use whisper_rs_sys::{
     ggml_backend_buffer_type_t, ggml_backend_vk_buffer_type, ggml_backend_vk_get_device_count,
     ggml_backend_vk_get_device_description, ggml_backend_vk_get_device_memory,
@@ -45,6 +50,85 @@ pub fn list_devices() -> Vec<VkDeviceInfo> {
     }
 }
 
+/// Environment variables that point Vulkan drivers at their on-disk pipeline cache:
+/// Mesa (RADV, ANV, NVK, ...) and the NVIDIA proprietary driver.
+const CACHE_DIR_VARS: &[&str] = &["MESA_SHADER_CACHE_DIR", "__GL_SHADER_DISK_CACHE_PATH"];
+/// Environment variables that keep those caches enabled and stop the driver from evicting ggml's
+/// pipelines, which are large and numerous.
+const CACHE_ENABLE_VARS: &[(&str, &str)] = &[
+    ("MESA_SHADER_CACHE_DISABLE", "false"),
+    ("__GL_SHADER_DISK_CACHE", "1"),
+    ("__GL_SHADER_DISK_CACHE_SKIP_CLEANUP", "1"),
+];
+
+/// A persistent location for compiled Vulkan pipelines.
+///
+/// On first use, ggml compiles several hundred compute pipelines, which takes seconds. Drivers
+/// cache the result on disk, but by default in the home directory of the current user, which
+/// services often lack or can't write to, so the cost is paid again on every start.
+/// [`Self::install`] points the driver cache at a directory of your choice.
+///
+/// This only configures the driver; whether pipelines are cached is up to it.
+/// Mesa drivers and the NVIDIA proprietary driver are supported.
+///
+/// # Examples
+/// ```no_run
+/// # use whisper_rs::vulkan::ShaderCache;
+/// // at the very start of main, before any other thread is spawned
+/// ShaderCache::open_default().unwrap().install().unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct ShaderCache {
+    dir: PathBuf,
+}
+
+impl ShaderCache {
+    /// Use `dir` as the cache directory. It is created by [`Self::install`].
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// The default cache directory.
+    ///
+    /// This is `$WHISPER_RS_VULKAN_CACHE_DIR` if set, otherwise `whisper-rs/vulkan` inside the
+    /// user's cache directory, see [`crate::models::ModelCache::default_dir`].
+    pub fn default_dir() -> Option<PathBuf> {
+        if let Some(dir) = std::env::var_os("WHISPER_RS_VULKAN_CACHE_DIR") {
+            return Some(PathBuf::from(dir));
+        }
+        crate::models::user_cache_dir().map(|b| b.join("whisper-rs").join("vulkan"))
+    }
+
+    /// Use the cache at [`Self::default_dir`].
+    pub fn open_default() -> io::Result<Self> {
+        Self::default_dir()
+            .map(Self::new)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no cache directory found"))
+    }
+
+    /// The directory backing this cache.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Create the cache directory and configure the drivers to use it.
+    ///
+    /// Drivers read their configuration from the environment when Vulkan is first initialized,
+    /// so call this before loading a model or calling [`list_devices`]. Later calls have no effect.
+    /// Modifying the environment is not thread-safe on most platforms, so call it before
+    /// spawning threads. Any driver cache location already set in the environment is replaced.
+    pub fn install(&self) -> io::Result<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        for var in CACHE_DIR_VARS {
+            std::env::set_var(var, &self.dir);
+        }
+        for (var, value) in CACHE_ENABLE_VARS {
+            std::env::set_var(var, value);
+        }
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod vulkan_tests {
     use super::*;
@@ -54,6 +138,18 @@ mod vulkan_tests {
         let _ = list_devices();
     }
 
+    #[test]
+    fn shader_cache_is_installed() {
+        let dir = std::env::temp_dir().join(format!("whisper-rs-vk-{}", std::process::id()));
+        ShaderCache::new(&dir).install().unwrap();
+        assert!(dir.is_dir());
+        assert_eq!(
+            std::env::var_os("MESA_SHADER_CACHE_DIR"),
+            Some(dir.clone().into_os_string())
+        );
+        std::fs::remove_dir(&dir).unwrap();
+    }
+
     #[test]
     fn sane_device_info() {
         let gpus = list_devices();
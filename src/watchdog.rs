@@ -0,0 +1,240 @@
+use crate::whisper_params::LogitsFilterFn;
+use crate::FullParams;
+use std::fmt;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+use whisper_rs_sys::whisper_token_data;
+
+/// The longest repeating token sequence [`Watchdog::max_repeats`] looks for.
+const MAX_PERIOD: usize = 16;
+
+/// Limits on a single run of [`crate::WhisperState::full`], which is aborted with
+/// [`crate::WhisperError::DeadlineExceeded`] once one is exceeded, see
+/// [`FullParams::set_watchdog`].
+///
+/// Some inputs, such as long stretches of noise, make the decoder hang on to a window with
+/// temperature fallbacks or repeat the same phrase until the text context is full. A service
+/// can bound how long such input ties up a state instead of waiting it out.
+///
+/// # Examples
+/// ```no_run
+/// # use whisper_rs::{FullParams, SamplingStrategy, Watchdog};
+/// # use std::time::Duration;
+/// let mut params = FullParams::new(SamplingStrategy::default());
+/// params.set_watchdog(
+///     Watchdog::new()
+///         .deadline(Duration::from_secs(60))
+///         .max_rtf(0.5)
+///         .max_repeats(8),
+/// );
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Watchdog {
+    deadline: Option<Duration>,
+    max_rtf: Option<f32>,
+    max_repeats: Option<usize>,
+}
+
+impl Watchdog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Abort runs taking longer than `deadline`.
+    ///
+    /// Defaults to no deadline.
+    pub fn deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Abort runs taking longer than `max_rtf` times the duration of their audio, e.g. 0.5 to
+    /// abort an hour of audio after 30 minutes. Combined with [`Self::deadline`], whichever
+    /// comes first applies.
+    ///
+    /// Defaults to no limit.
+    pub fn max_rtf(mut self, max_rtf: f32) -> Self {
+        self.max_rtf = Some(max_rtf);
+        self
+    }
+
+    /// Abort runs whose decoder produces the same token, or sequence of up to 16 tokens, more
+    /// than `max_repeats` times in a row within a segment.
+    ///
+    /// Defaults to no limit.
+    pub fn max_repeats(mut self, max_repeats: usize) -> Self {
+        self.max_repeats = Some(max_repeats);
+        self
+    }
+}
+
+/// Which limit of a [`Watchdog`] was exceeded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchdogReason {
+    /// The run took longer than [`Watchdog::deadline`] or [`Watchdog::max_rtf`] allow.
+    Deadline,
+    /// The decoder repeated itself more than [`Watchdog::max_repeats`] allows.
+    RepetitionLoop,
+}
+
+impl fmt::Display for WatchdogReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Deadline => write!(f, "the deadline passed"),
+            Self::RepetitionLoop => write!(f, "the decoder got stuck repeating itself"),
+        }
+    }
+}
+
+/// A watchdog installed on [`FullParams`], shared with its callbacks.
+#[derive(Debug)]
+pub(crate) struct WatchdogRun {
+    watchdog: Watchdog,
+    deadline: Mutex<Option<Instant>>,
+    tripped: Mutex<Option<WatchdogReason>>,
+}
+
+impl WatchdogRun {
+    /// Start timing a run over `samples` samples of 16 kHz audio.
+    pub(crate) fn start(&self, samples: usize) {
+        let audio =
+            Duration::from_secs_f64(samples as f64 / whisper_rs_sys::WHISPER_SAMPLE_RATE as f64);
+        let limits = [
+            self.watchdog.deadline,
+            self.watchdog
+                .max_rtf
+                .map(|rtf| audio.mul_f64(rtf.max(0.0) as f64)),
+        ];
+        let limit = limits.into_iter().flatten().min();
+        *lock(&self.deadline) = limit.map(|limit| Instant::now() + limit);
+        *lock(&self.tripped) = None;
+    }
+
+    /// Why the last run was aborted, if it was.
+    pub(crate) fn tripped(&self) -> Option<WatchdogReason> {
+        *lock(&self.tripped)
+    }
+
+    fn trip(&self, reason: WatchdogReason) {
+        lock(&self.tripped).get_or_insert(reason);
+    }
+
+    /// Whether to abort the run, called by whisper.cpp between graph computations.
+    fn should_abort(&self) -> bool {
+        if lock(&self.deadline).is_some_and(|deadline| Instant::now() >= deadline) {
+            self.trip(WatchdogReason::Deadline);
+        }
+        self.tripped().is_some()
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Whether `tokens` end with a sequence of up to [`MAX_PERIOD`] tokens repeated more than
+/// `max_repeats` times.
+fn is_looping(tokens: &[i32], max_repeats: usize) -> bool {
+    let copies = max_repeats.saturating_add(1);
+    (1..=MAX_PERIOD).any(|period| {
+        let Some(len) = period
+            .checked_mul(copies)
+            .filter(|&len| len <= tokens.len())
+        else {
+            return false;
+        };
+        let tail = &tokens[tokens.len() - len..];
+        tail.iter().zip(&tail[period..]).all(|(a, b)| a == b)
+    })
+}
+
+impl FullParams<'_, '_> {
+    /// Abort runs with these parameters that exceed the limits of `watchdog`, returning
+    /// [`crate::WhisperError::DeadlineExceeded`] from [`crate::WhisperState::full`].
+    ///
+    /// The watchdog is checked between the encoder and decoder computations whisper.cpp runs,
+    /// so a run stops shortly after a limit passes rather than immediately. This replaces any
+    /// callback set with [`Self::set_abort_callback_safe`] and any earlier watchdog, and clones of
+    /// these parameters share the watchdog, so don't run them at the same time.
+    ///
+    /// Defaults to no watchdog.
+    pub fn set_watchdog(&mut self, watchdog: Watchdog) {
+        let run = Arc::new(WatchdogRun {
+            watchdog,
+            deadline: Mutex::new(None),
+            tripped: Mutex::new(None),
+        });
+        let abort = run.clone();
+        self.set_abort_callback_safe(move || abort.should_abort());
+        if let Some(filter) = self.watchdog_filter.take() {
+            self.remove_logits_filter(&filter);
+        }
+        if let Some(max_repeats) = watchdog.max_repeats {
+            let watched = run.clone();
+            let mut ids = Vec::new();
+            let filter: Arc<Mutex<LogitsFilterFn>> = Arc::new(Mutex::new(Box::new(
+                move |tokens: &[whisper_token_data], _: &mut [f32]| {
+                    ids.clear();
+                    ids.extend(tokens.iter().map(|t| t.id));
+                    if is_looping(&ids, max_repeats) {
+                        watched.trip(WatchdogReason::RepetitionLoop);
+                    }
+                },
+            )));
+            self.push_logits_filter(filter.clone());
+            self.watchdog_filter = Some(filter);
+        }
+        self.watchdog = Some(run);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn repeated_sequences_are_loops() {
+        assert!(is_looping(&[1, 2, 3, 2, 3, 2, 3], 2));
+        assert!(!is_looping(&[1, 2, 3, 2, 3, 2, 3], 3));
+        assert!(is_looping(&[5, 5, 5], 2));
+        assert!(!is_looping(&[1, 2, 3, 4], 1));
+    }
+
+    #[test]
+    fn deadline_trips_once_passed() {
+        let run = WatchdogRun {
+            watchdog: Watchdog::new().max_rtf(0.0),
+            deadline: Mutex::new(None),
+            tripped: Mutex::new(None),
+        };
+        run.start(16000);
+        assert!(run.should_abort());
+        assert_eq!(run.tripped(), Some(WatchdogReason::Deadline));
+
+        let run = WatchdogRun {
+            watchdog: Watchdog::new().deadline(Duration::from_secs(3600)),
+            ..run
+        };
+        run.start(16000);
+        assert!(!run.should_abort());
+    }
+
+    #[test]
+    fn watchdogs_replace_each_other() {
+        let filters = |params: &FullParams| params.logits_filters.as_ref().map_or(0, |f| f.len());
+        let mut params = FullParams::new(crate::SamplingStrategy::Greedy { best_of: 1 });
+        params.add_logits_filter(|_, _| {});
+        params.set_watchdog(Watchdog::new().max_repeats(4));
+        params.set_watchdog(Watchdog::new().max_repeats(8));
+        assert_eq!(filters(&params), 2);
+
+        params.set_watchdog(Watchdog::new());
+        assert_eq!(filters(&params), 1);
+
+        params.clear_logits_filters();
+        params.set_watchdog(Watchdog::new().max_repeats(4));
+        params.set_watchdog(Watchdog::new());
+        assert!(params.logits_filters.is_none());
+        assert!(params.fp.logits_filter_callback.is_none());
+    }
+}
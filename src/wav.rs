@@ -0,0 +1,28 @@
+use std::io::{self, Write};
+
+/// Encode samples as a 16 bit PCM mono WAV file at 16 kHz.
+///
+/// Samples are clamped to `-1.0..=1.0`.
+pub fn write_wav<W: Write>(mut writer: W, samples: &[f32]) -> io::Result<()> {
+    let data_len = u32::try_from(samples.len() * 2)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "too many samples for WAV"))?;
+
+    writer.write_all(b"RIFF")?;
+    writer.write_all(&(36 + data_len).to_le_bytes())?;
+    writer.write_all(b"WAVEfmt ")?;
+    writer.write_all(&16u32.to_le_bytes())?; // fmt chunk size
+    writer.write_all(&1u16.to_le_bytes())?; // PCM
+    writer.write_all(&1u16.to_le_bytes())?; // mono
+    writer.write_all(&whisper_rs_sys::WHISPER_SAMPLE_RATE.to_le_bytes())?;
+    writer.write_all(&(whisper_rs_sys::WHISPER_SAMPLE_RATE * 2).to_le_bytes())?; // byte rate
+    writer.write_all(&2u16.to_le_bytes())?; // block align
+    writer.write_all(&16u16.to_le_bytes())?; // bits per sample
+    writer.write_all(b"data")?;
+    writer.write_all(&data_len.to_le_bytes())?;
+
+    for sample in samples {
+        let value = (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+        writer.write_all(&value.to_le_bytes())?;
+    }
+    writer.flush()
+}
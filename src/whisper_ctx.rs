@@ -1,7 +1,14 @@
+use crate::backends::LiveContext;
+use crate::common_logging::generic_warn;
+use crate::compat;
 use crate::error::WhisperError;
+use crate::health::UsageCounters;
+use crate::model_fetch::{self, ModelLoadError};
+use crate::observer::{DtwFallback, Observers};
 use crate::WhisperTokenId;
 use std::borrow::Cow;
 use std::ffi::{c_int, CStr, CString};
+use std::path::{Path, PathBuf};
 
 /// Safe Rust wrapper around a Whisper context.
 ///
@@ -11,6 +18,30 @@ use std::ffi::{c_int, CStr, CString};
 #[derive(Debug)]
 pub struct WhisperInnerContext {
     pub(crate) ctx: *mut whisper_rs_sys::whisper_context,
+    /// Path the model was loaded from, if it was loaded from a file.
+    pub(crate) model_path: Option<PathBuf>,
+    /// Where whisper.cpp will look for a CoreML encoder when creating states.
+    pub(crate) coreml_encoder_path: Option<PathBuf>,
+    /// OpenVINO encoder to load into every new state.
+    pub(crate) openvino_encoder: Option<OpenVinoEncoderParameters>,
+    /// Directory of links created to point whisper.cpp at an overridden CoreML encoder.
+    coreml_link_dir: Option<PathBuf>,
+    /// Runs and errors over all states created from this context.
+    pub(crate) usage: UsageCounters,
+    /// Notified of the runs of all states, see [`crate::WhisperContext::add_observer`].
+    pub(crate) observers: Observers,
+    /// Dropped after the context is freed.
+    _live: LiveContext,
+    /// Device selection the model was loaded with, see [`crate::WhisperContext::backend_info`].
+    pub(crate) use_gpu: bool,
+    pub(crate) gpu_device: c_int,
+    pub(crate) flash_attn: bool,
+    /// Whether the decoder was left out, see [`WhisperContextParameters::encoder_only`].
+    pub(crate) encoder_only: bool,
+    /// Whether DTW timestamps are computed, after any fallback for lack of memory.
+    pub(crate) dtw: bool,
+    /// Reported to the observers of every run, see [`crate::Observer::on_dtw_fallback`].
+    pub(crate) dtw_fallback: Option<DtwFallback>,
 }
 
 impl WhisperInnerContext {
@@ -21,15 +52,50 @@ impl WhisperInnerContext {
     /// * parameters: A parameter struct containing the parameters to use.
     ///
     /// # Returns
-    /// Ok(Self) on success, Err(WhisperError) on failure.
+    /// Ok(Self) on success, Err(WhisperError) on failure, including
+    /// [`WhisperError::UnsupportedConfiguration`] for parameters the model or backend can't honor.
     ///
     /// # C++ equivalent
     /// `struct whisper_context * whisper_init_from_file_with_params_no_state(const char * path_model, struct whisper_context_params params);`
     pub fn new_with_params(
         path: &str,
-        parameters: WhisperContextParameters,
+        mut parameters: WhisperContextParameters,
     ) -> Result<Self, WhisperError> {
-        let path_cstr = CString::new(path)?;
+        compat::check_params(&parameters)?;
+        if parameters.encoder_only {
+            let file = std::fs::File::open(path).map_err(|e| {
+                generic_warn!("whisper-rs: failed to open model {}: {}", path, e);
+                WhisperError::InitError
+            })?;
+            // whisper.cpp only finds the OpenVINO encoder next to models loaded from a file
+            if let Some(openvino) = &mut parameters.openvino_encoder {
+                openvino
+                    .model_path
+                    .get_or_insert_with(|| openvino_encoder_path_for(path));
+            }
+            let mut ctx = Self::new_without_decoder(std::io::BufReader::new(file), parameters)?;
+            ctx.model_path = Some(PathBuf::from(path));
+            return Ok(ctx);
+        }
+        let dtw_fallback = compat::check_dtw_memory(&mut parameters)?;
+
+        // whisper.cpp derives the CoreML encoder path from the model path when each state is created,
+        // so to override it the model is loaded through a link placed next to a link to the encoder
+        let (load_path, coreml_encoder_path, coreml_link_dir) =
+            match &parameters.coreml_encoder_path {
+                Some(encoder) => {
+                    let dir = link_coreml_encoder(Path::new(path), encoder)?;
+                    (dir.join("model.bin"), Some(encoder.clone()), Some(dir))
+                }
+                None => (
+                    PathBuf::from(path),
+                    Some(coreml_encoder_path_for(path)),
+                    None,
+                ),
+            };
+
+        let path_cstr = CString::new(load_path.to_string_lossy().as_ref())?;
+        let live = LiveContext::new();
         let ctx = unsafe {
             whisper_rs_sys::whisper_init_from_file_with_params_no_state(
                 path_cstr.as_ptr(),
@@ -37,9 +103,29 @@ impl WhisperInnerContext {
             )
         };
         if ctx.is_null() {
+            if let Some(dir) = &coreml_link_dir {
+                let _ = std::fs::remove_dir_all(dir);
+            }
             Err(WhisperError::InitError)
         } else {
-            Ok(Self { ctx })
+            let ctx = Self {
+                ctx,
+                model_path: Some(PathBuf::from(path)),
+                coreml_encoder_path,
+                openvino_encoder: parameters.openvino_encoder.clone(),
+                coreml_link_dir,
+                usage: UsageCounters::default(),
+                observers: Observers::default(),
+                _live: live,
+                use_gpu: parameters.use_gpu,
+                gpu_device: parameters.gpu_device,
+                flash_attn: parameters.flash_attn,
+                encoder_only: false,
+                dtw: !matches!(parameters.dtw_parameters.mode, DtwMode::None),
+                dtw_fallback,
+            };
+            compat::check_model(&parameters, &ctx)?;
+            Ok(ctx)
         }
     }
 
@@ -49,14 +135,22 @@ impl WhisperInnerContext {
     /// * buffer: The buffer containing the model.
     ///
     /// # Returns
-    /// Ok(Self) on success, Err(WhisperError) on failure.
+    /// Ok(Self) on success, Err(WhisperError) on failure, including
+    /// [`WhisperError::UnsupportedConfiguration`] for parameters the model or backend can't honor.
     ///
     /// # C++ equivalent
     /// `struct whisper_context * whisper_init_from_buffer_with_params_no_state(void * buffer, size_t buffer_size, struct whisper_context_params params);`
     pub fn new_from_buffer_with_params(
         buffer: &[u8],
-        parameters: WhisperContextParameters,
+        mut parameters: WhisperContextParameters,
     ) -> Result<Self, WhisperError> {
+        compat::check_params(&parameters)?;
+
+        if parameters.encoder_only {
+            return Self::new_without_decoder(buffer, parameters);
+        }
+        let dtw_fallback = compat::check_dtw_memory(&mut parameters)?;
+        let live = LiveContext::new();
         let ctx = unsafe {
             whisper_rs_sys::whisper_init_from_buffer_with_params_no_state(
                 buffer.as_ptr() as _,
@@ -64,10 +158,86 @@ impl WhisperInnerContext {
                 parameters.to_c_struct(),
             )
         };
+        Self::without_file(ctx, live, &parameters, dtw_fallback)
+    }
+
+    /// Load the model read from `reader` without its decoder layers, streaming it to whisper.cpp
+    /// rather than collecting it in memory first.
+    fn new_without_decoder(
+        reader: impl std::io::Read,
+        parameters: WhisperContextParameters,
+    ) -> Result<Self, WhisperError> {
+        model_fetch::load(reader, parameters).map_err(|e| match e {
+            ModelLoadError::Fetch(e) => {
+                generic_warn!(
+                    "whisper-rs: failed to read the model without its decoder: {}",
+                    e
+                );
+                WhisperError::InitError
+            }
+            ModelLoadError::Load(e) => e,
+        })
+    }
+
+    /// Create a new WhisperContext from a model read through `loader`.
+    ///
+    /// # Safety
+    /// The callbacks of `loader` must be safe to call with its context until this returns.
+    ///
+    /// # Returns
+    /// Ok(Self) on success, Err(WhisperError) on failure, including
+    /// [`WhisperError::UnsupportedConfiguration`] for parameters the model or backend can't honor.
+    ///
+    /// # C++ equivalent
+    /// `struct whisper_context * whisper_init_with_params_no_state(struct whisper_model_loader * loader, struct whisper_context_params params);`
+    pub(crate) unsafe fn new_from_loader_with_params(
+        loader: &mut whisper_rs_sys::whisper_model_loader,
+        mut parameters: WhisperContextParameters,
+    ) -> Result<Self, WhisperError> {
+        compat::check_params(&parameters)?;
+        let dtw_fallback = compat::check_dtw_memory(&mut parameters)?;
+
+        let live = LiveContext::new();
+        let ctx = unsafe {
+            whisper_rs_sys::whisper_init_with_params_no_state(loader, parameters.to_c_struct())
+        };
+        Self::without_file(ctx, live, &parameters, dtw_fallback)
+    }
+
+    /// Wrap a context loaded from somewhere other than a file, or fail if it didn't load.
+    /// `dtw_fallback` is the fallback [`compat::check_dtw_memory`] took, if any.
+    fn without_file(
+        ctx: *mut whisper_rs_sys::whisper_context,
+        live: LiveContext,
+        parameters: &WhisperContextParameters,
+        dtw_fallback: Option<DtwFallback>,
+    ) -> Result<Self, WhisperError> {
+        if parameters.coreml_encoder_path.is_some() {
+            generic_warn!(
+                "whisper-rs: a CoreML encoder path can only be used with models loaded from a file, ignoring it"
+            );
+        }
         if ctx.is_null() {
             Err(WhisperError::InitError)
         } else {
-            Ok(Self { ctx })
+            let ctx = Self {
+                ctx,
+                model_path: None,
+                coreml_encoder_path: None,
+                openvino_encoder: parameters.openvino_encoder.clone(),
+                coreml_link_dir: None,
+                usage: UsageCounters::default(),
+                observers: Observers::default(),
+                _live: live,
+                use_gpu: parameters.use_gpu,
+                gpu_device: parameters.gpu_device,
+                flash_attn: parameters.flash_attn,
+                encoder_only: parameters.encoder_only,
+                dtw: !matches!(parameters.dtw_parameters.mode, DtwMode::None),
+                dtw_fallback,
+            };
+            compat::check_model(parameters, &ctx)?;
+            Ok(ctx)
         }
     }
 
@@ -99,15 +269,22 @@ impl WhisperInnerContext {
                 max_tokens as c_int,
             )
         };
-        if ret == -1 {
+        if ret < 0 {
+            // whisper.cpp returns minus the number of tokens needed if they don't fit
             Err(WhisperError::InvalidText)
         } else {
-            // SAFETY: when ret != -1, we know that the length of the vector is at least ret tokens
+            // SAFETY: when ret >= 0, whisper.cpp has written ret tokens, at most max_tokens
             unsafe { tokens.set_len(ret as usize) };
             Ok(tokens)
         }
     }
 
+    pub fn token_count(&self, text: &str) -> Result<usize, WhisperError> {
+        let text = CString::new(text)?;
+        let ret = unsafe { whisper_rs_sys::whisper_token_count(self.ctx, text.as_ptr()) };
+        usize::try_from(ret).map_err(|_| WhisperError::InvalidText)
+    }
+
     /// Get n_vocab.
     ///
     /// # Returns
@@ -426,7 +603,75 @@ impl WhisperInnerContext {
 impl Drop for WhisperInnerContext {
     fn drop(&mut self) {
         unsafe { whisper_rs_sys::whisper_free(self.ctx) };
+        if let Some(dir) = &self.coreml_link_dir {
+            let _ = std::fs::remove_dir_all(dir);
+        }
+    }
+}
+
+/// Where whisper.cpp looks for the OpenVINO encoder of the model at `model_path`.
+fn openvino_encoder_path_for(model_path: &str) -> PathBuf {
+    let stem = model_path
+        .rfind('.')
+        .map_or(model_path, |pos| &model_path[..pos]);
+    PathBuf::from(format!("{}-encoder-openvino.xml", stem))
+}
+
+/// The CoreML encoder path whisper.cpp derives from a model path:
+/// `ggml-base.en.bin` and `ggml-base.en-q5_0.bin` both map to `ggml-base.en-encoder.mlmodelc`.
+fn coreml_encoder_path_for(model_path: &str) -> PathBuf {
+    let mut path = model_path;
+    if let Some(pos) = path.rfind('.') {
+        path = &path[..pos];
+    }
+    if let Some(pos) = path.rfind('-') {
+        let suffix = &path.as_bytes()[pos..];
+        if suffix.len() == 5 && suffix[1] == b'q' && suffix[3] == b'_' {
+            path = &path[..pos];
+        }
     }
+    PathBuf::from(format!("{}-encoder.mlmodelc", path))
+}
+
+/// Create a private directory holding `model.bin` and `model-encoder.mlmodelc`,
+/// linking to `model` and `encoder` respectively.
+fn link_coreml_encoder(model: &Path, encoder: &Path) -> Result<PathBuf, WhisperError> {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    let dir = std::env::temp_dir().join(format!(
+        "whisper-rs-coreml-{}-{}",
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    let link = || -> std::io::Result<()> {
+        std::fs::create_dir_all(&dir)?;
+        let model = std::fs::canonicalize(model)?;
+        let encoder = std::fs::canonicalize(encoder)?;
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(model, dir.join("model.bin"))?;
+            std::os::unix::fs::symlink(encoder, dir.join("model-encoder.mlmodelc"))?;
+            Ok(())
+        }
+        #[cfg(not(unix))]
+        {
+            let _ = (model, encoder);
+            Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "CoreML encoder overrides are only supported on unix platforms",
+            ))
+        }
+    };
+    link().map_err(|e| {
+        generic_warn!(
+            "whisper-rs: failed to set up CoreML encoder override: {}",
+            e
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+        WhisperError::InitError
+    })?;
+    Ok(dir)
 }
 
 // following implementations are safe
@@ -445,6 +690,41 @@ pub struct WhisperContextParameters<'a> {
     pub gpu_device: c_int,
     /// DTW token level timestamp parameters
     pub dtw_parameters: DtwParameters<'a>,
+    /// Path to a compiled CoreML encoder (`.mlmodelc`) to use instead of the one next to the model,
+    /// default None.
+    ///
+    /// Only has an effect with the `coreml` feature, and only for models loaded from a file.
+    pub coreml_encoder_path: Option<PathBuf>,
+    /// Load an OpenVINO encoder into every state created from this context, default None.
+    ///
+    /// Requires whisper.cpp to have been built with OpenVINO support;
+    /// use [`crate::WhisperState::encoder_backend`] to check whether it loaded.
+    pub openvino_encoder: Option<OpenVinoEncoderParameters>,
+    /// Load only the encoder, default false. See [`Self::encoder_only`].
+    pub encoder_only: bool,
+}
+
+/// Where to find an OpenVINO encoder, see [`WhisperContextParameters::openvino_encoder`].
+#[derive(Debug, Clone, Default)]
+pub struct OpenVinoEncoderParameters {
+    /// Path to the OpenVINO encoder IR (`.xml`).
+    /// Defaults to `<model>-encoder-openvino.xml` next to the model file.
+    pub model_path: Option<PathBuf>,
+    /// OpenVINO device to run the encoder on, e.g. `CPU` or `GPU`. Defaults to `CPU`.
+    pub device: Option<String>,
+    /// Directory used to cache compiled encoder blobs, speeding up later loads. Defaults to none.
+    pub cache_dir: Option<PathBuf>,
+}
+
+/// Which implementation runs the encoder of a [`crate::WhisperState`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EncoderBackend {
+    /// The regular GGML encoder.
+    Ggml,
+    /// A CoreML encoder.
+    CoreMl,
+    /// An OpenVINO encoder.
+    OpenVino,
 }
 
 #[allow(clippy::derivable_impls)] // this impl cannot be derived
@@ -455,6 +735,9 @@ impl<'a> Default for WhisperContextParameters<'a> {
             flash_attn: false,
             gpu_device: 0,
             dtw_parameters: DtwParameters::default(),
+            coreml_encoder_path: None,
+            openvino_encoder: None,
+            encoder_only: false,
         }
     }
 }
@@ -462,14 +745,26 @@ impl<'a> WhisperContextParameters<'a> {
     pub fn new() -> Self {
         Self::default()
     }
+    /// Run the model on a GPU if whisper.cpp was built with a GPU backend, otherwise on the CPU.
+    /// See [`crate::WhisperContext::backend_info`] for the device actually used.
+    ///
+    /// Defaults to true with a GPU feature such as `cuda` or `metal` enabled, false otherwise.
     pub fn use_gpu(&mut self, use_gpu: bool) -> &mut Self {
         self.use_gpu = use_gpu;
         self
     }
+    /// Use flash attention in the encoder and decoder, which is faster and needs less memory
+    /// on most GPUs. DTW timestamps are disabled while it is enabled.
+    ///
+    /// Defaults to false.
     pub fn flash_attn(&mut self, flash_attn: bool) -> &mut Self {
         self.flash_attn = flash_attn;
         self
     }
+    /// Index of the GPU to run on when [`Self::use_gpu`] is enabled, counting only GPUs in the
+    /// order ggml registers them.
+    ///
+    /// Defaults to 0.
     pub fn gpu_device(&mut self, gpu_device: c_int) -> &mut Self {
         self.gpu_device = gpu_device;
         self
@@ -478,6 +773,34 @@ impl<'a> WhisperContextParameters<'a> {
         self.dtw_parameters = dtw_parameters;
         self
     }
+    pub fn coreml_encoder_path(&mut self, path: impl Into<PathBuf>) -> &mut Self {
+        self.coreml_encoder_path = Some(path.into());
+        self
+    }
+    pub fn openvino_encoder(&mut self, openvino_encoder: OpenVinoEncoderParameters) -> &mut Self {
+        self.openvino_encoder = Some(openvino_encoder);
+        self
+    }
+    /// Load the encoder without the decoder, for contexts that only ever run
+    /// [`crate::WhisperState::encode`], e.g. to benchmark an encoder or to compile an OpenVINO
+    /// encoder into its [`OpenVinoEncoderParameters::cache_dir`] ahead of time.
+    ///
+    /// The model is rewritten while whisper.cpp reads it to keep a single decoder layer, which
+    /// whisper.cpp needs to create states, so the decoder weights and the decoder caches of
+    /// each state are mostly left out. For most models that is close to half the memory;
+    /// large-v3-turbo, with its 4 decoder layers, saves much less. The model then reports
+    /// one text layer, and decoding with it, such as [`crate::WhisperState::full`], fails with
+    /// [`WhisperError::DecoderNotLoaded`], as does loading it with DTW token timestamps.
+    /// whisper.cpp has no way to hand out the encoder output itself.
+    ///
+    /// CoreML encoders can't be used, as whisper.cpp only finds them next to the model file it
+    /// loads.
+    ///
+    /// Defaults to false.
+    pub fn encoder_only(&mut self, encoder_only: bool) -> &mut Self {
+        self.encoder_only = encoder_only;
+        self
+    }
 
     fn to_c_struct(&self) -> whisper_rs_sys::whisper_context_params {
         let dtw_token_timestamps = !matches!(self.dtw_parameters.mode, DtwMode::None);
@@ -571,10 +894,80 @@ impl<'a> WhisperContextParameters<'a> {
 }
 
 /// [EXPERIMENTAL] Enable Token-level timestamps with DTW, default Disabled
+///
+/// DTW aligns each token with the audio using the cross-attention of a few alignment heads,
+/// which gives much more accurate word timings than token timestamps. The result is in
+/// [`crate::TokenData::t_dtw`] and [`crate::TranscriptToken::t_dtw`], and subtitle cues
+/// cut at word boundaries use it when available.
+///
+/// # Examples
+/// ```
+/// # use whisper_rs::{DtwModelPreset, DtwParameters, WhisperContextParameters};
+/// let mut params = WhisperContextParameters::default();
+/// params.dtw_parameters(DtwParameters::preset(DtwModelPreset::BaseEn));
+/// ```
 #[derive(Debug, Clone)]
 pub struct DtwParameters<'a> {
     pub mode: DtwMode<'a>,
+    /// Bytes allocated for the DTW computation of each state, default 128 MiB.
+    /// Every run allocates the buffer anew, so concurrent states each need this much.
     pub dtw_mem_size: usize,
+    memory_fallback: bool,
+}
+
+impl<'a> DtwParameters<'a> {
+    /// Use the alignment heads of a standard model, see [`DtwMode::ModelPreset`].
+    pub fn preset(model_preset: DtwModelPreset) -> Self {
+        Self {
+            mode: DtwMode::ModelPreset { model_preset },
+            ..Default::default()
+        }
+    }
+
+    /// Use every head of the top `n_top` text layers, see [`DtwMode::TopMost`].
+    /// Works with any model, including those without a preset.
+    pub fn top_most(n_top: c_int) -> Self {
+        Self {
+            mode: DtwMode::TopMost { n_top },
+            ..Default::default()
+        }
+    }
+
+    /// Use a custom list of alignment heads, see [`DtwMode::Custom`].
+    pub fn custom(aheads: &'a [whisper_rs_sys::whisper_ahead]) -> Self {
+        Self {
+            mode: DtwMode::Custom { aheads },
+            ..Default::default()
+        }
+    }
+
+    /// Set [`Self::dtw_mem_size`].
+    pub fn with_mem_size(mut self, dtw_mem_size: usize) -> Self {
+        self.dtw_mem_size = dtw_mem_size;
+        self
+    }
+
+    /// Fall back to regular token timestamps, with a warning, if the buffer is larger than the
+    /// memory available when the model is loaded, rather than refusing to load it with
+    /// [`crate::UnsupportedConfiguration::DtwMemoryUnavailable`]. Default true.
+    ///
+    /// Every run of a context that fell back reports it to
+    /// [`crate::Observer::on_dtw_fallback`], and [`crate::BackendInfo::dtw_timestamps`] tells
+    /// whether DTW stayed enabled.
+    ///
+    /// Available memory is what the system or the process's cgroup has available on Linux, the
+    /// free and inactive memory on macOS, and the available physical memory on Windows.
+    /// Elsewhere it isn't known, and DTW is always enabled.
+    pub fn with_memory_fallback(mut self, memory_fallback: bool) -> Self {
+        self.memory_fallback = memory_fallback;
+        self
+    }
+
+    /// Whether DTW is turned off when its buffer doesn't fit in memory,
+    /// see [`Self::with_memory_fallback`].
+    pub fn memory_fallback(&self) -> bool {
+        self.memory_fallback
+    }
 }
 
 impl Default for DtwParameters<'_> {
@@ -582,6 +975,7 @@ impl Default for DtwParameters<'_> {
         Self {
             mode: DtwMode::None,
             dtw_mem_size: 1024 * 1024 * 128,
+            memory_fallback: true,
         }
     }
 }
@@ -605,7 +999,7 @@ pub enum DtwMode<'a> {
     ModelPreset { model_preset: DtwModelPreset },
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum DtwModelPreset {
     TinyEn,
     Tiny,
@@ -621,6 +1015,74 @@ pub enum DtwModelPreset {
     LargeV3Turbo,
 }
 
+impl DtwModelPreset {
+    /// The preset for a model of [`crate::models::KNOWN_MODELS`] by name, e.g. `"base.en"`,
+    /// including quantized variants such as `"large-v3-turbo-q5_0"`.
+    pub fn for_model_name(name: &str) -> Option<Self> {
+        let base = name.split("-q").next().unwrap_or(name);
+        Some(match base {
+            "tiny.en" => Self::TinyEn,
+            "tiny" => Self::Tiny,
+            "base.en" => Self::BaseEn,
+            "base" => Self::Base,
+            "small.en" => Self::SmallEn,
+            "small" => Self::Small,
+            "medium.en" => Self::MediumEn,
+            "medium" => Self::Medium,
+            "large-v1" => Self::LargeV1,
+            "large-v2" => Self::LargeV2,
+            "large-v3" => Self::LargeV3,
+            "large-v3-turbo" => Self::LargeV3Turbo,
+            _ => return None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_coreml_encoder_path_for() {
+        assert_eq!(
+            coreml_encoder_path_for("models/ggml-base.en.bin"),
+            PathBuf::from("models/ggml-base.en-encoder.mlmodelc")
+        );
+        assert_eq!(
+            coreml_encoder_path_for("models/ggml-base.en-q5_0.bin"),
+            PathBuf::from("models/ggml-base.en-encoder.mlmodelc")
+        );
+        assert_eq!(
+            coreml_encoder_path_for("ggml-large-v3-turbo.bin"),
+            PathBuf::from("ggml-large-v3-turbo-encoder.mlmodelc")
+        );
+    }
+
+    #[test]
+    fn test_device_selection_reaches_c_params() {
+        let mut params = WhisperContextParameters::new();
+        params.use_gpu(true).gpu_device(2).flash_attn(true);
+        let c_params = params.to_c_struct();
+        assert!(c_params.use_gpu);
+        assert!(c_params.flash_attn);
+        assert_eq!(c_params.gpu_device, 2);
+        assert!(!c_params.dtw_token_timestamps);
+    }
+
+    #[test]
+    fn test_dtw_preset_for_model_name() {
+        assert_eq!(
+            DtwModelPreset::for_model_name("base.en"),
+            Some(DtwModelPreset::BaseEn)
+        );
+        assert_eq!(
+            DtwModelPreset::for_model_name("large-v3-turbo-q5_0"),
+            Some(DtwModelPreset::LargeV3Turbo)
+        );
+        assert_eq!(DtwModelPreset::for_model_name("distil-large-v3"), None);
+    }
+}
+
 #[cfg(test)]
 #[cfg(feature = "test-with-tiny-model")]
 mod test_with_tiny_model {
@@ -1,20 +1,37 @@
 use std::borrow::Cow;
-use std::ffi::c_int;
+use std::ffi::{c_int, CString};
+use std::path::Path;
 use std::sync::Arc;
 
 use crate::{
-    WhisperContextParameters, WhisperError, WhisperInnerContext, WhisperState, WhisperTokenId,
+    EncoderBackend, WhisperContextParameters, WhisperError, WhisperInnerContext, WhisperState,
+    WhisperTokenId,
 };
 
+/// A loaded Whisper model.
+///
+/// Cloning is cheap: clones share the same model weights, which are freed once the last clone
+/// and the last [`WhisperState`] created from them are dropped. A context is loaded without a
+/// state of its own; create one per concurrent transcription with [`Self::create_state`].
+#[derive(Clone)]
 pub struct WhisperContext {
     ctx: Arc<WhisperInnerContext>,
 }
 
 impl WhisperContext {
-    fn wrap(ctx: WhisperInnerContext) -> Self {
+    pub(crate) fn wrap(ctx: WhisperInnerContext) -> Self {
         Self { ctx: Arc::new(ctx) }
     }
 
+    #[cfg(feature = "server")]
+    pub(crate) fn downgrade(&self) -> std::sync::Weak<WhisperInnerContext> {
+        Arc::downgrade(&self.ctx)
+    }
+
+    pub(crate) fn inner(&self) -> &WhisperInnerContext {
+        &self.ctx
+    }
+
     /// Create a new WhisperContext from a file, with parameters.
     ///
     /// # Arguments
@@ -22,7 +39,8 @@ impl WhisperContext {
     /// * parameters: A parameter struct containing the parameters to use.
     ///
     /// # Returns
-    /// Ok(Self) on success, Err(WhisperError) on failure.
+    /// Ok(Self) on success, Err(WhisperError) on failure, including
+    /// [`WhisperError::UnsupportedConfiguration`] for parameters the model or backend can't honor.
     ///
     /// # C++ equivalent
     /// `struct whisper_context * whisper_init_from_file_with_params_no_state(const char * path_model, struct whisper_context_params params);`
@@ -36,11 +54,16 @@ impl WhisperContext {
 
     /// Create a new WhisperContext from a buffer.
     ///
+    /// This doesn't touch the file system, so it also works where there is none, e.g. with a model
+    /// embedded with [`include_bytes!`]. See [`Self::from_fetcher`] to fetch the model in chunks,
+    /// and [`Self::from_reader`] to stream it from any [`std::io::Read`].
+    ///
     /// # Arguments
     /// * buffer: The buffer containing the model.
     ///
     /// # Returns
-    /// Ok(Self) on success, Err(WhisperError) on failure.
+    /// Ok(Self) on success, Err(WhisperError) on failure, including
+    /// [`WhisperError::UnsupportedConfiguration`] for parameters the model or backend can't honor.
     ///
     /// # C++ equivalent
     /// `struct whisper_context * whisper_init_from_buffer_with_params_no_state(void * buffer, size_t buffer_size, struct whisper_context_params params);`
@@ -70,6 +93,59 @@ impl WhisperContext {
         self.ctx.tokenize(text, max_tokens)
     }
 
+    /// Convert the provided text into tokens, however many it takes.
+    ///
+    /// Mid-sentence words are tokenized with their leading space, which usually gives different
+    /// tokens: tokenize `" word"` rather than `"word"` to bias words within a sentence.
+    ///
+    /// # Returns
+    /// `Ok(Vec<WhisperTokenId>)` on success, `Err(WhisperError::NullByteInString)` if the text
+    /// contains a nul byte.
+    ///
+    /// # C++ equivalent
+    /// `int whisper_token_count(struct whisper_context * ctx, const char * text)` and
+    /// `int whisper_tokenize(struct whisper_context * ctx, const char * text, whisper_token * tokens, int n_max_tokens);`
+    pub fn tokenize_all(&self, text: &str) -> Result<Vec<WhisperTokenId>, WhisperError> {
+        let count = self.ctx.token_count(text)?;
+        self.ctx.tokenize(text, count)
+    }
+
+    /// Count the tokens of the provided text, e.g. to check that a prompt fits in
+    /// [`Self::max_prompt_tokens`].
+    ///
+    /// # Returns
+    /// `Ok(usize)` on success, `Err(WhisperError::NullByteInString)` if the text contains a nul
+    /// byte.
+    ///
+    /// # C++ equivalent
+    /// `int whisper_token_count(struct whisper_context * ctx, const char * text)`
+    pub fn count_tokens(&self, text: &str) -> Result<usize, WhisperError> {
+        self.ctx.token_count(text)
+    }
+
+    /// Convert text tokens back into text.
+    ///
+    /// Tokens are byte sequences that may split a character, so the bytes of all tokens are
+    /// joined before decoding them; invalid UTF-8 is replaced with the Unicode replacement
+    /// character. Special and timestamp tokens, from [`Self::token_eot`] on, and ids outside the
+    /// vocabulary are skipped.
+    pub fn detokenize(&self, tokens: &[WhisperTokenId]) -> String {
+        let eot = self.token_eot();
+        let mut bytes = Vec::new();
+        for &token in tokens.iter().filter(|&&t| (0..eot).contains(&t)) {
+            if let Ok(token) = self.ctx.token_to_bytes(token) {
+                bytes.extend_from_slice(token);
+            }
+        }
+        String::from_utf8_lossy(&bytes).into_owned()
+    }
+
+    /// The most prompt tokens whisper.cpp uses, `n_text_ctx / 2`. Longer prompts, including
+    /// the text of previous windows, are cut from the front.
+    pub fn max_prompt_tokens(&self) -> usize {
+        (self.n_text_ctx() / 2).max(0) as usize
+    }
+
     /// Get n_vocab.
     ///
     /// # Returns
@@ -419,6 +495,10 @@ impl WhisperContext {
 
     /// Print performance statistics to stderr.
     ///
+    /// Contexts created by this crate have no built-in state, so this only reports the time
+    /// taken to load the model and the time since. Use [`crate::WhisperState::timings`] for the
+    /// time spent transcribing.
+    ///
     /// # C++ equivalent
     /// `void whisper_print_timings(struct whisper_context * ctx)`
     pub fn print_timings(&self) {
@@ -450,10 +530,28 @@ impl WhisperContext {
         self.ctx.token_transcribe()
     }
 
-    // we don't implement `whisper_init()` here since i have zero clue what `whisper_model_loader` does
-
     /// Create a new state object, ready for use.
     ///
+    /// Each state holds its own KV caches and results, so any number of them can transcribe at
+    /// the same time, one per thread, while sharing the model weights of this context. States
+    /// keep the model alive, so they can be dropped in any order, before or after the context.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// # use whisper_rs::{FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters};
+    /// let ctx = WhisperContext::new_with_params("model.bin", WhisperContextParameters::default()).unwrap();
+    /// let files: Vec<Vec<f32>> = vec![vec![0.0; 16000]; 4];
+    /// std::thread::scope(|scope| {
+    ///     for audio in &files {
+    ///         let mut state = ctx.create_state().unwrap();
+    ///         scope.spawn(move || {
+    ///             let params = FullParams::new(SamplingStrategy::default());
+    ///             state.full(params, audio).unwrap();
+    ///         });
+    ///     }
+    /// });
+    /// ```
+    ///
     /// # Returns
     /// Ok(WhisperState) on success, Err(WhisperError) on failure.
     ///
@@ -462,10 +560,64 @@ impl WhisperContext {
     pub fn create_state(&self) -> Result<WhisperState, WhisperError> {
         let state = unsafe { whisper_rs_sys::whisper_init_state(self.ctx.ctx) };
         if state.is_null() {
-            Err(WhisperError::InitError)
-        } else {
-            // SAFETY: this is known to be a valid pointer to a `whisper_state` struct
-            Ok(unsafe { WhisperState::new(self.ctx.clone(), state) })
+            return Err(WhisperError::InitError);
         }
+        // SAFETY: this is known to be a valid pointer to a `whisper_state` struct
+        let mut state = unsafe { WhisperState::new(self.ctx.clone(), state) };
+
+        if let Some(openvino) = &self.ctx.openvino_encoder {
+            let to_cstring = |p: &Path| CString::new(p.to_string_lossy().as_ref());
+            let model_path = openvino.model_path.as_deref().map(to_cstring).transpose()?;
+            let device = CString::new(openvino.device.as_deref().unwrap_or("CPU"))?;
+            let cache_dir = openvino.cache_dir.as_deref().map(to_cstring).transpose()?;
+
+            let ret = unsafe {
+                whisper_rs_sys::whisper_ctx_init_openvino_encoder_with_state(
+                    self.ctx.ctx,
+                    state.ptr,
+                    model_path.as_ref().map_or(std::ptr::null(), |p| p.as_ptr()),
+                    device.as_ptr(),
+                    cache_dir.as_ref().map_or(std::ptr::null(), |p| p.as_ptr()),
+                )
+            };
+            if ret == 0 {
+                state.encoder_backend = EncoderBackend::OpenVino;
+            }
+        } else if cfg!(feature = "coreml")
+            && self
+                .ctx
+                .coreml_encoder_path
+                .as_deref()
+                .is_some_and(Path::exists)
+        {
+            state.encoder_backend = EncoderBackend::CoreMl;
+        }
+
+        Ok(state)
+    }
+
+    /// The path this model was loaded from, or `None` if it was loaded from a buffer.
+    pub fn model_path(&self) -> Option<&Path> {
+        self.ctx.model_path.as_deref()
+    }
+
+    /// Where whisper.cpp will look for a CoreML encoder when creating states:
+    /// either [`WhisperContextParameters::coreml_encoder_path`], or the path derived from the model path.
+    ///
+    /// Only meaningful with the `coreml` feature. `None` if the model was loaded from a buffer.
+    pub fn coreml_encoder_path(&self) -> Option<&Path> {
+        self.ctx.coreml_encoder_path.as_deref()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn contexts_and_states_can_be_shared_between_threads() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<WhisperContext>();
+        assert_send_sync::<WhisperState>();
     }
 }
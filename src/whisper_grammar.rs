@@ -78,3 +78,41 @@ impl WhisperGrammarElement {
         }
     }
 }
+
+/// Grammar elements in the layout `whisper_full_params::grammar_rules` takes: an array of
+/// pointers to the start of each rule, shared between clones of [`crate::FullParams`].
+#[derive(Debug)]
+pub(crate) struct GrammarRules {
+    /// The rules one after another, each ending with [`WhisperGrammarElementType::End`].
+    pub(crate) elements: Vec<whisper_rs_sys::whisper_grammar_element>,
+    /// Start of each rule in `elements`.
+    pub(crate) rules: Vec<*const whisper_rs_sys::whisper_grammar_element>,
+}
+
+// the pointers only point into `elements`, which is never modified
+unsafe impl Send for GrammarRules {}
+
+unsafe impl Sync for GrammarRules {}
+
+impl GrammarRules {
+    pub(crate) fn new(grammar: &[WhisperGrammarElement]) -> Self {
+        let mut elements: Vec<_> = grammar.iter().map(|e| e.to_c_type()).collect();
+        let end = whisper_rs_sys::whisper_gretype::from(WhisperGrammarElementType::End);
+        if elements.last().is_some_and(|e| e.type_ != end) {
+            elements
+                .push(WhisperGrammarElement::new(WhisperGrammarElementType::End, 0).to_c_type());
+        }
+        let starts = std::iter::once(0).chain(
+            elements
+                .iter()
+                .enumerate()
+                .filter(|(_, e)| e.type_ == end)
+                .map(|(i, _)| i + 1),
+        );
+        let rules = starts
+            .filter(|&start| start < elements.len())
+            .map(|start| elements[start..].as_ptr())
+            .collect();
+        Self { elements, rules }
+    }
+}
@@ -1,12 +1,19 @@
-use crate::whisper_grammar::WhisperGrammarElement;
+use crate::language_defaults;
+use crate::watchdog::WatchdogRun;
+use crate::whisper_grammar::{GrammarRules, WhisperGrammarElement};
 use crate::whisper_vad::WhisperVadParams;
+use crate::{ContextCompression, Language, WhisperError};
 use std::ffi::{c_char, c_float, c_int, CString};
+use std::iter::Peekable;
 use std::marker::PhantomData;
-use std::sync::Arc;
-use whisper_rs_sys::whisper_token;
-
-/// The sampling strategy to use to pick tokens from a list of likely possibilities.
-#[derive(Debug, Clone)]
+use std::str::CharIndices;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+use whisper_rs_sys::{whisper_token, whisper_token_data};
+
+/// The sampling strategy to use to pick tokens from a list of likely possibilities,
+/// along with its tuning, see [`FullParams::set_sampling_strategy`].
+#[derive(Debug, Clone, PartialEq)]
 pub enum SamplingStrategy {
     /// Greedy sampling: picks the token with the highest probability after having seen `best_of` tokens.
     Greedy {
@@ -28,6 +35,13 @@ pub enum SamplingStrategy {
     },
 }
 
+impl Default for SamplingStrategy {
+    /// Greedy sampling with the `whisper.cpp` default of `best_of: 5`.
+    fn default() -> Self {
+        Self::Greedy { best_of: 5 }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct SegmentCallbackData {
     pub segment: i32,
@@ -36,36 +50,182 @@ pub struct SegmentCallbackData {
     pub text: String,
 }
 
+/// When [`FullParams::set_segment_callback_batched`] delivers segments.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SegmentBatch {
+    /// Deliver a batch once this many segments are pending.
+    pub max_segments: usize,
+    /// Also deliver the pending segments once the oldest has waited this long.
+    /// Only checked when a new segment arrives, there is no timer.
+    pub max_delay: Option<Duration>,
+}
+
+impl SegmentBatch {
+    /// Deliver segments in batches of `max_segments`, clamped to at least 1.
+    pub fn segments(max_segments: usize) -> Self {
+        Self {
+            max_segments: max_segments.max(1),
+            max_delay: None,
+        }
+    }
+
+    /// Deliver pending segments after `max_delay`, even if the batch isn't full.
+    pub fn max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = Some(max_delay);
+        self
+    }
+}
+
 type SegmentCallbackFn = Box<dyn FnMut(SegmentCallbackData)>;
+pub(crate) type LogitsFilterFn = Box<dyn FnMut(&[whisper_token_data], &mut [f32]) + Send>;
+type LogitsFilterChain = Vec<Arc<Mutex<LogitsFilterFn>>>;
+
+struct SegmentBatcher {
+    callback: Box<dyn FnMut(Vec<SegmentCallbackData>) + Send>,
+    batch: SegmentBatch,
+    pending: Vec<SegmentCallbackData>,
+    oldest: Option<Instant>,
+}
+
+impl SegmentBatcher {
+    fn push(&mut self, segment: SegmentCallbackData) {
+        let oldest = *self.oldest.get_or_insert_with(Instant::now);
+        self.pending.push(segment);
+        let overdue = match self.batch.max_delay {
+            Some(delay) => oldest.elapsed() >= delay,
+            None => false,
+        };
+        if self.pending.len() >= self.batch.max_segments || overdue {
+            self.flush();
+        }
+    }
+
+    fn flush(&mut self) {
+        self.oldest = None;
+        if !self.pending.is_empty() {
+            (self.callback)(std::mem::take(&mut self.pending));
+        }
+    }
+}
+
+/// Encoder frames needed for `chunk_ms` of audio, 0 (the full context) from 30 seconds.
+fn audio_ctx_for_ms(chunk_ms: u32) -> c_int {
+    const FRAME_MS: u32 = 20;
+    let full = whisper_rs_sys::WHISPER_CHUNK_SIZE * 1000;
+    if chunk_ms >= full {
+        return 0;
+    }
+    // a little slack, so speech at the very end of the chunk isn't cut off
+    (chunk_ms.div_ceil(FRAME_MS) + 64).min(full / FRAME_MS) as c_int
+}
+
+/// Mirrors how `whisper_full_with_state` builds its list of temperatures.
+fn fallback_temperatures(temperature: f32, temperature_inc: f32) -> Vec<f32> {
+    let mut temperatures = vec![temperature];
+    if temperature_inc > 0.0 {
+        let mut t = temperature + temperature_inc;
+        while t < 1.0 + 1e-6 {
+            temperatures.push(t);
+            t += temperature_inc;
+        }
+    }
+    temperatures
+}
+
+fn strategy_id(sampling_strategy: &SamplingStrategy) -> whisper_rs_sys::whisper_sampling_strategy {
+    match sampling_strategy {
+        SamplingStrategy::Greedy { .. } => {
+            whisper_rs_sys::whisper_sampling_strategy_WHISPER_SAMPLING_GREEDY
+        }
+        SamplingStrategy::BeamSearch { .. } => {
+            whisper_rs_sys::whisper_sampling_strategy_WHISPER_SAMPLING_BEAM_SEARCH
+        }
+    }
+}
+
+/// Maximum number of parallel decoders whisper.cpp allocates per state (`WHISPER_MAX_DECODERS`).
+pub(crate) const WHISPER_MAX_DECODERS: c_int = 8;
 
 #[derive(Clone)]
 pub struct FullParams<'a, 'b> {
     pub(crate) fp: whisper_rs_sys::whisper_full_params,
     phantom_lang: PhantomData<&'a str>,
     phantom_tokens: PhantomData<&'b [c_int]>,
-    grammar: Option<Vec<whisper_rs_sys::whisper_grammar_element>>,
+    pub(crate) grammar: Option<Arc<GrammarRules>>,
     progress_callback_safe: Option<Arc<Box<dyn FnMut(i32)>>>,
     abort_callback_safe: Option<Arc<Box<dyn FnMut() -> bool>>>,
     segment_calllback_safe: Option<Arc<SegmentCallbackFn>>,
+    pub(crate) logits_filters: Option<Arc<LogitsFilterChain>>,
+    segment_batcher: Option<Arc<Mutex<SegmentBatcher>>>,
+    pub(crate) language_defaults: bool,
+    pub(crate) pad_short_audio: bool,
+    /// Owners of the initial prompt and prompt tokens `fp` points into, shared between clones.
+    initial_prompt: Option<Arc<CString>>,
+    prompt_tokens: Option<Arc<[whisper_token]>>,
+    suppress_regex: Option<Arc<CString>>,
+    pub(crate) watchdog: Option<Arc<WatchdogRun>>,
+    /// The repetition check of `watchdog` in the logits filters, removed when it is replaced.
+    pub(crate) watchdog_filter: Option<Arc<Mutex<LogitsFilterFn>>>,
+    pub(crate) context_compression: Option<ContextCompression>,
+    /// Parameters with a language default that were set explicitly, as `language_defaults` flags.
+    pub(crate) explicit: u8,
 }
 
 impl<'a, 'b> FullParams<'a, 'b> {
     /// Create a new set of parameters for the decoder.
     pub fn new(sampling_strategy: SamplingStrategy) -> FullParams<'a, 'b> {
-        let mut fp = unsafe {
-            whisper_rs_sys::whisper_full_default_params(match sampling_strategy {
-                SamplingStrategy::Greedy { .. } => {
-                    whisper_rs_sys::whisper_sampling_strategy_WHISPER_SAMPLING_GREEDY
-                }
-                SamplingStrategy::BeamSearch { .. } => {
-                    whisper_rs_sys::whisper_sampling_strategy_WHISPER_SAMPLING_BEAM_SEARCH
-                }
-            } as _)
+        let fp = unsafe {
+            whisper_rs_sys::whisper_full_default_params(strategy_id(&sampling_strategy) as _)
         };
 
+        let mut params = Self {
+            fp,
+            phantom_lang: PhantomData,
+            phantom_tokens: PhantomData,
+            grammar: None,
+            progress_callback_safe: None,
+            abort_callback_safe: None,
+            segment_calllback_safe: None,
+            logits_filters: None,
+            segment_batcher: None,
+            language_defaults: true,
+            pad_short_audio: true,
+            initial_prompt: None,
+            prompt_tokens: None,
+            suppress_regex: None,
+            watchdog: None,
+            watchdog_filter: None,
+            context_compression: None,
+            explicit: 0,
+        };
+        params.set_sampling_strategy(sampling_strategy);
+        params
+    }
+
+    /// Parameters for real-time use on short chunks, trading some accuracy for latency.
+    ///
+    /// Decodes greedily in a single pass, see [`Self::disable_fallback`], into a single segment,
+    /// without conditioning on earlier text, and with the audio context reduced to
+    /// [`Self::LOW_LATENCY_AUDIO_CTX`], which covers chunks of up to 15 seconds.
+    /// Use [`Self::set_audio_ctx_for_ms`] to shrink it further for shorter chunks.
+    pub fn low_latency() -> FullParams<'a, 'b> {
+        let mut params = Self::new(SamplingStrategy::Greedy { best_of: 1 });
+        params.set_audio_ctx(Self::LOW_LATENCY_AUDIO_CTX);
+        params.set_single_segment(true);
+        params.set_no_context(true);
+        params.disable_fallback();
+        params
+    }
+
+    /// Audio context of [`Self::low_latency`]: 768 frames, about 15 seconds.
+    pub const LOW_LATENCY_AUDIO_CTX: c_int = 768;
+
+    /// Switch to another sampling strategy, keeping all other parameters.
+    pub fn set_sampling_strategy(&mut self, sampling_strategy: SamplingStrategy) {
+        self.fp.strategy = strategy_id(&sampling_strategy) as _;
         match sampling_strategy {
             SamplingStrategy::Greedy { best_of } => {
-                fp.greedy.best_of = best_of;
+                self.fp.greedy.best_of = best_of;
             }
             SamplingStrategy::BeamSearch {
                 mut beam_size,
@@ -75,19 +235,25 @@ impl<'a, 'b> FullParams<'a, 'b> {
                     beam_size = 1;
                 }
 
-                fp.beam_search.beam_size = beam_size;
-                fp.beam_search.patience = patience;
+                self.fp.beam_search.beam_size = beam_size;
+                self.fp.beam_search.patience = patience;
             }
         }
+    }
 
-        Self {
-            fp,
-            phantom_lang: PhantomData,
-            phantom_tokens: PhantomData,
-            grammar: None,
-            progress_callback_safe: None,
-            abort_callback_safe: None,
-            segment_calllback_safe: None,
+    /// The sampling strategy and its tuning, as set by [`Self::new`] or [`Self::set_sampling_strategy`].
+    pub fn sampling_strategy(&self) -> SamplingStrategy {
+        if self.fp.strategy
+            == whisper_rs_sys::whisper_sampling_strategy_WHISPER_SAMPLING_BEAM_SEARCH as _
+        {
+            SamplingStrategy::BeamSearch {
+                beam_size: self.fp.beam_search.beam_size,
+                patience: self.fp.beam_search.patience,
+            }
+        } else {
+            SamplingStrategy::Greedy {
+                best_of: self.fp.greedy.best_of,
+            }
         }
     }
 
@@ -98,7 +264,16 @@ impl<'a, 'b> FullParams<'a, 'b> {
         self.fp.n_threads = n_threads;
     }
 
-    /// Max tokens to use from past text as prompt for the decoder
+    /// Max tokens to use from past text as prompt for the decoder.
+    ///
+    /// Each 30 second window is decoded with the text of the previous ones as context, which keeps
+    /// spelling and style consistent across a long recording. On repetitive audio it also lets a
+    /// repeated phrase feed on itself; a small value such as 64 limits how far back that reaches,
+    /// at some cost in coherence. 0 uses no past text at all, like [`Self::set_no_context`],
+    /// but also drops the initial prompt.
+    ///
+    /// whisper.cpp never uses more than half the model's text context, 224 tokens for the
+    /// released models, so larger values have no further effect. Must not be negative.
     ///
     /// Defaults to 16384.
     pub fn set_n_max_text_ctx(&mut self, n_max_text_ctx: c_int) {
@@ -107,18 +282,35 @@ impl<'a, 'b> FullParams<'a, 'b> {
 
     /// Set the start offset in milliseconds to use for decoding.
     ///
+    /// Only the audio from the offset on is transcribed, and timestamps stay relative to the start
+    /// of the buffer passed to whisper.cpp, so segments of a re-processed part of a recording line
+    /// up with those of the full run. The spectrogram is still computed for the whole buffer;
+    /// for a short part of a very long buffer, passing the subslice is faster.
+    ///
     /// Defaults to 0.
     pub fn set_offset_ms(&mut self, offset_ms: c_int) {
         self.fp.offset_ms = offset_ms;
     }
 
-    /// Set the audio duration to process in milliseconds.
+    /// Set the audio duration to process in milliseconds, from [`Self::set_offset_ms`].
+    /// 0 means to the end of the audio, and so does a duration running past it.
     ///
     /// Defaults to 0.
     pub fn set_duration_ms(&mut self, duration_ms: c_int) {
         self.fp.duration_ms = duration_ms;
     }
 
+    /// Transcribe only `range` of the audio, see [`Self::set_offset_ms`] and
+    /// [`Self::set_duration_ms`]. An empty range transcribes to the end of the audio.
+    ///
+    /// [`crate::WhisperState::full`] returns [`WhisperError::TimeRangeOutOfBounds`]
+    /// if the range starts past the end of the audio, and ends a range running past it there.
+    pub fn set_time_range(&mut self, range: std::ops::Range<Duration>) {
+        let to_ms = |d: Duration| d.as_millis().min(c_int::MAX as u128) as c_int;
+        self.fp.offset_ms = to_ms(range.start);
+        self.fp.duration_ms = to_ms(range.end.saturating_sub(range.start));
+    }
+
     /// Set whether to translate the output to the language specified by `language`.
     ///
     /// Defaults to false.
@@ -126,6 +318,17 @@ impl<'a, 'b> FullParams<'a, 'b> {
         self.fp.translate = translate;
     }
 
+    /// Pad audio shorter than about a second with silence, instead of returning
+    /// [`WhisperError::AudioTooShort`]. whisper.cpp silently skips such audio otherwise.
+    ///
+    /// The padding is added at the end, and timestamps are clamped to the end of the real audio.
+    /// A [`Self::set_duration_ms`] that is too short is lengthened to match.
+    ///
+    /// Defaults to true.
+    pub fn set_pad_short_audio(&mut self, pad: bool) {
+        self.pad_short_audio = pad;
+    }
+
     /// Do not use past transcription (if any) as initial prompt for the decoder.
     ///
     /// Defaults to false.
@@ -207,26 +410,39 @@ impl<'a, 'b> FullParams<'a, 'b> {
 
     /// # EXPERIMENTAL
     ///
-    /// Set maximum segment length in characters.
+    /// Set maximum segment length in characters. 0 means no limit, and 1 produces a segment
+    /// per token, for word-level timing.
+    ///
+    /// Segments are split using token timestamps, so a value above 0 also enables
+    /// [`Self::set_token_timestamps`], as the whisper.cpp command line tool does.
+    /// Use [`Self::set_split_on_word`] to avoid splitting inside words, e.g. for subtitles.
     ///
     /// Defaults to 0.
     pub fn set_max_len(&mut self, max_len: c_int) {
         self.fp.max_len = max_len;
+        if max_len > 0 {
+            self.fp.token_timestamps = true;
+        }
     }
 
     /// # EXPERIMENTAL
     ///
-    /// Should the timestamps be split on words instead of characters?
+    /// Should segments split by [`Self::set_max_len`] end at word boundaries instead of
+    /// in the middle of a word? Segments may then exceed the maximum length by a word.
     ///
     /// Defaults to false.
     pub fn set_split_on_word(&mut self, split_on_word: bool) {
         self.fp.split_on_word = split_on_word;
+        self.explicit |= language_defaults::SPLIT_ON_WORD;
     }
 
     /// # EXPERIMENTAL
     ///
     /// Set maximum tokens per segment. 0 means no limit.
     ///
+    /// Unlike [`Self::set_max_len`], this limits the text the decoder produces per segment
+    /// rather than splitting segments afterwards, and doesn't need token timestamps.
+    ///
     /// Defaults to 0.
     pub fn set_max_tokens(&mut self, max_tokens: c_int) {
         self.fp.max_tokens = max_tokens;
@@ -245,11 +461,22 @@ impl<'a, 'b> FullParams<'a, 'b> {
     ///
     /// Overwrite the audio context size. 0 = default.
     ///
+    /// The encoder always processes a full 30 second window of 1500 frames, 20 ms each, even for
+    /// shorter input. Shrinking the context makes it process only the first `audio_ctx` frames,
+    /// which cuts encoding time roughly in proportion, but anything beyond is not transcribed
+    /// and accuracy drops somewhat. See [`Self::set_audio_ctx_for_ms`] to size it for a chunk.
+    ///
     /// Defaults to 0.
     pub fn set_audio_ctx(&mut self, audio_ctx: c_int) {
         self.fp.audio_ctx = audio_ctx;
     }
 
+    /// Shrink the audio context to what `chunk_ms` of audio needs, see [`Self::set_audio_ctx`].
+    /// Chunks of 30 seconds or more use the full context.
+    pub fn set_audio_ctx_for_ms(&mut self, chunk_ms: u32) {
+        self.fp.audio_ctx = audio_ctx_for_ms(chunk_ms);
+    }
+
     /// # EXPERIMENTAL
     ///
     /// Enable tinydiarize support.
@@ -275,25 +502,46 @@ impl<'a, 'b> FullParams<'a, 'b> {
         // set the tokens
         self.fp.prompt_tokens = tokens_ptr;
         self.fp.prompt_n_tokens = tokens_len;
+        self.prompt_tokens = None;
+    }
+
+    /// Like [`Self::set_tokens`], but keeps a copy of `tokens`, so they needn't outlive the params,
+    /// e.g. to carry the tokens of one chunk over as the context of the next.
+    /// whisper.cpp ignores the [`Self::set_initial_prompt`] if prompt tokens are set.
+    ///
+    /// An empty slice removes the prompt tokens.
+    pub fn set_prompt_tokens(&mut self, tokens: &[crate::WhisperTokenId]) {
+        if tokens.is_empty() {
+            self.fp.prompt_tokens = std::ptr::null();
+            self.fp.prompt_n_tokens = 0;
+            self.prompt_tokens = None;
+            return;
+        }
+        let tokens: Arc<[whisper_token]> = tokens.into();
+        self.fp.prompt_tokens = tokens.as_ptr();
+        self.fp.prompt_n_tokens = tokens.len() as c_int;
+        self.prompt_tokens = Some(tokens);
     }
 
     /// Set the target language.
     ///
-    /// For auto-detection, set this to either "auto" or None.
+    /// For auto-detection, set this to [`Language::Auto`].
     ///
-    /// Defaults to "en".
-    pub fn set_language(&mut self, language: Option<&'a str>) {
-        self.fp.language = match language {
-            Some(language) => CString::new(language)
-                .expect("Language contains null byte")
-                .into_raw() as *const _,
-            None => std::ptr::null(),
-        };
+    /// Also applies the [`crate::LanguageDefaults`] of the language to all parameters that weren't set explicitly,
+    /// unless turned off with [`Self::set_language_defaults`].
+    /// To apply them to a detected language, see [`crate::WhisperState::detect_language_defaults`].
+    ///
+    /// Defaults to [`Language::English`].
+    pub fn set_language(&mut self, language: Language) {
+        self.fp.language = language.as_ptr();
+        if self.language_defaults {
+            self.apply_language_defaults(language.code());
+        }
     }
 
     /// Set `detect_language`.
     ///
-    /// Has the same effect as setting the language to "auto" or None.
+    /// Has the same effect as setting the language to [`Language::Auto`].
     ///
     /// Defaults to false.
     pub fn set_detect_language(&mut self, detect_language: bool) {
@@ -307,6 +555,7 @@ impl<'a, 'b> FullParams<'a, 'b> {
     /// Defaults to true.
     pub fn set_suppress_blank(&mut self, suppress_blank: bool) {
         self.fp.suppress_blank = suppress_blank;
+        self.explicit |= language_defaults::SUPPRESS_BLANK;
     }
 
     /// Set suppress_non_speech_tokens.
@@ -316,16 +565,70 @@ impl<'a, 'b> FullParams<'a, 'b> {
     /// Defaults to false.
     pub fn set_suppress_nst(&mut self, suppress_nst: bool) {
         self.fp.suppress_nst = suppress_nst;
+        self.explicit |= language_defaults::SUPPRESS_NST;
+    }
+
+    /// Never produce tokens whose text matches `regex`.
+    ///
+    /// whisper.cpp matches the pattern (ECMAScript syntax) against the whole text of each token in
+    /// the vocabulary once per run, and suppresses the matching tokens at every decoding step.
+    /// Since words are often split into several tokens, this suits patterns on short pieces of text,
+    /// e.g. `"\\s*[\\[(♪*].*"` for the tokens starting annotations like `[Music]` or `(laughs)`.
+    /// Calling this more than once replaces the previous pattern.
+    ///
+    /// whisper.cpp compiles the pattern with `std::regex`, which throws on a syntax error; an
+    /// exception can't cross into Rust, so [`Self::validate`] rejects unbalanced parentheses and
+    /// brackets, repetitions of nothing, malformed `{n,m}` counts, reversed ranges and a trailing
+    /// backslash with [`WhisperError::InvalidSuppressRegex`]. Keep to the common ECMAScript syntax:
+    /// rarer errors, such as an unknown escape in some standard libraries, are not caught.
+    ///
+    /// # Panics
+    /// This method will panic if `regex` contains a null byte.
+    ///
+    /// Defaults to no pattern.
+    pub fn set_suppress_regex(&mut self, regex: &str) {
+        let regex = Arc::new(CString::new(regex).expect("Suppress regex contains null byte"));
+        self.fp.suppress_regex = regex.as_ptr();
+        self.suppress_regex = Some(regex);
+    }
+
+    /// Remove the pattern set with [`Self::set_suppress_regex`].
+    pub fn clear_suppress_regex(&mut self) {
+        self.fp.suppress_regex = std::ptr::null();
+        self.suppress_regex = None;
     }
 
     /// Set initial decoding temperature.
     /// See <https://ai.stackexchange.com/a/32478> for more information.
     ///
+    /// A window is decoded at this temperature first. If the result fails the
+    /// [`Self::set_entropy_thold`] or [`Self::set_logprob_thold`] checks, it is decoded again at
+    /// increasingly higher temperatures, see [`Self::set_temperature_inc`] and [`Self::temperatures`].
+    ///
     /// Defaults to 0.0.
     pub fn set_temperature(&mut self, temperature: f32) {
         self.fp.temperature = temperature;
     }
 
+    /// Decode every window once at [`Self::set_temperature`], accepting the result even if it
+    /// fails the entropy or log probability checks.
+    ///
+    /// Retrying at higher temperatures recovers from repetition loops and garbled windows, at the
+    /// cost of decoding a window up to six times. Without it, the time a window takes no longer
+    /// depends on how hard the audio is, which bounds latency for real-time use.
+    /// Equivalent to `set_temperature_inc(0.0)`.
+    pub fn disable_fallback(&mut self) {
+        self.fp.temperature_inc = 0.0;
+    }
+
+    /// The temperatures a window is decoded at, in order, until a result passes the checks:
+    /// the initial temperature, then steps of [`Self::set_temperature_inc`] up to 1.0.
+    ///
+    /// With the defaults, these are 0.0, 0.2, 0.4, 0.6, 0.8 and 1.0.
+    pub fn temperatures(&self) -> Vec<f32> {
+        fallback_temperatures(self.fp.temperature, self.fp.temperature_inc)
+    }
+
     /// Set max_initial_ts.
     /// See <https://github.com/openai/whisper/blob/f82bc59f5ea234d4b97fb2860842ed38519f7e65/whisper/decoding.py#L97>
     /// for more information.
@@ -344,7 +647,8 @@ impl<'a, 'b> FullParams<'a, 'b> {
         self.fp.length_penalty = length_penalty;
     }
 
-    /// Set temperature_inc.
+    /// Set temperature_inc, the step by which the temperature is raised for each retry of a window.
+    /// 0.0 or less disables retries, see [`Self::disable_fallback`].
     /// See <https://github.com/openai/whisper/blob/f82bc59f5ea234d4b97fb2860842ed38519f7e65/whisper/transcribe.py#L274-L278>
     /// for more information.
     ///
@@ -354,14 +658,19 @@ impl<'a, 'b> FullParams<'a, 'b> {
     }
 
     /// Set entropy_thold. Similar to OpenAI's compression_ratio_threshold.
+    /// A window is retried at a higher temperature if the entropy of its tokens is below this,
+    /// which happens when the decoder gets stuck repeating itself.
     /// See <https://github.com/openai/whisper/blob/f82bc59f5ea234d4b97fb2860842ed38519f7e65/whisper/transcribe.py#L274-L278> for more information.
     ///
     /// Defaults to 2.4.
     pub fn set_entropy_thold(&mut self, entropy_thold: f32) {
         self.fp.entropy_thold = entropy_thold;
+        self.explicit |= language_defaults::ENTROPY_THOLD;
     }
 
     /// Set logprob_thold.
+    /// A window is retried at a higher temperature if the average log probability of its tokens
+    /// is below this.
     /// See <https://github.com/openai/whisper/blob/f82bc59f5ea234d4b97fb2860842ed38519f7e65/whisper/transcribe.py#L274-L278>
     /// for more information.
     ///
@@ -370,11 +679,15 @@ impl<'a, 'b> FullParams<'a, 'b> {
         self.fp.logprob_thold = logprob_thold;
     }
 
-    /// Set no_speech_thold. Currently (as of v1.3.0) not implemented.
+    /// Set no_speech_thold: windows whose no-speech probability is above this, and whose average
+    /// log probability is below [`Self::set_logprob_thold`], are treated as silence and skipped.
+    /// The probability of each segment is reported by [`crate::WhisperSegment::no_speech_probability`];
+    /// see [`crate::NoSpeechFilter`] to drop segments on it alone.
     ///
     /// Defaults to 0.6.
     pub fn set_no_speech_thold(&mut self, no_speech_thold: f32) {
         self.fp.no_speech_thold = no_speech_thold;
+        self.explicit |= language_defaults::NO_SPEECH_THOLD;
     }
 
     /// Set the callback for new segments.
@@ -468,9 +781,11 @@ impl<'a, 'b> FullParams<'a, 'b> {
                 self.fp.new_segment_callback_user_data = closure as *mut c_void;
                 self.fp.new_segment_callback = Some(trampoline::<SegmentCallbackFn>);
                 self.segment_calllback_safe = None;
+                self.segment_batcher = None;
             }
             None => {
                 self.segment_calllback_safe = None;
+                self.segment_batcher = None;
                 self.fp.new_segment_callback = None;
                 self.fp.new_segment_callback_user_data = std::ptr::null_mut::<c_void>();
             }
@@ -534,15 +849,89 @@ impl<'a, 'b> FullParams<'a, 'b> {
                 self.fp.new_segment_callback_user_data = closure as *mut c_void;
                 self.fp.new_segment_callback = Some(trampoline::<SegmentCallbackFn>);
                 self.segment_calllback_safe = None;
+                self.segment_batcher = None;
             }
             None => {
                 self.segment_calllback_safe = None;
+                self.segment_batcher = None;
                 self.fp.new_segment_callback = None;
                 self.fp.new_segment_callback_user_data = std::ptr::null_mut::<c_void>();
             }
         }
     }
 
+    /// Set a callback for new segments that receives them in batches, see [`SegmentBatch`].
+    ///
+    /// Cheaper than [`Self::set_segment_callback_safe_lossy`] when whisper.cpp produces many tiny segments,
+    /// e.g. with a small [`Self::set_max_len`], and the callback takes a lock or sends to a channel.
+    /// Segments still pending when [`crate::WhisperState::full`] returns are delivered before it returns.
+    /// Invalid UTF-8 is replaced, as in [`Self::set_segment_callback_safe_lossy`].
+    ///
+    /// Defaults to None.
+    pub fn set_segment_callback_batched<O, F>(&mut self, batch: SegmentBatch, closure: O)
+    where
+        F: FnMut(Vec<SegmentCallbackData>) + Send + 'static,
+        O: Into<Option<F>>,
+    {
+        use std::ffi::{c_void, CStr};
+        use whisper_rs_sys::{whisper_context, whisper_state};
+
+        unsafe extern "C" fn trampoline(
+            _: *mut whisper_context,
+            state: *mut whisper_state,
+            n_new: i32,
+            user_data: *mut c_void,
+        ) {
+            let batcher = &*(user_data as *const Mutex<SegmentBatcher>);
+            let mut batcher = batcher.lock().unwrap_or_else(PoisonError::into_inner);
+            let n_segments = whisper_rs_sys::whisper_full_n_segments_from_state(state);
+            for i in n_segments - n_new..n_segments {
+                let text = whisper_rs_sys::whisper_full_get_segment_text_from_state(state, i);
+                batcher.push(SegmentCallbackData {
+                    segment: i,
+                    start_timestamp: whisper_rs_sys::whisper_full_get_segment_t0_from_state(
+                        state, i,
+                    ),
+                    end_timestamp: whisper_rs_sys::whisper_full_get_segment_t1_from_state(state, i),
+                    text: CStr::from_ptr(text).to_string_lossy().into_owned(),
+                });
+            }
+        }
+
+        self.segment_calllback_safe = None;
+        match closure.into() {
+            Some(closure) => {
+                let batcher = Arc::new(Mutex::new(SegmentBatcher {
+                    callback: Box::new(closure),
+                    batch: SegmentBatch {
+                        max_segments: batch.max_segments.max(1),
+                        ..batch
+                    },
+                    pending: Vec::new(),
+                    oldest: None,
+                }));
+                self.fp.new_segment_callback_user_data = Arc::as_ptr(&batcher) as *mut c_void;
+                self.fp.new_segment_callback = Some(trampoline);
+                self.segment_batcher = Some(batcher);
+            }
+            None => {
+                self.segment_batcher = None;
+                self.fp.new_segment_callback = None;
+                self.fp.new_segment_callback_user_data = std::ptr::null_mut::<c_void>();
+            }
+        }
+    }
+
+    /// Deliver the segments still pending in the batched segment callback, if one is set.
+    pub(crate) fn flush_segment_batch(&self) {
+        if let Some(batcher) = &self.segment_batcher {
+            batcher
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .flush();
+        }
+    }
+
     /// Set the callback for progress updates.
     ///
     /// Note that is still a C callback.
@@ -613,6 +1002,9 @@ impl<'a, 'b> FullParams<'a, 'b> {
     /// See `set_progress_callback` if you need to use `whisper_context` and `whisper_state`,
     /// or extend this one to support their use.
     ///
+    /// A run the callback stops fails with [`WhisperError::Aborted`], which
+    /// [`crate::RetryPolicy`] doesn't retry.
+    ///
     /// Defaults to None.
     pub fn set_abort_callback_safe<O, F>(&mut self, closure: O)
     where
@@ -706,11 +1098,17 @@ impl<'a, 'b> FullParams<'a, 'b> {
         &mut self,
         logits_filter_callback: crate::WhisperLogitsFilterCallback,
     ) {
+        if self.logits_filters.take().is_some() {
+            self.fp.logits_filter_callback_user_data = std::ptr::null_mut();
+        }
         self.fp.logits_filter_callback = logits_filter_callback;
     }
 
     /// Set the user data to be passed to the logits filter callback.
     ///
+    /// Filters added with [`Self::add_logits_filter`] are removed along with their callback,
+    /// which only works with its own user data.
+    ///
     /// # Safety
     /// See the safety notes for `set_filter_logits_callback`.
     ///
@@ -719,9 +1117,99 @@ impl<'a, 'b> FullParams<'a, 'b> {
         &mut self,
         user_data: *mut std::ffi::c_void,
     ) {
+        if self.logits_filters.take().is_some() {
+            self.fp.logits_filter_callback = None;
+        }
         self.fp.logits_filter_callback_user_data = user_data;
     }
 
+    /// Add a closure to the chain of logits filters, run by each decoder before picking the next token.
+    ///
+    /// The closure receives the tokens decoded so far in the current segment (including timestamp
+    /// tokens, excluding the prompt) and the logits for every token in the vocabulary, which it may modify
+    /// to bias or suppress tokens. Filters run in the order they were added.
+    ///
+    /// Clones of these parameters share the filters added before cloning, and closures may be called
+    /// from several decoders of one run. Setting a raw callback or its user data with
+    /// [`Self::set_filter_logits_callback`] or [`Self::set_filter_logits_callback_user_data`]
+    /// replaces the whole chain.
+    ///
+    /// Defaults to no filters.
+    pub fn add_logits_filter<F>(&mut self, filter: F)
+    where
+        F: FnMut(&[whisper_token_data], &mut [f32]) + Send + 'static,
+    {
+        self.push_logits_filter(Arc::new(Mutex::new(Box::new(filter))));
+    }
+
+    /// [`Self::add_logits_filter`], keeping a handle to remove it with
+    /// [`Self::remove_logits_filter`].
+    pub(crate) fn push_logits_filter(&mut self, filter: Arc<Mutex<LogitsFilterFn>>) {
+        // copy on write, so filters added to a clone don't show up in the original
+        let mut chain: LogitsFilterChain =
+            self.logits_filters.as_deref().cloned().unwrap_or_default();
+        chain.push(filter);
+        self.set_logits_filter_chain(chain);
+    }
+
+    /// Remove a filter added with [`Self::push_logits_filter`], if it is still in the chain.
+    pub(crate) fn remove_logits_filter(&mut self, filter: &Arc<Mutex<LogitsFilterFn>>) {
+        let Some(chain) = &self.logits_filters else {
+            return;
+        };
+        let chain: LogitsFilterChain = chain
+            .iter()
+            .filter(|f| !Arc::ptr_eq(f, filter))
+            .cloned()
+            .collect();
+        if chain.is_empty() {
+            self.clear_logits_filters();
+        } else {
+            self.set_logits_filter_chain(chain);
+        }
+    }
+
+    fn set_logits_filter_chain(&mut self, chain: LogitsFilterChain) {
+        use std::ffi::c_void;
+        use whisper_rs_sys::{whisper_context, whisper_state};
+
+        unsafe extern "C" fn trampoline(
+            ctx: *mut whisper_context,
+            _: *mut whisper_state,
+            tokens: *const whisper_token_data,
+            n_tokens: c_int,
+            logits: *mut f32,
+            user_data: *mut c_void,
+        ) {
+            let filters = &*(user_data as *const LogitsFilterChain);
+            let tokens = if tokens.is_null() || n_tokens <= 0 {
+                &[][..]
+            } else {
+                std::slice::from_raw_parts(tokens, n_tokens as usize)
+            };
+            let n_vocab = whisper_rs_sys::whisper_n_vocab(ctx).max(0) as usize;
+            let logits = std::slice::from_raw_parts_mut(logits, n_vocab);
+            for filter in filters {
+                let mut filter = filter.lock().unwrap_or_else(PoisonError::into_inner);
+                filter(tokens, logits);
+            }
+        }
+
+        let chain = Arc::new(chain);
+
+        self.fp.logits_filter_callback = Some(trampoline);
+        self.fp.logits_filter_callback_user_data = Arc::as_ptr(&chain) as *mut c_void;
+        self.logits_filters = Some(chain);
+    }
+
+    /// Remove all filters added with [`Self::add_logits_filter`].
+    pub fn clear_logits_filters(&mut self) {
+        if self.logits_filters.take().is_some() {
+            self.fp.logits_filter_callback = None;
+            self.fp.logits_filter_callback_user_data = std::ptr::null_mut();
+        }
+    }
+
     /// Set the callback that is called each time before ggml computation starts.
     ///
     /// Note that this callback has not been Rustified yet (and likely never will be, unless someone else feels the need to do so).
@@ -749,20 +1237,18 @@ impl<'a, 'b> FullParams<'a, 'b> {
 
     /// Enable an array of grammar elements to be passed to the whisper model.
     ///
+    /// The rules follow one after another, each ending with
+    /// [`crate::WhisperGrammarElementType::End`], and are numbered in that order for
+    /// [`crate::WhisperGrammarElementType::RuleReference`] and [`Self::set_start_rule`].
+    /// See [`crate::Grammar`] to compile them from a GBNF grammar instead.
+    ///
     /// Defaults to an empty vector.
     pub fn set_grammar(&mut self, grammar: Option<&[WhisperGrammarElement]>) {
         if let Some(grammar) = grammar {
-            // convert to c types
-            let inner = grammar.iter().map(|e| e.to_c_type()).collect::<Vec<_>>();
-            // turn into ptr and len
-            let grammar_ptr = inner.as_ptr() as *mut _;
-            let grammar_len = inner.len();
-
-            self.grammar = Some(inner);
-
-            // set the grammar
-            self.fp.grammar_rules = grammar_ptr;
-            self.fp.n_grammar_rules = grammar_len;
+            let grammar = Arc::new(GrammarRules::new(grammar));
+            self.fp.grammar_rules = grammar.rules.as_ptr() as *mut _;
+            self.fp.n_grammar_rules = grammar.rules.len();
+            self.grammar = Some(grammar);
         } else {
             self.grammar = None;
             self.fp.grammar_rules = std::ptr::null_mut();
@@ -806,9 +1292,16 @@ impl<'a, 'b> FullParams<'a, 'b> {
     /// // ... further usage of params ...
     /// ```
     pub fn set_initial_prompt(&mut self, initial_prompt: &str) {
-        self.fp.initial_prompt = CString::new(initial_prompt)
-            .expect("Initial prompt contains null byte")
-            .into_raw() as *const c_char;
+        let initial_prompt =
+            Arc::new(CString::new(initial_prompt).expect("Initial prompt contains null byte"));
+        self.fp.initial_prompt = initial_prompt.as_ptr();
+        self.initial_prompt = Some(initial_prompt);
+    }
+
+    /// Remove the initial prompt set with [`Self::set_initial_prompt`].
+    pub fn clear_initial_prompt(&mut self) {
+        self.fp.initial_prompt = std::ptr::null();
+        self.initial_prompt = None;
     }
 
     /// Enable or disable VAD.
@@ -843,6 +1336,213 @@ impl<'a, 'b> FullParams<'a, 'b> {
     pub fn set_vad_params(&mut self, params: WhisperVadParams) {
         self.fp.vad_params = params.into_inner();
     }
+
+    /// Check these parameters against a model and an input before they are passed to whisper.cpp.
+    ///
+    /// Several out-of-range values cause assertions or out-of-bounds accesses inside whisper.cpp,
+    /// so [`crate::WhisperState::full`] calls this before every run. It is exposed so callers can
+    /// validate a configuration up front.
+    ///
+    /// # Arguments
+    /// * n_audio_ctx: The audio context size of the model, see [`crate::WhisperContext::model_n_audio_ctx`].
+    /// * n_samples: The number of 16 kHz samples that will be transcribed.
+    ///
+    /// # Returns
+    /// Ok(()) if the parameters are usable, otherwise the first problem found.
+    pub fn validate(&self, n_audio_ctx: c_int, n_samples: usize) -> Result<(), WhisperError> {
+        if self.fp.n_threads < 1 {
+            return Err(WhisperError::InvalidThreadCount);
+        }
+
+        if self.fp.audio_ctx < 0 || self.fp.audio_ctx > n_audio_ctx {
+            return Err(WhisperError::InvalidAudioCtx {
+                audio_ctx: self.fp.audio_ctx,
+                n_audio_ctx,
+            });
+        }
+
+        if n_samples > c_int::MAX as usize {
+            return Err(WhisperError::TooManySamples(n_samples));
+        }
+        let audio_ms = n_samples as i64 * 1000 / whisper_rs_sys::WHISPER_SAMPLE_RATE as i64;
+        let offset_ms = self.fp.offset_ms as i64;
+        let duration_ms = self.fp.duration_ms as i64;
+        // a duration running past the end is cut there by `clamp_duration`
+        if offset_ms < 0 || duration_ms < 0 || (offset_ms > 0 && offset_ms >= audio_ms) {
+            return Err(WhisperError::TimeRangeOutOfBounds {
+                offset_ms: self.fp.offset_ms,
+                duration_ms: self.fp.duration_ms,
+                audio_ms,
+            });
+        }
+
+        if self.fp.strategy
+            == whisper_rs_sys::whisper_sampling_strategy_WHISPER_SAMPLING_BEAM_SEARCH
+            && !(1..=WHISPER_MAX_DECODERS).contains(&self.fp.beam_search.beam_size)
+        {
+            return Err(WhisperError::InvalidBeamSize {
+                beam_size: self.fp.beam_search.beam_size,
+                max: WHISPER_MAX_DECODERS,
+            });
+        }
+
+        if self.fp.n_max_text_ctx < 0 {
+            return Err(WhisperError::InvalidMaxTextCtx(self.fp.n_max_text_ctx));
+        }
+
+        // used for temperature fallback with either strategy, so always check it
+        if self.fp.greedy.best_of > WHISPER_MAX_DECODERS {
+            return Err(WhisperError::InvalidBestOf {
+                best_of: self.fp.greedy.best_of,
+                max: WHISPER_MAX_DECODERS,
+            });
+        }
+
+        if let Some(position) = self
+            .suppress_regex
+            .as_ref()
+            .and_then(|regex| regex_syntax_error(&regex.to_string_lossy()))
+        {
+            return Err(WhisperError::InvalidSuppressRegex { position });
+        }
+
+        Ok(())
+    }
+
+    /// Cut a duration running past the end of `n_samples` of audio short at the end, which
+    /// whisper.cpp would otherwise read beyond. Call after [`Self::validate`].
+    pub(crate) fn clamp_duration(&mut self, n_samples: usize) {
+        let audio_ms = n_samples as i64 * 1000 / whisper_rs_sys::WHISPER_SAMPLE_RATE as i64;
+        let available = (audio_ms - self.fp.offset_ms as i64).max(0);
+        if self.fp.duration_ms as i64 > available {
+            self.fp.duration_ms = available as c_int;
+        }
+    }
+}
+
+/// Byte offset of the first syntax error in the ECMAScript `pattern` that `std::regex` is sure to
+/// throw on, see [`FullParams::set_suppress_regex`].
+fn regex_syntax_error(pattern: &str) -> Option<usize> {
+    let mut chars = pattern.char_indices().peekable();
+    let mut groups = Vec::new();
+    // whether the last item can take a quantifier
+    let mut repeatable = false;
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some((_, 'b' | 'B')) => repeatable = false,
+                Some(_) => repeatable = true,
+                None => return Some(i),
+            },
+            '(' => {
+                groups.push(i);
+                if chars.next_if(|&(_, c)| c == '?').is_some()
+                    && chars
+                        .next_if(|&(_, c)| matches!(c, ':' | '=' | '!'))
+                        .is_none()
+                {
+                    return Some(i);
+                }
+                repeatable = false;
+            }
+            ')' => {
+                if groups.pop().is_none() {
+                    return Some(i);
+                }
+                repeatable = true;
+            }
+            '|' | '^' | '$' => repeatable = false,
+            '*' | '+' | '?' => {
+                if !repeatable {
+                    return Some(i);
+                }
+                chars.next_if(|&(_, c)| c == '?');
+                repeatable = false;
+            }
+            '{' => {
+                let mut counts = String::new();
+                while let Some((_, c)) = chars.next_if(|&(_, c)| c != '}') {
+                    counts.push(c);
+                }
+                let (min, max) = counts.split_once(',').unwrap_or((&counts, &counts));
+                let min = min.parse::<u32>();
+                let max = match max {
+                    "" => Ok(u32::MAX),
+                    max => max.parse::<u32>(),
+                };
+                match (chars.next(), min, max) {
+                    (Some(_), Ok(min), Ok(max)) if repeatable && min <= max => {}
+                    _ => return Some(i),
+                }
+                chars.next_if(|&(_, c)| c == '?');
+                repeatable = false;
+            }
+            '[' => {
+                if !class_is_valid(&mut chars) {
+                    return Some(i);
+                }
+                repeatable = true;
+            }
+            _ => repeatable = true,
+        }
+    }
+    groups.first().copied()
+}
+
+/// Consume a bracket expression after its `[`, returning whether it is closed and its ranges
+/// are in order.
+fn class_is_valid(chars: &mut Peekable<CharIndices>) -> bool {
+    chars.next_if(|&(_, c)| c == '^');
+    // the last single character, which can start a range
+    let mut last = None;
+    while let Some((_, c)) = chars.next() {
+        last = match c {
+            ']' => return true,
+            '\\' => match class_escape(chars) {
+                Some(escaped) => escaped,
+                None => return false,
+            },
+            '[' if chars
+                .peek()
+                .is_some_and(|&(_, c)| matches!(c, ':' | '.' | '=')) =>
+            {
+                // a character class name such as [:alpha:], ending with the same mark and ]
+                let (_, mark) = chars.next().unwrap();
+                let mut previous = None;
+                loop {
+                    match chars.next() {
+                        Some((_, ']')) if previous == Some(mark) => break,
+                        Some((_, c)) => previous = Some(c),
+                        None => return false,
+                    }
+                }
+                None
+            }
+            '-' if last.is_some() && chars.peek().is_some_and(|&(_, c)| c != ']') => {
+                let end = match chars.next() {
+                    Some((_, '\\')) => match class_escape(chars) {
+                        Some(escaped) => escaped,
+                        None => return false,
+                    },
+                    next => next.map(|(_, c)| c),
+                };
+                if end.zip(last).is_some_and(|(end, start)| end < start) {
+                    return false;
+                }
+                None
+            }
+            c => Some(c),
+        };
+    }
+    false
+}
+
+/// The character an escape in a bracket expression stands for, if it is a single one,
+/// or `None` if the pattern ends after the backslash. Letters are taken to be class escapes
+/// such as `\d`, which can't bound a range.
+fn class_escape(chars: &mut Peekable<CharIndices>) -> Option<Option<char>> {
+    let (_, c) = chars.next()?;
+    Some((!c.is_ascii_alphabetic()).then_some(c))
 }
 
 // following implementations are safe
@@ -874,6 +1574,22 @@ mod test_whisper_params_initial_prompt {
         assert_eq!(params.get_initial_prompt(), prompt);
     }
 
+    #[test]
+    fn test_prompt_outlives_the_original_params() {
+        let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 5 });
+        params.set_initial_prompt("Kubernetes, etcd");
+        let tokens = vec![1, 2, 3];
+        params.set_prompt_tokens(&tokens);
+        drop(tokens);
+        let cloned = params.clone();
+        drop(params);
+        assert_eq!(cloned.get_initial_prompt(), "Kubernetes, etcd");
+        let tokens = unsafe {
+            std::slice::from_raw_parts(cloned.fp.prompt_tokens, cloned.fp.prompt_n_tokens as usize)
+        };
+        assert_eq!(tokens, [1, 2, 3]);
+    }
+
     #[test]
     #[should_panic(expected = "Initial prompt contains null byte")]
     fn test_initial_prompt_null_byte() {
@@ -927,3 +1643,261 @@ mod test_whisper_params_initial_prompt {
         );
     }
 }
+
+#[cfg(test)]
+mod test_whisper_params_suppress_regex {
+    use super::*;
+
+    #[test]
+    fn test_suppress_regex_is_owned_and_cleared() {
+        let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
+        assert!(params.fp.suppress_regex.is_null());
+        params.set_suppress_regex(&String::from("\\s*\\[.*"));
+        let cloned = params.clone();
+        drop(params);
+        let regex = unsafe { std::ffi::CStr::from_ptr(cloned.fp.suppress_regex) };
+        assert_eq!(regex.to_str().unwrap(), "\\s*\\[.*");
+
+        let mut params = cloned;
+        params.clear_suppress_regex();
+        assert!(params.fp.suppress_regex.is_null());
+    }
+
+    #[test]
+    #[should_panic(expected = "Suppress regex contains null byte")]
+    fn test_suppress_regex_null_byte() {
+        let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
+        params.set_suppress_regex("a\0b");
+    }
+
+    #[test]
+    fn test_invalid_suppress_regex_is_rejected() {
+        let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
+        params.set_suppress_regex("\\s*[\\[(♪*].*");
+        assert!(params.validate(1500, 16000).is_ok());
+        params.set_suppress_regex("(music");
+        assert!(matches!(
+            params.validate(1500, 16000),
+            Err(WhisperError::InvalidSuppressRegex { position: 0 })
+        ));
+    }
+
+    #[test]
+    fn test_regex_syntax_errors_are_found() {
+        let valid = [
+            "",
+            "^\\s*\\[.*\\]$",
+            "(?:la)+|[a-z0-9_-]{2,}?",
+            "\\d{3}",
+            "[]a]",
+            "[[:alpha:]-]",
+            "\\(\\)",
+            "a{1,2}",
+        ];
+        for pattern in valid {
+            assert_eq!(regex_syntax_error(pattern), None, "{}", pattern);
+        }
+        let invalid = [
+            ("(a", 0),
+            ("a)", 1),
+            ("*a", 0),
+            ("a|+", 2),
+            ("a**", 2),
+            ("a{2,1}", 1),
+            ("a{x}", 1),
+            ("[a", 0),
+            ("x[z-a]", 1),
+            ("a\\", 1),
+            ("(?<a>b)", 0),
+        ];
+        for (pattern, position) in invalid {
+            assert_eq!(regex_syntax_error(pattern), Some(position), "{}", pattern);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_whisper_params_sampling_strategy {
+    use super::*;
+
+    #[test]
+    fn test_sampling_strategy_round_trips() {
+        let mut params = FullParams::new(SamplingStrategy::default());
+        assert_eq!(params.sampling_strategy(), SamplingStrategy::default());
+
+        let beam_search = SamplingStrategy::BeamSearch {
+            beam_size: 4,
+            patience: -1.0,
+        };
+        params.set_sampling_strategy(beam_search.clone());
+        assert_eq!(params.sampling_strategy(), beam_search);
+    }
+}
+
+#[cfg(test)]
+mod test_whisper_params_segment_length {
+    use super::*;
+
+    #[test]
+    fn test_max_len_enables_token_timestamps() {
+        let mut params = FullParams::new(SamplingStrategy::default());
+        params.set_max_len(0);
+        assert!(!params.fp.token_timestamps);
+        params.set_max_len(42);
+        assert!(params.fp.token_timestamps);
+    }
+}
+
+#[cfg(test)]
+mod test_whisper_params_validate {
+    use super::*;
+
+    #[test]
+    fn test_validate_rejects_out_of_range_values() {
+        let one_second = 16000;
+        let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
+        assert!(params.validate(1500, one_second).is_ok());
+
+        params.set_n_threads(0);
+        assert!(matches!(
+            params.validate(1500, one_second),
+            Err(WhisperError::InvalidThreadCount)
+        ));
+        params.set_n_threads(1);
+
+        params.set_audio_ctx(3000);
+        assert!(matches!(
+            params.validate(1500, one_second),
+            Err(WhisperError::InvalidAudioCtx { .. })
+        ));
+        params.set_audio_ctx(0);
+
+        params.set_offset_ms(1000);
+        assert!(matches!(
+            params.validate(1500, one_second),
+            Err(WhisperError::TimeRangeOutOfBounds { .. })
+        ));
+        params.set_offset_ms(500);
+        params.set_duration_ms(500);
+        assert!(params.validate(1500, one_second).is_ok());
+        params.set_time_range(Duration::from_millis(250)..Duration::from_millis(1250));
+        assert_eq!((params.fp.offset_ms, params.fp.duration_ms), (250, 1000));
+        // running past the end is accepted, and cut at the end before reaching whisper.cpp
+        assert!(params.validate(1500, one_second).is_ok());
+        params.clamp_duration(one_second);
+        assert_eq!((params.fp.offset_ms, params.fp.duration_ms), (250, 750));
+        params.set_duration_ms(500);
+        params.clamp_duration(one_second);
+        assert_eq!(params.fp.duration_ms, 500);
+        params.set_time_range(Duration::ZERO..Duration::ZERO);
+
+        params.set_n_max_text_ctx(-1);
+        assert!(matches!(
+            params.validate(1500, one_second),
+            Err(WhisperError::InvalidMaxTextCtx(-1))
+        ));
+        params.set_n_max_text_ctx(0);
+        assert!(params.validate(1500, one_second).is_ok());
+
+        let params = FullParams::new(SamplingStrategy::BeamSearch {
+            beam_size: 16,
+            patience: -1.0,
+        });
+        assert!(matches!(
+            params.validate(1500, one_second),
+            Err(WhisperError::InvalidBeamSize { .. })
+        ));
+    }
+}
+
+#[cfg(test)]
+mod test_whisper_params_segment_batch {
+    use super::*;
+
+    #[test]
+    fn segments_are_delivered_in_batches() {
+        let batches = Arc::new(Mutex::new(Vec::new()));
+        let sink = batches.clone();
+        let mut batcher = SegmentBatcher {
+            callback: Box::new(move |batch: Vec<SegmentCallbackData>| {
+                sink.lock()
+                    .unwrap()
+                    .push(batch.iter().map(|s| s.segment).collect::<Vec<_>>())
+            }),
+            batch: SegmentBatch::segments(2),
+            pending: Vec::new(),
+            oldest: None,
+        };
+        for segment in 0..5 {
+            batcher.push(SegmentCallbackData {
+                segment,
+                start_timestamp: 0,
+                end_timestamp: 0,
+                text: String::new(),
+            });
+        }
+        batcher.flush();
+        assert_eq!(
+            *batches.lock().unwrap(),
+            vec![vec![0, 1], vec![2, 3], vec![4]]
+        );
+    }
+}
+
+#[cfg(test)]
+mod test_whisper_params_logits_filters {
+    use super::*;
+
+    unsafe extern "C" fn raw_filter(
+        _: *mut whisper_rs_sys::whisper_context,
+        _: *mut whisper_rs_sys::whisper_state,
+        _: *const whisper_token_data,
+        _: c_int,
+        _: *mut f32,
+        _: *mut std::ffi::c_void,
+    ) {
+    }
+
+    #[test]
+    fn raw_callback_and_user_data_replace_the_chain() {
+        let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
+        params.add_logits_filter(|_, _| {});
+        let mut user_data = 0u8;
+        unsafe { params.set_filter_logits_callback_user_data(&mut user_data as *mut u8 as _) };
+        // the trampoline of the chain must not be called with foreign user data
+        assert!(params.logits_filters.is_none());
+        assert!(params.fp.logits_filter_callback.is_none());
+
+        params.add_logits_filter(|_, _| {});
+        unsafe { params.set_filter_logits_callback(Some(raw_filter)) };
+        assert!(params.logits_filters.is_none());
+        assert!(params.fp.logits_filter_callback_user_data.is_null());
+
+        unsafe { params.set_filter_logits_callback_user_data(&mut user_data as *mut u8 as _) };
+        assert!(params.fp.logits_filter_callback.is_some());
+        assert!(!params.fp.logits_filter_callback_user_data.is_null());
+    }
+}
+
+#[cfg(test)]
+mod test_whisper_params_decoding {
+    use super::*;
+
+    #[test]
+    fn default_schedule_steps_up_to_one() {
+        let temperatures = fallback_temperatures(0.0, 0.2);
+        let expected = [0.0, 0.2, 0.4, 0.6, 0.8, 1.0];
+        assert_eq!(temperatures.len(), expected.len());
+        for (t, e) in temperatures.iter().zip(expected) {
+            assert!((t - e).abs() < 1e-5, "{} != {}", t, e);
+        }
+        assert_eq!(fallback_temperatures(0.3, 0.0), [0.3]);
+    }
+
+    #[test]
+    fn audio_ctx_covers_the_chunk() {
+        assert_eq!(audio_ctx_for_ms(5000), 250 + 64);
+        assert_eq!(audio_ctx_for_ms(29_990), 1500);
+        assert_eq!(audio_ctx_for_ms(30_000), 0);
+    }
+}
@@ -1,11 +1,13 @@
 use crate::whisper_state::WhisperSegment;
 use crate::WhisperState;
 use std::ffi::c_int;
+use std::iter::FusedIterator;
 
-/// An iterator over a [`WhisperState`]'s result.
+/// An iterator over a [`WhisperState`]'s result, see [`WhisperState::segments`].
 pub struct WhisperStateSegmentIterator<'a> {
     state_ptr: &'a WhisperState,
     current_segment: c_int,
+    end: c_int,
 }
 
 impl<'a> WhisperStateSegmentIterator<'a> {
@@ -13,6 +15,7 @@ impl<'a> WhisperStateSegmentIterator<'a> {
         Self {
             state_ptr,
             current_segment: 0,
+            end: state_ptr.full_n_segments(),
         }
     }
 }
@@ -21,8 +24,39 @@ impl<'a> Iterator for WhisperStateSegmentIterator<'a> {
     type Item = WhisperSegment<'a>;
 
     fn next(&mut self) -> Option<Self::Item> {
+        if self.current_segment >= self.end {
+            return None;
+        }
         let ret = self.state_ptr.get_segment(self.current_segment);
         self.current_segment += 1;
         ret
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = (self.end - self.current_segment).max(0) as usize;
+        (len, Some(len))
+    }
+}
+
+impl DoubleEndedIterator for WhisperStateSegmentIterator<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.current_segment >= self.end {
+            return None;
+        }
+        self.end -= 1;
+        self.state_ptr.get_segment(self.end)
+    }
+}
+
+impl ExactSizeIterator for WhisperStateSegmentIterator<'_> {}
+
+impl FusedIterator for WhisperStateSegmentIterator<'_> {}
+
+impl<'a> IntoIterator for &'a WhisperState {
+    type Item = WhisperSegment<'a>;
+    type IntoIter = WhisperStateSegmentIterator<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.segments()
+    }
 }
@@ -1,21 +1,68 @@
-use std::ffi::c_int;
+use std::borrow::Cow;
+use std::ffi::{c_int, c_void};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use crate::{FullParams, WhisperError, WhisperInnerContext, WhisperTokenId};
+use crate::common_logging::generic_warn;
+use crate::context_compression::Word;
+use crate::observer::{FallbackRecorder, Observer, RunEnd, RunStart};
+use crate::transcribe::{pad_short_input, SAMPLES_PER_CS};
+use crate::{
+    ContextCompression, EncoderBackend, FullParams, Language, ModelCapabilities, Transcript,
+    UnsupportedConfiguration, WatchdogReason, WhisperError, WhisperInnerContext, WhisperTokenId,
+};
 
 mod iterator;
 mod segment;
+mod snapshot;
+mod timings;
 mod token;
+mod tokens;
+mod windows;
 
 pub use iterator::WhisperStateSegmentIterator;
 pub use segment::WhisperSegment;
+pub use snapshot::StateSnapshot;
+pub use timings::WhisperTimings;
 pub use token::WhisperToken;
+pub use tokens::{SegmentTokens, TokenData};
+pub use windows::DecodeWindow;
 
 /// Rustified pointer to a Whisper state.
 #[derive(Debug)]
 pub struct WhisperState {
     ctx: Arc<WhisperInnerContext>,
-    ptr: *mut whisper_rs_sys::whisper_state,
+    pub(crate) ptr: *mut whisper_rs_sys::whisper_state,
+    pub(crate) encoder_backend: EncoderBackend,
+    /// End of the real audio in centiseconds, if the last input to [`Self::full`] was padded.
+    audio_end: Option<i64>,
+    timings: WhisperTimings,
+    /// Start of the last input to [`Self::full`] and end of all inputs so far, in centiseconds.
+    offsets: (i64, i64),
+    /// Text tokens of the last segments, see [`StateSnapshot::context_tokens`].
+    context: Vec<WhisperTokenId>,
+    /// Whether [`Self::context`] was restored and still has to be passed to whisper.cpp.
+    restored: bool,
+    /// Entities of context dropped from [`Self::context`], see [`ContextCompression`].
+    entities: Vec<Word>,
+    /// Language of the last run, see [`StateSnapshot::language`].
+    language: Option<Language>,
+    /// Number of tokens passed to the last successful [`Self::decode`], if it came after the
+    /// last [`Self::full`] call.
+    decoded_tokens: usize,
+    /// How far the low-level pipeline got, see [`Self::encode`] and [`Self::decode`].
+    stage: Stage,
+    /// Whether the warning about translating with a pruned decoder was logged.
+    warned_pruned: bool,
+}
+
+/// The last step of the pipeline run on a state, each needing the one before.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Stage {
+    Empty,
+    Mel,
+    Encoded,
+    Decoded,
 }
 
 unsafe impl Send for WhisperState {}
@@ -38,7 +85,84 @@ impl WhisperState {
         ctx: Arc<WhisperInnerContext>,
         ptr: *mut whisper_rs_sys::whisper_state,
     ) -> Self {
-        Self { ctx, ptr }
+        Self {
+            ctx,
+            ptr,
+            encoder_backend: EncoderBackend::Ggml,
+            audio_end: None,
+            timings: WhisperTimings::default(),
+            offsets: (0, 0),
+            context: Vec::new(),
+            restored: false,
+            entities: Vec::new(),
+            language: None,
+            decoded_tokens: 0,
+            stage: Stage::Empty,
+            warned_pruned: false,
+        }
+    }
+
+    /// Clamp a timestamp of the last result to the end of the audio, excluding any padding.
+    pub(crate) fn clamp_to_audio(&self, t: i64) -> i64 {
+        self.audio_end.map_or(t, |end| t.min(end))
+    }
+
+    /// Which encoder this state runs.
+    ///
+    /// [`EncoderBackend::OpenVino`] is only reported if the OpenVINO encoder loaded successfully.
+    /// whisper-rs-sys builds whisper.cpp with `WHISPER_COREML_ALLOW_FALLBACK`, so when a CoreML
+    /// encoder fails to load, whisper.cpp logs an error and the state runs the GGML encoder
+    /// instead of failing to be created. [`EncoderBackend::CoreMl`] means the encoder was found,
+    /// not that it loaded.
+    pub fn encoder_backend(&self) -> EncoderBackend {
+        self.encoder_backend
+    }
+
+    /// Time spent computing in this state, see [`WhisperTimings`].
+    ///
+    /// Unlike [`crate::WhisperContext::print_timings`], this covers only this state, and returns
+    /// the numbers rather than printing them.
+    pub fn timings(&self) -> WhisperTimings {
+        self.timings
+    }
+
+    /// Start counting [`Self::timings`] from zero.
+    pub fn reset_timings(&mut self) {
+        self.timings = WhisperTimings::default();
+    }
+
+    /// Where the last input to [`Self::full`] starts in the audio of the whole job, in
+    /// centiseconds, counting every input since the state was created or restored from a
+    /// [`StateSnapshot`]. Add it to segment timestamps to place them in the whole job.
+    pub fn audio_offset(&self) -> i64 {
+        self.offsets.0
+    }
+
+    /// Capture what this state carries into the next [`Self::full`] call, to checkpoint a long
+    /// job and resume it with [`Self::restore`] after a crash.
+    ///
+    /// Only the text tokens of the decoded segments are kept as context, up to the half of the
+    /// text context whisper.cpp prompts with, so a resumed run is prompted the same way as an
+    /// uninterrupted one apart from the timestamp tokens between segments.
+    pub fn snapshot(&self) -> StateSnapshot {
+        StateSnapshot {
+            offset: self.offsets.1,
+            context_tokens: self.context.clone(),
+            language: self.language,
+        }
+    }
+
+    /// Continue from a [`Self::snapshot`], usually in a new state after a crash: the next
+    /// [`Self::full`] call is prompted with the saved context, and [`Self::audio_offset`] counts
+    /// on from the saved offset.
+    ///
+    /// The context is only used if that call sets neither [`FullParams::set_no_context`] nor a
+    /// prompt of its own, as whisper.cpp keeps a single prompt.
+    pub fn restore(&mut self, snapshot: &StateSnapshot) {
+        self.offsets = (snapshot.offset, snapshot.offset);
+        self.context = snapshot.context_tokens.clone();
+        self.restored = !self.context.is_empty();
+        self.language = snapshot.language;
     }
 
     /// Convert raw PCM audio (floating point 32 bit) to log mel spectrogram.
@@ -57,6 +181,7 @@ impl WhisperState {
         if threads < 1 {
             return Err(WhisperError::InvalidThreadCount);
         }
+        let started = Instant::now();
         let ret = unsafe {
             whisper_rs_sys::whisper_pcm_to_mel_with_state(
                 self.ctx.ctx,
@@ -66,9 +191,13 @@ impl WhisperState {
                 threads as c_int,
             )
         };
+        self.timings.mel_calls += 1;
+        self.timings.mel += started.elapsed();
+        self.stage = Stage::Empty;
         if ret == -1 {
             Err(WhisperError::UnableToCalculateSpectrogram)
         } else if ret == 0 {
+            self.stage = Stage::Mel;
             Ok(())
         } else {
             Err(WhisperError::GenericError(ret))
@@ -78,34 +207,46 @@ impl WhisperState {
     /// This can be used to set a custom log mel spectrogram inside the provided whisper state.
     /// Use this instead of whisper_pcm_to_mel() if you want to provide your own log mel spectrogram.
     ///
+    /// The spectrogram must match what the model was trained on: 10 ms frames of log10 power,
+    /// clamped to 8 below the maximum and scaled as `(x + 4) / 4`, as whisper.cpp computes them.
+    /// It is stored band by band: all frames of the lowest band first, then the next one.
+    ///
     /// # Note
     /// This is a low-level function.
     /// If you're a typical user, you probably don't want to use this function.
     /// See instead [WhisperState::pcm_to_mel].
     ///
     /// # Arguments
-    /// * data: The log mel spectrogram.
+    /// * data: The log mel spectrogram, `n_mel` rows of the same number of frames.
+    /// * n_mel: The number of mel bands, which must be the model's, see
+    ///   [`ModelCapabilities::n_mels`].
     ///
     /// # Returns
-    /// Ok(()) on success, Err(WhisperError) on failure.
+    /// Ok(()) on success, Err(WhisperError) on failure:
+    /// * [`WhisperError::InvalidMelBands`] if `n_mel` isn't the model's.
+    /// * [`WhisperError::InvalidMelShape`] if `data` is empty or not a multiple of `n_mel` long.
     ///
     /// # C++ equivalent
     /// `int whisper_set_mel(struct whisper_context * ctx, const float * data, int n_len, int n_mel)`
-    pub fn set_mel(&mut self, data: &[f32]) -> Result<(), WhisperError> {
-        let hop_size = 160;
-        let n_len = (data.len() / hop_size) * 2;
+    pub fn set_mel(&mut self, data: &[f32], n_mel: c_int) -> Result<(), WhisperError> {
+        let n_len = mel_frames(data.len(), n_mel, ModelCapabilities::of(&self.ctx).n_mels)?;
+        let started = Instant::now();
         let ret = unsafe {
             whisper_rs_sys::whisper_set_mel_with_state(
                 self.ctx.ctx,
                 self.ptr,
                 data.as_ptr(),
                 n_len as c_int,
-                80 as c_int,
+                n_mel,
             )
         };
+        self.timings.mel_calls += 1;
+        self.timings.mel += started.elapsed();
+        self.stage = Stage::Empty;
         if ret == -1 {
             Err(WhisperError::InvalidMelBands)
         } else if ret == 0 {
+            self.stage = Stage::Mel;
             Ok(())
         } else {
             Err(WhisperError::GenericError(ret))
@@ -115,12 +256,16 @@ impl WhisperState {
     /// Run the Whisper encoder on the log mel spectrogram stored inside the provided whisper state.
     /// Make sure to call [WhisperState::pcm_to_mel] or [WhisperState::set_mel] first.
     ///
+    /// To leave the decoder out of the context and its states when only the encoder is ever run,
+    /// see [`crate::WhisperContextParameters::encoder_only`].
+    ///
     /// # Arguments
     /// * offset: Can be used to specify the offset of the first frame in the spectrogram. Usually 0.
     /// * threads: How many threads to use. Defaults to 1. Must be at least 1, returns an error otherwise.
     ///
     /// # Returns
     /// Ok(()) on success, Err(WhisperError) on failure.
+    /// [`WhisperError::SpectrogramNotInitialized`] if no spectrogram was computed or set yet.
     ///
     /// # C++ equivalent
     /// `int whisper_encode(struct whisper_context * ctx, int offset, int n_threads)`
@@ -128,6 +273,10 @@ impl WhisperState {
         if threads < 1 {
             return Err(WhisperError::InvalidThreadCount);
         }
+        if self.stage < Stage::Mel {
+            return Err(WhisperError::SpectrogramNotInitialized);
+        }
+        let started = Instant::now();
         let ret = unsafe {
             whisper_rs_sys::whisper_encode_with_state(
                 self.ctx.ctx,
@@ -136,9 +285,13 @@ impl WhisperState {
                 threads as c_int,
             )
         };
+        self.timings.encode_calls += 1;
+        self.timings.encode += started.elapsed();
+        self.stage = Stage::Mel;
         if ret == -1 {
             Err(WhisperError::UnableToCalculateEvaluation)
         } else if ret == 0 {
+            self.stage = Stage::Encoded;
             Ok(())
         } else {
             Err(WhisperError::GenericError(ret))
@@ -147,16 +300,22 @@ impl WhisperState {
 
     /// Run the Whisper decoder to obtain the logits and probabilities for the next token.
     /// Make sure to call [WhisperState::encode] first.
-    /// tokens + n_tokens is the provided context for the decoder.
+    ///
+    /// The state keeps a single cache of the tokens decoded so far: `n_past` says how many of
+    /// them `tokens` follows, and anything cached after that is overwritten. Since decoding
+    /// leaves the encoder output untouched, the hypotheses of a custom beam search can be scored
+    /// one after the other on one state, each decoded from the end of the prefix still cached,
+    /// or from scratch. See `examples/beam_search.rs`.
     ///
     /// # Arguments
     /// * tokens: The tokens to decode.
-    /// * n_tokens: The number of tokens to decode.
     /// * n_past: The number of past tokens to use for the decoding.
-    /// * n_threads: How many threads to use. Defaults to 1. Must be at least 1, returns an error otherwise.
+    /// * threads: How many threads to use. Defaults to 1. Must be at least 1, returns an error otherwise.
     ///
     /// # Returns
     /// Ok(()) on success, Err(WhisperError) on failure.
+    /// [`WhisperError::DecoderNotLoaded`] if the context was loaded without its decoder.
+    /// [`WhisperError::EncodeNotComplete`] if the encoder wasn't run on the current spectrogram.
     ///
     /// # C++ equivalent
     /// `int whisper_decode(struct whisper_context * ctx, const whisper_token * tokens, int n_tokens, int n_past, int n_threads)`
@@ -169,6 +328,11 @@ impl WhisperState {
         if threads < 1 {
             return Err(WhisperError::InvalidThreadCount);
         }
+        self.check_decoder()?;
+        if self.stage < Stage::Encoded {
+            return Err(WhisperError::EncodeNotComplete);
+        }
+        let started = Instant::now();
         let ret = unsafe {
             whisper_rs_sys::whisper_decode_with_state(
                 self.ctx.ctx,
@@ -179,15 +343,30 @@ impl WhisperState {
                 threads as c_int,
             )
         };
+        self.timings.decode_calls += 1;
+        self.timings.decode += started.elapsed();
+        self.timings.decoded_tokens += tokens.len() as u64;
+        self.decoded_tokens = 0;
+        self.stage = Stage::Encoded;
         if ret == -1 {
             Err(WhisperError::UnableToCalculateEvaluation)
         } else if ret == 0 {
+            self.decoded_tokens = tokens.len();
+            self.stage = Stage::Decoded;
             Ok(())
         } else {
             Err(WhisperError::GenericError(ret))
         }
     }
 
+    /// Fail if the context was loaded without its decoder.
+    fn check_decoder(&self) -> Result<(), WhisperError> {
+        if self.ctx.encoder_only {
+            return Err(WhisperError::DecoderNotLoaded);
+        }
+        Ok(())
+    }
+
     // Language functions
     /// Use mel data at offset_ms to try and auto-detect the spoken language
     /// Make sure to call [`Self::pcm_to_mel`] or [`Self::set_mel`] first
@@ -199,17 +378,23 @@ impl WhisperState {
     /// # Returns
     /// `Ok((i32, Vec<f32>))` on success where the i32 is detected language id and Vec<f32>
     /// is array with the probabilities of all languages, `Err(WhisperError)` on failure.
+    /// [`WhisperError::DecoderNotLoaded`] if the context was loaded without its decoder.
+    ///
+    /// The detection encodes the spectrogram at `offset_ms` and decodes a single step, replacing
+    /// the encoder output and logits of earlier calls, so [`Self::get_logits`] is only available
+    /// again after the next [`Self::decode`].
     ///
     /// # C++ equivalent
     /// `int whisper_lang_auto_detect(struct whisper_context * ctx, int offset_ms, int n_threads, float * lang_probs)`
     pub fn lang_detect(
-        &self,
+        &mut self,
         offset_ms: usize,
         threads: usize,
     ) -> Result<(i32, Vec<f32>), WhisperError> {
         if threads < 1 {
             return Err(WhisperError::InvalidThreadCount);
         }
+        self.check_decoder()?;
 
         let mut lang_probs: Vec<f32> = vec![0.0; crate::standalone::get_lang_max_id() as usize + 1];
         let ret = unsafe {
@@ -221,6 +406,8 @@ impl WhisperState {
                 lang_probs.as_mut_ptr(),
             )
         };
+        self.decoded_tokens = 0;
+        self.stage = lang_detect_stage(self.stage, ret >= 0);
         if ret < 0 {
             Err(WhisperError::GenericError(ret))
         } else {
@@ -228,22 +415,102 @@ impl WhisperState {
         }
     }
 
+    /// Detect the spoken language of `samples` without transcribing them.
+    ///
+    /// Computes the mel spectrogram of `samples`, encodes the first 30 seconds and runs a single
+    /// decoder step, which is much cheaper than [`Self::full`]. Use it to decide which model or
+    /// parameters to transcribe with, or whether to transcribe at all.
+    ///
+    /// # Returns
+    /// `Ok(probabilities)` on success, the probability of every language in
+    /// [`Language::ALL`], most likely first. `Err(WhisperError)` on failure.
+    ///
+    /// # C++ equivalent
+    /// `int whisper_lang_auto_detect(struct whisper_context * ctx, int offset_ms, int n_threads, float * lang_probs)`
+    pub fn detect_language(
+        &mut self,
+        samples: &[f32],
+        threads: usize,
+    ) -> Result<Vec<(Language, f32)>, WhisperError> {
+        self.pcm_to_mel(samples, threads)?;
+        let (_, probabilities) = self.lang_detect(0, threads)?;
+        Ok(Language::ranked(&probabilities))
+    }
+
+    /// Detect which of the `allowed` languages `samples` are spoken in, see
+    /// [`Self::detect_language`].
+    ///
+    /// Short or noisy clips are often detected as a language that can't be in them. When the
+    /// candidates are known, e.g. the languages a service supports, this picks the likeliest of
+    /// them instead. Pass the first language to [`FullParams::set_language`] to transcribe in it.
+    ///
+    /// # Returns
+    /// `Ok(probabilities)` on success, the probability of each allowed language given that it
+    /// is one of them, most likely first. With no allowed languages, all languages are allowed.
+    /// `Err(WhisperError)` on failure.
+    pub fn detect_language_among(
+        &mut self,
+        samples: &[f32],
+        threads: usize,
+        allowed: &[Language],
+    ) -> Result<Vec<(Language, f32)>, WhisperError> {
+        let ranked = self.detect_language(samples, threads)?;
+        Ok(Language::restrict(ranked, allowed))
+    }
+
     // logit functions
-    /// Gets logits obtained from the last call to [WhisperState::decode].
-    /// As of whisper.cpp 1.4.1, only a single row of logits is available, corresponding to the last token in the input.
+    /// Gets the logits of the next token after the last call to [WhisperState::decode]: one
+    /// score per token of the vocabulary, before any softmax, for sampling, calibration or
+    /// rescoring of your own. whisper.cpp only computes them for the last token passed in.
+    ///
+    /// After [`Self::full`] they are left over from whichever decoder ran last. To inspect the
+    /// logits of each step of [`Self::full`], see [`FullParams::add_logits_filter`].
     ///
     /// # Returns
-    /// A slice of logits with length equal to n_vocab.
+    /// A slice of logits with length equal to n_vocab, valid until the state is used again.
+    /// [`WhisperError::DecodeNotComplete`] if nothing was decoded since the spectrogram or
+    /// encoder output last changed.
+    ///
+    /// # Examples
+    /// Greedy decoding by hand:
+    /// ```no_run
+    /// # use whisper_rs::{WhisperContext, WhisperContextParameters};
+    /// # let ctx = WhisperContext::new_with_params("model.bin", WhisperContextParameters::default()).unwrap();
+    /// # let mut state = ctx.create_state().unwrap();
+    /// # let audio = vec![0.0f32; 16000];
+    /// state.pcm_to_mel(&audio, 1).unwrap();
+    /// state.encode(0, 1).unwrap();
+    /// let mut tokens = vec![ctx.token_sot(), ctx.token_not()];
+    /// while tokens.len() < 32 {
+    ///     let new = if tokens.len() == 2 { &tokens[..] } else { &tokens[tokens.len() - 1..] };
+    ///     state.decode(new, tokens.len() - new.len(), 1).unwrap();
+    ///     let logits = state.get_logits().unwrap();
+    ///     let (best, _) = logits
+    ///         .iter()
+    ///         .enumerate()
+    ///         .max_by(|a, b| a.1.total_cmp(b.1))
+    ///         .unwrap();
+    ///     if best as i32 == ctx.token_eot() {
+    ///         break;
+    ///     }
+    ///     tokens.push(best as i32);
+    /// }
+    /// ```
     ///
     /// # C++ equivalent
-    /// `float * whisper_get_logits(struct whisper_context * ctx)`
+    /// `float * whisper_get_logits_from_state(struct whisper_state * state)`
     pub fn get_logits(&self) -> Result<&[f32], WhisperError> {
+        if self.stage < Stage::Decoded {
+            return Err(WhisperError::DecodeNotComplete);
+        }
         let ret = unsafe { whisper_rs_sys::whisper_get_logits_from_state(self.ptr) };
         if ret.is_null() {
             return Err(WhisperError::NullPointer);
         }
-        let n_vocab = self.n_vocab();
-        Ok(unsafe { std::slice::from_raw_parts(ret, n_vocab as usize) })
+        let n_vocab = self.n_vocab().max(0) as usize;
+        // whisper.cpp keeps a row per token decoded, but only fills in the last one
+        let row = self.decoded_tokens.saturating_sub(1);
+        Ok(unsafe { std::slice::from_raw_parts(ret.add(row * n_vocab), n_vocab) })
     }
 
     // model attributes
@@ -281,6 +548,12 @@ impl WhisperState {
     ///
     /// # Returns
     /// Ok(c_int) on success, Err(WhisperError) on failure.
+    /// The parameters are checked with [`FullParams::validate`] first, so out-of-range values
+    /// are reported as errors instead of reaching whisper.cpp. Contexts loaded without their
+    /// decoder return [`WhisperError::DecoderNotLoaded`]. Translation with a model that
+    /// can't translate, see [`ModelCapabilities::translation`], is reported as
+    /// [`UnsupportedConfiguration::TranslationUnsupported`]; with a pruned decoder, see
+    /// [`ModelCapabilities::pruned_decoder`], it runs but logs a warning the first time.
     ///
     /// # C++ equivalent
     /// `int whisper_full_with_state(
@@ -290,11 +563,99 @@ impl WhisperState {
     ///                            const float * samples,
     ///                                    int   n_samples)`
     pub fn full(&mut self, params: FullParams, data: &[f32]) -> Result<c_int, WhisperError> {
+        let observers = self.ctx.observers.get();
+        let run = RunStart {
+            samples: data.len(),
+            n_threads: params.fp.n_threads,
+        };
+        observers.iter().for_each(|o| o.on_run_start(&run));
+        if let Some(fallback) = &self.ctx.dtw_fallback {
+            observers.iter().for_each(|o| o.on_dtw_fallback(fallback));
+        }
+        let started = Instant::now();
+        let result = self.run_full(params, data, &observers);
+
+        if !observers.is_empty() {
+            let segments = if result.is_ok() {
+                self.full_n_segments()
+            } else {
+                0
+            };
+            for segment in self.segments().take(segments.max(0) as usize) {
+                observers.iter().for_each(|o| o.on_segment(&segment));
+            }
+            let end = RunEnd {
+                duration: started.elapsed(),
+                audio_duration: Duration::from_secs_f64(
+                    data.len() as f64 / whisper_rs_sys::WHISPER_SAMPLE_RATE as f64,
+                ),
+                segments,
+                error: result.err(),
+            };
+            observers.iter().for_each(|o| o.on_run_end(&end));
+        }
+        result
+    }
+
+    /// [`Self::full`], without the start and end events of `observers`.
+    fn run_full(
+        &mut self,
+        mut params: FullParams,
+        data: &[f32],
+        observers: &[Arc<dyn Observer>],
+    ) -> Result<c_int, WhisperError> {
         if data.is_empty() {
             // can randomly trigger segmentation faults if we don't check this
             return Err(WhisperError::NoSamples);
         }
+        self.check_decoder()?;
+        let no_context = params.fp.no_context;
+        let own_prompt = params.fp.prompt_n_tokens != 0 || !params.fp.initial_prompt.is_null();
+        let compression = params.context_compression.filter(|_| !no_context);
+        if let (Some(compression), false) = (compression, own_prompt) {
+            let prompt =
+                compression.prompt(&self.entities, &self.context, |id| self.token_text(id));
+            params.set_prompt_tokens(&prompt);
+            // the raw tail whisper.cpp keeps from the last run is replaced by the prompt
+            params.fp.no_context = true;
+        } else if self.restored && !no_context && !own_prompt {
+            params.set_prompt_tokens(&self.context);
+        }
+        params.validate(self.ctx.model_n_audio_ctx(), data.len())?;
+        params.clamp_duration(data.len());
+        let samples = data.len();
+        let input = pad_short_input(&mut params, data)?;
+        self.audio_end =
+            (input.len() != data.len()).then_some((data.len() / SAMPLES_PER_CS) as i64);
+        let data = &*input;
+        if params.fp.translate {
+            let capabilities = ModelCapabilities::of(&self.ctx);
+            if !capabilities.translation {
+                return Err(WhisperError::UnsupportedConfiguration(
+                    UnsupportedConfiguration::TranslationUnsupported,
+                ));
+            }
+            if capabilities.pruned_decoder && !self.warned_pruned {
+                generic_warn!(
+                    "whisper-rs: this model has a pruned decoder and wasn't trained to translate, \
+                     the translation may be unreliable"
+                );
+                self.warned_pruned = true;
+            }
+        }
 
+        let started = Instant::now();
+        if let Some(watchdog) = &params.watchdog {
+            watchdog.start(data.len());
+        }
+
+        let mut fallbacks = FallbackRecorder::new(observers, &params.fp);
+        if !observers.is_empty() && params.fp.temperature_inc > 0.0 {
+            fallbacks.install(&mut params.fp);
+        }
+        let mut abort = AbortRecorder::new(&params.fp);
+        abort.install(&mut params.fp);
+        self.decoded_tokens = 0;
         let ret = unsafe {
             whisper_rs_sys::whisper_full_with_state(
                 self.ctx.ctx,
@@ -304,17 +665,84 @@ impl WhisperState {
                 data.len() as c_int,
             )
         };
-        if ret == -1 {
-            Err(WhisperError::UnableToCalculateSpectrogram)
-        } else if ret == 7 {
-            Err(WhisperError::FailedToEncode)
-        } else if ret == 8 {
-            Err(WhisperError::FailedToDecode)
-        } else if ret == 0 {
-            Ok(ret)
+        params.flush_segment_batch();
+        let tripped = params.watchdog.as_ref().and_then(|w| w.tripped());
+        let result = full_result(ret, tripped, abort.aborted);
+        self.ctx.usage.record(&result);
+        // a successful run leaves the spectrogram, encoder output and logits of its last window
+        self.stage = if result.is_ok() {
+            Stage::Decoded
         } else {
-            Err(WhisperError::GenericError(ret))
+            Stage::Empty
+        };
+
+        let duration = started.elapsed();
+        let audio_duration =
+            Duration::from_secs_f64(data.len() as f64 / whisper_rs_sys::WHISPER_SAMPLE_RATE as f64);
+        let segments = if result.is_ok() {
+            self.full_n_segments()
+        } else {
+            0
+        };
+        self.timings.runs += 1;
+        self.timings.full += duration;
+        self.timings.audio += audio_duration;
+        self.timings.segments += segments.max(0) as u32;
+        self.timings.tokens += self
+            .segments()
+            .take(segments.max(0) as usize)
+            .map(|segment| segment.n_tokens().max(0) as u64)
+            .sum::<u64>();
+        if result.is_ok() {
+            self.advance(samples, no_context, compression, segments);
+        }
+        result
+    }
+
+    /// Track the context and audio offset after a successful run over `samples` of audio.
+    fn advance(
+        &mut self,
+        samples: usize,
+        no_context: bool,
+        compression: Option<ContextCompression>,
+        segments: c_int,
+    ) {
+        let end = self.offsets.1;
+        self.offsets = (end, end + (samples / SAMPLES_PER_CS) as i64);
+        self.restored = false;
+        self.language = usize::try_from(self.full_lang_id_from_state())
+            .ok()
+            .and_then(|id| Language::ALL.get(id).copied());
+        if no_context {
+            self.context.clear();
+            self.entities.clear();
         }
+        let eot = self.ctx.token_eot();
+        let tokens: Vec<WhisperTokenId> = self
+            .segments()
+            .take(segments.max(0) as usize)
+            .flat_map(|segment| segment.text_tokens().map(|t| t.id).collect::<Vec<_>>())
+            .filter(|&id| id < eot)
+            .collect();
+        self.context.extend(tokens);
+        let max = (self.ctx.model_n_text_ctx() / 2).max(0) as usize;
+        let excess = self.context.len().saturating_sub(max);
+        if let Some(compression) = compression {
+            let mut entities = std::mem::take(&mut self.entities);
+            compression.remember(&mut entities, &self.context[..excess], |id| {
+                self.token_text(id)
+            });
+            self.entities = entities;
+        }
+        self.context.drain(..excess);
+    }
+
+    /// The text of a token of the vocabulary, empty if it has none.
+    fn token_text(&self, token: WhisperTokenId) -> String {
+        self.ctx
+            .token_to_str_lossy(token)
+            .map(Cow::into_owned)
+            .unwrap_or_default()
     }
 
     /// Number of generated text segments.
@@ -359,4 +787,170 @@ impl WhisperState {
     pub fn as_iter(&self) -> WhisperStateSegmentIterator<'_> {
         WhisperStateSegmentIterator::new(self)
     }
+
+    /// Get an iterator over the segments of the last run, in order.
+    ///
+    /// Each [`WhisperSegment`] borrows this state, and gives access to its text, timestamps
+    /// and [`WhisperSegment::tokens`]. `&WhisperState` implements [`IntoIterator`] as well:
+    ///
+    /// ```no_run
+    /// # fn print(state: &whisper_rs::WhisperState) {
+    /// for segment in state {
+    ///     println!("[{} - {}] {}", segment.start_timestamp(), segment.end_timestamp(), segment);
+    /// }
+    /// # }
+    /// ```
+    pub fn segments(&self) -> WhisperStateSegmentIterator<'_> {
+        WhisperStateSegmentIterator::new(self)
+    }
+
+    /// Copy all segments of the last run into an owned [`Transcript`].
+    ///
+    /// # Returns
+    /// * On success: the transcript, with any invalid UTF-8 replaced with the replacement character
+    /// * On failure: [`WhisperError::NullPointer`]
+    pub fn transcript(&self) -> Result<Transcript, WhisperError> {
+        Transcript::try_from(self)
+    }
+}
+
+/// Number of frames in a spectrogram of `len` values with `n_mel` bands, for a model with
+/// `model_n_mel` bands.
+fn mel_frames(len: usize, n_mel: c_int, model_n_mel: c_int) -> Result<usize, WhisperError> {
+    if n_mel != model_n_mel {
+        return Err(WhisperError::InvalidMelBands);
+    }
+    let bands = n_mel.max(1) as usize;
+    if len == 0 || !len.is_multiple_of(bands) {
+        return Err(WhisperError::InvalidMelShape { len, n_mel });
+    }
+    if len / bands > c_int::MAX as usize {
+        return Err(WhisperError::TooManySamples(len));
+    }
+    Ok(len / bands)
+}
+
+/// How far the pipeline got after a language detection, which runs the encoder and a decoder
+/// step of its own, from `stage` before it.
+fn lang_detect_stage(stage: Stage, succeeded: bool) -> Stage {
+    if succeeded {
+        Stage::Encoded
+    } else {
+        stage.min(Stage::Mel)
+    }
+}
+
+/// The result of `whisper_full_with_state` returning `ret`. A run stopped by the watchdog or
+/// the abort callback fails to encode or decode, and is reported as stopped instead.
+fn full_result(
+    ret: c_int,
+    tripped: Option<WatchdogReason>,
+    aborted: bool,
+) -> Result<c_int, WhisperError> {
+    if ret == 0 {
+        return Ok(ret);
+    }
+    if let Some(reason) = tripped {
+        return Err(WhisperError::DeadlineExceeded { reason });
+    }
+    if aborted {
+        return Err(WhisperError::Aborted);
+    }
+    Err(match ret {
+        -1 | -2 => WhisperError::UnableToCalculateSpectrogram,
+        -6 => WhisperError::FailedToEncode,
+        -7 | -8 => WhisperError::FailedToDecode,
+        _ => WhisperError::GenericError(ret),
+    })
+}
+
+/// Chains the abort callback of a run to record whether it stopped the run.
+struct AbortRecorder {
+    callback: whisper_rs_sys::ggml_abort_callback,
+    user_data: *mut c_void,
+    aborted: bool,
+}
+
+impl AbortRecorder {
+    fn new(fp: &whisper_rs_sys::whisper_full_params) -> Self {
+        Self {
+            callback: fp.abort_callback,
+            user_data: fp.abort_callback_user_data,
+            aborted: false,
+        }
+    }
+
+    /// Put this recorder in front of the abort callback of `fp`, if it has one.
+    /// The recorder must stay in place until the run is over.
+    fn install(&mut self, fp: &mut whisper_rs_sys::whisper_full_params) {
+        if self.callback.is_some() {
+            fp.abort_callback = Some(record_abort);
+            fp.abort_callback_user_data = self as *mut Self as *mut c_void;
+        }
+    }
+}
+
+unsafe extern "C" fn record_abort(user_data: *mut c_void) -> bool {
+    // SAFETY: user_data is the AbortRecorder installed for this run
+    let recorder = &mut *(user_data as *mut AbortRecorder);
+    let abort = recorder
+        .callback
+        .is_some_and(|callback| callback(recorder.user_data));
+    recorder.aborted |= abort;
+    abort
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn language_detection_replaces_the_logits() {
+        assert_eq!(lang_detect_stage(Stage::Decoded, true), Stage::Encoded);
+        assert_eq!(lang_detect_stage(Stage::Decoded, false), Stage::Mel);
+        assert_eq!(lang_detect_stage(Stage::Empty, false), Stage::Empty);
+    }
+
+    #[test]
+    fn stopped_runs_are_not_compute_failures() {
+        assert_eq!(full_result(0, None, false).unwrap(), 0);
+        assert!(matches!(
+            full_result(-6, None, false),
+            Err(WhisperError::FailedToEncode)
+        ));
+        assert!(matches!(
+            full_result(-8, None, false),
+            Err(WhisperError::FailedToDecode)
+        ));
+        assert!(matches!(
+            full_result(-6, None, true),
+            Err(WhisperError::Aborted)
+        ));
+        assert!(matches!(
+            full_result(-7, Some(WatchdogReason::Deadline), true),
+            Err(WhisperError::DeadlineExceeded { .. })
+        ));
+        // a run that finished regardless succeeded
+        assert!(full_result(0, None, true).is_ok());
+    }
+
+    #[test]
+    fn mel_shape_is_validated() {
+        assert_eq!(mel_frames(80 * 3000, 80, 80).unwrap(), 3000);
+        assert!(matches!(
+            mel_frames(80 * 3000, 80, 128),
+            Err(WhisperError::InvalidMelBands)
+        ));
+        assert!(matches!(
+            mel_frames(80 * 3000 + 1, 80, 80),
+            Err(WhisperError::InvalidMelShape {
+                len: 240_001,
+                n_mel: 80
+            })
+        ));
+        assert!(matches!(
+            mel_frames(0, 80, 80),
+            Err(WhisperError::InvalidMelShape { .. })
+        ));
+    }
 }
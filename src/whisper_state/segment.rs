@@ -1,4 +1,4 @@
-use crate::{WhisperError, WhisperState, WhisperToken};
+use crate::{SegmentTokens, TokenData, WhisperError, WhisperState, WhisperToken};
 use std::borrow::Cow;
 use std::ffi::{c_int, CStr};
 use std::fmt;
@@ -44,9 +44,9 @@ impl<'a> WhisperSegment<'a> {
     /// # C++ equivalent
     /// `int64_t whisper_full_get_segment_t0(struct whisper_context * ctx, int i_segment)`
     pub fn start_timestamp(&self) -> i64 {
-        unsafe {
+        self.state.clamp_to_audio(unsafe {
             whisper_rs_sys::whisper_full_get_segment_t0_from_state(self.state.ptr, self.segment_idx)
-        }
+        })
     }
 
     /// Get the end time of the specified segment.
@@ -57,9 +57,9 @@ impl<'a> WhisperSegment<'a> {
     /// # C++ equivalent
     /// `int64_t whisper_full_get_segment_t1(struct whisper_context * ctx, int i_segment)`
     pub fn end_timestamp(&self) -> i64 {
-        unsafe {
+        self.state.clamp_to_audio(unsafe {
             whisper_rs_sys::whisper_full_get_segment_t1_from_state(self.state.ptr, self.segment_idx)
-        }
+        })
     }
 
     /// Get number of tokens in this segment.
@@ -169,6 +169,22 @@ impl<'a> WhisperSegment<'a> {
             .then(|| unsafe { WhisperToken::new_unchecked(self, token) })
     }
 
+    /// Iterate over the data of every token in this segment, including special tokens.
+    ///
+    /// # C++ equivalent
+    /// `whisper_token_data whisper_full_get_token_data(struct whisper_context * ctx, int i_segment, int i_token)`
+    /// and `const char * whisper_full_get_token_text(struct whisper_context * ctx, int i_segment, int i_token)`
+    /// for each token up to `int whisper_full_n_tokens(struct whisper_context * ctx, int i_segment)`
+    pub fn tokens(&self) -> SegmentTokens<'_, 'a> {
+        SegmentTokens::new(self)
+    }
+
+    /// Iterate over the tokens of this segment that hold text, skipping the special tokens
+    /// such as timestamps and `[_BEG_]`, e.g. for formatting code that highlights words.
+    pub fn text_tokens(&self) -> impl DoubleEndedIterator<Item = TokenData<'a>> + '_ {
+        self.tokens().filter(|token| !token.special)
+    }
+
     /// The same as [`Self::get_token`] but without any bounds check.
     ///
     /// # Safety
@@ -210,3 +226,12 @@ impl fmt::Debug for WhisperSegment<'_> {
             .finish_non_exhaustive()
     }
 }
+
+impl<'a, 'b> IntoIterator for &'a WhisperSegment<'b> {
+    type Item = TokenData<'b>;
+    type IntoIter = SegmentTokens<'a, 'b>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.tokens()
+    }
+}
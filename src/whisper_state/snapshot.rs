@@ -0,0 +1,116 @@
+use crate::{Language, WhisperTokenId};
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+const HEADER: &str = "# whisper-rs snapshot 1";
+
+/// The decoder state a [`crate::WhisperState`] carries from one [`crate::WhisperState::full`]
+/// call to the next, see [`crate::WhisperState::snapshot`].
+///
+/// Saving one after each chunk of a long job lets it resume after a crash from the last chunk
+/// transcribed, with the same context fed to the decoder as if it had never stopped, instead of
+/// reprocessing everything before it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StateSnapshot {
+    /// Where the next run starts in the audio of the whole job, in centiseconds: the length of
+    /// all the audio transcribed so far, without padding. See [`crate::WhisperState::audio_offset`].
+    pub offset: i64,
+    /// The text tokens of the last segments, which the next run is prompted with unless
+    /// [`crate::FullParams::set_no_context`] is set.
+    pub context_tokens: Vec<WhisperTokenId>,
+    /// The language of the last run, which is worth passing to
+    /// [`crate::FullParams::set_language`] on resume if it was detected.
+    pub language: Option<Language>,
+}
+
+impl StateSnapshot {
+    /// Write the snapshot to `path`, replacing it atomically so a crash while saving never
+    /// leaves a truncated checkpoint behind.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        let mut contents = format!("{HEADER}\noffset = {}\n", self.offset);
+        if let Some(language) = self.language {
+            contents.push_str(&format!("language = {}\n", language.code()));
+        }
+        let tokens: Vec<String> = self.context_tokens.iter().map(|t| t.to_string()).collect();
+        contents.push_str(&format!("context = {}\n", tokens.join(" ")));
+
+        let tmp = path.with_extension(format!("{}.tmp", std::process::id()));
+        let mut file = fs::File::create(&tmp)?;
+        file.write_all(contents.as_bytes())?;
+        file.sync_all()?;
+        if let Err(e) = fs::rename(&tmp, path) {
+            let _ = fs::remove_file(&tmp);
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Read a snapshot written by [`Self::save`].
+    ///
+    /// # Returns
+    /// An error of kind [`io::ErrorKind::InvalidData`] if the file isn't a snapshot.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let contents = fs::read_to_string(path)?;
+        Self::parse(&contents).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not a whisper-rs state snapshot",
+            )
+        })
+    }
+
+    fn parse(contents: &str) -> Option<Self> {
+        let mut lines = contents.lines();
+        if lines.next()?.trim() != HEADER {
+            return None;
+        }
+        let mut snapshot = Self::default();
+        for line in lines.filter(|line| !line.trim().is_empty()) {
+            let (name, value) = line.split_once('=')?;
+            let value = value.trim();
+            match name.trim() {
+                "offset" => snapshot.offset = value.parse().ok()?,
+                "language" => snapshot.language = Some(value.parse().ok()?),
+                "context" => {
+                    snapshot.context_tokens = value
+                        .split_whitespace()
+                        .map(str::parse)
+                        .collect::<Result<_, _>>()
+                        .ok()?
+                }
+                // written by a newer version, not needed to resume
+                _ => {}
+            }
+        }
+        Some(snapshot)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn snapshot_round_trips_through_a_file() {
+        let path =
+            std::env::temp_dir().join(format!("whisper-rs-snapshot-{}.txt", std::process::id()));
+        let snapshot = StateSnapshot {
+            offset: 360_000,
+            context_tokens: vec![50364, 440, 1723],
+            language: Some(Language::German),
+        };
+        snapshot.save(&path).unwrap();
+        assert_eq!(StateSnapshot::load(&path).unwrap(), snapshot);
+
+        let empty = StateSnapshot::default();
+        empty.save(&path).unwrap();
+        assert_eq!(StateSnapshot::load(&path).unwrap(), empty);
+
+        fs::write(&path, "offset = 12\n").unwrap();
+        let error = StateSnapshot::load(&path).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        let _ = fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,71 @@
+use std::time::Duration;
+
+/// Time a [`crate::WhisperState`] has spent computing since it was created or
+/// [`crate::WhisperState::reset_timings`] was last called, see
+/// [`crate::WhisperState::timings`].
+///
+/// whisper.cpp keeps finer-grained timings inside each state, but only reports them for the
+/// state built into a context, which this crate doesn't use, and then only by printing them.
+/// These are measured around the calls into whisper.cpp instead: [`Self::full`] covers whole
+/// runs, including the mel spectrogram, encoding and decoding they do internally, while the
+/// other durations count the low-level calls made directly.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WhisperTimings {
+    /// Number of calls to [`crate::WhisperState::full`], including failed ones.
+    pub runs: u32,
+    /// Time spent in [`crate::WhisperState::full`].
+    pub full: Duration,
+    /// Duration of the audio passed to [`crate::WhisperState::full`], including any padding.
+    pub audio: Duration,
+    /// Number of text segments produced by successful runs.
+    pub segments: u32,
+    /// Number of tokens in those segments, including special and timestamp tokens.
+    pub tokens: u64,
+    /// Number of calls to [`crate::WhisperState::pcm_to_mel`] and [`crate::WhisperState::set_mel`].
+    pub mel_calls: u32,
+    /// Time spent in those calls.
+    pub mel: Duration,
+    /// Number of calls to [`crate::WhisperState::encode`].
+    pub encode_calls: u32,
+    /// Time spent in those calls.
+    pub encode: Duration,
+    /// Number of calls to [`crate::WhisperState::decode`].
+    pub decode_calls: u32,
+    /// Time spent in those calls.
+    pub decode: Duration,
+    /// Number of tokens passed to those calls.
+    pub decoded_tokens: u64,
+}
+
+impl WhisperTimings {
+    /// Time spent per second of audio transcribed by [`crate::WhisperState::full`], or `None`
+    /// before the first run. Below 1 is faster than real time.
+    pub fn real_time_factor(&self) -> Option<f64> {
+        (!self.audio.is_zero()).then(|| self.full.as_secs_f64() / self.audio.as_secs_f64())
+    }
+
+    /// Average time per token produced by [`crate::WhisperState::full`], or `None` before any
+    /// tokens were produced.
+    pub fn per_token(&self) -> Option<Duration> {
+        let tokens = u32::try_from(self.tokens).unwrap_or(u32::MAX);
+        (tokens > 0).then(|| self.full / tokens)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn rates_need_some_work_done() {
+        let mut timings = WhisperTimings::default();
+        assert_eq!(timings.real_time_factor(), None);
+        assert_eq!(timings.per_token(), None);
+
+        timings.full = Duration::from_secs(5);
+        timings.audio = Duration::from_secs(20);
+        timings.tokens = 50;
+        assert_eq!(timings.real_time_factor(), Some(0.25));
+        assert_eq!(timings.per_token(), Some(Duration::from_millis(100)));
+    }
+}
@@ -35,18 +35,24 @@ impl<'a, 'b> WhisperToken<'a, 'b> {
     /// Get token data for this token in its segment.
     ///
     /// # Returns
-    /// [`WhisperTokenData`]
+    /// [`WhisperTokenData`]. Timestamps are clamped to the end of the audio if it was padded,
+    /// see [`crate::FullParams::set_pad_short_audio`].
     ///
     /// # C++ equivalent
     /// `whisper_token_data whisper_full_get_token_data(struct whisper_context * ctx, int i_segment, int i_token)`
     pub fn token_data(&self) -> WhisperTokenData {
-        unsafe {
+        let state = self.segment.get_state();
+        let mut data = unsafe {
             whisper_rs_sys::whisper_full_get_token_data_from_state(
-                self.segment.get_state().ptr,
+                state.ptr,
                 self.segment.segment_index(),
                 self.token_idx,
             )
-        }
+        };
+        data.t0 = state.clamp_to_audio(data.t0);
+        data.t1 = state.clamp_to_audio(data.t1);
+        data.t_dtw = state.clamp_to_audio(data.t_dtw);
+        data
     }
 
     /// Get the probability of this token in its segment.
@@ -66,6 +72,15 @@ impl<'a, 'b> WhisperToken<'a, 'b> {
         }
     }
 
+    /// Whether this is a special token (start/end of transcript, language, task or timestamp)
+    /// rather than text.
+    ///
+    /// # Returns
+    /// `bool`
+    pub fn is_special(&self) -> bool {
+        self.token_id() >= self.segment.get_state().ctx.token_eot()
+    }
+
     fn to_raw_cstr(&self) -> Result<&'b CStr, WhisperError> {
         let ret = unsafe {
             whisper_rs_sys::whisper_full_get_token_text_from_state(
@@ -0,0 +1,105 @@
+use crate::{WhisperSegment, WhisperToken, WhisperTokenId};
+use std::borrow::Cow;
+use std::ffi::c_int;
+use std::iter::FusedIterator;
+
+/// The data of a single token, borrowed from the [`crate::WhisperState`] it was decoded in.
+///
+/// See [`crate::TranscriptToken`] for an owned copy.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenData<'a> {
+    pub id: WhisperTokenId,
+    /// The decoded text. Tokens that split a multi-byte character contain the replacement character.
+    pub text: Cow<'a, str>,
+    /// Probability of this token.
+    pub p: f32,
+    /// Log probability of this token.
+    pub plog: f32,
+    /// Start time in centiseconds. Only set if token timestamps were enabled, otherwise -1.
+    pub t0: i64,
+    /// End time in centiseconds. Only set if token timestamps were enabled, otherwise -1.
+    pub t1: i64,
+    /// Time in centiseconds from DTW alignment. Only set if DTW was enabled, otherwise -1.
+    pub t_dtw: i64,
+    /// Whether this is a special token rather than text, see [`WhisperToken::is_special`].
+    pub special: bool,
+}
+
+impl TokenData<'_> {
+    /// Whether [`Self::t0`] and [`Self::t1`] hold real timestamps.
+    pub fn has_timestamps(&self) -> bool {
+        self.t0 >= 0 && self.t1 >= self.t0
+    }
+}
+
+impl<'b> From<&WhisperToken<'_, 'b>> for TokenData<'b> {
+    /// Gets the text with [`WhisperToken::to_str_lossy`]; it is empty in the unlikely case of
+    /// whisper.cpp returning a null pointer.
+    fn from(token: &WhisperToken<'_, 'b>) -> Self {
+        let data = token.token_data();
+        Self {
+            id: data.id,
+            text: token.to_str_lossy().unwrap_or_default(),
+            p: data.p,
+            plog: data.plog,
+            t0: data.t0,
+            t1: data.t1,
+            t_dtw: data.t_dtw,
+            special: token.is_special(),
+        }
+    }
+}
+
+/// An iterator over the [`TokenData`] of a segment, see [`WhisperSegment::tokens`].
+pub struct SegmentTokens<'a, 'b> {
+    segment: &'a WhisperSegment<'b>,
+    next: c_int,
+    end: c_int,
+}
+
+impl<'a, 'b> SegmentTokens<'a, 'b> {
+    pub(super) fn new(segment: &'a WhisperSegment<'b>) -> Self {
+        Self {
+            segment,
+            next: 0,
+            end: segment.n_tokens(),
+        }
+    }
+
+    fn data(&self, token: c_int) -> TokenData<'b> {
+        // SAFETY: only called with indices between 0 and the segment's token count
+        let token = unsafe { WhisperToken::new_unchecked(self.segment, token) };
+        TokenData::from(&token)
+    }
+}
+
+impl<'b> Iterator for SegmentTokens<'_, 'b> {
+    type Item = TokenData<'b>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next >= self.end {
+            return None;
+        }
+        self.next += 1;
+        Some(self.data(self.next - 1))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = (self.end - self.next).max(0) as usize;
+        (len, Some(len))
+    }
+}
+
+impl DoubleEndedIterator for SegmentTokens<'_, '_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.next >= self.end {
+            return None;
+        }
+        self.end -= 1;
+        Some(self.data(self.end))
+    }
+}
+
+impl ExactSizeIterator for SegmentTokens<'_, '_> {}
+
+impl FusedIterator for SegmentTokens<'_, '_> {}
@@ -0,0 +1,210 @@
+use crate::transcribe::SAMPLES_PER_CS;
+use crate::{FullParams, WhisperError, WhisperState, WhisperTokenId};
+use std::ffi::{c_int, c_void, CStr};
+use std::ops::Range;
+
+/// Length of the audio window Whisper decodes at once, in centiseconds.
+const WINDOW_CS: i64 = whisper_rs_sys::WHISPER_CHUNK_SIZE as i64 * 100;
+
+/// One pass of whisper.cpp over a window of up to 30 seconds of audio,
+/// see [`WhisperState::full_with_windows`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodeWindow {
+    /// The samples of the input this window started at and advanced past.
+    /// The next window starts where this one ends.
+    pub samples: Range<usize>,
+    /// The text context the decoder was prompted with: the initial prompt and the tokens
+    /// of earlier windows, truncated to the last [`FullParams::set_n_max_text_ctx`] tokens.
+    ///
+    /// This is reconstructed rather than reported by whisper.cpp. It does not include context
+    /// carried over from an earlier run on the same state, and whisper.cpp drops the context
+    /// of a window it had to decode at a temperature above 0.5, which can't be seen from outside.
+    pub prompt_tokens: Vec<WhisperTokenId>,
+    /// Indices of the segments produced in this window, for [`WhisperState::get_segment`].
+    pub segments: Range<c_int>,
+}
+
+/// Chains to the encoder-begin callback set on the params, if any.
+struct WindowRecorder {
+    segments_at_start: Vec<c_int>,
+    callback: whisper_rs_sys::whisper_encoder_begin_callback,
+    user_data: *mut c_void,
+}
+
+unsafe extern "C" fn record_window(
+    ctx: *mut whisper_rs_sys::whisper_context,
+    state: *mut whisper_rs_sys::whisper_state,
+    user_data: *mut c_void,
+) -> bool {
+    // SAFETY: user_data is the WindowRecorder living on the stack of full_with_windows
+    let recorder = &mut *(user_data as *mut WindowRecorder);
+    recorder
+        .segments_at_start
+        .push(whisper_rs_sys::whisper_full_n_segments_from_state(state));
+    match recorder.callback {
+        Some(callback) => callback(ctx, state, recorder.user_data),
+        None => true,
+    }
+}
+
+impl WhisperState {
+    /// Like [`Self::full`], also reporting each 30 second window whisper.cpp decoded:
+    /// which samples it covered, the prompt it was given, and the segments it produced.
+    ///
+    /// Meant for auditing how long-form audio is stitched together, e.g. why a window repeated
+    /// text from its predecessor. Any encoder-begin callback already set on `params` is still called.
+    pub fn full_with_windows(
+        &mut self,
+        mut params: FullParams,
+        data: &[f32],
+    ) -> Result<Vec<DecodeWindow>, WhisperError> {
+        let prompt = self.initial_prompt(&params)?;
+        let n_max_text_ctx = params
+            .fp
+            .n_max_text_ctx
+            .min(self.ctx.model_n_text_ctx() / 2);
+        let start_cs = (params.fp.offset_ms / 10) as i64;
+        let audio_cs = (data.len() / SAMPLES_PER_CS) as i64;
+        let end_cs = match params.fp.duration_ms {
+            duration if duration > 0 => (start_cs + (duration / 10) as i64).min(audio_cs),
+            _ => audio_cs,
+        };
+
+        let mut recorder = WindowRecorder {
+            segments_at_start: Vec::new(),
+            callback: params.fp.encoder_begin_callback,
+            user_data: params.fp.encoder_begin_callback_user_data,
+        };
+        params.fp.encoder_begin_callback = Some(record_window);
+        params.fp.encoder_begin_callback_user_data = &mut recorder as *mut WindowRecorder as _;
+        self.full(params, data)?;
+
+        let n_segments = self.full_n_segments();
+        let mut bounds = recorder.segments_at_start;
+        bounds.push(n_segments);
+        let segments: Vec<_> = (0..n_segments)
+            .filter_map(|i| self.get_segment(i))
+            .map(|s| (s.end_timestamp(), s.tokens().map(|t| t.id).collect()))
+            .collect();
+        Ok(split_windows(
+            &bounds,
+            &segments,
+            prompt,
+            n_max_text_ctx,
+            start_cs..end_cs,
+            data.len(),
+        ))
+    }
+
+    /// The prompt the first window starts with.
+    fn initial_prompt(&self, params: &FullParams) -> Result<Vec<WhisperTokenId>, WhisperError> {
+        if !params.fp.prompt_tokens.is_null() && params.fp.prompt_n_tokens > 0 {
+            // SAFETY: set from a slice borrowed for the lifetime of the params
+            let tokens = unsafe {
+                std::slice::from_raw_parts(
+                    params.fp.prompt_tokens,
+                    params.fp.prompt_n_tokens as usize,
+                )
+            };
+            return Ok(tokens.to_vec());
+        }
+        // whisper.cpp only uses the initial prompt without prompt tokens
+        let mut prompt = Vec::new();
+        if !params.fp.initial_prompt.is_null() {
+            // SAFETY: non-null strings in the params are always valid CStrings owned by the params
+            let text = unsafe { CStr::from_ptr(params.fp.initial_prompt) }.to_str()?;
+            let max_tokens = self.ctx.model_n_text_ctx().max(1) as usize;
+            prompt = self.ctx.tokenize(text, max_tokens)?;
+        }
+        Ok(prompt)
+    }
+}
+
+/// Rebuild the windows of a run from the number of segments at the start of each window and
+/// after the last one, and the end and tokens of every segment.
+fn split_windows(
+    bounds: &[c_int],
+    segments: &[(i64, Vec<WhisperTokenId>)],
+    mut prompt: Vec<WhisperTokenId>,
+    n_max_text_ctx: c_int,
+    range_cs: Range<i64>,
+    n_samples: usize,
+) -> Vec<DecodeWindow> {
+    let mut windows = Vec::with_capacity(bounds.len().saturating_sub(1));
+    let mut seek = range_cs.start;
+    for pair in bounds.windows(2) {
+        let range = pair[0] as usize..pair[1] as usize;
+        let window_end = match segments[range.clone()].last() {
+            Some((end, _)) => *end,
+            None => seek + WINDOW_CS,
+        }
+        .clamp(seek, range_cs.end);
+
+        let skip = prompt.len().saturating_sub(n_max_text_ctx.max(0) as usize);
+        windows.push(DecodeWindow {
+            samples: cs_to_sample(seek, n_samples)..cs_to_sample(window_end, n_samples),
+            prompt_tokens: prompt[skip..].to_vec(),
+            segments: pair[0]..pair[1],
+        });
+        for (_, tokens) in &segments[range] {
+            prompt.extend(tokens);
+        }
+        seek = window_end;
+    }
+    windows
+}
+
+fn cs_to_sample(cs: i64, len: usize) -> usize {
+    (cs.max(0) as usize * SAMPLES_PER_CS).min(len)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn windows_advance_past_their_segments() {
+        // 70 seconds from 5 seconds in: two full windows, then one cut short by the audio
+        let segments = [
+            (1000, vec![1, 2]),
+            (3400, vec![3]),
+            (6000, vec![4, 5]),
+            (7500, vec![6]),
+        ];
+        let n_samples = 75 * 16000;
+        let windows = split_windows(&[0, 2, 3, 4], &segments, vec![], 224, 500..7500, n_samples);
+        assert_eq!(windows.len(), 3);
+        assert_eq!(windows[0].samples, 500 * 160..3400 * 160);
+        assert_eq!(windows[0].segments, 0..2);
+        assert_eq!(windows[1].samples, 3400 * 160..6000 * 160);
+        assert_eq!(windows[2].samples, 6000 * 160..n_samples);
+        assert_eq!(windows[2].segments, 3..4);
+    }
+
+    #[test]
+    fn empty_windows_advance_a_full_window() {
+        let windows = split_windows(
+            &[0, 0, 1],
+            &[(4000, vec![1])],
+            vec![],
+            224,
+            0..4500,
+            45 * 16000,
+        );
+        assert_eq!(windows[0].samples, 0..3000 * 160);
+        assert!(windows[0].segments.is_empty());
+        assert_eq!(windows[1].samples, 3000 * 160..4000 * 160);
+    }
+
+    #[test]
+    fn prompts_carry_the_last_tokens() {
+        let segments = [(3000, vec![3, 4]), (6000, vec![5, 6, 7]), (9000, vec![8])];
+        let windows = split_windows(&[0, 1, 2, 3], &segments, vec![1, 2], 4, 0..9000, 90 * 16000);
+        assert_eq!(windows[0].prompt_tokens, [1, 2]);
+        assert_eq!(windows[1].prompt_tokens, [1, 2, 3, 4]);
+        assert_eq!(windows[2].prompt_tokens, [4, 5, 6, 7]);
+
+        let windows = split_windows(&[0, 1], &segments[..1], vec![1, 2], 0, 0..3000, 30 * 16000);
+        assert!(windows[0].prompt_tokens.is_empty());
+    }
+}
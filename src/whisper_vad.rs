@@ -1,3 +1,4 @@
+use crate::backends::LiveContext;
 use crate::WhisperError;
 use std::ffi::{c_char, CString};
 use std::os::raw::c_int;
@@ -140,6 +141,7 @@ impl WhisperVadContextParams {
 /// You probably want to use [`Self::segments_from_samples`].
 pub struct WhisperVadContext {
     ptr: *mut whisper_vad_context,
+    _live: LiveContext,
 }
 unsafe impl Send for WhisperVadContext {}
 unsafe impl Sync for WhisperVadContext {}
@@ -149,13 +151,14 @@ impl WhisperVadContext {
         let model_path = CString::new(model_path)
             .expect("VAD model path contains null byte")
             .into_raw() as *const c_char;
+        let live = LiveContext::new();
         let ptr =
             unsafe { whisper_vad_init_from_file_with_params(model_path, params.into_inner()) };
 
         if ptr.is_null() {
             Err(WhisperError::NullPointer)
         } else {
-            Ok(Self { ptr })
+            Ok(Self { ptr, _live: live })
         }
     }
 
@@ -5,18 +5,21 @@ extern crate bindgen;
 use cmake::Config;
 use std::env;
 use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::io::{BufRead, BufReader, Write};
 use std::path::PathBuf;
 use std::process::Command;
 
 fn main() {
     let target = env::var("TARGET").unwrap();
+    if cfg!(feature = "minimal") {
+        check_minimal_features();
+    }
     // Link C++ standard library
     if let Some(cpp_stdlib) = get_cpp_link_stdlib(&target) {
         println!("cargo:rustc-link-lib=dylib={}", cpp_stdlib);
     }
     // Link macOS Accelerate framework for matrix calculations
-    if target.contains("apple") {
+    if target.contains("apple") && cfg!(not(feature = "minimal")) {
         println!("cargo:rustc-link-lib=framework=Accelerate");
         #[cfg(feature = "coreml")]
         {
@@ -161,12 +164,19 @@ fn main() {
             .unwrap_or(false)
     };
     
-    // If whisper.cpp doesn't exist locally, download it
+    // If whisper.cpp doesn't exist locally, download it. Builds with different features or
+    // targets share the sources next to the manifest, so only one of them fetches at a time.
     let whisper_exists = whisper_cpp_source.exists() && dir_has_contents(&whisper_cpp_source);
-    
+    let fetch_lock =
+        (!whisper_exists).then(|| BuildLock::acquire(manifest_dir.join("whisper.cpp.lock")));
+    // another build may have fetched them while we waited
+    let whisper_exists = whisper_cpp_source.exists() && dir_has_contents(&whisper_cpp_source);
+
     if !whisper_exists {
         println!("cargo:warning=whisper.cpp not found, downloading from GitHub...");
-        
+        // what a build killed while fetching left behind, safe to remove under the lock
+        remove_staging_dirs(&manifest_dir, "whisper.cpp");
+
         // Try to initialize submodule first (if in a git repo)
         let git_result = Command::new("git")
             .args(&["submodule", "update", "--init", "--recursive", "whisper.cpp"])
@@ -183,31 +193,18 @@ fn main() {
         if !submodule_success {
             println!("cargo:warning=Submodule init failed, cloning whisper.cpp directly...");
             
-            // Clone whisper.cpp to a temp location
-            let temp_whisper = out.join("whisper.cpp-temp");
-            if temp_whisper.exists() {
-                std::fs::remove_dir_all(&temp_whisper).unwrap_or_default();
-            }
-            
+            // Clone whisper.cpp next to its final location, so moving it there is atomic and
+            // an interrupted clone never looks like a complete checkout
+            let staging = StagingDir::new(&manifest_dir, "whisper.cpp");
             let clone_result = Command::new("git")
-                .args(&["clone", "--depth", "1", "https://github.com/ggerganov/whisper.cpp.git", temp_whisper.to_str().unwrap()])
+                .args(&["clone", "--depth", "1", "https://github.com/ggerganov/whisper.cpp.git", staging.path.to_str().unwrap()])
                 .output();
             
             match clone_result {
                 Ok(output) if output.status.success() => {
-                    // Move the cloned directory to the expected location
-                    if whisper_cpp_source.exists() {
-                        std::fs::remove_dir_all(&whisper_cpp_source).ok();
-                    }
-                    std::fs::create_dir_all(whisper_cpp_source.parent().unwrap()).unwrap();
-                    
-                    // Try rename first, fall back to copy if rename fails (cross-filesystem)
-                    if std::fs::rename(&temp_whisper, &whisper_cpp_source).is_err() {
-                        fs_extra::dir::copy(&temp_whisper, &manifest_dir, &Default::default()).unwrap_or_else(|e| {
-                            panic!("Failed to copy cloned whisper.cpp: {}", e);
-                        });
-                        std::fs::remove_dir_all(&temp_whisper).ok();
-                    }
+                    staging.persist(&whisper_cpp_source).unwrap_or_else(|e| {
+                        panic!("Failed to move cloned whisper.cpp to {}: {}", whisper_cpp_source.display(), e);
+                    });
                     println!("cargo:warning=Successfully downloaded whisper.cpp");
                 }
                 Ok(output) => {
@@ -222,30 +219,41 @@ fn main() {
             println!("cargo:warning=Successfully initialized whisper.cpp submodule");
         }
     }
+    drop(fetch_lock);
 
-    // Now copy whisper.cpp to the build directory
+    // Now copy whisper.cpp to the build directory. The copy is staged and moved into place
+    // once complete, so a build interrupted while copying starts over instead of compiling
+    // a partial tree.
     if !whisper_root.exists() || !whisper_root.join("CMakeLists.txt").exists() {
+        remove_staging_dirs(&out, "whisper.cpp");
+        std::fs::remove_dir_all(out.join("whisper.cpp-temp")).ok();
         if whisper_root.exists() {
             std::fs::remove_dir_all(&whisper_root).unwrap_or_default();
         }
-        std::fs::create_dir_all(&whisper_root).unwrap();
-        fs_extra::dir::copy(&whisper_cpp_source, &out, &Default::default()).unwrap_or_else(|e| {
+        let staging = StagingDir::new(&out, "whisper.cpp");
+        std::fs::create_dir_all(&staging.path).unwrap();
+        let mut options = fs_extra::dir::CopyOptions::new();
+        options.content_only = true;
+        fs_extra::dir::copy(&whisper_cpp_source, &staging.path, &options).unwrap_or_else(|e| {
             panic!(
                 "Failed to copy whisper sources from {} to {}: {}",
                 whisper_cpp_source.display(),
-                whisper_root.display(),
+                staging.path.display(),
                 e
             )
         });
         
         // Verify CMakeLists.txt exists after copy
-        if !whisper_root.join("CMakeLists.txt").exists() {
+        if !staging.path.join("CMakeLists.txt").exists() {
             panic!(
                 "CMakeLists.txt not found in {} after copy. Source: {}",
-                whisper_root.display(),
+                staging.path.display(),
                 whisper_cpp_source.display()
             );
         }
+        staging.persist(&whisper_root).unwrap_or_else(|e| {
+            panic!("Failed to move whisper sources to {}: {}", whisper_root.display(), e)
+        });
     }
 
     if env::var("WHISPER_DONT_GENERATE_BINDINGS").is_ok() {
@@ -692,9 +700,19 @@ fn main() {
             config.define("CMAKE_CXX_COMPILER", "icpx");
         }
 
+        if cfg!(feature = "minimal") {
+            configure_minimal(&mut config);
+        }
+
         let destination = config.build();
 
         add_link_search_path(&out.join("build")).unwrap();
+        // lets the size regression test in lib.rs find the libraries, installed to lib or lib64
+        // depending on the platform's CMAKE_INSTALL_LIBDIR
+        println!(
+            "cargo:rustc-env=WHISPER_RS_SYS_INSTALL_DIR={}",
+            destination.display()
+        );
 
         println!("cargo:rustc-link-search=native={}", destination.display());
         if cfg!(feature = "intel-sycl") {
@@ -708,7 +726,8 @@ fn main() {
             println!("cargo:rustc-link-lib=static=ggml-base");
             println!("cargo:rustc-link-lib=static=ggml-cpu");
         }
-        if cfg!(target_os = "macos") || cfg!(feature = "openblas") {
+        let accelerate = cfg!(target_os = "macos") && cfg!(not(feature = "minimal"));
+        if accelerate || cfg!(feature = "openblas") {
             println!("cargo:rustc-link-lib=static=ggml-blas");
         }
         if cfg!(feature = "vulkan") {
@@ -746,11 +765,80 @@ fn main() {
             .expect("Failed to read whisper.cpp CMake config")
             .expect("Could not find whisper.cpp version declaration"),
     );
+    // empty when built from a source tree without git metadata
+    println!(
+        "cargo:WHISPER_CPP_COMMIT={}",
+        get_git_commit(&whisper_cpp_source).unwrap_or_default()
+    );
+    println!(
+        "cargo:GGML_COMMIT={}",
+        get_ggml_commit(&whisper_root).unwrap_or_default()
+    );
 
     // for whatever reason this file is generated during build and triggers cargo complaining
     _ = std::fs::remove_file("bindings/javascript/package.json");
 }
 
+/// The `minimal` profile is CPU-only, so refuse the features that would add a backend back in.
+fn check_minimal_features() {
+    let conflicting = [
+        ("coreml", cfg!(feature = "coreml")),
+        ("cuda", cfg!(feature = "cuda")),
+        ("hipblas", cfg!(feature = "hipblas")),
+        ("openblas", cfg!(feature = "openblas")),
+        ("metal", cfg!(feature = "metal")),
+        ("vulkan", cfg!(feature = "vulkan")),
+        ("intel-sycl", cfg!(feature = "intel-sycl")),
+        ("openmp", cfg!(feature = "openmp")),
+        ("use-shared-ggml", cfg!(feature = "use-shared-ggml")),
+    ];
+    let enabled: Vec<_> = conflicting
+        .iter()
+        .filter(|(_, on)| *on)
+        .map(|(name, _)| *name)
+        .collect();
+    if !enabled.is_empty() {
+        panic!(
+            "the minimal feature builds a CPU-only library and cannot be combined with: {}",
+            enabled.join(", ")
+        );
+    }
+}
+
+/// Strip everything but the CPU backend, and optimize for size.
+fn configure_minimal(config: &mut Config) {
+    for option in [
+        "GGML_METAL",
+        "GGML_ACCELERATE",
+        "GGML_BLAS",
+        "GGML_OPENMP",
+        "GGML_LLAMAFILE",
+        "GGML_BACKEND_DL",
+        "GGML_CPU_ALL_VARIANTS",
+        "GGML_CPU_REPACK",
+        "WHISPER_SDL2",
+        "WHISPER_CURL",
+        "WHISPER_FFMPEG",
+        "WHISPER_COREML",
+        "WHISPER_OPENVINO",
+    ] {
+        config.define(option, "OFF");
+    }
+    // overrides the build type chosen above, also in debug builds
+    config.define("CMAKE_BUILD_TYPE", "MinSizeRel");
+    config.profile("MinSizeRel");
+
+    // Exceptions stay on: whisper.cpp catches exceptions while loading a model and allocating
+    // buffers, so -fno-exceptions would turn those errors into aborts. RTTI stays on with them,
+    // as the type info of thrown exceptions is emitted either way and -fno-rtti saves little.
+    if !env::var("TARGET").unwrap().contains("msvc") {
+        for flag in ["-ffunction-sections", "-fdata-sections"] {
+            config.cflag(flag);
+            config.cxxflag(flag);
+        }
+    }
+}
+
 // From https://github.com/alexcrichton/cc-rs/blob/fba7feded71ee4f63cfe885673ead6d7b4f2f454/src/lib.rs#L2462
 fn get_cpp_link_stdlib(target: &str) -> Option<&'static str> {
     if target.contains("msvc") {
@@ -789,6 +877,162 @@ fn get_whisper_cpp_version(whisper_root: &std::path::Path) -> std::io::Result<Op
     Ok(None)
 }
 
+/// An exclusive lock shared by all build processes, held from [`Self::acquire`] until dropped.
+///
+/// Taken by creating the lock file, which fails atomically if it exists, as file locks are
+/// newer than the toolchains this crate supports. The file holds the id of the process that
+/// took it, so a lock left behind by a build that was interrupted or killed is taken over as
+/// soon as that process is gone, or once it is older than any fetch takes if that can't be told.
+struct BuildLock {
+    path: PathBuf,
+}
+
+impl BuildLock {
+    fn acquire(path: PathBuf) -> Self {
+        const STALE_AFTER: std::time::Duration = std::time::Duration::from_secs(30 * 60);
+        let mut waiting = false;
+        loop {
+            match std::fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)
+            {
+                Ok(mut file) => {
+                    writeln!(file, "{}", std::process::id()).ok();
+                    return Self { path };
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                    let expired = std::fs::metadata(&path)
+                        .and_then(|m| m.modified())
+                        .ok()
+                        .and_then(|modified| modified.elapsed().ok())
+                        .is_some_and(|age| age > STALE_AFTER);
+                    if expired || Self::holder_exited(&path) {
+                        println!("cargo:warning=Taking over stale lock {}", path.display());
+                        std::fs::remove_file(&path).ok();
+                    } else {
+                        if !waiting {
+                            println!(
+                                "cargo:warning=Waiting for another build to release {}",
+                                path.display()
+                            );
+                            waiting = true;
+                        }
+                        std::thread::sleep(std::time::Duration::from_millis(200));
+                    }
+                }
+                Err(e) => panic!("Failed to create lock {}: {}", path.display(), e),
+            }
+        }
+    }
+
+    /// Whether the process that took the lock at `path` is known to have exited. False while
+    /// it is still writing its id, or if there's no way to tell.
+    fn holder_exited(path: &std::path::Path) -> bool {
+        let Some(pid) = std::fs::read_to_string(path)
+            .ok()
+            .and_then(|pid| pid.trim().parse::<u32>().ok())
+        else {
+            return false;
+        };
+        process_running(pid) == Some(false)
+    }
+}
+
+/// Whether the process with id `pid` is running, if that can be found out.
+fn process_running(pid: u32) -> Option<bool> {
+    if cfg!(target_os = "linux") {
+        return Some(std::path::Path::new("/proc").join(pid.to_string()).exists());
+    }
+    if cfg!(windows) {
+        let output = Command::new("tasklist")
+            .args(["/FI", &format!("PID eq {}", pid), "/FO", "CSV", "/NH"])
+            .output()
+            .ok()?;
+        let listed = String::from_utf8_lossy(&output.stdout).contains(&format!("\"{}\"", pid));
+        return output.status.success().then_some(listed);
+    }
+    // signal 0 only checks that the process exists
+    let status = Command::new("kill")
+        .args(["-0", &pid.to_string()])
+        .stderr(std::process::Stdio::null())
+        .status()
+        .ok()?;
+    Some(status.success())
+}
+
+impl Drop for BuildLock {
+    fn drop(&mut self) {
+        std::fs::remove_file(&self.path).ok();
+    }
+}
+
+/// A directory that is removed when dropped, unless [`Self::persist`] moved it into place.
+/// Build scripts unwind on panic, so failed builds clean up after themselves.
+struct StagingDir {
+    path: PathBuf,
+}
+
+impl StagingDir {
+    /// A staging path for `name` in `parent`, unique to this process and not yet created.
+    fn new(parent: &std::path::Path, name: &str) -> Self {
+        let path = parent.join(format!("{}.staging-{}", name, std::process::id()));
+        std::fs::remove_dir_all(&path).ok();
+        Self { path }
+    }
+
+    /// Atomically move the staged directory to `dest`, which must be missing or empty.
+    fn persist(self, dest: &std::path::Path) -> std::io::Result<()> {
+        // an empty directory, such as an uninitialized submodule, is replaced
+        std::fs::remove_dir(dest).ok();
+        std::fs::rename(&self.path, dest)
+    }
+}
+
+impl Drop for StagingDir {
+    fn drop(&mut self) {
+        std::fs::remove_dir_all(&self.path).ok();
+    }
+}
+
+/// Remove the staging directories for `name` in `parent` that builds killed before they could
+/// clean up left behind.
+fn remove_staging_dirs(parent: &std::path::Path, name: &str) {
+    let prefix = format!("{}.staging-", name);
+    for entry in parent.read_dir().into_iter().flatten().flatten() {
+        if entry.file_name().to_string_lossy().starts_with(&prefix) {
+            std::fs::remove_dir_all(entry.path()).ok();
+        }
+    }
+}
+
+/// The commit checked out in `repo`, if it is a git checkout of its own and git is installed.
+/// A vendored copy inside another checkout, such as the crate's own repository or the workspace
+/// depending on it, doesn't count: git would report that checkout's commit instead.
+fn get_git_commit(repo: &std::path::Path) -> Option<String> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--show-toplevel", "HEAD"])
+        .current_dir(repo)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let stdout = String::from_utf8(output.stdout).ok()?;
+    let mut lines = stdout.lines();
+    let toplevel = std::fs::canonicalize(lines.next()?.trim()).ok()?;
+    if toplevel != std::fs::canonicalize(repo).ok()? {
+        return None;
+    }
+    Some(lines.next()?.trim().to_string()).filter(|c| !c.is_empty())
+}
+
+/// The ggml commit whisper.cpp last synced its copy of ggml with, as recorded by its sync script.
+fn get_ggml_commit(whisper_root: &std::path::Path) -> Option<String> {
+    let commit = std::fs::read_to_string(whisper_root.join("scripts/sync-ggml.last")).ok()?;
+    Some(commit.trim().to_string()).filter(|c| !c.is_empty())
+}
+
 /// Copy whisper-specific GGML DLLs to the target directory for runtime on Windows
 /// lib_base_name comes from DEP_GGML_RS_GGML_WHISPER_BASENAME (typically "ggml_whisper")
 /// This copies DLLs like: ggml_whisper.dll, ggml_whisper-base.dll, ggml_whisper-cpu.dll, etc.
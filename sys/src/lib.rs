@@ -3,3 +3,58 @@
 #![allow(non_snake_case)]
 
 include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
+
+#[cfg(all(test, feature = "minimal"))]
+mod test {
+    /// Total size of the static libraries of the `minimal` profile, installed to `lib` or
+    /// `lib64` depending on the platform.
+    fn minimal_size() -> u64 {
+        let install = std::path::Path::new(env!("WHISPER_RS_SYS_INSTALL_DIR"));
+        let mut total = 0;
+        for dir in [install.join("lib"), install.join("lib64")] {
+            let Ok(entries) = std::fs::read_dir(&dir) else {
+                continue;
+            };
+            for entry in entries {
+                let path = entry.unwrap().path();
+                let is_library = path
+                    .extension()
+                    .is_some_and(|ext| ext == "a" || ext == "lib");
+                if is_library {
+                    total += std::fs::metadata(&path).unwrap().len();
+                }
+            }
+        }
+        total
+    }
+
+    /// Size the static libraries of the `minimal` profile may add up to, in bytes. It is meant to
+    /// catch a GPU backend or another dependency being linked in by accident, each of which adds
+    /// tens of megabytes, while leaving room for the debug info of test builds.
+    const MINIMAL_BUDGET: u64 = 64 * 1024 * 1024;
+
+    /// Checks the size against [`MINIMAL_BUDGET`], or against `WHISPER_RS_SYS_MINIMAL_BUDGET`,
+    /// in bytes, if it was set when building the tests, e.g. to hold a release build to a
+    /// tighter limit.
+    #[test]
+    fn minimal_libraries_stay_within_budget() {
+        let total = minimal_size();
+        assert!(
+            total > 0,
+            "no static libraries in {}",
+            env!("WHISPER_RS_SYS_INSTALL_DIR")
+        );
+        let budget =
+            option_env!("WHISPER_RS_SYS_MINIMAL_BUDGET").map_or(MINIMAL_BUDGET, |budget| {
+                budget
+                    .parse()
+                    .expect("WHISPER_RS_SYS_MINIMAL_BUDGET must be a number of bytes")
+            });
+        assert!(
+            total <= budget,
+            "minimal build is {} bytes, over the budget of {} bytes",
+            total,
+            budget
+        );
+    }
+}